@@ -0,0 +1,52 @@
+//! Evaluation throughput on call-heavy scripts. Run with `cargo bench`, and
+//! again with `--features pool` to compare.
+
+#![allow(non_snake_case)]
+
+use std::time::Duration;
+use std::time::Instant;
+
+use monkey::environment::Environment;
+use monkey::evaluator::Eval;
+use monkey::lexer::Lexer;
+use monkey::parser::Parser;
+
+const SCRIPTS: &[(&str, &str)] = &[
+    (
+        "loop",
+        "let loop = fn(n, acc) { if (n == 0) { acc } else { loop(n - 1, acc + n) } };
+         loop(20000, 0)",
+    ),
+    (
+        "fib",
+        "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+         fib(18)",
+    ),
+    (
+        "closures",
+        "let add = fn(x) { fn(y) { x + y } };
+         let loop = fn(n) { if (n == 0) { 0 } else { add(n)(1); loop(n - 1) } };
+         loop(10000)",
+    ),
+];
+
+/// How long each script is run for.
+const BUDGET: Duration = Duration::from_secs(2);
+
+fn main() {
+    for (name, source) in SCRIPTS {
+        let program = Parser::New(Lexer::New(source)).ParseProgram();
+        let start = Instant::now();
+        let mut runs = 0u32;
+        while start.elapsed() < BUDGET {
+            Eval(program.clone().into(), &Environment::New());
+            runs += 1;
+        }
+        println!(
+            "{:<10} {:>10.3} ms/run ({} runs)",
+            name,
+            start.elapsed().as_secs_f64() * 1000.0 / runs as f64,
+            runs
+        );
+    }
+}
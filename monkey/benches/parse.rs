@@ -0,0 +1,50 @@
+//! Parse throughput on a large generated program. Run with `cargo bench
+//! --bench parse`.
+
+#![allow(non_snake_case)]
+
+use std::time::Duration;
+use std::time::Instant;
+
+use monkey::lexer::Lexer;
+use monkey::parser::Parser;
+
+/// How many functions the program defines.
+const FUNCTIONS: usize = 2000;
+
+/// How long the program is parsed for.
+const BUDGET: Duration = Duration::from_secs(2);
+
+/// A program of functions with a bit of everything in them.
+fn program() -> String {
+    let mut source = String::new();
+    for i in 0..FUNCTIONS {
+        source += &format!(
+            "let f{i} = fn(a, b) {{
+                 let xs = [a, b, a * b + {i}, \"s${{a}}\"];
+                 if (a < b) {{ return xs[0:2]; }}
+                 match (b) {{ 1 => {{ \"one\": xs }}, _ => f{i}(b, a - 1) }}
+             }};\n",
+            i = i
+        );
+    }
+    source
+}
+
+fn main() {
+    let source = program();
+    let start = Instant::now();
+    let mut runs = 0u32;
+    while start.elapsed() < BUDGET {
+        let program = Parser::New(Lexer::New(&source)).ParseProgram();
+        assert_eq!(program.statements.len(), FUNCTIONS);
+        runs += 1;
+    }
+    let perRun = start.elapsed().as_secs_f64() / runs as f64;
+    println!(
+        "parse      {:>10.3} ms/run ({} runs, {:.1} MB/s)",
+        perRun * 1000.0,
+        runs,
+        source.len() as f64 / perRun / 1e6
+    );
+}
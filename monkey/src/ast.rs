@@ -1,28 +1,97 @@
+use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Range;
+use std::rc::Rc;
+
 use enum_dispatch::enum_dispatch;
+use itertools::Itertools;
 
+use crate::token::Position;
 use crate::token::Token;
 
 #[enum_dispatch]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeEnum {
     Expression(ExpressionEnum),
     Statement(StatementEnum),
     Program(Program),
 }
 
+impl Display for NodeEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expression(e) => Display::fmt(e, f),
+            Self::Statement(s) => Display::fmt(s, f),
+            Self::Program(p) => Display::fmt(p, f),
+        }
+    }
+}
+
 #[enum_dispatch(NodeEnum)]
-pub trait Node: std::fmt::Debug + Clone {
+pub trait Node: std::fmt::Debug + Clone + Display {
     fn TokenLiteral(&self) -> &str;
-    fn String(&self) -> String;
+    /// Where the token `TokenLiteral` is from starts in the source.
+    fn Position(&self) -> Position;
+    /// The byte range of the source the node was parsed from, like a
+    /// `Token`'s `Span`. Nodes the parser makes up span the code they stand
+    /// for, and nodes made any other way span nothing at 0.
+    fn Span(&self) -> Range<usize>;
+    /// The node as code, which its `Display` prints without building a
+    /// string for every node in it.
+    fn String(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Where a node's source starts and ends, in bytes. Spans are all equal, so
+/// that nodes compare equal wherever they are; compare their `Range`s to
+/// tell them apart.
+#[derive(Debug, Default, Clone, Copy, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn Range(self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+        }
+    }
+}
+
+// like a token's position, where a node is is left out of the fingerprint
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
 }
 
 #[enum_dispatch]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StatementEnum {
     Let(LetStatement),
     Return(ReturnStatement),
     Expression(ExpressionStatement),
     Block(BlockStatement),
+    Defer(DeferStatement),
+    Destructure(DestructureStatement),
+    Throw(ThrowStatement),
 }
 
 impl Node for StatementEnum {
@@ -32,15 +101,47 @@ impl Node for StatementEnum {
             Self::Return(s) => s.TokenLiteral(),
             Self::Expression(s) => s.TokenLiteral(),
             Self::Block(s) => s.TokenLiteral(),
+            Self::Defer(s) => s.TokenLiteral(),
+            Self::Destructure(s) => s.TokenLiteral(),
+            Self::Throw(s) => s.TokenLiteral(),
         }
     }
 
-    fn String(&self) -> String {
+    fn Position(&self) -> Position {
+        match self {
+            Self::Let(s) => s.Position(),
+            Self::Return(s) => s.Position(),
+            Self::Expression(s) => s.Position(),
+            Self::Block(s) => s.Position(),
+            Self::Defer(s) => s.Position(),
+            Self::Destructure(s) => s.Position(),
+            Self::Throw(s) => s.Position(),
+        }
+    }
+
+    fn Span(&self) -> Range<usize> {
+        match self {
+            Self::Let(s) => s.Span(),
+            Self::Return(s) => s.Span(),
+            Self::Expression(s) => s.Span(),
+            Self::Block(s) => s.Span(),
+            Self::Defer(s) => s.Span(),
+            Self::Destructure(s) => s.Span(),
+            Self::Throw(s) => s.Span(),
+        }
+    }
+}
+
+impl Display for StatementEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Let(s) => s.String(),
-            Self::Return(s) => s.String(),
-            Self::Expression(s) => s.String(),
-            Self::Block(s) => s.String(),
+            Self::Let(s) => Display::fmt(s, f),
+            Self::Return(s) => Display::fmt(s, f),
+            Self::Expression(s) => Display::fmt(s, f),
+            Self::Block(s) => Display::fmt(s, f),
+            Self::Defer(s) => Display::fmt(s, f),
+            Self::Destructure(s) => Display::fmt(s, f),
+            Self::Throw(s) => Display::fmt(s, f),
         }
     }
 }
@@ -49,16 +150,30 @@ impl Node for StatementEnum {
 pub trait Statement: Node {}
 
 #[enum_dispatch]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionEnum {
     Identifier(Identifier),
     IntegerLiteral(IntegerLiteral),
+    StringLiteral(StringLiteral),
     PrefixExpression(PrefixExpression),
     InfixExpression(InfixExpression),
+    UpdateExpression(UpdateExpression),
     Boolean(Boolean),
+    NullLiteral(NullLiteral),
     IfExpression(IfExpression),
+    MatchExpression(MatchExpression),
     FunctionLiteral(FunctionLiteral),
+    MacroLiteral(MacroLiteral),
     CallExpression(CallExpression),
+    ArrayLiteral(ArrayLiteral),
+    IndexExpression(IndexExpression),
+    SliceExpression(SliceExpression),
+    SpreadExpression(SpreadExpression),
+    HashLiteral(HashLiteral),
+    KeywordArgument(KeywordArgument),
+    TryExpression(TryExpression),
+    ImportExpression(ImportExpression),
 }
 
 impl Node for ExpressionEnum {
@@ -66,25 +181,105 @@ impl Node for ExpressionEnum {
         match self {
             Self::Identifier(e) => e.TokenLiteral(),
             Self::IntegerLiteral(e) => e.TokenLiteral(),
+            Self::StringLiteral(e) => e.TokenLiteral(),
             Self::PrefixExpression(e) => e.TokenLiteral(),
             Self::InfixExpression(e) => e.TokenLiteral(),
+            Self::UpdateExpression(e) => e.TokenLiteral(),
             Self::Boolean(e) => e.TokenLiteral(),
+            Self::NullLiteral(e) => e.TokenLiteral(),
             Self::IfExpression(e) => e.TokenLiteral(),
+            Self::MatchExpression(e) => e.TokenLiteral(),
             Self::FunctionLiteral(e) => e.TokenLiteral(),
+            Self::MacroLiteral(e) => e.TokenLiteral(),
             Self::CallExpression(e) => e.TokenLiteral(),
+            Self::ArrayLiteral(e) => e.TokenLiteral(),
+            Self::IndexExpression(e) => e.TokenLiteral(),
+            Self::SliceExpression(e) => e.TokenLiteral(),
+            Self::SpreadExpression(e) => e.TokenLiteral(),
+            Self::HashLiteral(e) => e.TokenLiteral(),
+            Self::KeywordArgument(e) => e.TokenLiteral(),
+            Self::TryExpression(e) => e.TokenLiteral(),
+            Self::ImportExpression(e) => e.TokenLiteral(),
         }
     }
 
-    fn String(&self) -> String {
+    fn Position(&self) -> Position {
+        match self {
+            Self::Identifier(e) => e.Position(),
+            Self::IntegerLiteral(e) => e.Position(),
+            Self::StringLiteral(e) => e.Position(),
+            Self::PrefixExpression(e) => e.Position(),
+            Self::InfixExpression(e) => e.Position(),
+            Self::UpdateExpression(e) => e.Position(),
+            Self::Boolean(e) => e.Position(),
+            Self::NullLiteral(e) => e.Position(),
+            Self::IfExpression(e) => e.Position(),
+            Self::MatchExpression(e) => e.Position(),
+            Self::FunctionLiteral(e) => e.Position(),
+            Self::MacroLiteral(e) => e.Position(),
+            Self::CallExpression(e) => e.Position(),
+            Self::ArrayLiteral(e) => e.Position(),
+            Self::IndexExpression(e) => e.Position(),
+            Self::SliceExpression(e) => e.Position(),
+            Self::SpreadExpression(e) => e.Position(),
+            Self::HashLiteral(e) => e.Position(),
+            Self::KeywordArgument(e) => e.Position(),
+            Self::TryExpression(e) => e.Position(),
+            Self::ImportExpression(e) => e.Position(),
+        }
+    }
+
+    fn Span(&self) -> Range<usize> {
+        match self {
+            Self::Identifier(e) => e.Span(),
+            Self::IntegerLiteral(e) => e.Span(),
+            Self::StringLiteral(e) => e.Span(),
+            Self::PrefixExpression(e) => e.Span(),
+            Self::InfixExpression(e) => e.Span(),
+            Self::UpdateExpression(e) => e.Span(),
+            Self::Boolean(e) => e.Span(),
+            Self::NullLiteral(e) => e.Span(),
+            Self::IfExpression(e) => e.Span(),
+            Self::MatchExpression(e) => e.Span(),
+            Self::FunctionLiteral(e) => e.Span(),
+            Self::MacroLiteral(e) => e.Span(),
+            Self::CallExpression(e) => e.Span(),
+            Self::ArrayLiteral(e) => e.Span(),
+            Self::IndexExpression(e) => e.Span(),
+            Self::SliceExpression(e) => e.Span(),
+            Self::SpreadExpression(e) => e.Span(),
+            Self::HashLiteral(e) => e.Span(),
+            Self::KeywordArgument(e) => e.Span(),
+            Self::TryExpression(e) => e.Span(),
+            Self::ImportExpression(e) => e.Span(),
+        }
+    }
+}
+
+impl Display for ExpressionEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Identifier(e) => e.String(),
-            Self::IntegerLiteral(e) => e.String(),
-            Self::PrefixExpression(e) => e.String(),
-            Self::InfixExpression(e) => e.String(),
-            Self::Boolean(e) => e.String(),
-            Self::IfExpression(e) => e.String(),
-            Self::FunctionLiteral(e) => e.String(),
-            Self::CallExpression(e) => e.String(),
+            Self::Identifier(e) => Display::fmt(e, f),
+            Self::IntegerLiteral(e) => Display::fmt(e, f),
+            Self::StringLiteral(e) => Display::fmt(e, f),
+            Self::PrefixExpression(e) => Display::fmt(e, f),
+            Self::InfixExpression(e) => Display::fmt(e, f),
+            Self::UpdateExpression(e) => Display::fmt(e, f),
+            Self::Boolean(e) => Display::fmt(e, f),
+            Self::NullLiteral(e) => Display::fmt(e, f),
+            Self::IfExpression(e) => Display::fmt(e, f),
+            Self::MatchExpression(e) => Display::fmt(e, f),
+            Self::FunctionLiteral(e) => Display::fmt(e, f),
+            Self::MacroLiteral(e) => Display::fmt(e, f),
+            Self::CallExpression(e) => Display::fmt(e, f),
+            Self::ArrayLiteral(e) => Display::fmt(e, f),
+            Self::IndexExpression(e) => Display::fmt(e, f),
+            Self::SliceExpression(e) => Display::fmt(e, f),
+            Self::SpreadExpression(e) => Display::fmt(e, f),
+            Self::HashLiteral(e) => Display::fmt(e, f),
+            Self::KeywordArgument(e) => Display::fmt(e, f),
+            Self::TryExpression(e) => Display::fmt(e, f),
+            Self::ImportExpression(e) => Display::fmt(e, f),
         }
     }
 }
@@ -92,7 +287,8 @@ impl Node for ExpressionEnum {
 #[enum_dispatch(ExpressionEnum)]
 pub trait Expression: Node {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
     pub statements: Vec<StatementEnum>,
 }
@@ -106,18 +302,192 @@ impl Node for Program {
         }
     }
 
-    fn String(&self) -> String {
-        self.statements
-            .iter()
-            .map(|stmt| stmt.String())
-            .reduce(|acc, stmt| acc + &stmt)
-            .unwrap_or_default()
+    fn Position(&self) -> Position {
+        if !self.statements.is_empty() {
+            self.statements[0].Position()
+        } else {
+            Position::default()
+        }
+    }
+
+    fn Span(&self) -> Range<usize> {
+        match (self.statements.first(), self.statements.last()) {
+            (Some(first), Some(last)) => first.Span().start..last.Span().end,
+            _ => 0..0,
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.statements.iter().format(""))
+    }
+}
+
+impl Program {
+    /// A hash of the program's syntax tree, which is the same for programs
+    /// that differ only in whitespace and comments, on any machine and in
+    /// any run. It may change between versions of the interpreter.
+    pub fn Fingerprint(&self) -> u64 {
+        let mut hasher = Fnv(FNV_OFFSET);
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The names bound at the top level, in the order they are bound, so a
+    /// host can check a script defines what it needs before running it. A
+    /// name bound twice is listed twice; the last is the one that counts.
+    pub fn Declarations(&self) -> Vec<Declaration> {
+        let mut declarations = vec![];
+        for stmt in &self.statements {
+            match stmt {
+                StatementEnum::Let(l) => declarations.push(Declaration {
+                    name: l.name.value.clone(),
+                    kind: DeclarationKind::Of(&l.value),
+                    span: l.name.Span(),
+                }),
+                StatementEnum::Destructure(d) => {
+                    declarations.extend(d.pattern.Names().iter().map(|name| Declaration {
+                        name: name.value.clone(),
+                        kind: DeclarationKind::Value,
+                        span: name.Span(),
+                    }))
+                }
+                _ => {}
+            }
+        }
+        declarations
+    }
+
+    /// Checks that the program defines `name` as a function taking `arity`
+    /// arguments, for hosts that call into scripts by name. The last binding
+    /// of `name` is the one checked.
+    pub fn RequireFn(&self, name: &str, arity: usize) -> Result<(), EntryPointError> {
+        let declaration = self
+            .Declarations()
+            .into_iter()
+            .rev()
+            .find(|d| d.name == name);
+        match declaration.map(|d| d.kind) {
+            None => Err(EntryPointError::Missing { name: name.into() }),
+            Some(DeclarationKind::Function { parameters }) if parameters.len() == arity => Ok(()),
+            Some(DeclarationKind::Function { parameters }) => Err(EntryPointError::WrongArity {
+                name: name.into(),
+                expected: arity,
+                found: parameters.len(),
+            }),
+            Some(_) => Err(EntryPointError::NotAFunction { name: name.into() }),
+        }
+    }
+}
+
+/// Why a program doesn't have a function `Program::RequireFn` asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryPointError {
+    Missing {
+        name: String,
+    },
+    /// Bound to something other than a function literal.
+    NotAFunction {
+        name: String,
+    },
+    WrongArity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for EntryPointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { name } => write!(f, "the script doesn't define `{}`", name),
+            Self::NotAFunction { name } => {
+                write!(f, "`{}` must be defined as a function", name)
+            }
+            Self::WrongArity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` must take {} arguments, but takes {}",
+                name, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EntryPointError {}
+
+/// A name a program binds at its top level. See `Program::Declarations`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    /// Where the name is in the source, as by `Node::Span`.
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeclarationKind {
+    /// Bound to a function literal, with these parameters.
+    Function { parameters: Vec<String> },
+    /// Bound to a macro literal, with these parameters.
+    Macro { parameters: Vec<String> },
+    /// Bound to anything else, whose value is only known once it runs.
+    Value,
+}
+
+impl DeclarationKind {
+    fn Of(value: &ExpressionEnum) -> Self {
+        let names =
+            |parameters: &[Identifier]| parameters.iter().map(|p| p.value.clone()).collect();
+        match value {
+            ExpressionEnum::FunctionLiteral(f) => Self::Function {
+                parameters: names(&f.parameters),
+            },
+            ExpressionEnum::MacroLiteral(m) => Self::Macro {
+                parameters: names(&m.parameters),
+            },
+            _ => Self::Value,
+        }
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x100_0000_01b3;
+
+/// FNV-1a, which unlike the standard library's hasher is specified, so its
+/// hashes can be stored.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // lengths and enum variants are hashed as 64 bits everywhere
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LetStatement {
     pub token: Token,
+    pub span: Span,
     pub name: Identifier,
     pub value: ExpressionEnum,
 }
@@ -127,16 +497,86 @@ impl Node for LetStatement {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        format!("let {} = {};", self.name.String(), self.value.String())
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for LetStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "let {} = {};", self.name, self.value)
     }
 }
 
 impl Expression for LetStatement {}
 
-#[derive(Debug, Clone)]
+/// `let [a, b] = value;` or `let {a, b} = value;`, binding each name to an
+/// element of an array or to the value under the same (string) key of a hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DestructureStatement {
+    pub token: Token,
+    pub span: Span,
+    pub pattern: DestructurePattern,
+    pub value: ExpressionEnum,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DestructurePattern {
+    Array(Vec<Identifier>),
+    Hash(Vec<Identifier>),
+}
+
+impl DestructurePattern {
+    pub fn Names(&self) -> &[Identifier] {
+        match self {
+            Self::Array(names) | Self::Hash(names) => names,
+        }
+    }
+}
+
+impl Display for DestructurePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.Names().iter().format(", ");
+        match self {
+            Self::Array(_) => write!(f, "[{}]", names),
+            Self::Hash(_) => write!(f, "{{{}}}", names),
+        }
+    }
+}
+
+impl Node for DestructureStatement {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for DestructureStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "let {} = {};", self.pattern, self.value)
+    }
+}
+
+impl Statement for DestructureStatement {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Identifier {
     pub token: Token,
+    pub span: Span,
     pub value: String,
 }
 
@@ -145,14 +585,26 @@ impl Node for Identifier {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        self.token.literal.to_string()
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
     }
 }
 
-#[derive(Debug, Clone)]
+impl Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.token.literal)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReturnStatement {
     pub token: Token,
+    pub span: Span,
     pub returnValue: ExpressionEnum,
 }
 
@@ -161,34 +613,134 @@ impl Node for ReturnStatement {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        format!("return {};", self.returnValue.String())
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for ReturnStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "return {};", self.returnValue)
     }
 }
 
 impl Statement for ReturnStatement {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeferStatement {
+    pub token: Token,
+    pub span: Span,
+    pub expression: ExpressionEnum,
+}
+
+impl Node for DeferStatement {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for DeferStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "defer {};", self.expression)
+    }
+}
+
+impl Statement for DeferStatement {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThrowStatement {
+    pub token: Token,
+    pub span: Span,
+    pub value: ExpressionEnum,
+}
+
+impl Node for ThrowStatement {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for ThrowStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "throw {};", self.value)
+    }
+}
+
+impl Statement for ThrowStatement {}
+
+#[derive(Debug, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExpressionStatement {
+    /// The first token of the expression, which for `(a - b) - c` is the
+    /// parenthesis.
     pub token: Token,
+    pub span: Span,
     pub expression: ExpressionEnum,
 }
 
+// the token only tells how the expression was written, so it is left out
+// like a span
+impl PartialEq for ExpressionStatement {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl Hash for ExpressionStatement {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.expression.hash(state)
+    }
+}
+
 impl Node for ExpressionStatement {
     fn TokenLiteral(&self) -> &str {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        self.expression.String()
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for ExpressionStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.expression, f)
     }
 }
 
 impl Statement for ExpressionStatement {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IntegerLiteral {
     pub token: Token,
+    pub span: Span,
     pub value: i64,
 }
 
@@ -197,16 +749,61 @@ impl Node for IntegerLiteral {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        self.TokenLiteral().to_string()
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for IntegerLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.TokenLiteral())
     }
 }
 
 impl Expression for IntegerLiteral {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringLiteral {
+    /// Its literal is left empty; `value` is the literal.
+    pub token: Token,
+    pub span: Span,
+    /// Escapes already processed. Identical literals in a program share one
+    /// allocation.
+    pub value: Rc<str>,
+}
+
+impl Node for StringLiteral {
+    fn TokenLiteral(&self) -> &str {
+        &self.value
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for StringLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.TokenLiteral())
+    }
+}
+
+impl Expression for StringLiteral {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrefixExpression {
     pub token: Token,
+    pub span: Span,
     pub operator: String,
     pub right: Box<ExpressionEnum>,
 }
@@ -216,16 +813,28 @@ impl Node for PrefixExpression {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        format!("({}{})", self.operator, self.right.String())
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for PrefixExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}{})", self.operator, self.right)
     }
 }
 
 impl Expression for PrefixExpression {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InfixExpression {
     pub token: Token,
+    pub span: Span,
     pub left: Box<ExpressionEnum>,
     pub operator: String,
     pub right: Box<ExpressionEnum>,
@@ -236,85 +845,331 @@ impl Node for InfixExpression {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        format!(
-            "({} {} {})",
-            self.left.String(),
-            self.operator,
-            self.right.String()
-        )
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for InfixExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {} {})", self.left, self.operator, self.right)
     }
 }
 
 impl Expression for InfixExpression {}
 
-#[derive(Debug, Clone)]
-pub struct Boolean {
+/// `++x`, `--x`, `x++` or `x--`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpdateExpression {
     pub token: Token,
-    pub value: bool,
+    pub span: Span,
+    pub operator: String,
+    /// True for `++x`, which evaluates to the new value; `x++` evaluates to the
+    /// old one.
+    pub prefix: bool,
+    pub target: Identifier,
 }
 
-impl Node for Boolean {
+impl Node for UpdateExpression {
     fn TokenLiteral(&self) -> &str {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        self.TokenLiteral().to_string()
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
     }
 }
 
-impl Expression for Boolean {}
+impl Display for UpdateExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.prefix {
+            write!(f, "({}{})", self.operator, self.target)
+        } else {
+            write!(f, "({}{})", self.target, self.operator)
+        }
+    }
+}
 
-#[derive(Debug, Clone)]
-pub struct IfExpression {
+impl Expression for UpdateExpression {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Boolean {
     pub token: Token,
-    pub condition: Box<ExpressionEnum>,
-    pub consequence: Box<BlockStatement>,
-    pub alternative: Option<Box<BlockStatement>>,
+    pub span: Span,
+    pub value: bool,
 }
 
-impl Node for IfExpression {
+impl Node for Boolean {
     fn TokenLiteral(&self) -> &str {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        let mut out = format!(
-            "if{} {}",
-            self.condition.String(),
-            self.consequence.String()
-        );
-        if let Some(alternative) = self.alternative.as_ref() {
-            out += &format!(" else {}", alternative.String());
-        }
-        out
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
     }
 }
 
-impl Expression for IfExpression {}
+impl Display for Boolean {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.TokenLiteral())
+    }
+}
 
-#[derive(Debug, Clone)]
-pub struct BlockStatement {
+impl Expression for Boolean {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NullLiteral {
     pub token: Token,
-    pub statements: Vec<StatementEnum>,
+    pub span: Span,
 }
 
-impl Node for BlockStatement {
+impl Node for NullLiteral {
     fn TokenLiteral(&self) -> &str {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        itertools::join(self.statements.iter().map(|s| s.String()), "")
+    fn Position(&self) -> Position {
+        self.token.position
     }
-}
 
-impl Statement for BlockStatement {}
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
 
-#[derive(Debug, Clone)]
+impl Display for NullLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.TokenLiteral())
+    }
+}
+
+impl Expression for NullLiteral {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IfExpression {
+    pub token: Token,
+    pub span: Span,
+    pub condition: Box<ExpressionEnum>,
+    pub consequence: Box<BlockStatement>,
+    pub alternative: Option<Box<BlockStatement>>,
+}
+
+impl Node for IfExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for IfExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "if{} {}", self.condition, self.consequence)?;
+        if let Some(alternative) = self.alternative.as_ref() {
+            write!(f, " else {}", alternative)?;
+        }
+        Ok(())
+    }
+}
+
+impl Expression for IfExpression {}
+
+/// `try { body } catch (name) { handler }`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TryExpression {
+    pub token: Token,
+    pub span: Span,
+    pub body: Box<BlockStatement>,
+    pub name: Identifier,
+    pub handler: Box<BlockStatement>,
+}
+
+impl Node for TryExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for TryExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "try {} catch ({}) {}",
+            self.body, self.name, self.handler
+        )
+    }
+}
+
+impl Expression for TryExpression {}
+
+/// `import path`, where `path` evaluates to the name of the file to import.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportExpression {
+    pub token: Token,
+    pub span: Span,
+    pub path: Box<ExpressionEnum>,
+}
+
+impl Node for ImportExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for ImportExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "import {}", self.path)
+    }
+}
+
+impl Expression for ImportExpression {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchExpression {
+    pub token: Token,
+    pub span: Span,
+    pub subject: Box<ExpressionEnum>,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Node for MatchExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for MatchExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "match {} {{ {} }}",
+            self.subject,
+            self.arms.iter().format(", ")
+        )
+    }
+}
+
+impl Expression for MatchExpression {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchArm {
+    pub pattern: MatchPattern,
+    /// Either a block or a single expression statement.
+    pub body: Box<StatementEnum>,
+}
+
+impl Display for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.body.as_ref() {
+            StatementEnum::Block(b) => write!(f, "{} => {{ {} }}", self.pattern, b),
+            other => write!(f, "{} => {}", self.pattern, other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchPattern {
+    /// An integer, string, boolean or null literal, compared by value.
+    Literal(ExpressionEnum),
+    /// `_`, which matches anything.
+    Wildcard(Token),
+}
+
+impl Display for MatchPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal(e) => Display::fmt(e, f),
+            Self::Wildcard(t) => f.write_str(&t.literal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockStatement {
+    pub token: Token,
+    pub span: Span,
+    pub statements: Vec<StatementEnum>,
+}
+
+impl Node for BlockStatement {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for BlockStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.statements.iter().format(""))
+    }
+}
+
+impl Statement for BlockStatement {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionLiteral {
     pub token: Token,
+    pub span: Span,
     pub parameters: Vec<Identifier>,
     pub body: Box<BlockStatement>,
 }
@@ -324,19 +1179,69 @@ impl Node for FunctionLiteral {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        format!(
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for FunctionLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
             "{}({}){}",
             self.TokenLiteral(),
-            itertools::join(self.parameters.iter().map(|p| p.String()), ", "),
-            self.body.String()
+            self.parameters.iter().format(", "),
+            self.body
         )
     }
 }
 
-#[derive(Debug, Clone)]
+/// `macro(params) { body }`. Only means something bound by a top-level `let`,
+/// where macro expansion picks it up.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MacroLiteral {
+    pub token: Token,
+    pub span: Span,
+    pub parameters: Vec<Identifier>,
+    pub body: Box<BlockStatement>,
+}
+
+impl Node for MacroLiteral {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for MacroLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}({}){}",
+            self.TokenLiteral(),
+            self.parameters.iter().format(", "),
+            self.body
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CallExpression {
     pub token: Token,
+    pub span: Span,
     pub function: Box<ExpressionEnum>,
     pub arguments: Vec<ExpressionEnum>,
 }
@@ -346,16 +1251,964 @@ impl Node for CallExpression {
         &self.token.literal
     }
 
-    fn String(&self) -> String {
-        format!(
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for CallExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
             "{}({})",
-            self.function.String(),
-            itertools::join(self.arguments.iter().map(|a| a.String()), ", ")
+            self.function,
+            self.arguments.iter().format(", ")
         )
     }
 }
 
 impl Expression for CallExpression {}
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrayLiteral {
+    pub token: Token,
+    pub span: Span,
+    pub elements: Vec<ExpressionEnum>,
+}
+
+impl Node for ArrayLiteral {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for ArrayLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.elements.iter().format(", "))
+    }
+}
+
+impl Expression for ArrayLiteral {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexExpression {
+    pub token: Token,
+    pub span: Span,
+    pub left: Box<ExpressionEnum>,
+    pub index: Box<ExpressionEnum>,
+}
+
+impl Node for IndexExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for IndexExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}[{}])", self.left, self.index)
+    }
+}
+
+impl Expression for IndexExpression {}
+
+/// `left[start:end]`, where either bound may be left out.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SliceExpression {
+    pub token: Token,
+    pub span: Span,
+    pub left: Box<ExpressionEnum>,
+    pub start: Option<Box<ExpressionEnum>>,
+    pub end: Option<Box<ExpressionEnum>>,
+}
+
+impl Node for SliceExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for SliceExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}[", self.left)?;
+        if let Some(start) = &self.start {
+            write!(f, "{}", start)?;
+        }
+        f.write_str(":")?;
+        if let Some(end) = &self.end {
+            write!(f, "{}", end)?;
+        }
+        f.write_str("])")
+    }
+}
+
+impl Expression for SliceExpression {}
+
+/// `...value`, expanding an array into the array literal or argument list it
+/// appears in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpreadExpression {
+    pub token: Token,
+    pub span: Span,
+    pub value: Box<ExpressionEnum>,
+}
+
+impl Node for SpreadExpression {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for SpreadExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "...{}", self.value)
+    }
+}
+
+impl Expression for SpreadExpression {}
+
+/// `name: value` in an argument list, binding the parameter called `name`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeywordArgument {
+    pub token: Token,
+    pub span: Span,
+    pub name: Identifier,
+    pub value: Box<ExpressionEnum>,
+}
+
+impl Node for KeywordArgument {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for KeywordArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)
+    }
+}
+
+impl Expression for KeywordArgument {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashLiteral {
+    pub token: Token,
+    pub span: Span,
+    pub pairs: Vec<(ExpressionEnum, ExpressionEnum)>,
+}
+
+impl Node for HashLiteral {
+    fn TokenLiteral(&self) -> &str {
+        &self.token.literal
+    }
+
+    fn Position(&self) -> Position {
+        self.token.position
+    }
+
+    fn Span(&self) -> Range<usize> {
+        self.span.Range()
+    }
+}
+
+impl Display for HashLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self
+            .pairs
+            .iter()
+            .format_with(", ", |(k, v), f| f(&format_args!("{}: {}", k, v)));
+        write!(f, "{{{}}}", pairs)
+    }
+}
+
+impl Expression for HashLiteral {}
+
+/// A pass that rebuilds a syntax tree, like desugaring or macro expansion.
+/// Each method takes a node and returns what replaces it; by default the node
+/// is rebuilt from its children, folded in turn, with the matching `Rebuild`
+/// function. An override can rebuild the node first and then change it, so
+/// inner nodes are folded before outer ones, or change it before (or instead
+/// of) going further down. As with `Visitor`, identifiers that name
+/// something and the literals of match patterns aren't folded.
+pub trait Fold {
+    fn FoldNode(&mut self, node: NodeEnum) -> NodeEnum {
+        RebuildNode(self, node)
+    }
+
+    fn FoldProgram(&mut self, program: Program) -> Program {
+        RebuildProgram(self, program)
+    }
+
+    fn FoldStatement(&mut self, stmt: StatementEnum) -> StatementEnum {
+        RebuildStatement(self, stmt)
+    }
+
+    /// The blocks of `if`, `try` and functions, and block statements. A
+    /// statement that is a block is folded as a statement first, so a pass
+    /// can replace it with another kind of statement.
+    fn FoldBlock(&mut self, block: BlockStatement) -> BlockStatement {
+        RebuildBlock(self, block)
+    }
+
+    fn FoldExpression(&mut self, exp: ExpressionEnum) -> ExpressionEnum {
+        RebuildExpression(self, exp)
+    }
+}
+
+pub fn RebuildNode<F: Fold + ?Sized>(folder: &mut F, node: NodeEnum) -> NodeEnum {
+    match node {
+        NodeEnum::Program(p) => folder.FoldProgram(p).into(),
+        NodeEnum::Statement(s) => folder.FoldStatement(s).into(),
+        NodeEnum::Expression(e) => folder.FoldExpression(e).into(),
+    }
+}
+
+pub fn RebuildProgram<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: foldStatements(folder, program.statements),
+    }
+}
+
+fn foldStatements<F: Fold + ?Sized>(
+    folder: &mut F,
+    statements: Vec<StatementEnum>,
+) -> Vec<StatementEnum> {
+    statements
+        .into_iter()
+        .map(|s| folder.FoldStatement(s))
+        .collect()
+}
+
+fn foldExpressions<F: Fold + ?Sized>(
+    folder: &mut F,
+    expressions: Vec<ExpressionEnum>,
+) -> Vec<ExpressionEnum> {
+    expressions
+        .into_iter()
+        .map(|e| folder.FoldExpression(e))
+        .collect()
+}
+
+fn foldBoxed<F: Fold + ?Sized>(folder: &mut F, exp: ExpressionEnum) -> Box<ExpressionEnum> {
+    Box::new(folder.FoldExpression(exp))
+}
+
+fn foldBlock<F: Fold + ?Sized>(folder: &mut F, block: BlockStatement) -> Box<BlockStatement> {
+    Box::new(folder.FoldBlock(block))
+}
+
+pub fn RebuildBlock<F: Fold + ?Sized>(folder: &mut F, block: BlockStatement) -> BlockStatement {
+    BlockStatement {
+        token: block.token,
+        span: block.span,
+        statements: foldStatements(folder, block.statements),
+    }
+}
+
+pub fn RebuildStatement<F: Fold + ?Sized>(folder: &mut F, stmt: StatementEnum) -> StatementEnum {
+    match stmt {
+        StatementEnum::Let(l) => LetStatement {
+            value: folder.FoldExpression(l.value),
+            ..l
+        }
+        .into(),
+        StatementEnum::Return(r) => ReturnStatement {
+            returnValue: folder.FoldExpression(r.returnValue),
+            ..r
+        }
+        .into(),
+        StatementEnum::Expression(e) => ExpressionStatement {
+            expression: folder.FoldExpression(e.expression),
+            ..e
+        }
+        .into(),
+        StatementEnum::Block(b) => folder.FoldBlock(b).into(),
+        StatementEnum::Defer(d) => DeferStatement {
+            expression: folder.FoldExpression(d.expression),
+            ..d
+        }
+        .into(),
+        StatementEnum::Destructure(d) => DestructureStatement {
+            value: folder.FoldExpression(d.value),
+            ..d
+        }
+        .into(),
+        StatementEnum::Throw(t) => ThrowStatement {
+            value: folder.FoldExpression(t.value),
+            ..t
+        }
+        .into(),
+    }
+}
+
+pub fn RebuildExpression<F: Fold + ?Sized>(folder: &mut F, exp: ExpressionEnum) -> ExpressionEnum {
+    match exp {
+        ExpressionEnum::Identifier(_)
+        | ExpressionEnum::IntegerLiteral(_)
+        | ExpressionEnum::StringLiteral(_)
+        | ExpressionEnum::UpdateExpression(_)
+        | ExpressionEnum::Boolean(_)
+        | ExpressionEnum::NullLiteral(_) => exp,
+        ExpressionEnum::PrefixExpression(p) => PrefixExpression {
+            right: foldBoxed(folder, *p.right),
+            ..p
+        }
+        .into(),
+        ExpressionEnum::InfixExpression(i) => InfixExpression {
+            left: foldBoxed(folder, *i.left),
+            right: foldBoxed(folder, *i.right),
+            ..i
+        }
+        .into(),
+        ExpressionEnum::IfExpression(i) => IfExpression {
+            token: i.token,
+            span: i.span,
+            condition: foldBoxed(folder, *i.condition),
+            consequence: foldBlock(folder, *i.consequence),
+            alternative: i.alternative.map(|a| foldBlock(folder, *a)),
+        }
+        .into(),
+        ExpressionEnum::MatchExpression(m) => MatchExpression {
+            token: m.token,
+            span: m.span,
+            subject: foldBoxed(folder, *m.subject),
+            arms: m
+                .arms
+                .into_iter()
+                .map(|arm| MatchArm {
+                    pattern: arm.pattern,
+                    body: Box::new(folder.FoldStatement(*arm.body)),
+                })
+                .collect(),
+        }
+        .into(),
+        ExpressionEnum::FunctionLiteral(f) => FunctionLiteral {
+            body: foldBlock(folder, *f.body),
+            ..f
+        }
+        .into(),
+        ExpressionEnum::MacroLiteral(m) => MacroLiteral {
+            body: foldBlock(folder, *m.body),
+            ..m
+        }
+        .into(),
+        ExpressionEnum::CallExpression(c) => CallExpression {
+            token: c.token,
+            span: c.span,
+            function: foldBoxed(folder, *c.function),
+            arguments: foldExpressions(folder, c.arguments),
+        }
+        .into(),
+        ExpressionEnum::ArrayLiteral(a) => ArrayLiteral {
+            token: a.token,
+            span: a.span,
+            elements: foldExpressions(folder, a.elements),
+        }
+        .into(),
+        ExpressionEnum::IndexExpression(i) => IndexExpression {
+            token: i.token,
+            span: i.span,
+            left: foldBoxed(folder, *i.left),
+            index: foldBoxed(folder, *i.index),
+        }
+        .into(),
+        ExpressionEnum::SliceExpression(s) => SliceExpression {
+            token: s.token,
+            span: s.span,
+            left: foldBoxed(folder, *s.left),
+            start: s.start.map(|b| foldBoxed(folder, *b)),
+            end: s.end.map(|b| foldBoxed(folder, *b)),
+        }
+        .into(),
+        ExpressionEnum::SpreadExpression(s) => SpreadExpression {
+            value: foldBoxed(folder, *s.value),
+            ..s
+        }
+        .into(),
+        ExpressionEnum::HashLiteral(h) => HashLiteral {
+            token: h.token,
+            span: h.span,
+            pairs: h
+                .pairs
+                .into_iter()
+                .map(|(k, v)| (folder.FoldExpression(k), folder.FoldExpression(v)))
+                .collect(),
+        }
+        .into(),
+        ExpressionEnum::KeywordArgument(k) => KeywordArgument {
+            value: foldBoxed(folder, *k.value),
+            ..k
+        }
+        .into(),
+        ExpressionEnum::TryExpression(t) => TryExpression {
+            token: t.token,
+            span: t.span,
+            body: foldBlock(folder, *t.body),
+            name: t.name,
+            handler: foldBlock(folder, *t.handler),
+        }
+        .into(),
+        ExpressionEnum::ImportExpression(i) => ImportExpression {
+            path: foldBoxed(folder, *i.path),
+            ..i
+        }
+        .into(),
+    }
+}
+
+/// Rebuilds `node`, passing every expression in it through `modifier`, inner
+/// expressions first. Identifiers that name something (bindings, parameters,
+/// keyword argument names) aren't expressions and are left alone.
+pub fn Modify(
+    node: NodeEnum,
+    modifier: &mut dyn FnMut(ExpressionEnum) -> ExpressionEnum,
+) -> NodeEnum {
+    struct Modifier<'a>(&'a mut dyn FnMut(ExpressionEnum) -> ExpressionEnum);
+
+    impl Fold for Modifier<'_> {
+        fn FoldExpression(&mut self, exp: ExpressionEnum) -> ExpressionEnum {
+            let exp = RebuildExpression(self, exp);
+            (self.0)(exp)
+        }
+    }
+
+    Modifier(modifier).FoldNode(node)
+}
+
+/// Like `Modify`, for statements: rebuilds `node`, passing every statement in
+/// it, those nested in blocks and match arms included, through `modifier`,
+/// inner statements first.
+pub fn ModifyStatements(
+    node: NodeEnum,
+    modifier: &mut dyn FnMut(StatementEnum) -> StatementEnum,
+) -> NodeEnum {
+    struct Modifier<'a>(&'a mut dyn FnMut(StatementEnum) -> StatementEnum);
+
+    impl Fold for Modifier<'_> {
+        fn FoldStatement(&mut self, stmt: StatementEnum) -> StatementEnum {
+            let stmt = RebuildStatement(self, stmt);
+            (self.0)(stmt)
+        }
+    }
+
+    Modifier(modifier).FoldNode(node)
+}
+
+/// A read-only pass over a syntax tree. Each method visits one kind of node
+/// and by default walks its children with the matching `Walk` function;
+/// override the ones an analysis cares about and, to keep going below the
+/// node, call its `Walk` function from the override. Like `Modify`, the walk
+/// only visits identifiers that refer to something (see `VisitIdentifier`),
+/// and not the literals of match patterns.
+pub trait Visitor<'src> {
+    fn VisitNode(&mut self, node: &'src NodeEnum) {
+        WalkNode(self, node)
+    }
+
+    fn VisitProgram(&mut self, program: &'src Program) {
+        WalkProgram(self, program)
+    }
+
+    /// Dispatches to the method for the kind of statement.
+    fn VisitStatement(&mut self, stmt: &'src StatementEnum) {
+        WalkStatement(self, stmt)
+    }
+
+    /// Dispatches to the method for the kind of expression.
+    fn VisitExpression(&mut self, exp: &'src ExpressionEnum) {
+        WalkExpression(self, exp)
+    }
+
+    fn VisitLetStatement(&mut self, stmt: &'src LetStatement) {
+        self.VisitExpression(&stmt.value)
+    }
+
+    fn VisitReturnStatement(&mut self, stmt: &'src ReturnStatement) {
+        self.VisitExpression(&stmt.returnValue)
+    }
+
+    fn VisitExpressionStatement(&mut self, stmt: &'src ExpressionStatement) {
+        self.VisitExpression(&stmt.expression)
+    }
+
+    fn VisitBlockStatement(&mut self, block: &'src BlockStatement) {
+        WalkBlockStatement(self, block)
+    }
+
+    fn VisitDeferStatement(&mut self, stmt: &'src DeferStatement) {
+        self.VisitExpression(&stmt.expression)
+    }
+
+    fn VisitDestructureStatement(&mut self, stmt: &'src DestructureStatement) {
+        self.VisitExpression(&stmt.value)
+    }
+
+    fn VisitThrowStatement(&mut self, stmt: &'src ThrowStatement) {
+        self.VisitExpression(&stmt.value)
+    }
+
+    /// An identifier expression, or the variable `++` or `--` updates. Names
+    /// that bind something (`let` names, parameters, keyword argument names,
+    /// a `try`'s error name) aren't visited.
+    fn VisitIdentifier(&mut self, _ident: &'src Identifier) {}
+
+    fn VisitIntegerLiteral(&mut self, _lit: &'src IntegerLiteral) {}
+
+    fn VisitStringLiteral(&mut self, _lit: &'src StringLiteral) {}
+
+    fn VisitBoolean(&mut self, _lit: &'src Boolean) {}
+
+    fn VisitNullLiteral(&mut self, _lit: &'src NullLiteral) {}
+
+    fn VisitUpdateExpression(&mut self, exp: &'src UpdateExpression) {
+        self.VisitIdentifier(&exp.target)
+    }
+
+    fn VisitPrefixExpression(&mut self, exp: &'src PrefixExpression) {
+        self.VisitExpression(&exp.right)
+    }
+
+    fn VisitInfixExpression(&mut self, exp: &'src InfixExpression) {
+        WalkInfixExpression(self, exp)
+    }
+
+    fn VisitIfExpression(&mut self, exp: &'src IfExpression) {
+        WalkIfExpression(self, exp)
+    }
+
+    fn VisitMatchExpression(&mut self, exp: &'src MatchExpression) {
+        WalkMatchExpression(self, exp)
+    }
+
+    fn VisitFunctionLiteral(&mut self, lit: &'src FunctionLiteral) {
+        self.VisitBlockStatement(&lit.body)
+    }
+
+    fn VisitMacroLiteral(&mut self, lit: &'src MacroLiteral) {
+        self.VisitBlockStatement(&lit.body)
+    }
+
+    fn VisitCallExpression(&mut self, exp: &'src CallExpression) {
+        WalkCallExpression(self, exp)
+    }
+
+    fn VisitArrayLiteral(&mut self, lit: &'src ArrayLiteral) {
+        WalkArrayLiteral(self, lit)
+    }
+
+    fn VisitIndexExpression(&mut self, exp: &'src IndexExpression) {
+        WalkIndexExpression(self, exp)
+    }
+
+    fn VisitSliceExpression(&mut self, exp: &'src SliceExpression) {
+        WalkSliceExpression(self, exp)
+    }
+
+    fn VisitSpreadExpression(&mut self, exp: &'src SpreadExpression) {
+        self.VisitExpression(&exp.value)
+    }
+
+    fn VisitHashLiteral(&mut self, lit: &'src HashLiteral) {
+        WalkHashLiteral(self, lit)
+    }
+
+    fn VisitKeywordArgument(&mut self, arg: &'src KeywordArgument) {
+        self.VisitExpression(&arg.value)
+    }
+
+    fn VisitTryExpression(&mut self, exp: &'src TryExpression) {
+        WalkTryExpression(self, exp)
+    }
+
+    fn VisitImportExpression(&mut self, exp: &'src ImportExpression) {
+        self.VisitExpression(&exp.path)
+    }
+}
+
+// The nodes with a single child are walked by visiting it, so they have no
+// `Walk` function of their own.
+
+pub fn WalkNode<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, node: &'src NodeEnum) {
+    match node {
+        NodeEnum::Program(p) => visitor.VisitProgram(p),
+        NodeEnum::Statement(s) => visitor.VisitStatement(s),
+        NodeEnum::Expression(e) => visitor.VisitExpression(e),
+    }
+}
+
+pub fn WalkProgram<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, program: &'src Program) {
+    for stmt in &program.statements {
+        visitor.VisitStatement(stmt);
+    }
+}
+
+pub fn WalkStatement<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, stmt: &'src StatementEnum) {
+    match stmt {
+        StatementEnum::Let(l) => visitor.VisitLetStatement(l),
+        StatementEnum::Return(r) => visitor.VisitReturnStatement(r),
+        StatementEnum::Expression(e) => visitor.VisitExpressionStatement(e),
+        StatementEnum::Block(b) => visitor.VisitBlockStatement(b),
+        StatementEnum::Defer(d) => visitor.VisitDeferStatement(d),
+        StatementEnum::Destructure(d) => visitor.VisitDestructureStatement(d),
+        StatementEnum::Throw(t) => visitor.VisitThrowStatement(t),
+    }
+}
+
+pub fn WalkExpression<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, exp: &'src ExpressionEnum) {
+    match exp {
+        ExpressionEnum::Identifier(i) => visitor.VisitIdentifier(i),
+        ExpressionEnum::IntegerLiteral(i) => visitor.VisitIntegerLiteral(i),
+        ExpressionEnum::StringLiteral(s) => visitor.VisitStringLiteral(s),
+        ExpressionEnum::PrefixExpression(p) => visitor.VisitPrefixExpression(p),
+        ExpressionEnum::InfixExpression(i) => visitor.VisitInfixExpression(i),
+        ExpressionEnum::UpdateExpression(u) => visitor.VisitUpdateExpression(u),
+        ExpressionEnum::Boolean(b) => visitor.VisitBoolean(b),
+        ExpressionEnum::NullLiteral(n) => visitor.VisitNullLiteral(n),
+        ExpressionEnum::IfExpression(i) => visitor.VisitIfExpression(i),
+        ExpressionEnum::MatchExpression(m) => visitor.VisitMatchExpression(m),
+        ExpressionEnum::FunctionLiteral(f) => visitor.VisitFunctionLiteral(f),
+        ExpressionEnum::MacroLiteral(m) => visitor.VisitMacroLiteral(m),
+        ExpressionEnum::CallExpression(c) => visitor.VisitCallExpression(c),
+        ExpressionEnum::ArrayLiteral(a) => visitor.VisitArrayLiteral(a),
+        ExpressionEnum::IndexExpression(i) => visitor.VisitIndexExpression(i),
+        ExpressionEnum::SliceExpression(s) => visitor.VisitSliceExpression(s),
+        ExpressionEnum::SpreadExpression(s) => visitor.VisitSpreadExpression(s),
+        ExpressionEnum::HashLiteral(h) => visitor.VisitHashLiteral(h),
+        ExpressionEnum::KeywordArgument(k) => visitor.VisitKeywordArgument(k),
+        ExpressionEnum::TryExpression(t) => visitor.VisitTryExpression(t),
+        ExpressionEnum::ImportExpression(i) => visitor.VisitImportExpression(i),
+    }
+}
+
+pub fn WalkBlockStatement<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    block: &'src BlockStatement,
+) {
+    for stmt in &block.statements {
+        visitor.VisitStatement(stmt);
+    }
+}
+
+pub fn WalkInfixExpression<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    exp: &'src InfixExpression,
+) {
+    visitor.VisitExpression(&exp.left);
+    visitor.VisitExpression(&exp.right);
+}
+
+pub fn WalkIfExpression<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, exp: &'src IfExpression) {
+    visitor.VisitExpression(&exp.condition);
+    visitor.VisitBlockStatement(&exp.consequence);
+    if let Some(alternative) = &exp.alternative {
+        visitor.VisitBlockStatement(alternative);
+    }
+}
+
+pub fn WalkMatchExpression<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    exp: &'src MatchExpression,
+) {
+    visitor.VisitExpression(&exp.subject);
+    for arm in &exp.arms {
+        visitor.VisitStatement(&arm.body);
+    }
+}
+
+pub fn WalkCallExpression<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    exp: &'src CallExpression,
+) {
+    visitor.VisitExpression(&exp.function);
+    for arg in &exp.arguments {
+        visitor.VisitExpression(arg);
+    }
+}
+
+pub fn WalkArrayLiteral<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, lit: &'src ArrayLiteral) {
+    for element in &lit.elements {
+        visitor.VisitExpression(element);
+    }
+}
+
+pub fn WalkIndexExpression<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    exp: &'src IndexExpression,
+) {
+    visitor.VisitExpression(&exp.left);
+    visitor.VisitExpression(&exp.index);
+}
+
+pub fn WalkSliceExpression<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    exp: &'src SliceExpression,
+) {
+    visitor.VisitExpression(&exp.left);
+    for bound in exp.start.iter().chain(&exp.end) {
+        visitor.VisitExpression(bound);
+    }
+}
+
+pub fn WalkHashLiteral<'src, V: Visitor<'src> + ?Sized>(visitor: &mut V, lit: &'src HashLiteral) {
+    for (key, value) in &lit.pairs {
+        visitor.VisitExpression(key);
+        visitor.VisitExpression(value);
+    }
+}
+
+pub fn WalkTryExpression<'src, V: Visitor<'src> + ?Sized>(
+    visitor: &mut V,
+    exp: &'src TryExpression,
+) {
+    visitor.VisitBlockStatement(&exp.body);
+    visitor.VisitBlockStatement(&exp.handler);
+}
+
+/// A statement or expression somewhere in a syntax tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeRef<'src> {
+    Statement(&'src StatementEnum),
+    Expression(&'src ExpressionEnum),
+}
+
+/// Like a `NodeRef`, but letting the node be changed.
+#[derive(Debug)]
+pub enum NodeMut<'src> {
+    Statement(&'src mut StatementEnum),
+    Expression(&'src mut ExpressionEnum),
+}
+
+impl Program {
+    /// The statements and expressions of the program, depth first and in
+    /// source order: each node before the ones in it. The statements of a
+    /// block that belongs to an expression, like a function body, come right
+    /// after the expression. The names a node binds or updates, like a
+    /// parameter or the `x` of `x++`, aren't nodes of their own.
+    pub fn Iter(&self) -> Nodes<'_> {
+        Nodes {
+            stack: self
+                .statements
+                .iter()
+                .rev()
+                .map(NodeRef::Statement)
+                .collect(),
+        }
+    }
+
+    /// Calls `f` with every node `Iter` reaches, in the same order. A node's
+    /// children are reached after `f` has changed it, so they are the
+    /// children it has then. There is no mutable iterator, since the node it
+    /// gave out would still be borrowed while it gave out the nodes in it.
+    pub fn ForEachMut(&mut self, f: impl FnMut(NodeMut)) {
+        let program = Program {
+            statements: std::mem::take(&mut self.statements),
+        };
+        *self = EachMut(f).FoldProgram(program);
+    }
+}
+
+/// The iterator `Program::Iter` returns.
+pub struct Nodes<'src> {
+    /// The nodes still to be reached, the next one last.
+    stack: Vec<NodeRef<'src>>,
+}
+
+impl<'src> Iterator for Nodes<'src> {
+    type Item = NodeRef<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children().into_iter().rev());
+        Some(node)
+    }
+}
+
+impl<'src> NodeRef<'src> {
+    /// The nodes right inside this one, in source order.
+    fn children(self) -> Vec<NodeRef<'src>> {
+        let mut children = Children(vec![]);
+        match self {
+            NodeRef::Statement(stmt) => WalkStatement(&mut children, stmt),
+            NodeRef::Expression(exp) => WalkExpression(&mut children, exp),
+        }
+        children.0
+    }
+
+    /// The node with every node inside it replaced by `null`, to compare
+    /// what is left.
+    fn shallow(self) -> NodeEnum {
+        match self {
+            NodeRef::Statement(stmt) => RebuildStatement(&mut Hollow, stmt.clone()).into(),
+            NodeRef::Expression(exp) => RebuildExpression(&mut Hollow, exp.clone()).into(),
+        }
+    }
+}
+
+impl Display for NodeRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Statement(s) => Display::fmt(s, f),
+            Self::Expression(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+/// Collects the nodes right inside the one walked, without going into them.
+struct Children<'src>(Vec<NodeRef<'src>>);
+
+impl<'src> Visitor<'src> for Children<'src> {
+    fn VisitStatement(&mut self, stmt: &'src StatementEnum) {
+        self.0.push(NodeRef::Statement(stmt));
+    }
+
+    fn VisitExpression(&mut self, exp: &'src ExpressionEnum) {
+        self.0.push(NodeRef::Expression(exp));
+    }
+}
+
+struct Hollow;
+
+impl Fold for Hollow {
+    fn FoldStatement(&mut self, _: StatementEnum) -> StatementEnum {
+        ExpressionEnum::Null().into()
+    }
+
+    fn FoldExpression(&mut self, _: ExpressionEnum) -> ExpressionEnum {
+        ExpressionEnum::Null()
+    }
+}
+
+struct EachMut<F>(F);
+
+impl<F: FnMut(NodeMut)> Fold for EachMut<F> {
+    fn FoldStatement(&mut self, mut stmt: StatementEnum) -> StatementEnum {
+        (self.0)(NodeMut::Statement(&mut stmt));
+        RebuildStatement(self, stmt)
+    }
+
+    fn FoldExpression(&mut self, mut exp: ExpressionEnum) -> ExpressionEnum {
+        (self.0)(NodeMut::Expression(&mut exp));
+        RebuildExpression(self, exp)
+    }
+}
+
+/// Where two syntax trees first differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference<'src> {
+    /// The indexes of the nodes leading to the one that differs, each among
+    /// the nodes right inside the one before (see `Program::Iter`), starting
+    /// from the statements of the program.
+    pub path: Vec<usize>,
+    /// The node in each tree, or `None` if the other program has one more
+    /// statement there.
+    pub left: Option<NodeRef<'src>>,
+    pub right: Option<NodeRef<'src>>,
+}
+
+impl Display for Difference<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let node = |node: &Option<NodeRef>| match node {
+            Some(node) => format!("`{}`", node),
+            None => "nothing".into(),
+        };
+        write!(
+            f,
+            "at {:?}: {} != {}",
+            self.path,
+            node(&self.left),
+            node(&self.right)
+        )
+    }
+}
+
+/// The first node, depth first, where `a` and `b` differ other than in where
+/// their code is, or `None` if they are equal. A node that differs only in
+/// the nodes inside it isn't where the trees differ; the first of those is.
+/// One with a different number of them, like a call with another argument,
+/// is.
+pub fn Diff<'src>(a: &'src Program, b: &'src Program) -> Option<Difference<'src>> {
+    let nodes = |p: &'src Program| p.statements.iter().map(NodeRef::Statement).collect();
+    diffNodes(nodes(a), nodes(b), &mut vec![])
+}
+
+fn diffNodes<'src>(
+    a: Vec<NodeRef<'src>>,
+    b: Vec<NodeRef<'src>>,
+    path: &mut Vec<usize>,
+) -> Option<Difference<'src>> {
+    for i in 0..a.len().max(b.len()) {
+        path.push(i);
+        let (left, right) = (a.get(i).copied(), b.get(i).copied());
+        let difference = match (left, right) {
+            (Some(l), Some(r)) if l == r => None,
+            (Some(l), Some(r)) if l.shallow() == r.shallow() => {
+                diffNodes(l.children(), r.children(), path)
+            }
+            _ => Some(Difference {
+                path: path.clone(),
+                left,
+                right,
+            }),
+        };
+        if difference.is_some() {
+            return difference;
+        }
+        path.pop();
+    }
+    None
+}
+
+mod build;
+
 #[cfg(test)]
 mod tests;
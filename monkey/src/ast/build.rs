@@ -0,0 +1,217 @@
+//! Constructors for building a syntax tree without source, like a tool
+//! generating code does. They make the nodes the parser makes for the code
+//! the tree prints as, except that the tokens are at line 0 (see
+//! `Position`) and the nodes span nothing (see `Node::Span`).
+
+use super::*;
+use crate::lexer::Lexer;
+use crate::token::TokenKind;
+
+/// The token `code` is lexed as.
+fn lexed(code: &str) -> Token {
+    let mut token = Lexer::New(code).NextToken();
+    token.position = Position::default();
+    token
+}
+
+fn identifier(name: &str) -> Identifier {
+    Identifier {
+        token: Token::New(TokenKind::IDENT, name),
+        span: Span::default(),
+        value: name.into(),
+    }
+}
+
+fn block(statements: Vec<StatementEnum>) -> Box<BlockStatement> {
+    Box::new(BlockStatement {
+        token: Token::New(TokenKind::LBRACE, "{"),
+        span: Span::default(),
+        statements,
+    })
+}
+
+/// The token the code of `exp` starts with, which an expression statement
+/// is at.
+fn firstToken(exp: &ExpressionEnum) -> Token {
+    match exp {
+        ExpressionEnum::InfixExpression(i) => firstToken(&i.left),
+        ExpressionEnum::CallExpression(c) => firstToken(&c.function),
+        ExpressionEnum::IndexExpression(i) => firstToken(&i.left),
+        ExpressionEnum::SliceExpression(s) => firstToken(&s.left),
+        ExpressionEnum::UpdateExpression(u) if !u.prefix => u.target.token.clone(),
+        ExpressionEnum::StringLiteral(s) => Token::New(TokenKind::STRING, &*s.value),
+        ExpressionEnum::KeywordArgument(k) => k.name.token.clone(),
+        other => lexed(other.TokenLiteral()),
+    }
+}
+
+impl ExpressionEnum {
+    pub fn Ident(name: &str) -> Self {
+        identifier(name).into()
+    }
+
+    pub fn Int(value: i64) -> Self {
+        IntegerLiteral {
+            token: Token::New(TokenKind::INT, value.to_string()),
+            span: Span::default(),
+            value,
+        }
+        .into()
+    }
+
+    pub fn Str(value: &str) -> Self {
+        StringLiteral {
+            token: Token::New(TokenKind::STRING, ""),
+            span: Span::default(),
+            value: value.into(),
+        }
+        .into()
+    }
+
+    pub fn Bool(value: bool) -> Self {
+        let token = match value {
+            true => Token::New(TokenKind::TRUE, "true"),
+            false => Token::New(TokenKind::FALSE, "false"),
+        };
+        Boolean {
+            token,
+            span: Span::default(),
+            value,
+        }
+        .into()
+    }
+
+    pub fn Null() -> Self {
+        NullLiteral {
+            token: Token::New(TokenKind::NULL, "null"),
+            span: Span::default(),
+        }
+        .into()
+    }
+
+    /// `operator` applied to `right`, like `-x`.
+    pub fn Prefix(operator: &str, right: Self) -> Self {
+        PrefixExpression {
+            token: lexed(operator),
+            span: Span::default(),
+            operator: operator.into(),
+            right: Box::new(right),
+        }
+        .into()
+    }
+
+    /// `operator` applied to `left` and `right`, like `x + 1`.
+    pub fn Infix(left: Self, operator: &str, right: Self) -> Self {
+        InfixExpression {
+            token: lexed(operator),
+            span: Span::default(),
+            left: Box::new(left),
+            operator: operator.into(),
+            right: Box::new(right),
+        }
+        .into()
+    }
+
+    pub fn Call(function: Self, arguments: Vec<Self>) -> Self {
+        CallExpression {
+            token: Token::New(TokenKind::LPAREN, "("),
+            span: Span::default(),
+            function: Box::new(function),
+            arguments,
+        }
+        .into()
+    }
+
+    pub fn Index(left: Self, index: Self) -> Self {
+        IndexExpression {
+            token: Token::New(TokenKind::LBRACKET, "["),
+            span: Span::default(),
+            left: Box::new(left),
+            index: Box::new(index),
+        }
+        .into()
+    }
+
+    pub fn Array(elements: Vec<Self>) -> Self {
+        ArrayLiteral {
+            token: Token::New(TokenKind::LBRACKET, "["),
+            span: Span::default(),
+            elements,
+        }
+        .into()
+    }
+
+    pub fn Hash(pairs: Vec<(Self, Self)>) -> Self {
+        HashLiteral {
+            token: Token::New(TokenKind::LBRACE, "{"),
+            span: Span::default(),
+            pairs,
+        }
+        .into()
+    }
+
+    pub fn Function(parameters: &[&str], body: Vec<StatementEnum>) -> Self {
+        FunctionLiteral {
+            token: Token::New(TokenKind::FUNCTION, "fn"),
+            span: Span::default(),
+            parameters: parameters.iter().map(|p| identifier(p)).collect(),
+            body: block(body),
+        }
+        .into()
+    }
+
+    pub fn If(
+        condition: Self,
+        consequence: Vec<StatementEnum>,
+        alternative: Option<Vec<StatementEnum>>,
+    ) -> Self {
+        IfExpression {
+            token: Token::New(TokenKind::IF, "if"),
+            span: Span::default(),
+            condition: Box::new(condition),
+            consequence: block(consequence),
+            alternative: alternative.map(block),
+        }
+        .into()
+    }
+}
+
+/// The expression as a statement of its own, at the first token of the
+/// expression's code without the parentheses `String` adds.
+impl From<ExpressionEnum> for StatementEnum {
+    fn from(expression: ExpressionEnum) -> Self {
+        ExpressionStatement {
+            token: firstToken(&expression),
+            span: Span::default(),
+            expression,
+        }
+        .into()
+    }
+}
+
+impl LetStatement {
+    pub fn New(name: &str, value: ExpressionEnum) -> Self {
+        Self {
+            token: Token::New(TokenKind::LET, "let"),
+            span: Span::default(),
+            name: identifier(name),
+            value,
+        }
+    }
+}
+
+impl ReturnStatement {
+    pub fn New(returnValue: ExpressionEnum) -> Self {
+        Self {
+            token: Token::New(TokenKind::RETURN, "return"),
+            span: Span::default(),
+            returnValue,
+        }
+    }
+}
+
+impl Program {
+    pub fn New(statements: Vec<StatementEnum>) -> Self {
+        Self { statements }
+    }
+}
@@ -5,22 +5,16 @@ use crate::token::TokenKind;
 fn String() {
     let program = Program {
         statements: vec![LetStatement {
-            token: Token {
-                kind: TokenKind::LET,
-                literal: "let".into(),
-            },
+            token: Token::New(TokenKind::LET, "let"),
+            span: Span::default(),
             name: Identifier {
-                token: Token {
-                    kind: TokenKind::IDENT,
-                    literal: "myVar".into(),
-                },
+                token: Token::New(TokenKind::IDENT, "myVar"),
+                span: Span::default(),
                 value: "myVar".into(),
             },
             value: Identifier {
-                token: Token {
-                    kind: TokenKind::IDENT,
-                    literal: "anotherVar".into(),
-                },
+                token: Token::New(TokenKind::IDENT, "anotherVar"),
+                span: Span::default(),
                 value: "anotherVar".into(),
             }
             .into(),
@@ -30,3 +24,465 @@ fn String() {
 
     assert_eq!(program.String(), "let myVar = anotherVar;")
 }
+
+#[test]
+fn ModifyReachesEveryExpression() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let tests = vec![
+        ("1", "2"),
+        ("1 + 1", "(2 + 2)"),
+        ("-1", "(-2)"),
+        ("[1, 1][1:1]", "([2, 2][2:2])"),
+        ("{1: 1}[1]", "({2: 2}[2])"),
+        ("f(1, ...x, a: 1)", "f(2, ...x, a: 2)"),
+        ("if (1) { 1 } else { 1 }", "if2 2 else 2"),
+        (
+            "match (1) { 5 => 1, _ => { 1 } }",
+            "match 2 { 5 => 2, _ => { 2 } }",
+        ),
+        ("fn(x) { return 1; }", "fn(x)return 2;"),
+        ("let x = 1; defer 1; throw 1;", "let x = 2;defer 2;throw 2;"),
+        ("let [a, b] = 1;", "let [a, b] = 2;"),
+        ("try { 1 } catch (e) { 1 }", "try 2 catch (e) 2"),
+        ("import 1", "import 2"),
+    ];
+    for (input, expected) in tests {
+        let program = Parser::New(Lexer::New(input)).ParseProgram();
+        let modified = Modify(program.into(), &mut |exp| match exp {
+            ExpressionEnum::IntegerLiteral(i) if i.value == 1 => IntegerLiteral {
+                token: Token::New(TokenKind::INT, "2"),
+                span: Span::default(),
+                value: 2,
+            }
+            .into(),
+            exp => exp,
+        });
+        assert_eq!(modified.String(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn ModifyStatementsReachesEveryStatement() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let tests = vec![
+        ("throw 1;", "return 1;"),
+        ("if (x) { throw 1; }", "ifx return 1;"),
+        ("fn() { if (x) { throw 1; } }", "fn()ifx return 1;"),
+        (
+            "match (x) { _ => { throw 1; } }",
+            "match x { _ => { return 1; } }",
+        ),
+        (
+            "try { throw 1; } catch (e) { throw e; }",
+            "try return 1; catch (e) return e;",
+        ),
+        ("f(fn() { throw 1; })", "f(fn()return 1;)"),
+    ];
+    for (input, expected) in tests {
+        let program = Parser::New(Lexer::New(input)).ParseProgram();
+        let modified = ModifyStatements(program.into(), &mut |stmt| match stmt {
+            StatementEnum::Throw(t) => ReturnStatement {
+                token: Token::New(TokenKind::RETURN, "return"),
+                span: t.span,
+                returnValue: t.value,
+            }
+            .into(),
+            stmt => stmt,
+        });
+        assert_eq!(modified.String(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn FoldCanStopDescending() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// Doubles the integers outside functions.
+    struct Double;
+
+    impl Fold for Double {
+        fn FoldExpression(&mut self, exp: ExpressionEnum) -> ExpressionEnum {
+            match exp {
+                ExpressionEnum::FunctionLiteral(_) => exp,
+                ExpressionEnum::IntegerLiteral(i) => IntegerLiteral {
+                    token: Token::New(TokenKind::INT, (i.value * 2).to_string()),
+                    span: Span::default(),
+                    value: i.value * 2,
+                }
+                .into(),
+                exp => RebuildExpression(self, exp),
+            }
+        }
+    }
+
+    let program = Parser::New(Lexer::New("let f = fn() { 1 }; f() + [2, -3]")).ParseProgram();
+    assert_eq!(
+        Double.FoldProgram(program).String(),
+        "let f = fn()1;(f() + [4, (-6)])"
+    );
+}
+
+#[test]
+fn VisitorReachesEveryExpression() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// The identifiers referenced, and those outside functions.
+    #[derive(Default)]
+    struct Names<'src> {
+        all: Vec<&'src str>,
+        outside: Vec<&'src str>,
+        functions: usize,
+    }
+
+    impl<'src> Visitor<'src> for Names<'src> {
+        fn VisitIdentifier(&mut self, ident: &'src Identifier) {
+            self.all.push(&ident.value);
+            if self.functions == 0 {
+                self.outside.push(&ident.value);
+            }
+        }
+
+        fn VisitFunctionLiteral(&mut self, lit: &'src FunctionLiteral) {
+            self.functions += 1;
+            self.VisitBlockStatement(&lit.body);
+            self.functions -= 1;
+        }
+    }
+
+    let tests = vec![
+        ("a", vec!["a"]),
+        ("a + -b", vec!["a", "b"]),
+        ("a++", vec!["a"]),
+        ("[a, b][c:d]", vec!["a", "b", "c", "d"]),
+        ("{a: b}[c]", vec!["a", "b", "c"]),
+        ("f(a, ...b, k: c)", vec!["f", "a", "b", "c"]),
+        ("if (a) { b } else { c }", vec!["a", "b", "c"]),
+        ("match (a) { 1 => b, _ => { c } }", vec!["a", "b", "c"]),
+        ("let x = a; defer b; throw c;", vec!["a", "b", "c"]),
+        ("let [x, y] = a; return b;", vec!["a", "b"]),
+        ("try { a } catch (e) { e }", vec!["a", "e"]),
+        ("import a", vec!["a"]),
+        ("macro(x) { quote(x) }", vec!["quote", "x"]),
+    ];
+    for (input, expected) in tests {
+        let program: NodeEnum = Parser::New(Lexer::New(input)).ParseProgram().into();
+        let mut names = Names::default();
+        names.VisitNode(&program);
+        assert_eq!(names.all, expected, "{}", input);
+    }
+
+    let program = Parser::New(Lexer::New("let f = fn(x) { x + y }; f(z)")).ParseProgram();
+    let mut names = Names::default();
+    names.VisitProgram(&program);
+    assert_eq!(names.all, vec!["x", "y", "f", "z"]);
+    assert_eq!(names.outside, vec!["f", "z"]);
+}
+
+#[test]
+fn IterIsDepthFirst() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let program = Parser::New(Lexer::New("let a = f(1 + b); if (a) { c }")).ParseProgram();
+    let nodes: Vec<String> = program
+        .Iter()
+        .map(|node| match node {
+            NodeRef::Statement(s) => format!("stmt {}", s),
+            NodeRef::Expression(e) => e.String(),
+        })
+        .collect();
+    assert_eq!(
+        nodes,
+        vec![
+            "stmt let a = f((1 + b));",
+            "f((1 + b))",
+            "f",
+            "(1 + b)",
+            "1",
+            "b",
+            "stmt ifa c",
+            "ifa c",
+            "a",
+            "stmt c",
+            "c",
+        ]
+    );
+
+    let identifiers: Vec<&str> = program
+        .Iter()
+        .filter_map(|node| match node {
+            NodeRef::Expression(ExpressionEnum::Identifier(i)) => Some(i.value.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(identifiers, vec!["f", "b", "a", "c"]);
+}
+
+#[test]
+fn ForEachMutReachesEveryNode() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let mut program =
+        Parser::New(Lexer::New("let a = fn(x) { x + y }; throw a(b);")).ParseProgram();
+    program.ForEachMut(|node| match node {
+        NodeMut::Expression(ExpressionEnum::Identifier(i)) => {
+            i.value = i.value.to_uppercase();
+            i.token.literal = i.value.clone();
+        }
+        NodeMut::Statement(stmt) => {
+            if let StatementEnum::Throw(t) = stmt {
+                let value = t.value.clone();
+                *stmt = ReturnStatement::New(value).into();
+            }
+        }
+        _ => {}
+    });
+    assert_eq!(program.String(), "let a = fn(x)(X + Y);return A(B);");
+}
+
+#[test]
+fn Fingerprint() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let fingerprint = |input| Parser::New(Lexer::New(input)).ParseProgram().Fingerprint();
+    let program = "let add = fn(a, b) { a + b }; add(1, \"two\");";
+    assert_eq!(
+        fingerprint(program),
+        fingerprint("let add=fn(a,b){a+b};\n\n  add( 1 , \"two\" ) ;")
+    );
+    for changed in [
+        "let add = fn(a, b) { a - b }; add(1, \"two\");",
+        "let add = fn(a, c) { a + c }; add(1, \"two\");",
+        "let add = fn(a, b) { a + b }; add(1, \"2\");",
+        "let add = fn(a, b) { a + b }; add(1, 2);",
+        "let add = fn(a, b) { (a + b) }; add(1, \"two\"); 0",
+    ] {
+        assert_ne!(fingerprint(program), fingerprint(changed), "{}", changed);
+    }
+    // stored fingerprints stay valid
+    assert_eq!(fingerprint("1 + x"), 0xf85c_e9c8_1235_6eb9);
+}
+
+#[test]
+fn Declarations() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let input = r#"
+        let main = fn(args, env) { helper(args) };
+        let helper = |x| x;
+        let unless = macro(cond, body) { quote(1) };
+        let version = "1.0";
+        let [a, b] = [1, 2];
+        puts(version);
+        let f = fn() { let inner = 1; inner };
+        let version = 2;
+    "#;
+    let program = Parser::New(Lexer::New(input)).ParseProgram();
+    let function = |parameters: &[&str]| DeclarationKind::Function {
+        parameters: parameters.iter().map(|p| p.to_string()).collect(),
+    };
+    let declarations: Vec<(String, DeclarationKind)> = program
+        .Declarations()
+        .into_iter()
+        .map(|d| (d.name, d.kind))
+        .collect();
+    assert_eq!(
+        declarations,
+        vec![
+            ("main".into(), function(&["args", "env"])),
+            ("helper".into(), function(&["x"])),
+            (
+                "unless".into(),
+                DeclarationKind::Macro {
+                    parameters: vec!["cond".into(), "body".into()]
+                }
+            ),
+            ("version".into(), DeclarationKind::Value),
+            ("a".into(), DeclarationKind::Value),
+            ("b".into(), DeclarationKind::Value),
+            ("f".into(), function(&[])),
+            ("version".into(), DeclarationKind::Value),
+        ]
+    );
+
+    let declarations = program.Declarations();
+    for d in &declarations {
+        assert_eq!(&input[d.span.clone()], d.name);
+    }
+    // each binding of a name is where that binding is
+    assert!(declarations[3].span.end < declarations[7].span.start);
+}
+
+#[test]
+fn RequireFn() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let input = r#"
+        let handle = fn(request) { request };
+        let init = fn() { 1 };
+        let config = {"a": 1};
+        let init = fn(options) { options };
+    "#;
+    let program = Parser::New(Lexer::New(input)).ParseProgram();
+    assert_eq!(program.RequireFn("handle", 1), Ok(()));
+    assert_eq!(program.RequireFn("init", 1), Ok(()));
+    let errors = vec![
+        (
+            program.RequireFn("handle", 2),
+            "`handle` must take 2 arguments, but takes 1",
+        ),
+        (
+            program.RequireFn("config", 0),
+            "`config` must be defined as a function",
+        ),
+        (
+            program.RequireFn("main", 0),
+            "the script doesn't define `main`",
+        ),
+    ];
+    for (result, message) in errors {
+        assert_eq!(result.unwrap_err().to_string(), message);
+    }
+    assert_eq!(
+        program.RequireFn("init", 0),
+        Err(EntryPointError::WrongArity {
+            name: "init".into(),
+            expected: 0,
+            found: 1
+        })
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn Serializes() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let program = Parser::New(Lexer::New("let x = -a + \"s\";")).ParseProgram();
+    let json = serde_json::to_value(&program).unwrap();
+    let stmt = &json["statements"][0]["Let"];
+    assert_eq!(stmt["name"]["value"], "x");
+    assert_eq!(stmt["token"]["kind"], "LET");
+    assert_eq!(
+        stmt["value"]["InfixExpression"]["operator"],
+        serde_json::json!("+")
+    );
+    let left = &stmt["value"]["InfixExpression"]["left"]["PrefixExpression"];
+    assert_eq!(left["right"]["Identifier"]["value"], "a");
+    assert_eq!(
+        left["token"]["position"],
+        serde_json::json!({"line": 1, "column": 9, "offset": 8})
+    );
+    assert_eq!(
+        stmt["value"]["InfixExpression"]["right"]["StringLiteral"]["value"],
+        "s"
+    );
+}
+
+#[test]
+fn Builds() {
+    use crate::environment::Environment;
+    use crate::evaluator::Eval;
+    use crate::lexer::Lexer;
+    use crate::object::Object;
+    use crate::parser::Parser;
+
+    type E = ExpressionEnum;
+    let program = Program::New(vec![
+        LetStatement::New(
+            "add",
+            E::Function(
+                &["a", "b"],
+                vec![E::Infix(E::Ident("a"), "+", E::Ident("b")).into()],
+            ),
+        )
+        .into(),
+        E::Call(
+            E::Ident("add"),
+            vec![E::Int(1), E::Infix(E::Int(2), "*", E::Int(3))],
+        )
+        .into(),
+    ]);
+    let source = "let add = fn(a, b) { a + b }; add(1, 2 * 3)";
+    let parsed = Parser::New(Lexer::New(source)).ParseProgram();
+    assert_eq!(program.String(), parsed.String());
+    assert_eq!(program.Fingerprint(), parsed.Fingerprint());
+    let result = Eval(program.into(), &Environment::New()).unwrap();
+    assert_eq!(result.Inspect(), "7");
+
+    let exp = E::If(
+        E::Prefix("!", E::Bool(false)),
+        vec![ReturnStatement::New(E::Index(E::Array(vec![E::Str("s")]), E::Int(0))).into()],
+        Some(vec![E::Hash(vec![(E::Null(), E::Int(1))]).into()]),
+    );
+    assert_eq!(exp.String(), "if(!false) return ([s][0]); else {null: 1}");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn Deserializes() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let source = r#"let f = fn(x, ...rest) { match (x) { 1 => "one", _ => try { rest[0:1] } catch (e) { e } } }; f(k: 2)"#;
+    let program = Parser::New(Lexer::New(source)).ParseProgram();
+    let json = serde_json::to_string(&program).unwrap();
+    let loaded: Program = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.String(), program.String());
+    assert_eq!(loaded.Fingerprint(), program.Fingerprint());
+    assert_eq!(
+        loaded.statements[0].Position(),
+        program.statements[0].Position()
+    );
+}
+
+#[test]
+fn Diff() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let parse = |input| Parser::New(Lexer::New(input)).ParseProgram();
+    let program = parse("let a = 1 + b; f(x, y)");
+    assert_eq!(program, parse("let a =\n  1 + b;\nf(x, y);"));
+    assert_eq!(
+        super::Diff(&program, &parse("let a = (1 + b); f(x, y)")),
+        None
+    );
+    // a statement may start with a parenthesis or not
+    for (a, b) in [("(a - b) - c", "a - b - c"), ("(-i)", "-i")] {
+        assert_eq!(super::Diff(&parse(a), &parse(b)), None, "{}", a);
+        assert_eq!(parse(a).Fingerprint(), parse(b).Fingerprint(), "{}", a);
+    }
+
+    let tests = vec![
+        (
+            "let a = 1 - b; f(x, y)",
+            "at [0, 0]: `(1 + b)` != `(1 - b)`",
+        ),
+        ("let a = 1 + c; f(x, y)", "at [0, 0, 1]: `b` != `c`"),
+        (
+            "let c = 1 + d; f(x, y)",
+            "at [0]: `let a = (1 + b);` != `let c = (1 + d);`",
+        ),
+        ("let a = 1 + b; f(x)", "at [1, 0]: `f(x, y)` != `f(x)`"),
+        ("let a = 1 + b;", "at [1]: `f(x, y)` != nothing"),
+        ("let a = 1 + b; f(x, y); g", "at [2]: nothing != `g`"),
+    ];
+    for (input, expected) in tests {
+        let other = parse(input);
+        assert_ne!(program, other, "{}", input);
+        let difference = super::Diff(&program, &other).map(|d| d.to_string());
+        assert_eq!(difference.as_deref(), Some(expected), "{}", input);
+    }
+}
@@ -0,0 +1,495 @@
+//! Functions available in every environment without being bound. A binding
+//! with the same name shadows the builtin.
+
+use std::cmp::Ordering;
+use std::convert::TryInto;
+
+use crate::capability::Capability;
+use crate::evaluator::checkBooleanIn;
+use crate::evaluator::isError;
+use crate::evaluator::typeName;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Array;
+use crate::object::Boolean;
+use crate::object::Builtin;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::ObjectKind;
+use crate::object::Secret;
+use crate::object::SecureEq;
+use crate::object::Str;
+use crate::object::Truthy;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+mod convert;
+mod env;
+mod format;
+mod fs;
+mod hash;
+#[cfg(feature = "i18n")]
+mod i18n;
+mod math;
+mod random;
+#[cfg(feature = "regex")]
+mod regex;
+mod text;
+mod time;
+
+/// The name of every builtin, those behind a feature too, in the order
+/// compiled code numbers them. New ones go last, so saved bytecode keeps
+/// calling the same ones.
+pub(crate) const NAMES: &[&str] = &[
+    "len",
+    "puts",
+    "getenv",
+    "setenv",
+    "chars",
+    "bytes",
+    "graphemes",
+    "read_file",
+    "write_file",
+    "append_file",
+    "list_dir",
+    "format",
+    "printf",
+    "map",
+    "filter",
+    "reduce",
+    "sort",
+    "abs",
+    "min",
+    "max",
+    "pow",
+    "sqrt",
+    "floor",
+    "ceil",
+    "rand",
+    "rand_int",
+    "rand_seed",
+    "now",
+    "clock",
+    "sleep",
+    "keys",
+    "values",
+    "has",
+    "delete",
+    "merge",
+    "type",
+    "int",
+    "str",
+    "bool",
+    "format_number",
+    "upper",
+    "lower",
+    "re_match",
+    "re_find_all",
+    "re_replace",
+    "assert",
+    "exit",
+    "secret",
+    "secure_compare",
+];
+
+pub(crate) fn Lookup(name: &str) -> Option<Builtin> {
+    let func = match name {
+        "len" => len,
+        "puts" => puts,
+        "getenv" => env::getenv,
+        "setenv" => env::setenv,
+        "chars" => text::chars,
+        "bytes" => text::bytes,
+        "graphemes" => text::graphemes,
+        "read_file" => fs::readFile,
+        "write_file" => fs::writeFile,
+        "append_file" => fs::appendFile,
+        "list_dir" => fs::listDir,
+        "format" => format::format,
+        "printf" => format::printf,
+        "map" => map,
+        "filter" => filter,
+        "reduce" => reduce,
+        "sort" => sort,
+        "abs" => math::abs,
+        "min" => math::min,
+        "max" => math::max,
+        "pow" => math::pow,
+        "sqrt" => math::sqrt,
+        "floor" => math::floor,
+        "ceil" => math::ceil,
+        "rand" => random::rand,
+        "rand_int" => random::randInt,
+        "rand_seed" => random::randSeed,
+        "now" => time::now,
+        "clock" => time::clock,
+        "sleep" => time::sleep,
+        "keys" => hash::keys,
+        "values" => hash::values,
+        "has" => hash::has,
+        "delete" => hash::delete,
+        "merge" => hash::merge,
+        "type" => convert::r#type,
+        "int" => convert::int,
+        "str" => convert::str,
+        "bool" => convert::bool,
+        #[cfg(feature = "i18n")]
+        "format_number" => i18n::formatNumber,
+        #[cfg(feature = "i18n")]
+        "upper" => i18n::upper,
+        #[cfg(feature = "i18n")]
+        "lower" => i18n::lower,
+        #[cfg(feature = "regex")]
+        "re_match" => regex::reMatch,
+        #[cfg(feature = "regex")]
+        "re_find_all" => regex::reFindAll,
+        #[cfg(feature = "regex")]
+        "re_replace" => regex::reReplace,
+        "assert" => assert,
+        "exit" => exit,
+        "secret" => secret,
+        "secure_compare" => secureCompare,
+        _ => return None,
+    };
+    Some(Builtin::new(name, func))
+}
+
+/// The capability a builtin needs from its host, if it needs one.
+pub(crate) fn CapabilityOf(name: &str) -> Option<Capability> {
+    match name {
+        "puts" | "printf" => Some(Capability::Io),
+        "read_file" | "write_file" | "append_file" | "list_dir" => Some(Capability::Fs),
+        "now" | "clock" | "sleep" => Some(Capability::Time),
+        "rand" | "rand_int" => Some(Capability::Random),
+        "getenv" | "setenv" => Some(Capability::Env),
+        _ => None,
+    }
+}
+
+/// The builtin `name` as scripts run by `runtime` see it: those needing `Fs`
+/// only exist while it is allowed.
+pub(crate) fn Available(name: &str, runtime: &Runtime) -> Option<Builtin> {
+    match CapabilityOf(name) {
+        Some(Capability::Fs) if !runtime.IsAllowed(Capability::Fs) => None,
+        _ => Lookup(name),
+    }
+}
+
+/// Rejects an argument of the wrong type for the builtin `name`.
+pub(crate) fn unsupportedArgument(name: &str, arg: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::TypeError,
+        format!("argument to `{}` not supported, got {:?}", name, arg.Type()),
+    )
+    .with("type", typeName(arg))
+    .into()
+}
+
+/// The string argument `arg` of the builtin `name`.
+fn string(name: &str, arg: ObjectEnum) -> Result<String, ObjectEnum> {
+    match arg {
+        ObjectEnum::String(s) => Ok(s.value),
+        other => Err(unsupportedArgument(name, &other)),
+    }
+}
+
+fn len(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    if args.len() != 1 {
+        return wrongArgumentCount(1, args.len());
+    }
+    match &args[0] {
+        ObjectEnum::String(s) => Integer {
+            value: s.value.chars().count() as i64,
+        }
+        .into(),
+        ObjectEnum::Array(a) => Integer {
+            value: a.elements.len() as i64,
+        }
+        .into(),
+        ObjectEnum::HostArray(a) => Integer {
+            value: a.Len() as i64,
+        }
+        .into(),
+        ObjectEnum::Hash(h) => Integer {
+            value: h.pairs.len() as i64,
+        }
+        .into(),
+        other => unsupportedArgument("len", other),
+    }
+}
+
+fn puts(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let platform = runtime.Platform();
+    for arg in args {
+        platform.Write(&format!("{}\n", arg.Inspect()));
+    }
+    NULL
+}
+
+/// The elements of the array `arg` to the builtin `name`, or the error for it
+/// not being one.
+fn elements(name: &str, arg: ObjectEnum) -> Result<Vec<ObjectEnum>, ObjectEnum> {
+    match arg {
+        ObjectEnum::Array(a) => Ok(a.elements),
+        ObjectEnum::HostArray(a) => Ok(a.ToArray().elements),
+        other => Err(unsupportedArgument(name, &other)),
+    }
+}
+
+/// `map(arr, f)`: `f` applied to each element.
+fn map(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [arr, f]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let elements = match elements("map", arr) {
+        Ok(elements) => elements,
+        Err(err) => return err,
+    };
+    let mut mapped = Vec::with_capacity(elements.len());
+    for element in elements {
+        let result = runtime.Call(f.clone(), vec![element]);
+        if isError(&result) {
+            return result;
+        }
+        mapped.push(result);
+    }
+    Array { elements: mapped }.into()
+}
+
+/// `filter(arr, pred)`: the elements `pred` returns true for.
+fn filter(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [arr, pred]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let elements = match elements("filter", arr) {
+        Ok(elements) => elements,
+        Err(err) => return err,
+    };
+    let mut kept = vec![];
+    for element in elements {
+        let result = runtime.Call(pred.clone(), vec![element.clone()]);
+        if isError(&result) {
+            return result;
+        }
+        if let Some(err) = checkBooleanIn(&result, "result of the `filter` predicate", runtime) {
+            return err;
+        }
+        if result.isTruthy() {
+            kept.push(element);
+        }
+    }
+    Array { elements: kept }.into()
+}
+
+/// `reduce(arr, init, f)`: `f(f(f(init, arr[0]), arr[1]), ...)`.
+fn reduce(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [arr, init, f]: [ObjectEnum; 3] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(3, args.len()),
+    };
+    let elements = match elements("reduce", arr) {
+        Ok(elements) => elements,
+        Err(err) => return err,
+    };
+    let mut acc = init;
+    for element in elements {
+        acc = runtime.Call(f.clone(), vec![acc, element]);
+        if isError(&acc) {
+            return acc;
+        }
+    }
+    acc
+}
+
+/// `sort(arr)`: the elements of `arr`, which must all be integers or all be
+/// strings, in ascending order. `sort(arr, cmp)`: the elements ordered by
+/// `cmp(a, b)`, which returns a negative integer if `a` goes first, a
+/// positive one if `b` does, and 0 if either can. Equal elements keep
+/// their order.
+fn sort(runtime: &Runtime, mut args: Vec<ObjectEnum>) -> ObjectEnum {
+    if args.len() != 1 && args.len() != 2 {
+        return wrongArgumentCount(2, args.len());
+    }
+    let comparator = args.get(1).cloned();
+    let mut elements = match elements("sort", args.swap_remove(0)) {
+        Ok(elements) => elements,
+        Err(err) => return err,
+    };
+    let comparator = match comparator {
+        Some(comparator) => comparator,
+        None => {
+            if let Some(err) = checkSortable(&elements) {
+                return err;
+            }
+            // everything compares with everything else, and `Compare` is a
+            // total order
+            elements.sort_by(|a, b| a.Compare(b).unwrap());
+            return Array { elements }.into();
+        }
+    };
+    let compare = |a: &ObjectEnum, b: &ObjectEnum| {
+        let result = runtime.Call(comparator.clone(), vec![a.clone(), b.clone()]);
+        match result {
+            ObjectEnum::Integer(i) => Ok(i.value.cmp(&0)),
+            err if isError(&err) => Err(err),
+            other => Err(Error::new(
+                ErrorKind::TypeError,
+                format!(
+                    "result of the `sort` comparator must be an INTEGER, got {:?}",
+                    other.Type()
+                ),
+            )
+            .with("type", typeName(&other))
+            .into()),
+        }
+    };
+    match mergeSort(elements, &compare) {
+        Ok(elements) => Array { elements }.into(),
+        Err(err) => err,
+    }
+}
+
+fn checkSortable(elements: &[ObjectEnum]) -> Option<ObjectEnum> {
+    let first = elements.first()?;
+    if !matches!(first.Type(), ObjectKind::INTEGER | ObjectKind::STRING) {
+        return Some(unsupportedArgument("sort", first));
+    }
+    let other = elements.iter().find(|e| e.Type() != first.Type())?;
+    Some(
+        Error::new(
+            ErrorKind::TypeError,
+            format!(
+                "elements passed to `sort` can't be compared: {:?} and {:?}",
+                first.Type(),
+                other.Type()
+            ),
+        )
+        .into(),
+    )
+}
+
+/// A stable sort that stops at the first comparison that fails. Unlike the
+/// standard library's, it copes with a comparator that isn't a total order,
+/// which a script's may not be.
+fn mergeSort(
+    mut elements: Vec<ObjectEnum>,
+    compare: &dyn Fn(&ObjectEnum, &ObjectEnum) -> Result<Ordering, ObjectEnum>,
+) -> Result<Vec<ObjectEnum>, ObjectEnum> {
+    if elements.len() < 2 {
+        return Ok(elements);
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let mut left = mergeSort(elements, compare)?.into_iter().peekable();
+    let mut right = mergeSort(right, compare)?.into_iter().peekable();
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if compare(a, b)? == Ordering::Greater {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn assert(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    Assert(args, None, runtime)
+}
+
+/// `assert(cond, message)`: an `AssertionError` unless `cond` is true, with
+/// `message` if it is given. The evaluator passes the `source` of `cond` when
+/// it sees the call, to be shown in the error.
+pub(crate) fn Assert(args: Vec<ObjectEnum>, source: Option<&str>, runtime: &Runtime) -> ObjectEnum {
+    if args.is_empty() || args.len() > 2 {
+        return wrongArgumentCount(2, args.len());
+    }
+    if let Some(err) = checkBooleanIn(&args[0], "condition of `assert`", runtime) {
+        return err;
+    }
+    if args[0].isTruthy() {
+        return NULL;
+    }
+    let mut message = String::from("assertion failed");
+    if let Some(source) = source {
+        message += ": ";
+        message += source;
+    }
+    if let Some(given) = args.get(1) {
+        message += ": ";
+        message += &given.Inspect();
+    }
+    let mut err = Error::new(ErrorKind::AssertionError, message);
+    if let Some(source) = source {
+        err = err.with(
+            "expression",
+            Str {
+                value: source.into(),
+            },
+        );
+    }
+    err.into()
+}
+
+/// `exit(code)`: stops the script with the status `code`, 0 if it is left
+/// out. The script ends as if by an error that can't be caught, which the
+/// host tells apart with `ObjectEnum::ExitCode`; the process goes on.
+fn exit(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let code = match &args[..] {
+        [] => 0,
+        [ObjectEnum::Integer(code)] => code.value,
+        [other] => return unsupportedArgument("exit", other),
+        _ => return wrongArgumentCount(1, args.len()),
+    };
+    if !(0..=255).contains(&code) {
+        return Error::new(
+            ErrorKind::ArgumentError,
+            format!(
+                "status passed to `exit` must be from 0 to 255, got {}",
+                code
+            ),
+        )
+        .into();
+    }
+    Error::new(ErrorKind::Exit, format!("exited with status {}", code))
+        .with("code", Integer { value: code })
+        .into()
+}
+
+/// `secret(value)`: `value` wrapped so it can't be seen.
+fn secret(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [value]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    Secret::New(value).into()
+}
+
+/// `secure_compare(a, b)`: whether two strings, either of which may be a
+/// secret, are equal, taking the same time wherever they differ.
+fn secureCompare(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    if args.len() != 2 {
+        return wrongArgumentCount(2, args.len());
+    }
+    let mut strings = vec![];
+    for arg in &args {
+        let value = match arg {
+            ObjectEnum::Secret(secret) => secret.Value(),
+            arg => arg,
+        };
+        match value {
+            ObjectEnum::String(s) => strings.push(s.value.as_bytes()),
+            // the type of what a secret holds is a secret too
+            _ => return unsupportedArgument("secure_compare", arg),
+        }
+    }
+    Boolean {
+        value: SecureEq(strings[0], strings[1]),
+    }
+    .into()
+}
@@ -0,0 +1,83 @@
+//! Builtins for asking what type a value is and turning it into another.
+
+use std::convert::TryInto;
+
+use crate::evaluator::typeName;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Boolean;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::object::Truthy;
+use crate::runtime::Runtime;
+
+fn single(args: Vec<ObjectEnum>) -> Result<ObjectEnum, ObjectEnum> {
+    let [arg]: [ObjectEnum; 1] = args
+        .try_into()
+        .map_err(|args: Vec<_>| wrongArgumentCount(1, args.len()))?;
+    Ok(arg)
+}
+
+fn cantConvert(value: &ObjectEnum, to: &str) -> ObjectEnum {
+    let message = match value {
+        ObjectEnum::String(s) => format!("can't convert {:?} to {}", s.value, to),
+        value => format!("can't convert {:?} to {}", value.Type(), to),
+    };
+    Error::new(ErrorKind::TypeError, message)
+        .with("type", typeName(value))
+        .into()
+}
+
+/// `type(x)`: the name of the type of `x`, like `"INTEGER"`.
+pub(super) fn r#type(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match single(args) {
+        Ok(value) => typeName(&value).into(),
+        Err(err) => err,
+    }
+}
+
+/// `int(x)`: the integer a string spells out in decimal, or 1 or 0 for a
+/// boolean.
+pub(super) fn int(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let value = match single(args) {
+        Ok(value) => value,
+        Err(err) => return err,
+    };
+    let converted = match &value {
+        ObjectEnum::Integer(i) => Some(i.value),
+        ObjectEnum::Boolean(b) => Some(i64::from(b.value)),
+        ObjectEnum::String(s) => s.value.parse().ok(),
+        _ => None,
+    };
+    match converted {
+        Some(value) => Integer { value }.into(),
+        None => cantConvert(&value, "INTEGER"),
+    }
+}
+
+/// `str(x)`: `x` as `puts` would print it.
+pub(super) fn str(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match single(args) {
+        Ok(ObjectEnum::String(s)) => s.into(),
+        Ok(value) => Str {
+            value: value.Inspect(),
+        }
+        .into(),
+        Err(err) => err,
+    }
+}
+
+/// `bool(x)`: whether `if (x)` takes its consequence, which is true for
+/// everything but `false` and `null`, like `!!x`.
+pub(super) fn bool(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match single(args) {
+        Ok(value) => Boolean {
+            value: value.isTruthy(),
+        }
+        .into(),
+        Err(err) => err,
+    }
+}
@@ -0,0 +1,54 @@
+//! Builtins for the environment variables of the host, which need the `Env`
+//! capability.
+
+use std::convert::TryInto;
+
+use super::string;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+/// `getenv(name)`: the value of the environment variable `name`, or null if
+/// it isn't set.
+pub(super) fn getenv(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [name]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    let name = match string("getenv", name) {
+        Ok(name) => name,
+        Err(err) => return err,
+    };
+    match runtime.Platform().Var(&name) {
+        Some(value) => Str { value }.into(),
+        None => NULL,
+    }
+}
+
+/// `setenv(name, value)`: sets the environment variable `name` to `value`.
+pub(super) fn setenv(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [name, value]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let (name, value) = match (string("setenv", name), string("setenv", value)) {
+        (Ok(name), Ok(value)) => (name, value),
+        (Err(err), _) | (_, Err(err)) => return err,
+    };
+    if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+        return Error::new(
+            ErrorKind::ArgumentError,
+            format!(
+                "can't set the environment variable {:?} to {:?}",
+                name, value
+            ),
+        )
+        .into();
+    }
+    runtime.Platform().SetVar(&name, &value);
+    NULL
+}
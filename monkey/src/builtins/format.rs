@@ -0,0 +1,160 @@
+//! `format` and `printf`, which fill in a format string like `"x=%d"`. A
+//! directive is `%`, then any of the flags `-` (align left) and `0` (pad
+//! integers with zeros), then a minimum width, then `d` for an integer, `x`
+//! for an integer in hexadecimal, `s` for any value as `puts` prints it, or
+//! a second `%` for a `%` sign. Widths go up to `MAX_WIDTH`.
+
+use super::unsupportedArgument;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+/// The widest a directive can pad to, so one call can't take all the memory
+/// there is.
+const MAX_WIDTH: usize = 4096;
+
+fn formatError(name: &str, message: String) -> ObjectEnum {
+    Error::new(ErrorKind::ArgumentError, format!("`{}`: {}", name, message)).into()
+}
+
+/// The format string, the first of `args`, with the rest of `args` filled in.
+fn render(name: &str, args: Vec<ObjectEnum>) -> Result<String, ObjectEnum> {
+    let mut args = args.into_iter();
+    let template = match args.next() {
+        Some(ObjectEnum::String(s)) => s.value,
+        Some(other) => return Err(unsupportedArgument(name, &other)),
+        None => return Err(formatError(name, "missing the format string".into())),
+    };
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    let mut used = 0;
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let (mut left, mut zeros) = (false, false);
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => left = true,
+                '0' => zeros = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let mut width = 0usize;
+        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            width = width.saturating_mul(10).saturating_add(digit as usize);
+            chars.next();
+        }
+        if width > MAX_WIDTH {
+            return Err(formatError(
+                name,
+                format!("width {} is more than {}", width, MAX_WIDTH),
+            ));
+        }
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
+            None => {
+                return Err(formatError(
+                    name,
+                    "format string ends in a directive".into(),
+                ))
+            }
+        };
+        if conversion == '%' {
+            out.push('%');
+            continue;
+        }
+        let arg = match args.next() {
+            Some(arg) => arg,
+            None => {
+                return Err(formatError(
+                    name,
+                    format!("directive {} has no argument", used + 1),
+                ))
+            }
+        };
+        used += 1;
+        let text = match (conversion, &arg) {
+            ('d', ObjectEnum::Integer(i)) => i.value.to_string(),
+            ('x', ObjectEnum::Integer(i)) if i.value < 0 => {
+                format!("-{:x}", i.value.unsigned_abs())
+            }
+            ('x', ObjectEnum::Integer(i)) => format!("{:x}", i.value),
+            ('s', arg) => arg.Inspect(),
+            ('d' | 'x', arg) => {
+                return Err(Error::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "`{}`: %{} needs an INTEGER, got {:?}",
+                        name,
+                        conversion,
+                        arg.Type()
+                    ),
+                )
+                .into())
+            }
+            (conversion, _) => {
+                return Err(formatError(
+                    name,
+                    format!("unknown directive %{}", conversion),
+                ))
+            }
+        };
+        pad(&mut out, &text, width, left, zeros && conversion != 's');
+    }
+    let extra = args.count();
+    if extra > 0 {
+        return Err(formatError(
+            name,
+            format!("{} more arguments than directives", extra),
+        ));
+    }
+    Ok(out)
+}
+
+/// Appends `text` to `out`, padded to `width` characters.
+fn pad(out: &mut String, text: &str, width: usize, left: bool, zeros: bool) {
+    let padding = width.saturating_sub(text.chars().count());
+    if left {
+        out.push_str(text);
+        out.extend(std::iter::repeat_n(' ', padding));
+    } else if zeros {
+        // the zeros go after the sign
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text),
+        };
+        out.push_str(sign);
+        out.extend(std::iter::repeat_n('0', padding));
+        out.push_str(digits);
+    } else {
+        out.extend(std::iter::repeat_n(' ', padding));
+        out.push_str(text);
+    }
+}
+
+/// `format(template, ...)`: `template` with the values filled in.
+pub(super) fn format(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match render("format", args) {
+        Ok(value) => Str { value }.into(),
+        Err(err) => err,
+    }
+}
+
+/// `printf(template, ...)`: writes what `format` would return, without
+/// adding a newline.
+pub(super) fn printf(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match render("printf", args) {
+        Ok(text) => {
+            runtime.Platform().Write(&text);
+            NULL
+        }
+        Err(err) => err,
+    }
+}
@@ -0,0 +1,101 @@
+//! Builtins for files and directories, which need the `Fs` capability. They
+//! go through the runtime's platform, like everything else, so a host can
+//! give scripts a file system of its own.
+
+use std::convert::TryInto;
+use std::io;
+use std::path::Path;
+
+use super::string;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Array;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+fn ioError(doing: &str, path: &str, err: io::Error) -> ObjectEnum {
+    Error::new(
+        ErrorKind::IoError,
+        format!("can't {} {}: {}", doing, path, err),
+    )
+    .with("path", Str { value: path.into() })
+    .into()
+}
+
+/// The path and contents arguments of `write_file` and `append_file`.
+fn pathAndContents(name: &str, args: Vec<ObjectEnum>) -> Result<(String, String), ObjectEnum> {
+    let [path, contents]: [ObjectEnum; 2] = args
+        .try_into()
+        .map_err(|args: Vec<_>| wrongArgumentCount(2, args.len()))?;
+    Ok((string(name, path)?, string(name, contents)?))
+}
+
+/// `read_file(path)`: the contents of the file at `path`, which must be
+/// UTF-8.
+pub(super) fn readFile(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [path]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    let path = match string("read_file", path) {
+        Ok(path) => path,
+        Err(err) => return err,
+    };
+    match runtime.Platform().ReadFile(Path::new(&path)) {
+        Ok(value) => Str { value }.into(),
+        Err(err) => ioError("read", &path, err),
+    }
+}
+
+/// `write_file(path, contents)`: creates or replaces the file at `path`.
+pub(super) fn writeFile(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let (path, contents) = match pathAndContents("write_file", args) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    match runtime.Platform().WriteFile(Path::new(&path), &contents) {
+        Ok(()) => NULL,
+        Err(err) => ioError("write", &path, err),
+    }
+}
+
+/// `append_file(path, contents)`: adds to the end of the file at `path`,
+/// creating it if need be.
+pub(super) fn appendFile(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let (path, contents) = match pathAndContents("append_file", args) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    match runtime.Platform().AppendFile(Path::new(&path), &contents) {
+        Ok(()) => NULL,
+        Err(err) => ioError("append to", &path, err),
+    }
+}
+
+/// `list_dir(path)`: the names of what is in the directory at `path`, sorted.
+pub(super) fn listDir(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [path]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    let path = match string("list_dir", path) {
+        Ok(path) => path,
+        Err(err) => return err,
+    };
+    match runtime.Platform().ReadDir(Path::new(&path)) {
+        Ok(mut names) => {
+            names.sort();
+            Array {
+                elements: names
+                    .into_iter()
+                    .map(|value| Str { value }.into())
+                    .collect(),
+            }
+            .into()
+        }
+        Err(err) => ioError("list", &path, err),
+    }
+}
@@ -0,0 +1,99 @@
+//! Builtins for working with hashes. Hashes are values like everything else,
+//! so `delete` and `merge` return new hashes rather than changing theirs.
+
+use std::convert::TryInto;
+
+use super::unsupportedArgument;
+use crate::evaluator::unusableHashKey;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Array;
+use crate::object::Boolean;
+use crate::object::Hash;
+use crate::object::HashKey;
+use crate::object::ObjectEnum;
+use crate::runtime::Runtime;
+
+fn hash(name: &str, arg: ObjectEnum) -> Result<Hash, ObjectEnum> {
+    match arg {
+        ObjectEnum::Hash(h) => Ok(h),
+        other => Err(unsupportedArgument(name, &other)),
+    }
+}
+
+/// The hash and key arguments of the builtin `name`.
+fn hashAndKey(name: &str, args: Vec<ObjectEnum>) -> Result<(Hash, HashKey), ObjectEnum> {
+    let [h, key]: [ObjectEnum; 2] = args
+        .try_into()
+        .map_err(|args: Vec<_>| wrongArgumentCount(2, args.len()))?;
+    let h = hash(name, h)?;
+    match key.HashKey() {
+        Some(key) => Ok((h, key)),
+        None => Err(unusableHashKey(&key)),
+    }
+}
+
+/// `keys(h)`: the keys of `h`, in the order it is printed in.
+pub(super) fn keys(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [h]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    match hash("keys", h) {
+        Ok(h) => Array {
+            elements: h.pairs.keys().map(HashKey::ToObject).collect(),
+        }
+        .into(),
+        Err(err) => err,
+    }
+}
+
+/// `values(h)`: the values of `h`, in the same order as `keys(h)`.
+pub(super) fn values(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [h]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    match hash("values", h) {
+        Ok(h) => Array {
+            elements: h.pairs.into_values().collect(),
+        }
+        .into(),
+        Err(err) => err,
+    }
+}
+
+/// `has(h, k)`: whether `h` has the key `k`, even if its value is null.
+pub(super) fn has(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match hashAndKey("has", args) {
+        Ok((h, key)) => Boolean {
+            value: h.pairs.contains_key(&key),
+        }
+        .into(),
+        Err(err) => err,
+    }
+}
+
+/// `delete(h, k)`: `h` without the key `k`, which it needn't have.
+pub(super) fn delete(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match hashAndKey("delete", args) {
+        Ok((mut h, key)) => {
+            h.pairs.remove(&key);
+            h.into()
+        }
+        Err(err) => err,
+    }
+}
+
+/// `merge(a, b)`: the pairs of both, with `b`'s value for keys in both.
+pub(super) fn merge(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [a, b]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let (mut a, b) = match (hash("merge", a), hash("merge", b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(err), _) | (_, Err(err)) => return err,
+    };
+    a.pairs.extend(b.pairs);
+    a.into()
+}
@@ -0,0 +1,201 @@
+//! Formatting for text meant for people, which depends on where they are.
+//! Locales are named by language, like `de` or `en-GB`; only the language is
+//! looked at, and only the few languages in `LOCALES` are known.
+
+use std::convert::TryInto;
+
+use super::string;
+use super::unsupportedArgument;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::HashKey;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::runtime::Runtime;
+
+struct Locale {
+    language: &'static str,
+    thousandsSep: &'static str,
+    decimalSep: &'static str,
+}
+
+const fn locale(
+    language: &'static str,
+    thousandsSep: &'static str,
+    decimalSep: &'static str,
+) -> Locale {
+    Locale {
+        language,
+        thousandsSep,
+        decimalSep,
+    }
+}
+
+const LOCALES: &[Locale] = &[
+    locale("en", ",", "."),
+    locale("de", ".", ","),
+    locale("es", ".", ","),
+    locale("fr", "\u{202f}", ","),
+    locale("it", ".", ","),
+    locale("nl", ".", ","),
+    locale("pt", ".", ","),
+    locale("tr", ".", ","),
+    locale("az", ".", ","),
+    locale("ja", ",", "."),
+    locale("zh", ",", "."),
+];
+
+fn findLocale(name: &str) -> Result<&'static Locale, ObjectEnum> {
+    let language = name.split(['-', '_']).next().unwrap_or_default();
+    LOCALES
+        .iter()
+        .find(|l| l.language.eq_ignore_ascii_case(language))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::ArgumentError,
+                format!("unknown locale `{}`", name),
+            )
+            .into()
+        })
+}
+
+/// `format_number(x, options)`: the integer `x` with its digits grouped in
+/// thousands. The options, all optional, are `locale` (`en` by default),
+/// `decimals`, the number of zeros after the decimal separator (0 by
+/// default), and `thousands_sep`, replacing the locale's separator.
+pub(super) fn formatNumber(_: &Runtime, mut args: Vec<ObjectEnum>) -> ObjectEnum {
+    if args.is_empty() || args.len() > 2 {
+        return wrongArgumentCount(2, args.len());
+    }
+    let options = if args.len() == 2 { args.pop() } else { None };
+    let value = match args.remove(0) {
+        ObjectEnum::Integer(i) => i.value,
+        other => return unsupportedArgument("format_number", &other),
+    };
+    let mut locale = &LOCALES[0];
+    let mut decimals = 0;
+    let mut thousandsSep = None;
+    match options {
+        None => {}
+        Some(ObjectEnum::Hash(options)) => {
+            for (key, value) in options.pairs {
+                let key = match key {
+                    HashKey::String(key) => key,
+                    key => return unknownOption(&key.ToObject().Inspect()),
+                };
+                let result = match (key.as_str(), value) {
+                    ("locale", ObjectEnum::String(s)) => findLocale(&s.value).map(|l| locale = l),
+                    ("decimals", ObjectEnum::Integer(i)) if i.value >= 0 => {
+                        decimals = i.value as usize;
+                        Ok(())
+                    }
+                    ("thousands_sep", ObjectEnum::String(s)) => {
+                        thousandsSep = Some(s.value);
+                        Ok(())
+                    }
+                    ("locale" | "decimals" | "thousands_sep", value) => Err(Error::new(
+                        ErrorKind::ArgumentError,
+                        format!(
+                            "option `{}` to `format_number` can't be {}",
+                            key,
+                            value.Inspect()
+                        ),
+                    )
+                    .into()),
+                    (key, _) => Err(unknownOption(key)),
+                };
+                if let Err(err) = result {
+                    return err;
+                }
+            }
+        }
+        Some(other) => return unsupportedArgument("format_number", &other),
+    }
+
+    let digits = value.unsigned_abs().to_string();
+    let sep = thousandsSep.as_deref().unwrap_or(locale.thousandsSep);
+    let mut formatted = String::new();
+    if value < 0 {
+        formatted.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push_str(sep);
+        }
+        formatted.push(digit);
+    }
+    if decimals > 0 {
+        formatted.push_str(locale.decimalSep);
+        formatted.push_str(&"0".repeat(decimals));
+    }
+    Str { value: formatted }.into()
+}
+
+fn unknownOption(key: &str) -> ObjectEnum {
+    Error::new(
+        ErrorKind::ArgumentError,
+        format!("unknown option `{}` to `format_number`", key),
+    )
+    .into()
+}
+
+/// The string and optional locale arguments of `upper` and `lower`, the
+/// locale's language if it was given.
+fn caseArgs(name: &str, args: Vec<ObjectEnum>) -> Result<(String, &'static str), ObjectEnum> {
+    let (s, locale) = match args.len() {
+        1 => {
+            let [s]: [ObjectEnum; 1] = args.try_into().unwrap();
+            (s, None)
+        }
+        2 => {
+            let [s, locale]: [ObjectEnum; 2] = args.try_into().unwrap();
+            (s, Some(locale))
+        }
+        n => return Err(wrongArgumentCount(2, n)),
+    };
+    let language = match locale {
+        Some(locale) => findLocale(&string(name, locale)?)?.language,
+        None => "",
+    };
+    Ok((string(name, s)?, language))
+}
+
+/// Turkish and Azerbaijani have a dotted and a dotless i, each with its own
+/// capital.
+fn dottedI(language: &str) -> bool {
+    language == "tr" || language == "az"
+}
+
+/// `upper(s, locale)`: `s` in capitals, following the rules of `locale` if
+/// it is given.
+pub(super) fn upper(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let (s, language) = match caseArgs("upper", args) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    let value = if dottedI(language) {
+        s.replace('i', "\u{130}").to_uppercase()
+    } else {
+        s.to_uppercase()
+    };
+    Str { value }.into()
+}
+
+/// `lower(s, locale)`: `s` without capitals, following the rules of `locale`
+/// if it is given.
+pub(super) fn lower(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let (s, language) = match caseArgs("lower", args) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    let value = if dottedI(language) {
+        s.replace('I', "\u{131}")
+            .replace('\u{130}', "i")
+            .to_lowercase()
+    } else {
+        s.to_lowercase()
+    };
+    Str { value }.into()
+}
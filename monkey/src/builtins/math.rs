@@ -0,0 +1,149 @@
+//! Arithmetic builtins. Monkey only has integers, so `sqrt` rounds down and
+//! `floor` and `ceil` give back the integer they are given.
+
+use std::cmp::Ordering;
+use std::convert::TryInto;
+
+use super::unsupportedArgument;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::runtime::Runtime;
+
+/// The single integer argument of the builtin `name`.
+fn integer(name: &str, args: Vec<ObjectEnum>) -> Result<i64, ObjectEnum> {
+    let [arg]: [ObjectEnum; 1] = args
+        .try_into()
+        .map_err(|args: Vec<_>| wrongArgumentCount(1, args.len()))?;
+    match arg {
+        ObjectEnum::Integer(i) => Ok(i.value),
+        other => Err(unsupportedArgument(name, &other)),
+    }
+}
+
+fn outOfRange(name: &str) -> ObjectEnum {
+    Error::new(
+        ErrorKind::ArgumentError,
+        format!("result of `{}` is out of range", name),
+    )
+    .into()
+}
+
+fn int(value: i64) -> ObjectEnum {
+    Integer { value }.into()
+}
+
+pub(super) fn abs(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    match integer("abs", args) {
+        Ok(value) => value.checked_abs().map_or_else(|| outOfRange("abs"), int),
+        Err(err) => err,
+    }
+}
+
+/// The integer square root, rounded down.
+pub(super) fn sqrt(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let value = match integer("sqrt", args) {
+        Ok(value) => value,
+        Err(err) => return err,
+    };
+    if value < 0 {
+        return Error::new(
+            ErrorKind::ArgumentError,
+            "argument to `sqrt` must not be negative",
+        )
+        .into();
+    }
+    // the float estimate can be off by one either way for large values
+    let mut root = (value as f64).sqrt() as i64;
+    while root.checked_mul(root).is_none_or(|square| square > value) {
+        root -= 1;
+    }
+    while (root + 1)
+        .checked_mul(root + 1)
+        .is_some_and(|square| square <= value)
+    {
+        root += 1;
+    }
+    int(root)
+}
+
+pub(super) fn floor(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    integer("floor", args).map_or_else(|err| err, int)
+}
+
+pub(super) fn ceil(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    integer("ceil", args).map_or_else(|err| err, int)
+}
+
+pub(super) fn pow(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let (base, exponent) = match args.as_slice() {
+        [ObjectEnum::Integer(base), ObjectEnum::Integer(exponent)] => (base.value, exponent.value),
+        [ObjectEnum::Integer(_), other] | [other, _] => return unsupportedArgument("pow", other),
+        _ => return wrongArgumentCount(2, args.len()),
+    };
+    if exponent < 0 {
+        return Error::new(
+            ErrorKind::ArgumentError,
+            "exponent of `pow` must not be negative",
+        )
+        .into();
+    }
+    exponent
+        .try_into()
+        .ok()
+        .and_then(|exponent| base.checked_pow(exponent))
+        .map_or_else(|| outOfRange("pow"), int)
+}
+
+pub(super) fn min(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    extreme("min", args, Ordering::Less)
+}
+
+pub(super) fn max(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    extreme("max", args, Ordering::Greater)
+}
+
+/// The first of `args`, or of the elements of a single array argument, that
+/// no other one is `wanted` of. They must all be comparable with each other.
+fn extreme(name: &str, args: Vec<ObjectEnum>, wanted: Ordering) -> ObjectEnum {
+    let single: Result<[ObjectEnum; 1], _> = args.try_into();
+    let values = match single {
+        Ok([ObjectEnum::Array(a)]) => a.elements,
+        Ok([ObjectEnum::HostArray(a)]) => a.ToArray().elements,
+        Ok([arg]) => vec![arg],
+        Err(args) => args,
+    };
+    let mut values = values.into_iter();
+    let mut best = match values.next() {
+        Some(first) => first,
+        None => {
+            return Error::new(
+                ErrorKind::ArgumentError,
+                format!("`{}` needs at least one value", name),
+            )
+            .into()
+        }
+    };
+    for value in values {
+        match value.Compare(&best) {
+            Some(ordering) if ordering == wanted => best = value,
+            Some(_) => {}
+            None => {
+                return Error::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "arguments to `{}` can't be compared: {:?} and {:?}",
+                        name,
+                        best.Type(),
+                        value.Type()
+                    ),
+                )
+                .into()
+            }
+        }
+    }
+    best
+}
@@ -0,0 +1,83 @@
+//! Random numbers, from the runtime's generator, so a host or a script that
+//! seeds it gets the same numbers on every run. The generator isn't fit for
+//! cryptography.
+
+use std::convert::TryInto;
+
+use super::unsupportedArgument;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::ObjectEnum;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+/// `rand()`: an integer from 0 to the largest integer.
+pub(super) fn rand(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    if !args.is_empty() {
+        return wrongArgumentCount(0, args.len());
+    }
+    Integer {
+        value: (runtime.NextRandom() >> 1) as i64,
+    }
+    .into()
+}
+
+/// `rand_int(a, b)`: an integer from `a` to `b`, both included, each as
+/// likely as the others.
+pub(super) fn randInt(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [a, b]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let (a, b) = match (a, b) {
+        (ObjectEnum::Integer(a), ObjectEnum::Integer(b)) => (a.value, b.value),
+        (ObjectEnum::Integer(_), other) | (other, _) => {
+            return unsupportedArgument("rand_int", &other)
+        }
+    };
+    if a > b {
+        return Error::new(
+            ErrorKind::ArgumentError,
+            format!("`rand_int` got an empty range: {} > {}", a, b),
+        )
+        .into();
+    }
+    // how many integers past `a` there are to choose from, wrapping to 0
+    // when there are 2^64 of them
+    let span = (b.wrapping_sub(a) as u64).wrapping_add(1);
+    let offset = if span == 0 {
+        runtime.NextRandom()
+    } else {
+        // rejecting the top of the range that doesn't fit a whole number of
+        // spans keeps every offset equally likely
+        let limit = u64::MAX - u64::MAX % span;
+        loop {
+            let x = runtime.NextRandom();
+            if x < limit {
+                break x % span;
+            }
+        }
+    };
+    Integer {
+        value: a.wrapping_add(offset as i64),
+    }
+    .into()
+}
+
+/// `rand_seed(n)`: makes the random numbers from here on the same for the
+/// same `n`.
+pub(super) fn randSeed(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [seed]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    match seed {
+        ObjectEnum::Integer(seed) => {
+            runtime.SeedRandom(seed.value as u64);
+            NULL
+        }
+        other => unsupportedArgument("rand_seed", &other),
+    }
+}
@@ -0,0 +1,152 @@
+//! Regular expressions, in the syntax of the `regex` crate. A match is given
+//! to scripts as the hash of its captures: the text of each group by its
+//! number, 0 being the whole match, and again by its name if it has one. A
+//! group that took no part in the match is null.
+
+use std::convert::TryInto;
+
+use regex::Captures;
+use regex::Regex;
+
+use super::string;
+use super::unsupportedArgument;
+use crate::evaluator::isError;
+use crate::evaluator::typeName;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Array;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Hash;
+use crate::object::HashKey;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+/// The pattern and text arguments of the builtin `name`.
+fn patternAndText(
+    name: &str,
+    pattern: ObjectEnum,
+    text: ObjectEnum,
+) -> Result<(Regex, String), ObjectEnum> {
+    let pattern = string(name, pattern)?;
+    let text = string(name, text)?;
+    match Regex::new(&pattern) {
+        Ok(re) => Ok((re, text)),
+        Err(err) => Err(Error::new(
+            ErrorKind::ArgumentError,
+            format!("invalid pattern passed to `{}`: {}", name, err),
+        )
+        .with("pattern", Str { value: pattern })
+        .into()),
+    }
+}
+
+fn captures(re: &Regex, caps: &Captures) -> ObjectEnum {
+    let text = |group: Option<regex::Match>| match group {
+        Some(group) => Str {
+            value: group.as_str().into(),
+        }
+        .into(),
+        None => NULL,
+    };
+    let mut pairs: Vec<(HashKey, ObjectEnum)> = (0..caps.len())
+        .map(|i| (HashKey::Integer(i as i64), text(caps.get(i))))
+        .collect();
+    pairs.extend(
+        re.capture_names()
+            .flatten()
+            .map(|name| (HashKey::String(name.into()), text(caps.name(name)))),
+    );
+    Hash {
+        pairs: pairs.into_iter().collect(),
+    }
+    .into()
+}
+
+/// `re_match(pattern, s)`: the captures of the first match of `pattern` in
+/// `s`, or null if there is none.
+pub(super) fn reMatch(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [pattern, text]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let (re, text) = match patternAndText("re_match", pattern, text) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    match re.captures(&text) {
+        Some(caps) => captures(&re, &caps),
+        None => NULL,
+    }
+}
+
+/// `re_find_all(pattern, s)`: the captures of every match of `pattern` in
+/// `s`, none overlapping.
+pub(super) fn reFindAll(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [pattern, text]: [ObjectEnum; 2] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(2, args.len()),
+    };
+    let (re, text) = match patternAndText("re_find_all", pattern, text) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    Array {
+        elements: re
+            .captures_iter(&text)
+            .map(|caps| captures(&re, &caps))
+            .collect(),
+    }
+    .into()
+}
+
+/// `re_replace(pattern, s, replacement)`: `s` with every match of `pattern`
+/// replaced. The replacement is either a string, where `$1` or `${name}`
+/// stands for a group, or a function given the captures that returns a
+/// string.
+pub(super) fn reReplace(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [pattern, text, replacement]: [ObjectEnum; 3] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(3, args.len()),
+    };
+    let (re, text) = match patternAndText("re_replace", pattern, text) {
+        Ok(args) => args,
+        Err(err) => return err,
+    };
+    let replacer = match replacement {
+        ObjectEnum::String(s) => {
+            return Str {
+                value: re.replace_all(&text, s.value.as_str()).into_owned(),
+            }
+            .into()
+        }
+        f @ (ObjectEnum::Function(_) | ObjectEnum::Builtin(_)) => f,
+        other => return unsupportedArgument("re_replace", &other),
+    };
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in re.captures_iter(&text) {
+        let whole = caps.get(0).expect("a match has group 0");
+        replaced.push_str(&text[last..whole.start()]);
+        match runtime.Call(replacer.clone(), vec![captures(&re, &caps)]) {
+            ObjectEnum::String(s) => replaced.push_str(&s.value),
+            err if isError(&err) => return err,
+            other => {
+                return Error::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "result of the `re_replace` replacement must be a STRING, got {:?}",
+                        other.Type()
+                    ),
+                )
+                .with("type", typeName(&other))
+                .into()
+            }
+        }
+        last = whole.end();
+    }
+    replaced.push_str(&text[last..]);
+    Str { value: replaced }.into()
+}
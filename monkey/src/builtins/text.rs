@@ -0,0 +1,55 @@
+//! The three ways to split a string: into the chars `len` and indexing count
+//! in, into its UTF-8 bytes, or into graphemes, the characters a reader sees,
+//! where an emoji with a skin tone or a letter with a combining accent is one.
+
+use std::convert::TryInto;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::unsupportedArgument;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Array;
+use crate::object::Integer;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::runtime::Runtime;
+
+/// Runs `split` on the only argument, which must be a string.
+fn split<T: Into<ObjectEnum>>(
+    name: &str,
+    args: Vec<ObjectEnum>,
+    split: impl Fn(&str) -> Vec<T>,
+) -> ObjectEnum {
+    let [arg]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    match arg {
+        ObjectEnum::String(s) => Array {
+            elements: split(&s.value).into_iter().map(Into::into).collect(),
+        }
+        .into(),
+        other => unsupportedArgument(name, &other),
+    }
+}
+
+/// `chars(s)`: the chars of `s`, each a string.
+pub(super) fn chars(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    split("chars", args, |s| {
+        s.chars().map(|c| Str { value: c.into() }).collect()
+    })
+}
+
+/// `bytes(s)`: the UTF-8 bytes of `s`, each an integer.
+pub(super) fn bytes(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    split("bytes", args, |s| {
+        s.bytes().map(|b| Integer { value: b.into() }).collect()
+    })
+}
+
+/// `graphemes(s)`: the extended grapheme clusters of `s`, each a string.
+pub(super) fn graphemes(_: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    split("graphemes", args, |s| {
+        s.graphemes(true).map(|g| Str { value: g.into() }).collect()
+    })
+}
@@ -0,0 +1,69 @@
+//! The clocks, and waiting. Both clocks count milliseconds, and come from the
+//! runtime's platform.
+
+use std::convert::TryInto;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use super::unsupportedArgument;
+use crate::evaluator::wrongArgumentCount;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::ObjectEnum;
+use crate::object::NULL;
+use crate::runtime::Runtime;
+
+fn millis(time: Duration) -> ObjectEnum {
+    Integer {
+        value: time.as_millis().try_into().unwrap_or(i64::MAX),
+    }
+    .into()
+}
+
+/// `now()`: the wall-clock time since the Unix epoch, which can jump.
+pub(super) fn now(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    if !args.is_empty() {
+        return wrongArgumentCount(0, args.len());
+    }
+    match runtime
+        .Platform()
+        .SystemTime()
+        .duration_since(SystemTime::UNIX_EPOCH)
+    {
+        Ok(time) => millis(time),
+        Err(before) => Integer {
+            value: -before.duration().as_millis().try_into().unwrap_or(i64::MAX),
+        }
+        .into(),
+    }
+}
+
+/// `clock()`: the time since some fixed point, which never goes backwards,
+/// for measuring how long something takes.
+pub(super) fn clock(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    if !args.is_empty() {
+        return wrongArgumentCount(0, args.len());
+    }
+    millis(runtime.Platform().Now())
+}
+
+/// `sleep(ms)`: waits `ms` milliseconds, or until the time limit runs out.
+pub(super) fn sleep(runtime: &Runtime, args: Vec<ObjectEnum>) -> ObjectEnum {
+    let [ms]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    match ms {
+        ObjectEnum::Integer(ms) if ms.value < 0 => Error::new(
+            ErrorKind::ArgumentError,
+            "argument to `sleep` must not be negative",
+        )
+        .into(),
+        ObjectEnum::Integer(ms) => {
+            runtime.Sleep(Duration::from_millis(ms.value as u64));
+            NULL
+        }
+        other => unsupportedArgument("sleep", &other),
+    }
+}
@@ -0,0 +1,266 @@
+//! The static call graph of a program: which functions call which, and which
+//! builtins they use, so a script can be audited before it runs.
+//!
+//! Functions are known by the name they are bound to with `let`; anything
+//! else, including anonymous functions, is attributed to the named function
+//! (or the top level, `<main>`) that contains it. Resolution is by name only,
+//! so two functions bound to the same name share a node.
+
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+
+use crate::ast::BlockStatement;
+use crate::ast::ExpressionEnum;
+use crate::ast::Program;
+use crate::ast::StatementEnum;
+use crate::builtins;
+
+/// The caller of calls made outside any named function.
+pub const MAIN: &str = "<main>";
+/// The callee of calls through anything but an identifier, like `f()()`.
+pub const DYNAMIC: &str = "<dynamic>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CalleeKind {
+    /// A function defined in the program.
+    Function,
+    Builtin,
+    /// A name the program doesn't define, like a parameter or a function the
+    /// host provides, or a dynamic call.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Call {
+    pub caller: String,
+    pub callee: String,
+    pub kind: CalleeKind,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CallGraph {
+    /// `MAIN` followed by the program's named functions, in source order.
+    pub functions: Vec<String>,
+    pub calls: BTreeSet<Call>,
+}
+
+impl CallGraph {
+    pub fn Build(program: &Program) -> Self {
+        let mut collector = Collector {
+            graph: CallGraph {
+                functions: vec![MAIN.into()],
+                calls: BTreeSet::new(),
+            },
+            defined: HashSet::new(),
+            defining: true,
+            current: MAIN.into(),
+        };
+        // functions can be called before the statement defining them, so all
+        // of them are found before any call is resolved
+        for defining in [true, false] {
+            collector.defining = defining;
+            for stmt in &program.statements {
+                collector.statement(stmt);
+            }
+        }
+        collector.graph
+    }
+
+    /// The builtins called anywhere in the program.
+    pub fn Builtins(&self) -> BTreeSet<&str> {
+        self.calls
+            .iter()
+            .filter(|call| call.kind == CalleeKind::Builtin)
+            .map(|call| call.callee.as_str())
+            .collect()
+    }
+
+    /// Renders the graph in Graphviz's DOT language. Builtins are drawn as
+    /// boxes and unknown callees dashed.
+    pub fn ToDot(&self) -> String {
+        let mut out = String::from("digraph calls {\n");
+        for function in &self.functions {
+            out += &format!("  \"{}\";\n", function);
+        }
+        let mut external = BTreeSet::new();
+        for call in &self.calls {
+            match call.kind {
+                CalleeKind::Function => {}
+                CalleeKind::Builtin => {
+                    external.insert((&call.callee, "shape=box"));
+                }
+                CalleeKind::Unknown => {
+                    external.insert((&call.callee, "style=dashed"));
+                }
+            }
+        }
+        for (callee, attributes) in external {
+            out += &format!("  \"{}\" [{}];\n", callee, attributes);
+        }
+        for call in &self.calls {
+            out += &format!("  \"{}\" -> \"{}\";\n", call.caller, call.callee);
+        }
+        out + "}\n"
+    }
+
+    pub fn ToJson(&self) -> String {
+        let functions = self.functions.iter().map(|f| jsonString(f));
+        let calls = self.calls.iter().map(|call| {
+            format!(
+                r#"{{"caller":{},"callee":{},"kind":{}}}"#,
+                jsonString(&call.caller),
+                jsonString(&call.callee),
+                jsonString(match call.kind {
+                    CalleeKind::Function => "function",
+                    CalleeKind::Builtin => "builtin",
+                    CalleeKind::Unknown => "unknown",
+                })
+            )
+        });
+        format!(
+            r#"{{"functions":[{}],"calls":[{}]}}"#,
+            itertools::join(functions, ","),
+            itertools::join(calls, ",")
+        )
+    }
+}
+
+fn jsonString(s: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            ch if ch.is_control() => out += &format!("\\u{:04x}", ch as u32),
+            ch => out.push(ch),
+        }
+    }
+    out + "\""
+}
+
+struct Collector {
+    graph: CallGraph,
+    /// Every name bound to a function literal, anywhere in the program.
+    defined: HashSet<String>,
+    /// Whether this pass only collects `defined`, ignoring calls.
+    defining: bool,
+    /// The named function being walked.
+    current: String,
+}
+
+impl Collector {
+    fn statement(&mut self, stmt: &StatementEnum) {
+        match stmt {
+            StatementEnum::Let(l) => match &l.value {
+                ExpressionEnum::FunctionLiteral(f) => {
+                    if self.defining && self.defined.insert(l.name.value.clone()) {
+                        self.graph.functions.push(l.name.value.clone());
+                    }
+                    let caller = std::mem::replace(&mut self.current, l.name.value.clone());
+                    self.block(&f.body);
+                    self.current = caller;
+                }
+                value => self.expression(value),
+            },
+            StatementEnum::Return(r) => self.expression(&r.returnValue),
+            StatementEnum::Expression(e) => self.expression(&e.expression),
+            StatementEnum::Defer(d) => self.expression(&d.expression),
+            StatementEnum::Throw(t) => self.expression(&t.value),
+            StatementEnum::Destructure(d) => self.expression(&d.value),
+            StatementEnum::Block(b) => self.block(b),
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) {
+        for stmt in &block.statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn expression(&mut self, exp: &ExpressionEnum) {
+        match exp {
+            ExpressionEnum::Identifier(_)
+            | ExpressionEnum::IntegerLiteral(_)
+            | ExpressionEnum::StringLiteral(_)
+            | ExpressionEnum::Boolean(_)
+            | ExpressionEnum::NullLiteral(_)
+            | ExpressionEnum::UpdateExpression(_) => {}
+            ExpressionEnum::PrefixExpression(p) => self.expression(&p.right),
+            ExpressionEnum::ImportExpression(i) => self.expression(&i.path),
+            ExpressionEnum::SpreadExpression(s) => self.expression(&s.value),
+            ExpressionEnum::KeywordArgument(k) => self.expression(&k.value),
+            ExpressionEnum::InfixExpression(i) => {
+                self.expression(&i.left);
+                self.expression(&i.right);
+            }
+            ExpressionEnum::IfExpression(i) => {
+                self.expression(&i.condition);
+                self.block(&i.consequence);
+                if let Some(alternative) = &i.alternative {
+                    self.block(alternative);
+                }
+            }
+            ExpressionEnum::MatchExpression(m) => {
+                self.expression(&m.subject);
+                for arm in &m.arms {
+                    self.statement(&arm.body);
+                }
+            }
+            ExpressionEnum::TryExpression(t) => {
+                self.block(&t.body);
+                self.block(&t.handler);
+            }
+            ExpressionEnum::FunctionLiteral(f) => self.block(&f.body),
+            ExpressionEnum::MacroLiteral(m) => self.block(&m.body),
+            ExpressionEnum::CallExpression(c) => {
+                let (callee, kind) = match &*c.function {
+                    ExpressionEnum::Identifier(i) if self.defined.contains(&i.value) => {
+                        (i.value.clone(), CalleeKind::Function)
+                    }
+                    ExpressionEnum::Identifier(i) if builtins::Lookup(&i.value).is_some() => {
+                        (i.value.clone(), CalleeKind::Builtin)
+                    }
+                    ExpressionEnum::Identifier(i) => (i.value.clone(), CalleeKind::Unknown),
+                    function => {
+                        self.expression(function);
+                        (DYNAMIC.into(), CalleeKind::Unknown)
+                    }
+                };
+                if !self.defining {
+                    self.graph.calls.insert(Call {
+                        caller: self.current.clone(),
+                        callee,
+                        kind,
+                    });
+                }
+                for arg in &c.arguments {
+                    self.expression(arg);
+                }
+            }
+            ExpressionEnum::ArrayLiteral(a) => {
+                for element in &a.elements {
+                    self.expression(element);
+                }
+            }
+            ExpressionEnum::HashLiteral(h) => {
+                for (key, value) in &h.pairs {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            ExpressionEnum::IndexExpression(i) => {
+                self.expression(&i.left);
+                self.expression(&i.index);
+            }
+            ExpressionEnum::SliceExpression(s) => {
+                self.expression(&s.left);
+                for bound in s.start.iter().chain(&s.end) {
+                    self.expression(bound);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
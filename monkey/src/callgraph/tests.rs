@@ -0,0 +1,86 @@
+use maplit::btreeset;
+
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+use super::Call;
+use super::CallGraph;
+use super::CalleeKind;
+
+fn build(input: &str) -> CallGraph {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "parser errors: {:?}", p.errors);
+    CallGraph::Build(&program)
+}
+
+fn call(caller: &str, callee: &str, kind: CalleeKind) -> Call {
+    Call {
+        caller: caller.into(),
+        callee: callee.into(),
+        kind,
+    }
+}
+
+#[test]
+fn BuildsCallGraph() {
+    let input = r#"
+        let main = fn() { if (true) { let helper = fn(x) { len(x) }; helper("a") } };
+        let apply = fn(f, x) { f(x) };
+        let twice = fn(x) { apply(fn(y) { puts(y) }, x); twice(x) };
+        main();
+        makeAdder(1)(2);
+    "#;
+    let graph = build(input);
+
+    assert_eq!(
+        graph.functions,
+        vec!["<main>", "main", "helper", "apply", "twice"]
+    );
+    assert_eq!(
+        graph.calls,
+        btreeset! {
+            call("<main>", "<dynamic>", CalleeKind::Unknown),
+            call("<main>", "main", CalleeKind::Function),
+            call("<main>", "makeAdder", CalleeKind::Unknown),
+            call("apply", "f", CalleeKind::Unknown),
+            call("helper", "len", CalleeKind::Builtin),
+            call("main", "helper", CalleeKind::Function),
+            call("twice", "apply", CalleeKind::Function),
+            call("twice", "puts", CalleeKind::Builtin),
+            call("twice", "twice", CalleeKind::Function),
+        }
+    );
+    assert_eq!(graph.Builtins(), btreeset! {"len", "puts"});
+}
+
+#[test]
+fn DefinitionsShadowBuiltins() {
+    let graph = build("let len = fn(x) { 0 }; len([1])");
+    assert_eq!(
+        graph.calls,
+        btreeset! {call("<main>", "len", CalleeKind::Function)}
+    );
+}
+
+#[test]
+fn Exports() {
+    let graph = build(r#"let greet = fn(name) { puts(name); log(name) }; greet("x")"#);
+    assert_eq!(
+        graph.ToDot(),
+        r#"digraph calls {
+  "<main>";
+  "greet";
+  "log" [style=dashed];
+  "puts" [shape=box];
+  "<main>" -> "greet";
+  "greet" -> "log";
+  "greet" -> "puts";
+}
+"#
+    );
+    assert_eq!(
+        graph.ToJson(),
+        r#"{"functions":["<main>","greet"],"calls":[{"caller":"<main>","callee":"greet","kind":"function"},{"caller":"greet","callee":"log","kind":"unknown"},{"caller":"greet","callee":"puts","kind":"builtin"}]}"#
+    );
+}
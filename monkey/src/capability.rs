@@ -0,0 +1,108 @@
+//! Which host capabilities a script could use, found before running it so a
+//! host can ask the user ("this script wants file access") or set up its
+//! sandbox accordingly.
+//!
+//! The analysis is conservative: any reference to a gated builtin's name
+//! counts, even where a binding of the same name might shadow it, and so
+//! does `import`. Code passed to `eval` isn't known until it runs, so a
+//! reference to `eval` counts as needing every capability.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use strum::IntoEnumIterator;
+
+use crate::ast::Identifier;
+use crate::ast::ImportExpression;
+use crate::ast::Program;
+use crate::ast::Visitor;
+use crate::builtins;
+
+#[derive(
+    strum_macros::Display,
+    strum_macros::EnumIter,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum Capability {
+    /// The standard streams, and reading the modules a script imports.
+    Io,
+    /// Reading and writing any file, listing directories, and importing a
+    /// module by a path that can leave the search path. Unlike the
+    /// others, it is denied until a host allows it, and until then its
+    /// builtins don't exist.
+    Fs,
+    Net,
+    /// Reading the clock, and sleeping.
+    Time,
+    /// Random numbers the script didn't seed.
+    Random,
+    /// Reading and setting environment variables.
+    Env,
+}
+
+/// What needs `Io` for importing modules, which reads files. Being a keyword,
+/// it can't clash with a name.
+const IMPORT: &str = "import";
+
+/// What may need any capability, by running code it is given.
+const EVAL: &str = "eval";
+
+/// For each capability a script needs, the names that need it.
+pub type Capabilities = BTreeMap<Capability, BTreeSet<String>>;
+
+/// The capabilities the builtins referenced by `program` need.
+pub fn Required(program: &Program) -> Capabilities {
+    RequiredWith(program, |_| None)
+}
+
+/// Like `Required`, also counting the functions a host provides, whose
+/// capabilities `host` gives by name.
+pub fn RequiredWith(program: &Program, host: impl Fn(&str) -> Option<Capability>) -> Capabilities {
+    let mut names = Names::default();
+    names.VisitProgram(program);
+
+    let mut capabilities = Capabilities::new();
+    for name in names.0 {
+        let needs: Vec<Capability> = match name {
+            IMPORT => vec![Capability::Io],
+            EVAL => Capability::iter().collect(),
+            _ => host(name)
+                .or_else(|| builtins::CapabilityOf(name))
+                .into_iter()
+                .collect(),
+        };
+        for capability in needs {
+            capabilities
+                .entry(capability)
+                .or_default()
+                .insert(name.to_string());
+        }
+    }
+    capabilities
+}
+
+/// Collects the identifiers a program references.
+#[derive(Default)]
+struct Names<'src>(BTreeSet<&'src str>);
+
+impl<'src> Visitor<'src> for Names<'src> {
+    fn VisitIdentifier(&mut self, ident: &'src Identifier) {
+        self.0.insert(&ident.value);
+    }
+
+    fn VisitImportExpression(&mut self, exp: &'src ImportExpression) {
+        self.0.insert(IMPORT);
+        self.VisitExpression(&exp.path);
+    }
+}
+
+#[cfg(test)]
+mod tests;
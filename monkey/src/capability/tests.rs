@@ -0,0 +1,79 @@
+use maplit::btreemap;
+use maplit::btreeset;
+
+use crate::ast::Program;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+use super::Capability;
+use super::Required;
+use super::RequiredWith;
+
+fn parse(input: &str) -> Program {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "parser errors: {:?}", p.errors);
+    program
+}
+
+#[test]
+fn PureScriptsNeedNothing() {
+    assert!(Required(&parse("let f = fn(x) { len(x) + 1 }; f([1])")).is_empty());
+}
+
+#[test]
+fn FindsReferencedBuiltins() {
+    // a reference is enough, the builtin doesn't have to be called directly
+    let tests = vec![
+        r#"puts("hi")"#,
+        "let log = puts; log(1)",
+        "let f = fn() { if (true) { [puts] } }",
+        r#""${puts(1)}""#,
+    ];
+    for input in tests {
+        assert_eq!(
+            Required(&parse(input)),
+            btreemap! {Capability::Io => btreeset! {"puts".to_string()}},
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn HostFunctions() {
+    let program = parse("let t = now(); fetch(url); puts(t)");
+    let capabilities = RequiredWith(&program, |name| match name {
+        "now" => Some(Capability::Time),
+        "fetch" => Some(Capability::Net),
+        _ => None,
+    });
+    assert_eq!(
+        capabilities,
+        btreemap! {
+            Capability::Io => btreeset! {"puts".to_string()},
+            Capability::Net => btreeset! {"fetch".to_string()},
+            Capability::Time => btreeset! {"now".to_string()},
+        }
+    );
+    assert_eq!(Capability::Random.to_string(), "random");
+}
+
+#[test]
+fn ImportsNeedIo() {
+    assert_eq!(
+        Required(&parse(r#"let m = fn() { import "m.mk" }"#)),
+        btreemap! {Capability::Io => btreeset! {"import".to_string()}}
+    );
+}
+
+#[test]
+fn EvalMayNeedAnything() {
+    use strum::IntoEnumIterator;
+
+    let program = parse(r#"let n = "get" + "env"; eval(n + "(\"HOME\")")"#);
+    let expected = Capability::iter()
+        .map(|capability| (capability, btreeset! {"eval".to_string()}))
+        .collect();
+    assert_eq!(Required(&program), expected);
+}
@@ -0,0 +1,310 @@
+//! Compiles a syntax tree to bytecode for a stack machine: the instructions
+//! of `code`, and the pool of constants they load. So far it compiles
+//! integers, strings, booleans and `null`, the operators on them, `if`,
+//! `let`s, which all bind globals, and references to builtins; anything
+//! else is a `CompileError`.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::ast::BlockStatement;
+use crate::ast::ExpressionEnum;
+use crate::ast::Node;
+use crate::ast::Program;
+use crate::ast::StatementEnum;
+use crate::builtins;
+use crate::compiler::code::Fits;
+use crate::compiler::code::Instructions;
+use crate::compiler::code::Make;
+use crate::compiler::code::Opcode;
+use crate::compiler::symbol_table::Scope;
+use crate::compiler::symbol_table::SymbolTable;
+use crate::object::HashKey;
+use crate::object::Integer;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+
+pub mod code;
+mod file;
+pub mod symbol_table;
+
+/// What a program compiles to, which `Save` writes to a file and `Load`
+/// reads back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bytecode {
+    pub instructions: Instructions,
+    /// What `Constant` instructions load, by index. Each integer and string
+    /// is there once, however often the program uses it.
+    pub constants: Vec<ObjectEnum>,
+}
+
+/// Why a program can't be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// Code the compiler doesn't handle yet, like a function.
+    Unsupported { code: String, span: Range<usize> },
+    /// An identifier no `let` before it binds, and that isn't a builtin.
+    Undefined { name: String, span: Range<usize> },
+    /// More constants, variables or bytes of instructions than an operand
+    /// can index.
+    TooLarge,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsupported { code, .. } => write!(f, "can't compile `{}` yet", code),
+            Self::Undefined { name, .. } => write!(f, "`{}` isn't defined", name),
+            Self::TooLarge => write!(
+                f,
+                "the program has more constants, variables or bytes of instructions than bytecode can index"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Compiles programs one after another into the same bytecode, so a REPL
+/// can compile each line it reads with the globals of the lines before.
+#[derive(Debug)]
+pub struct Compiler {
+    instructions: Vec<u8>,
+    constants: Vec<ObjectEnum>,
+    /// The index in `constants` of each integer and string there.
+    interned: HashMap<HashKey, usize>,
+    symbols: SymbolTable,
+    /// Where the instruction emitted last starts, and its opcode.
+    last: Option<(usize, Opcode)>,
+}
+
+impl Compiler {
+    /// A compiler with the builtins this build has in scope.
+    pub fn New() -> Self {
+        let mut symbols = SymbolTable::New();
+        for (index, name) in builtins::NAMES.iter().enumerate() {
+            if builtins::Lookup(name).is_some() {
+                symbols.DefineBuiltin(index, name);
+            }
+        }
+        Self {
+            instructions: vec![],
+            constants: vec![],
+            interned: HashMap::new(),
+            symbols,
+            last: None,
+        }
+    }
+
+    /// Appends the code of `program`. If it can't be compiled, the compiler
+    /// is left as it was.
+    pub fn Compile(&mut self, program: &Program) -> Result<(), CompileError> {
+        let (instructions, constants, symbols) = (
+            self.instructions.len(),
+            self.constants.len(),
+            self.symbols.clone(),
+        );
+        let result = program
+            .statements
+            .iter()
+            .try_for_each(|stmt| self.statement(stmt));
+        if result.is_err() {
+            self.instructions.truncate(instructions);
+            self.constants.truncate(constants);
+            self.interned.retain(|_, index| *index < constants);
+            self.symbols = symbols;
+            self.last = None;
+        }
+        result
+    }
+
+    /// The code of everything compiled so far.
+    pub fn Bytecode(&self) -> Bytecode {
+        Bytecode {
+            instructions: Instructions(self.instructions.clone()),
+            constants: self.constants.clone(),
+        }
+    }
+
+    fn statement(&mut self, stmt: &StatementEnum) -> Result<(), CompileError> {
+        match stmt {
+            StatementEnum::Expression(e) => {
+                self.expression(&e.expression)?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StatementEnum::Let(l) => {
+                // the value is compiled first, so it sees what the name was
+                // bound to before
+                self.expression(&l.value)?;
+                let symbol = self.symbols.Define(&l.name.value);
+                let op = match symbol.scope {
+                    Scope::Global => Opcode::SetGlobal,
+                    _ => Opcode::SetLocal,
+                };
+                self.emit(op, &[symbol.index])?;
+            }
+            StatementEnum::Block(b) => self.block(b)?,
+            other => return Err(unsupported(other)),
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &BlockStatement) -> Result<(), CompileError> {
+        block
+            .statements
+            .iter()
+            .try_for_each(|stmt| self.statement(stmt))
+    }
+
+    /// The block, leaving its value on the stack: that of its last
+    /// statement, if it is an expression, and `null` otherwise.
+    fn blockValue(&mut self, block: &BlockStatement) -> Result<(), CompileError> {
+        self.block(block)?;
+        match self.last {
+            Some((position, Opcode::Pop)) => {
+                self.instructions.truncate(position);
+                self.last = None;
+            }
+            _ => {
+                self.emit(Opcode::Null, &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, exp: &ExpressionEnum) -> Result<(), CompileError> {
+        match exp {
+            ExpressionEnum::IntegerLiteral(i) => {
+                self.constant(Integer { value: i.value }.into())?
+            }
+            ExpressionEnum::StringLiteral(s) => self.constant(
+                Str {
+                    value: s.value.to_string(),
+                }
+                .into(),
+            )?,
+            ExpressionEnum::Boolean(b) if b.value => self.emit(Opcode::True, &[])?,
+            ExpressionEnum::Boolean(_) => self.emit(Opcode::False, &[])?,
+            ExpressionEnum::NullLiteral(_) => self.emit(Opcode::Null, &[])?,
+            ExpressionEnum::Identifier(i) => {
+                let symbol =
+                    self.symbols
+                        .Resolve(&i.value)
+                        .ok_or_else(|| CompileError::Undefined {
+                            name: i.value.clone(),
+                            span: i.Span(),
+                        })?;
+                let op = match symbol.scope {
+                    Scope::Global => Opcode::GetGlobal,
+                    Scope::Local => Opcode::GetLocal,
+                    Scope::Builtin => Opcode::GetBuiltin,
+                    Scope::Free => Opcode::GetFree,
+                };
+                self.emit(op, &[symbol.index])?
+            }
+            ExpressionEnum::PrefixExpression(p) => {
+                let op = match p.operator.as_str() {
+                    "-" => Opcode::Minus,
+                    "!" => Opcode::Bang,
+                    _ => return Err(unsupported(exp)),
+                };
+                self.expression(&p.right)?;
+                self.emit(op, &[])?
+            }
+            ExpressionEnum::InfixExpression(i) if i.operator == "<" => {
+                self.expression(&i.right)?;
+                self.expression(&i.left)?;
+                self.emit(Opcode::GreaterThan, &[])?
+            }
+            ExpressionEnum::InfixExpression(i) => {
+                let op = match i.operator.as_str() {
+                    "+" => Opcode::Add,
+                    "-" => Opcode::Sub,
+                    "*" => Opcode::Mul,
+                    "/" => Opcode::Div,
+                    "%" => Opcode::Mod,
+                    "==" => Opcode::Equal,
+                    "!=" => Opcode::NotEqual,
+                    ">" => Opcode::GreaterThan,
+                    _ => return Err(unsupported(exp)),
+                };
+                self.expression(&i.left)?;
+                self.expression(&i.right)?;
+                self.emit(op, &[])?
+            }
+            ExpressionEnum::IfExpression(i) => {
+                self.expression(&i.condition)?;
+                // the jumps go to code that isn't compiled yet, so they are
+                // pointed at it once it is
+                let toAlternative = self.emit(Opcode::JumpNotTruthy, &[0])?;
+                self.blockValue(&i.consequence)?;
+                let toEnd = self.emit(Opcode::Jump, &[0])?;
+                self.jumpHere(toAlternative)?;
+                match &i.alternative {
+                    Some(alternative) => self.blockValue(alternative)?,
+                    None => {
+                        self.emit(Opcode::Null, &[])?;
+                    }
+                }
+                self.jumpHere(toEnd)?;
+                return Ok(());
+            }
+            _ => return Err(unsupported(exp)),
+        };
+        Ok(())
+    }
+
+    /// Loads `value`, adding it to the pool unless an equal constant is
+    /// already there.
+    fn constant(&mut self, value: ObjectEnum) -> Result<usize, CompileError> {
+        let index = match value.HashKey() {
+            Some(key) => match self.interned.get(&key) {
+                Some(&index) => index,
+                None => {
+                    self.interned.insert(key, self.constants.len());
+                    self.constants.push(value);
+                    self.constants.len() - 1
+                }
+            },
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
+        self.emit(Opcode::Constant, &[index])
+    }
+
+    /// Appends an instruction, returning where it starts.
+    fn emit(&mut self, op: Opcode, operands: &[usize]) -> Result<usize, CompileError> {
+        if !Fits(op, operands) {
+            return Err(CompileError::TooLarge);
+        }
+        let position = self.instructions.len();
+        self.instructions.extend(Make(op, operands));
+        self.last = Some((position, op));
+        Ok(position)
+    }
+
+    /// Points the jump at `position` to where the next instruction goes.
+    fn jumpHere(&mut self, position: usize) -> Result<(), CompileError> {
+        let op = Opcode::Lookup(self.instructions[position]).unwrap();
+        let target = self.instructions.len();
+        if !Fits(op, &[target]) {
+            return Err(CompileError::TooLarge);
+        }
+        let jump = Make(op, &[target]);
+        self.instructions[position..position + jump.len()].copy_from_slice(&jump);
+        Ok(())
+    }
+}
+
+fn unsupported(node: &impl Node) -> CompileError {
+    CompileError::Unsupported {
+        code: node.to_string(),
+        span: node.Span(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
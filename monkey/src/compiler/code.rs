@@ -0,0 +1,149 @@
+//! The instructions of the stack machine the compiler targets. Each is an
+//! opcode byte followed by its operands, which are big-endian and as wide as
+//! the opcode's `OperandWidths` say.
+
+use std::convert::TryInto;
+use std::fmt::Display;
+
+use strum::IntoEnumIterator;
+
+#[derive(strum_macros::EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Opcode {
+    /// Pushes the constant at its operand's index in the pool.
+    Constant,
+    /// Throws away the value on top of the stack, which an expression
+    /// statement leaves there.
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    True,
+    False,
+    Null,
+    Equal,
+    NotEqual,
+    /// `a > b`; `a < b` is compiled as `b > a`.
+    GreaterThan,
+    Minus,
+    Bang,
+    /// Jumps to its operand if the value it pops isn't truthy.
+    JumpNotTruthy,
+    Jump,
+    /// Pops the value of the global at its operand's index.
+    SetGlobal,
+    GetGlobal,
+    // new opcodes go last, so saved bytecode keeps meaning the same
+    /// Pushes the builtin at its operand's index in `builtins::NAMES`.
+    GetBuiltin,
+    /// Pops the value of the local of the function running at its operand's
+    /// index.
+    SetLocal,
+    GetLocal,
+    /// Pushes the value at its operand's index among those the function
+    /// running closes over.
+    GetFree,
+}
+
+impl Opcode {
+    /// How many bytes each of the opcode's operands takes.
+    pub fn OperandWidths(self) -> &'static [usize] {
+        match self {
+            Self::Constant
+            | Self::JumpNotTruthy
+            | Self::Jump
+            | Self::SetGlobal
+            | Self::GetGlobal => &[2],
+            Self::GetBuiltin | Self::SetLocal | Self::GetLocal | Self::GetFree => &[1],
+            _ => &[],
+        }
+    }
+
+    pub fn Lookup(byte: u8) -> Option<Self> {
+        Self::iter().find(|op| *op as u8 == byte)
+    }
+}
+
+/// The instruction `op` with `operands`, each cut to the width it takes.
+pub fn Make(op: Opcode, operands: &[usize]) -> Vec<u8> {
+    let mut instruction = vec![op as u8];
+    for (operand, width) in operands.iter().zip(op.OperandWidths()) {
+        match width {
+            1 => instruction.push(*operand as u8),
+            2 => instruction.extend((*operand as u16).to_be_bytes()),
+            _ => unreachable!("operands are 1 or 2 bytes wide"),
+        }
+    }
+    instruction
+}
+
+/// Whether every operand fits in the width `op` gives it.
+pub fn Fits(op: Opcode, operands: &[usize]) -> bool {
+    operands
+        .iter()
+        .zip(op.OperandWidths())
+        .all(|(operand, width)| *operand < 1 << (8 * width))
+}
+
+/// The operands of an `op` at the start of `bytes`, and how many bytes they
+/// take.
+pub fn ReadOperands(op: Opcode, bytes: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands = vec![];
+    let mut offset = 0;
+    for width in op.OperandWidths() {
+        match width {
+            1 => operands.push(bytes[offset] as usize),
+            2 => operands.push(ReadU16(&bytes[offset..]) as usize),
+            _ => unreachable!("operands are 1 or 2 bytes wide"),
+        }
+        offset += width;
+    }
+    (operands, offset)
+}
+
+pub fn ReadU16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes(bytes[..2].try_into().unwrap())
+}
+
+/// A sequence of instructions. It displays as one instruction a line, each
+/// at its offset, like `0003 Constant 1`, and stops at an instruction whose
+/// operands are cut off.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Instructions(pub Vec<u8>);
+
+impl From<Vec<Vec<u8>>> for Instructions {
+    fn from(instructions: Vec<Vec<u8>>) -> Self {
+        Self(instructions.concat())
+    }
+}
+
+impl Display for Instructions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut offset = 0;
+        while offset < self.0.len() {
+            let op = match Opcode::Lookup(self.0[offset]) {
+                Some(op) => op,
+                None => {
+                    writeln!(f, "{:04} unknown opcode {}", offset, self.0[offset])?;
+                    offset += 1;
+                    continue;
+                }
+            };
+            write!(f, "{:04} {:?}", offset, op)?;
+            let width: usize = op.OperandWidths().iter().sum();
+            if offset + 1 + width > self.0.len() {
+                writeln!(f, " (cut off)")?;
+                break;
+            }
+            let (operands, read) = ReadOperands(op, &self.0[offset + 1..]);
+            for operand in operands {
+                write!(f, " {}", operand)?;
+            }
+            writeln!(f)?;
+            offset += 1 + read;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! The `.monkeyc` format bytecode is saved in, so a script can be compiled
+//! once and loaded without parsing it again. A file is `MAGIC`, the format's
+//! `VERSION`, the instructions and then the constants. Numbers are
+//! little-endian: lengths and counts take 4 bytes, and integers 8. Each
+//! constant starts with a byte telling its type, and new types get new
+//! bytes, so a file keeps loading the same until the version changes.
+
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use super::code::Instructions;
+use super::code::Opcode;
+use super::code::ReadOperands;
+use super::Bytecode;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+
+/// Starts every file.
+const MAGIC: &[u8; 8] = b"MONKEYC\0";
+
+/// Goes up whenever a file of the format before wouldn't load the same.
+const VERSION: u16 = 1;
+
+const INTEGER: u8 = 0;
+const STRING: u8 = 1;
+
+impl Bytecode {
+    /// Writes the bytecode to `out` in the `.monkeyc` format. Only integer
+    /// and string constants can be saved, which are all the compiler makes.
+    pub fn Save(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        writeLen(out, self.instructions.0.len())?;
+        out.write_all(&self.instructions.0)?;
+        writeLen(out, self.constants.len())?;
+        for constant in &self.constants {
+            match constant {
+                ObjectEnum::Integer(i) => {
+                    out.write_all(&[INTEGER])?;
+                    out.write_all(&i.value.to_le_bytes())?;
+                }
+                ObjectEnum::String(s) => {
+                    out.write_all(&[STRING])?;
+                    writeLen(out, s.value.len())?;
+                    out.write_all(s.value.as_bytes())?;
+                }
+                other => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("can't save a constant of type {:?}", other.Type()),
+                    ))
+                }
+            }
+        }
+        out.flush()
+    }
+
+    /// Reads bytecode `Save` wrote, checking that it is all there and that
+    /// its instructions are ones this version has.
+    pub fn Load(input: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic).map_err(truncated)?;
+        if &magic != MAGIC {
+            return Err(invalid("the file isn't compiled Monkey".into()));
+        }
+        let version = u16::from_le_bytes(read(input)?);
+        if version != VERSION {
+            return Err(invalid(format!(
+                "the bytecode was saved in version {} of the format, and only version {} can be loaded",
+                version, VERSION
+            )));
+        }
+        let instructions = Instructions(readBytes(input)?);
+        let count = readLen(input)?;
+        let mut constants = vec![];
+        for _ in 0..count {
+            let [tag] = read(input)?;
+            constants.push(match tag {
+                INTEGER => Integer {
+                    value: i64::from_le_bytes(read(input)?),
+                }
+                .into(),
+                STRING => Str {
+                    value: String::from_utf8(readBytes(input)?)
+                        .map_err(|_| corrupt("a string constant isn't UTF-8"))?,
+                }
+                .into(),
+                tag => return Err(corrupt(&format!("a constant has unknown type {}", tag))),
+            });
+        }
+        if input.read(&mut [0])? != 0 {
+            return Err(corrupt("something comes after the constants"));
+        }
+        check(&instructions, constants.len())?;
+        Ok(Self {
+            instructions,
+            constants,
+        })
+    }
+}
+
+/// Checks that every instruction has a known opcode and all its operands,
+/// that the constants they load are among the `constants` there are, and
+/// that they jump to the start of an instruction or to the end.
+fn check(instructions: &Instructions, constants: usize) -> io::Result<()> {
+    let bytes = &instructions.0;
+    let mut starts = HashSet::new();
+    let mut targets = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        starts.insert(offset);
+        let op = Opcode::Lookup(bytes[offset])
+            .ok_or_else(|| corrupt(&format!("unknown opcode {}", bytes[offset])))?;
+        let width: usize = op.OperandWidths().iter().sum();
+        if offset + 1 + width > bytes.len() {
+            return Err(corrupt("the last instruction is cut off"));
+        }
+        let (operands, read) = ReadOperands(op, &bytes[offset + 1..]);
+        match op {
+            Opcode::Constant if operands[0] >= constants => {
+                return Err(corrupt(&format!(
+                    "constant {} is loaded, and there are only {}",
+                    operands[0], constants
+                )))
+            }
+            Opcode::Jump | Opcode::JumpNotTruthy => targets.push(operands[0]),
+            _ => {}
+        }
+        offset += 1 + read;
+    }
+    starts.insert(bytes.len());
+    match targets.into_iter().find(|target| !starts.contains(target)) {
+        Some(target) => Err(corrupt(&format!(
+            "a jump to {} doesn't land on an instruction",
+            target
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn writeLen(out: &mut impl Write, len: usize) -> io::Result<()> {
+    let len: u32 = len
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the bytecode is too large"))?;
+    out.write_all(&len.to_le_bytes())
+}
+
+fn read<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(bytes)
+}
+
+fn readLen(input: &mut impl Read) -> io::Result<usize> {
+    Ok(u32::from_le_bytes(read(input)?) as usize)
+}
+
+/// A length, and then that many bytes.
+fn readBytes(input: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = readLen(input)?;
+    let mut bytes = vec![];
+    // a corrupt length shouldn't allocate more than the input has
+    (&mut *input).take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(corrupt("it is cut off"));
+    }
+    Ok(bytes)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn corrupt(why: &str) -> io::Error {
+    invalid(format!("the bytecode is corrupt: {}", why))
+}
+
+fn truncated(error: io::Error) -> io::Error {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => corrupt("it is cut off"),
+        _ => error,
+    }
+}
@@ -0,0 +1,126 @@
+//! What the names of a program refer to as it is compiled, so that code
+//! reaches each value by the index of its slot instead of looking its name
+//! up while it runs.
+
+use std::collections::HashMap;
+
+/// Where a symbol's value is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Bound at the top level of the program.
+    Global,
+    /// Bound in the function being compiled, including its parameters.
+    Local,
+    Builtin,
+    /// A local of an enclosing function, which the function being compiled
+    /// closes over.
+    Free,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: Scope,
+    /// Which slot of its scope holds the value.
+    pub index: usize,
+}
+
+/// The symbols of one scope: the program's, or a function's, which is
+/// enclosed in that of the code the function is written in.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    outer: Option<Box<SymbolTable>>,
+    store: HashMap<String, Symbol>,
+    /// How many globals or locals are defined.
+    definitions: usize,
+    free: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn New() -> Self {
+        Self::default()
+    }
+
+    /// A table for a function written where `outer` is in scope.
+    pub fn Enclosed(outer: Self) -> Self {
+        Self {
+            outer: Some(Box::new(outer)),
+            ..Self::default()
+        }
+    }
+
+    /// The table this one is enclosed in, for going back to it once the
+    /// function is compiled.
+    pub fn Outer(self) -> Option<Self> {
+        self.outer.map(|outer| *outer)
+    }
+
+    /// Binds `name` in this scope, as a global if it is the program's and a
+    /// local otherwise. Binding a name again keeps its slot.
+    pub fn Define(&mut self, name: &str) -> Symbol {
+        let scope = match self.outer {
+            None => Scope::Global,
+            Some(_) => Scope::Local,
+        };
+        match self.store.get(name) {
+            Some(symbol) if symbol.scope == scope => symbol.clone(),
+            _ => {
+                let symbol = Symbol {
+                    name: name.into(),
+                    scope,
+                    index: self.definitions,
+                };
+                self.definitions += 1;
+                self.store.insert(name.into(), symbol.clone());
+                symbol
+            }
+        }
+    }
+
+    /// Binds `name` to the builtin at `index`, until it is bound to something
+    /// else.
+    pub fn DefineBuiltin(&mut self, index: usize, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.into(),
+            scope: Scope::Builtin,
+            index,
+        };
+        self.store.insert(name.into(), symbol.clone());
+        symbol
+    }
+
+    /// What `name` refers to here. A local of an enclosing function becomes
+    /// a free symbol of this one, and of every function in between.
+    pub fn Resolve(&mut self, name: &str) -> Option<Symbol> {
+        if let Some(symbol) = self.store.get(name) {
+            return Some(symbol.clone());
+        }
+        let symbol = self.outer.as_mut()?.Resolve(name)?;
+        match symbol.scope {
+            Scope::Global | Scope::Builtin => Some(symbol),
+            Scope::Local | Scope::Free => Some(self.defineFree(symbol)),
+        }
+    }
+
+    /// How many slots the globals or locals of this scope take.
+    pub fn Definitions(&self) -> usize {
+        self.definitions
+    }
+
+    /// What the free symbols of this scope refer to in the enclosing one,
+    /// by index, which is what a closure has to capture.
+    pub fn Free(&self) -> &[Symbol] {
+        &self.free
+    }
+
+    fn defineFree(&mut self, original: Symbol) -> Symbol {
+        let symbol = Symbol {
+            name: original.name.clone(),
+            scope: Scope::Free,
+            index: self.free.len(),
+        };
+        self.free.push(original);
+        self.store.insert(symbol.name.clone(), symbol.clone());
+        symbol
+    }
+}
@@ -0,0 +1,552 @@
+use std::io;
+
+use crate::ast::Program;
+use crate::lexer::Lexer;
+use crate::object::Integer;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::parser::Parser;
+
+use super::code::Instructions;
+use super::code::Make;
+use super::code::Opcode;
+use super::code::ReadOperands;
+use super::symbol_table::Scope;
+use super::symbol_table::Symbol;
+use super::symbol_table::SymbolTable;
+use super::Bytecode;
+use super::CompileError;
+use super::Compiler;
+
+fn parse(input: &str) -> Program {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "parser errors: {:?}", p.errors);
+    program
+}
+
+fn compile(input: &str) -> Bytecode {
+    let mut compiler = Compiler::New();
+    if let Err(e) = compiler.Compile(&parse(input)) {
+        panic!("compiling {:?}: {}", input, e);
+    }
+    compiler.Bytecode()
+}
+
+fn int(value: i64) -> ObjectEnum {
+    Integer { value }.into()
+}
+
+fn string(value: &str) -> ObjectEnum {
+    Str {
+        value: value.into(),
+    }
+    .into()
+}
+
+/// An input, and the constants and instructions it compiles to.
+type Case<'a> = (&'a str, Vec<ObjectEnum>, Vec<Vec<u8>>);
+
+fn testCompile(tests: Vec<Case>) {
+    for (input, constants, instructions) in tests {
+        let bytecode = compile(input);
+        let expected = Instructions::from(instructions);
+        assert_eq!(
+            bytecode.instructions, expected,
+            "instructions of {:?}:\n{}\nwant:\n{}",
+            input, bytecode.instructions, expected
+        );
+        assert_eq!(bytecode.constants, constants, "constants of {:?}", input);
+    }
+}
+
+#[test]
+fn MakesInstructions() {
+    assert_eq!(
+        Make(Opcode::Constant, &[65534]),
+        vec![Opcode::Constant as u8, 255, 254]
+    );
+    assert_eq!(Make(Opcode::Add, &[]), vec![Opcode::Add as u8]);
+    assert_eq!(
+        Make(Opcode::GetLocal, &[255]),
+        vec![Opcode::GetLocal as u8, 255]
+    );
+
+    let instruction = Make(Opcode::GetGlobal, &[513]);
+    assert_eq!(
+        ReadOperands(Opcode::GetGlobal, &instruction[1..]),
+        (vec![513], 2)
+    );
+}
+
+#[test]
+fn InstructionsDisassemble() {
+    let instructions = Instructions::from(vec![
+        Make(Opcode::Add, &[]),
+        Make(Opcode::Constant, &[2]),
+        Make(Opcode::Constant, &[65535]),
+        Make(Opcode::JumpNotTruthy, &[1]),
+    ]);
+    let expected = "\
+0000 Add
+0001 Constant 2
+0004 Constant 65535
+0007 JumpNotTruthy 1
+";
+    assert_eq!(instructions.to_string(), expected);
+
+    let mut truncated = Make(Opcode::Pop, &[]);
+    truncated.extend(&Make(Opcode::Constant, &[2])[..2]);
+    assert_eq!(
+        Instructions(truncated).to_string(),
+        "0000 Pop\n0001 Constant (cut off)\n"
+    );
+}
+
+#[test]
+fn CompilesIntegerArithmetic() {
+    testCompile(vec![
+        (
+            "1 + 2",
+            vec![int(1), int(2)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Constant, &[1]),
+                Make(Opcode::Add, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        (
+            "1; 2",
+            vec![int(1), int(2)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Pop, &[]),
+                Make(Opcode::Constant, &[1]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        (
+            "2 * 3 - 4 / 5 % 6",
+            vec![int(2), int(3), int(4), int(5), int(6)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Constant, &[1]),
+                Make(Opcode::Mul, &[]),
+                Make(Opcode::Constant, &[2]),
+                Make(Opcode::Constant, &[3]),
+                Make(Opcode::Div, &[]),
+                Make(Opcode::Constant, &[4]),
+                Make(Opcode::Mod, &[]),
+                Make(Opcode::Sub, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        (
+            "-(1)",
+            vec![int(1)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Minus, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+    ]);
+}
+
+#[test]
+fn CompilesBooleanExpressions() {
+    testCompile(vec![
+        (
+            "true; false; null",
+            vec![],
+            vec![
+                Make(Opcode::True, &[]),
+                Make(Opcode::Pop, &[]),
+                Make(Opcode::False, &[]),
+                Make(Opcode::Pop, &[]),
+                Make(Opcode::Null, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        (
+            "1 > 2",
+            vec![int(1), int(2)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Constant, &[1]),
+                Make(Opcode::GreaterThan, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        // the operands swap places
+        (
+            "1 < 2",
+            vec![int(2), int(1)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Constant, &[1]),
+                Make(Opcode::GreaterThan, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        (
+            "!(true == false) != true",
+            vec![],
+            vec![
+                Make(Opcode::True, &[]),
+                Make(Opcode::False, &[]),
+                Make(Opcode::Equal, &[]),
+                Make(Opcode::Bang, &[]),
+                Make(Opcode::True, &[]),
+                Make(Opcode::NotEqual, &[]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+    ]);
+}
+
+#[test]
+fn CompilesStrings() {
+    testCompile(vec![(
+        r#""mon" + "key""#,
+        vec![string("mon"), string("key")],
+        vec![
+            Make(Opcode::Constant, &[0]),
+            Make(Opcode::Constant, &[1]),
+            Make(Opcode::Add, &[]),
+            Make(Opcode::Pop, &[]),
+        ],
+    )]);
+}
+
+#[test]
+fn CompilesConditionals() {
+    testCompile(vec![
+        (
+            "if (true) { 10 }; 3333",
+            vec![int(10), int(3333)],
+            vec![
+                // 0000
+                Make(Opcode::True, &[]),
+                // 0001
+                Make(Opcode::JumpNotTruthy, &[10]),
+                // 0004
+                Make(Opcode::Constant, &[0]),
+                // 0007
+                Make(Opcode::Jump, &[11]),
+                // 0010
+                Make(Opcode::Null, &[]),
+                // 0011
+                Make(Opcode::Pop, &[]),
+                // 0012
+                Make(Opcode::Constant, &[1]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        (
+            "if (true) { 10 } else { 20 }",
+            vec![int(10), int(20)],
+            vec![
+                Make(Opcode::True, &[]),
+                Make(Opcode::JumpNotTruthy, &[10]),
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Jump, &[13]),
+                // 0010
+                Make(Opcode::Constant, &[1]),
+                // 0013
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        // a block without a value gives null
+        (
+            "if (false) {} else { let x = 1 }",
+            vec![int(1)],
+            vec![
+                Make(Opcode::False, &[]),
+                Make(Opcode::JumpNotTruthy, &[8]),
+                Make(Opcode::Null, &[]),
+                Make(Opcode::Jump, &[15]),
+                // 0008
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::SetGlobal, &[0]),
+                Make(Opcode::Null, &[]),
+                // 0015
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+    ]);
+}
+
+#[test]
+fn CompilesGlobalLetStatements() {
+    testCompile(vec![
+        (
+            "let one = 1; let two = one; two",
+            vec![int(1)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::SetGlobal, &[0]),
+                Make(Opcode::GetGlobal, &[0]),
+                Make(Opcode::SetGlobal, &[1]),
+                Make(Opcode::GetGlobal, &[1]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        // binding a name again reuses its global
+        (
+            "let x = 1; let x = x + 1",
+            vec![int(1)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::SetGlobal, &[0]),
+                Make(Opcode::GetGlobal, &[0]),
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::Add, &[]),
+                Make(Opcode::SetGlobal, &[0]),
+            ],
+        ),
+    ]);
+}
+
+#[test]
+fn EqualConstantsArePooledOnce() {
+    let input = r#"
+        let total = 0;
+        let total = total + 1 * 2; let total = total + 1 * 2;
+        let total = total + 1 * 2; let total = total + 1 * 2;
+        if (total > 1) { "big" } else { "small" }; "big"; "1"
+    "#;
+    let bytecode = compile(input);
+    assert_eq!(
+        bytecode.constants,
+        vec![
+            int(0),
+            int(1),
+            int(2),
+            string("big"),
+            string("small"),
+            string("1")
+        ]
+    );
+
+    // a line compiled later shares the pool of the ones before
+    let mut compiler = Compiler::New();
+    compiler.Compile(&parse("1; 2")).unwrap();
+    assert!(compiler.Compile(&parse("2; 3; undefined")).is_err());
+    compiler.Compile(&parse("3; 2; 1")).unwrap();
+    assert_eq!(compiler.Bytecode().constants, vec![int(1), int(2), int(3)]);
+}
+
+#[test]
+fn CompilesBuiltins() {
+    testCompile(vec![
+        (
+            "len; puts",
+            vec![],
+            vec![
+                Make(Opcode::GetBuiltin, &[0]),
+                Make(Opcode::Pop, &[]),
+                Make(Opcode::GetBuiltin, &[1]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+        // a global of the same name hides it
+        (
+            "let len = 1; len",
+            vec![int(1)],
+            vec![
+                Make(Opcode::Constant, &[0]),
+                Make(Opcode::SetGlobal, &[0]),
+                Make(Opcode::GetGlobal, &[0]),
+                Make(Opcode::Pop, &[]),
+            ],
+        ),
+    ]);
+}
+
+fn symbol(name: &str, scope: Scope, index: usize) -> Symbol {
+    Symbol {
+        name: name.into(),
+        scope,
+        index,
+    }
+}
+
+#[test]
+fn SymbolsResolveToTheirScopes() {
+    let mut global = SymbolTable::New();
+    global.DefineBuiltin(4, "chars");
+    assert_eq!(global.Define("a"), symbol("a", Scope::Global, 0));
+    assert_eq!(global.Define("b"), symbol("b", Scope::Global, 1));
+    assert_eq!(global.Define("a"), symbol("a", Scope::Global, 0));
+
+    let mut local = SymbolTable::Enclosed(global);
+    assert_eq!(local.Define("c"), symbol("c", Scope::Local, 0));
+    assert_eq!(local.Define("b"), symbol("b", Scope::Local, 1));
+    assert_eq!(local.Definitions(), 2);
+
+    assert_eq!(local.Resolve("a"), Some(symbol("a", Scope::Global, 0)));
+    assert_eq!(local.Resolve("b"), Some(symbol("b", Scope::Local, 1)));
+    assert_eq!(
+        local.Resolve("chars"),
+        Some(symbol("chars", Scope::Builtin, 4))
+    );
+    assert_eq!(local.Resolve("d"), None);
+    assert!(local.Free().is_empty());
+
+    let mut global = local.Outer().unwrap();
+    assert_eq!(global.Resolve("b"), Some(symbol("b", Scope::Global, 1)));
+    assert_eq!(global.Resolve("c"), None);
+    assert!(global.Outer().is_none());
+}
+
+#[test]
+fn LocalsOfEnclosingFunctionsResolveAsFree() {
+    let mut global = SymbolTable::New();
+    global.Define("a");
+    let mut first = SymbolTable::Enclosed(global);
+    first.Define("b");
+    first.Define("c");
+    let mut second = SymbolTable::Enclosed(first);
+    second.Define("d");
+    let mut third = SymbolTable::Enclosed(second);
+
+    assert_eq!(third.Resolve("a"), Some(symbol("a", Scope::Global, 0)));
+    assert_eq!(third.Resolve("c"), Some(symbol("c", Scope::Free, 0)));
+    assert_eq!(third.Resolve("b"), Some(symbol("b", Scope::Free, 1)));
+    assert_eq!(third.Resolve("d"), Some(symbol("d", Scope::Free, 2)));
+    assert_eq!(third.Resolve("c"), Some(symbol("c", Scope::Free, 0)));
+    // what the free symbols are where the function is written
+    assert_eq!(
+        third.Free(),
+        [
+            symbol("c", Scope::Free, 0),
+            symbol("b", Scope::Free, 1),
+            symbol("d", Scope::Local, 0),
+        ]
+    );
+
+    let second = third.Outer().unwrap();
+    assert_eq!(
+        second.Free(),
+        [symbol("c", Scope::Local, 1), symbol("b", Scope::Local, 0)]
+    );
+    assert!(second.Outer().unwrap().Free().is_empty());
+}
+
+#[test]
+fn CompileErrorsLeaveTheCompilerAsItWas() {
+    let mut compiler = Compiler::New();
+    compiler.Compile(&parse("let x = 1")).unwrap();
+    let before = compiler.Bytecode();
+
+    assert_eq!(
+        compiler.Compile(&parse("let y = 2; y + z")),
+        Err(CompileError::Undefined {
+            name: "z".into(),
+            span: 15..16,
+        })
+    );
+    let error = compiler.Compile(&parse("let f = fn(x) { x }")).unwrap_err();
+    assert_eq!(error.to_string(), "can't compile `fn(x)x` yet");
+    assert_eq!(compiler.Bytecode(), before);
+
+    // `y` was never bound
+    assert!(compiler.Compile(&parse("y")).is_err());
+    compiler.Compile(&parse("x")).unwrap();
+    assert_eq!(
+        compiler.Bytecode().instructions,
+        Instructions::from(vec![
+            Make(Opcode::Constant, &[0]),
+            Make(Opcode::SetGlobal, &[0]),
+            Make(Opcode::GetGlobal, &[0]),
+            Make(Opcode::Pop, &[]),
+        ])
+    );
+}
+
+#[test]
+fn SavedBytecodeLoadsTheSame() {
+    let bytecode = compile(r#"let s = "héllo"; if (s == "") { -1 } else { 9000000000 }"#);
+    let mut file = vec![];
+    bytecode.Save(&mut file).unwrap();
+    assert_eq!(&file[..10], b"MONKEYC\0\x01\0");
+    assert_eq!(Bytecode::Load(&mut &file[..]).unwrap(), bytecode);
+
+    let mut empty = vec![];
+    Compiler::New().Bytecode().Save(&mut empty).unwrap();
+    assert_eq!(
+        Bytecode::Load(&mut &empty[..]).unwrap(),
+        Compiler::New().Bytecode()
+    );
+}
+
+#[test]
+fn LoadRejectsWhatSaveDidntWrite() {
+    let mut file = vec![];
+    compile("1 + 2").Save(&mut file).unwrap();
+    let load = |bytes: &[u8]| {
+        let error = Bytecode::Load(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        error.to_string()
+    };
+
+    assert_eq!(load(b"#!/bin/sh"), "the file isn't compiled Monkey");
+    let mut newer = file.clone();
+    newer[8] = 2;
+    assert_eq!(
+        load(&newer),
+        "the bytecode was saved in version 2 of the format, and only version 1 can be loaded"
+    );
+    for len in [4, 12, file.len() - 1] {
+        assert_eq!(load(&file[..len]), "the bytecode is corrupt: it is cut off");
+    }
+    let mut longer = file.clone();
+    longer.push(0);
+    assert_eq!(
+        load(&longer),
+        "the bytecode is corrupt: something comes after the constants"
+    );
+    // the instructions start after the header and their length
+    let mut unknown = file.clone();
+    unknown[14] = 255;
+    assert_eq!(
+        load(&unknown),
+        "the bytecode is corrupt: unknown opcode 255"
+    );
+    // the second constant's index is its last byte
+    let mut missing = file.clone();
+    missing[19] = 2;
+    assert_eq!(
+        load(&missing),
+        "the bytecode is corrupt: constant 2 is loaded, and there are only 2"
+    );
+
+    let saved = |instructions: Vec<Vec<u8>>| {
+        let mut file = vec![];
+        Bytecode {
+            instructions: instructions.into(),
+            constants: vec![],
+        }
+        .Save(&mut file)
+        .unwrap();
+        file
+    };
+    // to the end is fine, but not past it or into an instruction
+    let jumps = vec![Make(Opcode::Jump, &[4]), Make(Opcode::Null, &[])];
+    assert!(Bytecode::Load(&mut &saved(jumps)[..]).is_ok());
+    for target in [5, 2] {
+        let jumps = vec![
+            Make(Opcode::JumpNotTruthy, &[target]),
+            Make(Opcode::Null, &[]),
+        ];
+        assert_eq!(
+            load(&saved(jumps)),
+            format!(
+                "the bytecode is corrupt: a jump to {} doesn't land on an instruction",
+                target
+            )
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Evaluation that can be suspended when it runs out of fuel or time, and
+//! resumed later, so one thread can time-slice many scripts.
+//!
+//! The tree-walking evaluator keeps its state on the Rust stack, so a
+//! resumable evaluation runs on a separate stack of its own (a coroutine).
+//! Running out of a limit switches back to the host's stack, leaving the
+//! evaluation exactly where it was.
+
+use std::rc::Rc;
+
+use corosensei::stack::DefaultStack;
+use corosensei::Coroutine;
+use corosensei::CoroutineResult;
+use corosensei::Yielder;
+
+use crate::ast::NodeEnum;
+use crate::environment::Env;
+use crate::evaluator::Eval;
+use crate::object::ObjectEnum;
+use crate::runtime::Limit;
+use crate::runtime::LimitHandler;
+use crate::runtime::Runtime;
+
+/// The stack of an evaluation whose runtime doesn't limit its depth, which
+/// deeper recursion overflows.
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+pub enum Step {
+    Done(Option<ObjectEnum>),
+    Suspended(Continuation),
+}
+
+/// An evaluation suspended because it ran out of `Limit`.
+pub struct Continuation {
+    coroutine: Coroutine<(), Limit, Option<ObjectEnum>, DefaultStack>,
+    limit: Limit,
+}
+
+impl Continuation {
+    /// The limit the evaluation ran out of.
+    pub fn Limit(&self) -> Limit {
+        self.limit
+    }
+
+    /// Continues the evaluation with a fresh budget of the same size.
+    pub fn Resume(mut self) -> Step {
+        match self.coroutine.resume(()) {
+            CoroutineResult::Yield(limit) => {
+                self.limit = limit;
+                Step::Suspended(self)
+            }
+            CoroutineResult::Return(result) => Step::Done(result),
+        }
+    }
+}
+
+/// Puts back the runtime's own limit handler once the evaluation finishes or
+/// its continuation is dropped.
+struct RestoreHandler {
+    runtime: Rc<Runtime>,
+    handler: Option<LimitHandler>,
+}
+
+impl Drop for RestoreHandler {
+    fn drop(&mut self) {
+        self.runtime.ReplaceLimitHandler(self.handler.take());
+    }
+}
+
+/// Evaluates `node` like `Eval`, except that running out of a limit suspends
+/// the evaluation instead of failing. While it runs, the evaluation replaces
+/// the runtime's limit handler. Its stack fits the runtime's depth limit,
+/// and is `STACK_SIZE` without one.
+pub fn EvalResumable(node: NodeEnum, env: &Env) -> Step {
+    let stackSize = env.borrow().Runtime().Limits().StackSize();
+    EvalResumableWithStack(node, env, stackSize.unwrap_or(STACK_SIZE))
+}
+
+/// Like `EvalResumable`, on a stack of `stackSize` bytes.
+pub fn EvalResumableWithStack(node: NodeEnum, env: &Env, stackSize: usize) -> Step {
+    let env = Rc::clone(env);
+    let stack = DefaultStack::new(stackSize).expect("failed to allocate evaluation stack");
+    let coroutine = Coroutine::with_stack(stack, move |yielder: &Yielder<(), Limit>, ()| {
+        let runtime = Rc::clone(env.borrow().Runtime());
+        let yielder: *const Yielder<(), Limit> = yielder;
+        let suspend: LimitHandler = Box::new(move |limit| {
+            // SAFETY: the handler is only installed while this coroutine is
+            // running and RestoreHandler removes it before the coroutine's
+            // stack, which holds the yielder, goes away. Limits are only
+            // checked by evaluations on this stack.
+            unsafe { (*yielder).suspend(limit) };
+            true
+        });
+        let _restore = RestoreHandler {
+            handler: runtime.ReplaceLimitHandler(Some(suspend)),
+            runtime,
+        };
+        Eval(node, &env)
+    });
+
+    Continuation {
+        coroutine,
+        limit: Limit::Fuel(0),
+    }
+    .Resume()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,117 @@
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::evaluator::Eval;
+use crate::lexer::Lexer;
+use crate::object::ErrorKind;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::parser::Parser;
+use crate::runtime::Limit;
+use crate::runtime::Limits;
+use crate::runtime::Runtime;
+
+use super::EvalResumable;
+use super::Step;
+
+fn fuelRuntime(fuel: u64) -> Rc<Runtime> {
+    Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(fuel),
+        time: None,
+        depth: None,
+    }))
+}
+
+fn start(input: &str, runtime: &Rc<Runtime>) -> Step {
+    let env = Environment::WithRuntime(Rc::clone(runtime));
+    let mut p = Parser::New(Lexer::New(input));
+    EvalResumable(p.ParseProgram().into(), &env)
+}
+
+fn sumTo(n: i64) -> String {
+    format!(
+        "let sum = fn(n) {{ if (n == 0) {{ 0 }} else {{ n + sum(n - 1) }} }}; sum({})",
+        n
+    )
+}
+
+#[test]
+fn FinishesWithoutSuspending() {
+    match start("1 + 2", &fuelRuntime(100)) {
+        Step::Done(Some(ObjectEnum::Integer(i))) => assert_eq!(i.value, 3),
+        _ => panic!("expected the evaluation to finish"),
+    }
+}
+
+#[test]
+fn TimeSlicesScripts() {
+    let mut pending: Vec<(i64, Step)> = (10..15)
+        .map(|n| (n, start(&sumTo(n), &fuelRuntime(5))))
+        .collect();
+
+    let mut suspensions = 0;
+    while !pending.is_empty() {
+        let mut next = vec![];
+        for (n, step) in pending {
+            match step {
+                Step::Suspended(continuation) => {
+                    assert_eq!(continuation.Limit(), Limit::Fuel(5));
+                    suspensions += 1;
+                    next.push((n, continuation.Resume()));
+                }
+                Step::Done(result) => match result.unwrap() {
+                    ObjectEnum::Integer(i) => assert_eq!(i.value, n * (n + 1) / 2),
+                    other => panic!("unexpected result {}", other.Inspect()),
+                },
+            }
+        }
+        pending = next;
+    }
+    assert!(suspensions > 5);
+}
+
+#[test]
+fn DroppingAContinuationRestoresTheHandler() {
+    let runtime = fuelRuntime(5);
+    runtime.OnLimitExceeded(|_| false);
+    match start(&sumTo(10), &runtime) {
+        Step::Suspended(continuation) => drop(continuation),
+        Step::Done(_) => panic!("expected the evaluation to suspend"),
+    }
+
+    // outside of EvalResumable the original handler applies again
+    runtime.Reset();
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let input = sumTo(10);
+    let mut p = Parser::New(Lexer::New(&input));
+    match Eval(p.ParseProgram().into(), &env) {
+        Some(ObjectEnum::Error(e)) => assert_eq!(e.message, "evaluation exceeded 5 steps"),
+        _ => panic!("expected a limit error"),
+    }
+}
+
+#[test]
+fn DeepRecursionReachesTheDepthLimit() {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: None,
+        time: None,
+        depth: Some(1000),
+    }));
+    // the calls are first in an array, which takes more stack than most
+    let deep = |n| {
+        format!(
+            "let f = fn(n) {{ if (n == 0) {{ 0 }} else {{ [f(n - 1)][0] + 1 }} }}; f({})",
+            n
+        )
+    };
+    match start(&deep(990), &runtime) {
+        Step::Done(Some(ObjectEnum::Integer(i))) => assert_eq!(i.value, 990),
+        _ => panic!("expected the evaluation to finish"),
+    }
+    match start(&deep(5000), &runtime) {
+        Step::Done(Some(ObjectEnum::Error(e))) => {
+            assert_eq!(e.kind, ErrorKind::StackOverflow);
+        }
+        _ => panic!("expected a stack overflow error"),
+    }
+}
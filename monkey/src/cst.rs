@@ -0,0 +1,86 @@
+//! A lossless parse: the syntax tree together with every token and the
+//! trivia before it, the whitespace the parser skips over, so a formatter or
+//! refactoring tool can give back the source exactly as it was written and
+//! find the trivia around any node.
+
+use crate::ast::Node;
+use crate::ast::Program;
+use crate::edition::Edition;
+use crate::lexer::Lexer;
+use crate::parser::ParseError;
+use crate::parser::Parser;
+use crate::token::Token;
+use crate::token::TokenKind;
+
+/// A token with the source it was lexed from and the trivia before it.
+#[derive(Debug, Clone)]
+pub struct TriviaToken {
+    pub leading: String,
+    pub token: Token,
+    /// Exactly as written, with a string's quotes.
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cst {
+    pub program: Program,
+    pub errors: Vec<ParseError>,
+    /// In source order, the last of them EOF with the trailing trivia.
+    pub tokens: Vec<TriviaToken>,
+}
+
+impl Cst {
+    /// The source the tree was parsed from.
+    pub fn Source(&self) -> String {
+        let mut source = String::new();
+        for token in &self.tokens {
+            source += &token.leading;
+            source += &token.text;
+        }
+        source
+    }
+
+    /// The token `node` is at (see `Node::Position`), with the trivia before
+    /// it. `None` for nodes made up by the parser.
+    pub fn TokenOf(&self, node: &impl Node) -> Option<&TriviaToken> {
+        let offset = node.Position().offset;
+        let index = self
+            .tokens
+            .binary_search_by_key(&offset, |token| token.token.position.offset)
+            .ok()?;
+        Some(&self.tokens[index])
+    }
+}
+
+/// Parses `input` like `Parser::WithEdition` does, keeping the trivia.
+pub fn Parse(input: &str, edition: Edition) -> Cst {
+    let mut p = Parser::WithEdition(Lexer::New(input), edition);
+    let program = p.ParseProgram();
+
+    let mut tokens = vec![];
+    let mut end = 0;
+    for token in Lexer::New(input) {
+        let span = token.Span();
+        let start = span.start.min(input.len());
+        // the lexer stops at a NUL, so whatever is after it goes with EOF
+        let stop = match token.kind {
+            TokenKind::EOF => input.len(),
+            _ => span.end.min(input.len()),
+        };
+        tokens.push(TriviaToken {
+            leading: input[end..start].to_string(),
+            text: input[start..stop].to_string(),
+            token,
+        });
+        end = stop;
+    }
+
+    Cst {
+        program,
+        errors: p.errors,
+        tokens,
+    }
+}
+
+#[cfg(test)]
+mod tests;
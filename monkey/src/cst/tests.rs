@@ -0,0 +1,31 @@
+use crate::edition::Edition;
+use crate::token::TokenKind;
+
+use super::Parse;
+
+#[test]
+fn RoundTrips() {
+    let tests = [
+        "",
+        "  \n",
+        "let x = 1;\n\n  let   s = \"a ${ x }\n b\";\t\nputs( s )\n",
+        "let é = \"unterminated",
+        "1 @ 2",
+        "1;\0 rest",
+    ];
+    for input in tests {
+        let cst = Parse(input, Edition::default());
+        assert_eq!(cst.Source(), input);
+        assert_eq!(cst.tokens.last().unwrap().token.kind, TokenKind::EOF);
+    }
+}
+
+#[test]
+fn TriviaOfNodes() {
+    let cst = Parse("let a = 1;\n\n   let b = a;", Edition::default());
+    assert!(cst.errors.is_empty());
+    let token = cst.TokenOf(&cst.program.statements[1]).unwrap();
+    assert_eq!(token.leading, "\n\n   ");
+    assert_eq!(token.text, "let");
+    assert_eq!(cst.tokens.last().unwrap().leading, "");
+}
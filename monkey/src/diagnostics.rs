@@ -0,0 +1,243 @@
+//! Parse errors, warnings and runtime errors as a terminal shows them: a
+//! header with the message, each line of the source the problem is about
+//! with the offending part underlined, and notes, optionally in color.
+//!
+//! ```text
+//! error: expected next token to be RPAREN, got SEMICOLON instead
+//!   --> 2:15
+//!   |
+//! 2 | let y = (x + 2;
+//!   |               ^ expected RPAREN
+//! ```
+
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::object::Error;
+use crate::parser::ParseError;
+use crate::warning::Warning;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        })
+    }
+}
+
+/// A part of the source a diagnostic is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// The byte range of the part. An empty range points just before the
+    /// byte it starts at.
+    pub span: Range<usize>,
+    /// Shown after the underline; may be empty.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// What kind of problem it is, like a warning's code or an error's kind.
+    pub code: Option<String>,
+    pub message: String,
+    /// The first is the main one, which the header points to.
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+impl Diagnostic {
+    /// The diagnostic with the lines of `source` its labels are on, in ANSI
+    /// colors if `color` is set.
+    pub fn Render(&self, source: &str, color: bool) -> String {
+        let paint = |code: &'static str| if color { code } else { "" };
+        let reset = paint(RESET);
+        let (accent, blue) = match self.severity {
+            Severity::Error => (paint(RED), paint(BLUE)),
+            Severity::Warning => (paint(YELLOW), paint(BLUE)),
+        };
+
+        let mut out = format!("{}{}", accent, self.severity);
+        if let Some(code) = &self.code {
+            out += &format!("[{}]", code);
+        }
+        out += &format!("{}{}: {}{}", reset, paint(BOLD), self.message, reset);
+
+        let lines: Vec<Line> = self
+            .labels
+            .iter()
+            .map(|label| Line::Of(source, &label.span))
+            .collect();
+        let width = lines
+            .iter()
+            .map(|line| line.number.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(width);
+        if let Some(first) = lines.first() {
+            out += &format!(
+                "\n{} {}-->{} {}:{}",
+                gutter, blue, reset, first.number, first.column
+            );
+            out += &format!("\n{} {}|{}", gutter, blue, reset);
+        }
+        for (i, (label, line)) in self.labels.iter().zip(&lines).enumerate() {
+            let (marker, color) = if i == 0 { ('^', accent) } else { ('-', blue) };
+            out += &format!(
+                "\n{}{:>width$} |{} {}",
+                blue,
+                line.number,
+                reset,
+                line.text,
+                width = width
+            );
+            out += &format!(
+                "\n{} {}|{} {}{}{}",
+                gutter,
+                blue,
+                reset,
+                " ".repeat(line.column - 1),
+                color,
+                marker.to_string().repeat(line.width)
+            );
+            if !label.message.is_empty() {
+                out += &format!(" {}", label.message);
+            }
+            out += reset;
+        }
+        for note in &self.notes {
+            out += &format!(
+                "\n{} {}={} {}note{}: {}",
+                gutter,
+                blue,
+                reset,
+                paint(BOLD),
+                reset,
+                note
+            );
+        }
+        out
+    }
+}
+
+/// The line of the source a label is on.
+struct Line<'src> {
+    text: &'src str,
+    number: usize,
+    /// Where the label starts, in chars from 1.
+    column: usize,
+    /// How many chars of the line the label covers, at least 1.
+    width: usize,
+}
+
+impl<'src> Line<'src> {
+    fn Of(source: &'src str, span: &Range<usize>) -> Self {
+        let start = floorCharBoundary(source, span.start);
+        let lineStart = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let lineEnd = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let end = floorCharBoundary(source, span.end.clamp(start, lineEnd));
+        Self {
+            text: &source[lineStart..lineEnd],
+            number: source[..lineStart].matches('\n').count() + 1,
+            column: source[lineStart..start].chars().count() + 1,
+            width: source[start..end].chars().count().max(1),
+        }
+    }
+}
+
+/// The last char boundary of `source` at or before `offset`.
+fn floorCharBoundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        let message = match &error.expected {
+            Some(expected) => format!("expected {}", expected),
+            None => String::new(),
+        };
+        let mut notes = vec![];
+        if error.found == "ILLEGAL" {
+            notes.push("this character isn't part of Monkey's syntax".into());
+        }
+        Self {
+            severity: Severity::Error,
+            code: None,
+            message: error.message.clone(),
+            labels: vec![Label {
+                span: error.span.clone(),
+                message,
+            }],
+            notes,
+        }
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code: Some(warning.code.to_string()),
+            message: warning.message.clone(),
+            labels: warning
+                .span
+                .iter()
+                .map(|span| Label {
+                    span: span.clone(),
+                    message: String::new(),
+                })
+                .collect(),
+            notes: vec![],
+        }
+    }
+}
+
+/// A runtime error has no position of its own, so its labels are the calls
+/// it came out of, innermost first. Their positions are in the source of
+/// the program that made them.
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        let mut labels = vec![];
+        let mut notes = vec![];
+        if let Some(trace) = &error.trace {
+            for frame in &trace.frames {
+                labels.push(Label {
+                    span: frame.call.offset..frame.call.offset,
+                    message: format!("in {}", frame.function),
+                });
+            }
+            if trace.elided > 0 {
+                notes.push(format!("... and {} more calls", trace.elided));
+            }
+        }
+        Self {
+            severity: Severity::Error,
+            code: Some(error.kind.to_string()),
+            message: error.message.clone(),
+            labels,
+            notes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
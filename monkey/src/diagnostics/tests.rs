@@ -0,0 +1,75 @@
+use crate::environment::Environment;
+use crate::evaluator::Eval;
+use crate::lexer::Lexer;
+use crate::object::ObjectEnum;
+use crate::parser::Parser;
+
+use super::Diagnostic;
+
+#[test]
+fn Warnings() {
+    let input = "let x = 1\nlet y = 2;";
+    let mut p = Parser::New(Lexer::New(input));
+    p.ParseProgram();
+    let warning = p.warnings.Get().first().unwrap();
+    assert_eq!(
+        Diagnostic::from(warning).Render(input, false),
+        "warning[missing-semicolon]: statement without a semicolon; edition 2 requires one
+  --> 1:10
+  |
+1 | let x = 1
+  |          ^"
+    );
+}
+
+#[test]
+fn RuntimeErrors() {
+    let input = "let inner = fn(x) { x + true };
+let outer = fn(x) { inner(x) + 1 };
+outer(1)";
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    let error = match Eval(program.into(), &Environment::New()) {
+        Some(ObjectEnum::Error(error)) => error,
+        other => panic!("not an error: {:?}", other),
+    };
+    assert_eq!(
+        Diagnostic::from(&error).Render(input, false),
+        "error[TypeError]: type mismatch: INTEGER + BOOLEAN
+  --> 2:21
+  |
+2 | let outer = fn(x) { inner(x) + 1 };
+  |                     ^ in inner
+3 | outer(1)
+  | - in outer"
+    );
+
+    // an error from outside any call has nothing to point at
+    let mut p = Parser::New(Lexer::New("1 + true"));
+    let program = p.ParseProgram();
+    let error = match Eval(program.into(), &Environment::New()) {
+        Some(ObjectEnum::Error(error)) => error,
+        other => panic!("not an error: {:?}", other),
+    };
+    assert_eq!(
+        Diagnostic::from(&error).Render("1 + true", false),
+        "error[TypeError]: type mismatch: INTEGER + BOOLEAN"
+    );
+}
+
+#[test]
+fn Colors() {
+    let input = "let x = @;";
+    let mut p = Parser::New(Lexer::New(input));
+    p.ParseProgram();
+    let error = p.errors.first().unwrap();
+    assert_eq!(
+        Diagnostic::from(error).Render(input, true),
+        "\x1b[1;31merror\x1b[0m\x1b[1m: no prefix parse function for ILLEGAL found\x1b[0m
+  \x1b[1;34m-->\x1b[0m 1:9
+  \x1b[1;34m|\x1b[0m
+\x1b[1;34m1 |\x1b[0m let x = @;
+  \x1b[1;34m|\x1b[0m         \x1b[1;31m^ expected an expression\x1b[0m
+  \x1b[1;34m=\x1b[0m \x1b[1mnote\x1b[0m: this character isn't part of Monkey's syntax"
+    );
+}
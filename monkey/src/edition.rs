@@ -0,0 +1,42 @@
+//! Language editions. Changes that would break existing scripts only apply
+//! to the editions that opt into them, so a script keeps the meaning it had
+//! when it was written. Edition 1 is the language as it has always been.
+
+use strum_macros::Display;
+use strum_macros::EnumString;
+
+#[derive(
+    Display, EnumString, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Edition {
+    #[default]
+    #[strum(serialize = "1")]
+    V1,
+    /// Strict truthiness, required semicolons and reserved words.
+    #[strum(serialize = "2")]
+    V2,
+}
+
+/// Words reserved from edition 2 on, for syntax to come.
+const RESERVED: &[&str] = &["while", "for", "break", "continue", "const"];
+
+impl Edition {
+    pub const LATEST: Edition = Edition::V2;
+
+    /// Whether conditions and the operand of `!` must be booleans, rather
+    /// than everything but `false` and `null` counting as true.
+    pub fn StrictTruthiness(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// Whether a statement has to end with a semicolon, unless it ends with a
+    /// block or comes last in one.
+    pub fn RequiresSemicolons(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// Whether `name` can't be used as an identifier.
+    pub fn IsReserved(self, name: &str) -> bool {
+        self >= Self::V2 && RESERVED.contains(&name)
+    }
+}
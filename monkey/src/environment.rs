@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::ExpressionEnum;
+use crate::object::ObjectEnum;
+use crate::runtime::Runtime;
+
+pub type Env = Rc<RefCell<Environment>>;
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    store: HashMap<String, ObjectEnum>,
+    outer: Option<Env>,
+    /// Expressions registered with `defer`, in the order they were deferred.
+    deferred: Vec<ExpressionEnum>,
+    /// Shared with every environment enclosed by this one.
+    runtime: Rc<Runtime>,
+}
+
+impl Environment {
+    pub fn New() -> Env {
+        Rc::new(RefCell::new(Self::default()))
+    }
+
+    /// Creates a global environment whose evaluation is governed by `runtime`.
+    pub fn WithRuntime(runtime: Rc<Runtime>) -> Env {
+        Rc::new(RefCell::new(Self {
+            runtime,
+            ..Self::default()
+        }))
+    }
+
+    pub fn NewEnclosed(outer: &Env) -> Env {
+        let runtime = Rc::clone(&outer.borrow().runtime);
+        Rc::new(RefCell::new(Self {
+            store: runtime.TakeStore(),
+            outer: Some(Rc::clone(outer)),
+            runtime,
+            ..Self::default()
+        }))
+    }
+
+    /// Hands the bindings table of `env` back to its runtime for reuse, if
+    /// nothing else (like a closure) still refers to `env`.
+    pub(crate) fn Release(env: Env) {
+        if let Ok(env) = Rc::try_unwrap(env) {
+            let env = env.into_inner();
+            env.runtime.RecycleStore(env.store);
+        }
+    }
+
+    pub fn Runtime(&self) -> &Rc<Runtime> {
+        &self.runtime
+    }
+
+    pub fn Get(&self, name: &str) -> Option<ObjectEnum> {
+        match self.store.get(name) {
+            Some(obj) => Some(obj.clone()),
+            None => self
+                .outer
+                .as_ref()
+                .and_then(|outer| outer.borrow().Get(name)),
+        }
+    }
+
+    /// This environment's own bindings (not those of outer environments).
+    pub(crate) fn Bindings(&self) -> &HashMap<String, ObjectEnum> {
+        &self.store
+    }
+
+    pub fn Set(&mut self, name: &str, val: ObjectEnum) -> ObjectEnum {
+        self.store.insert(name.to_string(), val.clone());
+        val
+    }
+
+    /// Rebinds `name` in the innermost environment that already binds it.
+    /// Returns false, changing nothing, if no environment does.
+    pub fn Assign(&mut self, name: &str, val: ObjectEnum) -> bool {
+        if let Some(slot) = self.store.get_mut(name) {
+            *slot = val;
+            true
+        } else {
+            match self.outer.as_ref() {
+                Some(outer) => outer.borrow_mut().Assign(name, val),
+                None => false,
+            }
+        }
+    }
+
+    /// Captures this environment's own bindings (not those of outer
+    /// environments) so they can be compared against or restored later.
+    pub fn Snapshot(&self) -> Snapshot {
+        Snapshot {
+            store: self.store.clone(),
+        }
+    }
+
+    /// Puts back the bindings captured by `snapshot`, discarding any made since.
+    pub fn Restore(&mut self, snapshot: Snapshot) {
+        self.store = snapshot.store;
+    }
+
+    pub(crate) fn Defer(&mut self, exp: ExpressionEnum) {
+        self.deferred.push(exp);
+    }
+
+    pub(crate) fn HasDeferred(&self) -> bool {
+        !self.deferred.is_empty()
+    }
+
+    /// Removes and returns the deferred expressions, most recent first.
+    pub(crate) fn TakeDeferred(&mut self) -> Vec<ExpressionEnum> {
+        let mut deferred = std::mem::take(&mut self.deferred);
+        deferred.reverse();
+        deferred
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    store: HashMap<String, ObjectEnum>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        name: String,
+        value: ObjectEnum,
+    },
+    Changed {
+        name: String,
+        old: ObjectEnum,
+        new: ObjectEnum,
+    },
+}
+
+impl Snapshot {
+    /// The bindings `env` added or changed since this snapshot was taken,
+    /// sorted by name.
+    pub fn Changes(&self, env: &Environment) -> Vec<Change> {
+        let mut changes: Vec<Change> = env
+            .store
+            .iter()
+            .filter_map(|(name, value)| match self.store.get(name) {
+                None => Some(Change::Added {
+                    name: name.clone(),
+                    value: value.clone(),
+                }),
+                Some(old) if old != value => Some(Change::Changed {
+                    name: name.clone(),
+                    old: old.clone(),
+                    new: value.clone(),
+                }),
+                Some(_) => None,
+            })
+            .collect();
+        changes.sort_by(|a, b| a.Name().cmp(b.Name()));
+        changes
+    }
+}
+
+impl Change {
+    pub fn Name(&self) -> &str {
+        match self {
+            Self::Added { name, .. } | Self::Changed { name, .. } => name,
+        }
+    }
+}
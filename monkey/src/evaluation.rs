@@ -0,0 +1,106 @@
+//! Evaluating a program and getting everything about the run back together:
+//! its value, what it printed, the warnings it raised, how much work it did
+//! and how long it took.
+
+use std::fmt::Display;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::ast::Program;
+use crate::environment::Env;
+use crate::evaluator::isError;
+use crate::evaluator::Eval;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::ObjectEnum;
+use crate::object::NULL;
+use crate::platform::Capture;
+use crate::runtime::RunStats;
+use crate::warning::Warning;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    /// What `Eval` returns for the program.
+    pub value: Option<ObjectEnum>,
+    /// Everything the program wrote to standard output.
+    pub output: String,
+    /// The warnings raised while evaluating.
+    pub diagnostics: Vec<Warning>,
+    pub stats: RunStats,
+    /// By the platform's clock.
+    pub duration: Duration,
+}
+
+impl Evaluation {
+    /// The value of the program, null if it has none.
+    pub fn Value(&self) -> ObjectEnum {
+        self.value.clone().unwrap_or(NULL)
+    }
+
+    /// Whether the program ended with an error.
+    pub fn IsError(&self) -> bool {
+        self.value.as_ref().is_some_and(isError)
+    }
+
+    /// The value of the program, null if it has none, or the error it ended
+    /// with. A value thrown and never caught is an `Uncaught` error holding
+    /// it (see `RuntimeError::Thrown`).
+    pub fn Result(&self) -> Result<ObjectEnum, RuntimeError> {
+        match self.Value() {
+            ObjectEnum::Error(err) => Err(RuntimeError(err)),
+            ObjectEnum::Thrown(thrown) => Err(RuntimeError(thrown.Uncaught())),
+            value => Ok(value),
+        }
+    }
+}
+
+/// The error a program ended with, for hosts that want a Rust error. It
+/// displays as the error's message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError(pub Error);
+
+impl RuntimeError {
+    /// The value thrown, if the program ended with one nothing caught.
+    pub fn Thrown(&self) -> Option<&ObjectEnum> {
+        match self.0.kind {
+            ErrorKind::Uncaught => self.0.data.get("value"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Evaluates `program` in `env` with a fresh budget (see `Runtime::Reset`).
+/// The output goes to the evaluation rather than to the runtime's platform,
+/// and the warnings raised before, if they weren't taken, are included.
+pub fn Evaluate(program: Program, env: &Env) -> Evaluation {
+    let runtime = Rc::clone(env.borrow().Runtime());
+    let platform = runtime.Platform();
+    let capture = Rc::new(Capture::New(Rc::clone(&platform)));
+    runtime.SetPlatform(capture.clone());
+    runtime.Reset();
+
+    let start = platform.Now();
+    let value = Eval(program.into(), env);
+    let duration = platform.Now().saturating_sub(start);
+    runtime.SetPlatform(platform);
+
+    let diagnostics = runtime.Warnings().Take();
+    Evaluation {
+        value,
+        output: capture.TakeOutput(),
+        diagnostics,
+        stats: runtime.Stats(),
+        duration,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,134 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::environment::Environment;
+use crate::lexer::Lexer;
+use crate::lexer::Tokenize;
+use crate::object::Builtin;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::object::Thrown;
+use crate::object::NULL;
+use crate::parser::Parser;
+use crate::platform::MemoryPlatform;
+use crate::runtime::RunStats;
+use crate::runtime::Runtime;
+use crate::warning::WarningCode;
+
+use super::Evaluate;
+use super::Evaluation;
+use super::RuntimeError;
+
+#[test]
+fn EvaluatesWithEverything() {
+    let platform = Rc::new(MemoryPlatform::New());
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    let env = Environment::WithRuntime(runtime);
+    let clock = Rc::clone(&platform);
+    env.borrow_mut().Set(
+        "tick",
+        Builtin::new("tick", move |_, _| {
+            clock.Advance(Duration::from_millis(5));
+            NULL
+        })
+        .into(),
+    );
+
+    let input = r#"
+        let f = fn(n) { if (n > 0) { 1 + f(n - 1) } else { 0 } };
+        puts("hi");
+        if (1) { tick() };
+        f(3)
+    "#;
+    let evaluation = Evaluate(Parser::New(Lexer::New(input)).ParseProgram(), &env);
+    assert_eq!(evaluation.Value().Inspect(), "3");
+    assert!(!evaluation.IsError());
+    assert_eq!(evaluation.output, "hi\n");
+    assert_eq!(platform.Output(), "");
+    assert_eq!(evaluation.duration, Duration::from_millis(5));
+    assert!(evaluation
+        .diagnostics
+        .iter()
+        .any(|w| w.code == WarningCode::Truthiness));
+    let RunStats {
+        steps,
+        builtinCalls,
+        maxDepth,
+    } = evaluation.stats;
+    assert!(steps > 4, "{}", steps);
+    assert_eq!((builtinCalls, maxDepth), (2, 4));
+
+    // each evaluation is counted on its own
+    let evaluation = Evaluate(Parser::New(Lexer::New("let x = 1;")).ParseProgram(), &env);
+    assert_eq!(evaluation.value, None);
+    assert_eq!(evaluation.Value(), NULL);
+    assert_eq!(evaluation.diagnostics, vec![]);
+    assert_eq!(
+        (evaluation.stats.builtinCalls, evaluation.stats.maxDepth),
+        (0, 0)
+    );
+
+    let evaluation = Evaluate(Parser::New(Lexer::New("1 + true")).ParseProgram(), &env);
+    assert!(evaluation.IsError());
+}
+
+#[test]
+fn RustErrors() {
+    fn run(input: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Tokenize(input)?;
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        if let Some(error) = p.errors.pop() {
+            return Err(error.into());
+        }
+        let value = Evaluate(program, &Environment::New()).Result()?;
+        Ok(value.Inspect())
+    }
+
+    assert_eq!(run("1 + 2").unwrap(), "3");
+    assert_eq!(
+        run("let x = @;").unwrap_err().to_string(),
+        "unexpected character `@` at 1:9"
+    );
+    assert_eq!(
+        run("let x = (1;").unwrap_err().to_string(),
+        "expected next token to be RPAREN, got SEMICOLON instead"
+    );
+    let error = run("let f = fn() { 1 + true }; f()").unwrap_err();
+    assert_eq!(error.to_string(), "type mismatch: INTEGER + BOOLEAN");
+    let error = error.downcast::<RuntimeError>().unwrap();
+    assert_eq!(error.0.kind, ErrorKind::TypeError);
+}
+
+#[test]
+fn ErrorsAndUncaughtThrowsAreErr() {
+    fn evaluate(input: &str) -> Evaluation {
+        Evaluate(
+            Parser::New(Lexer::New(input)).ParseProgram(),
+            &Environment::New(),
+        )
+    }
+
+    let error = evaluate("1 + true").Result().unwrap_err();
+    assert_eq!(error.0.kind, ErrorKind::TypeError);
+    assert_eq!(error.Thrown(), None);
+
+    let error = evaluate("throw [1, 2]").Result().unwrap_err();
+    assert_eq!(error.0.kind, ErrorKind::Uncaught);
+    assert_eq!(error.to_string(), "uncaught exception: [1, 2]");
+    assert_eq!(error.Thrown().map(Object::Inspect), Some("[1, 2]".into()));
+
+    // a value that still holds the throw, as one built by a host might
+    let evaluation = Evaluation {
+        value: Some(Thrown(Box::new(Integer { value: 3 }.into())).into()),
+        output: String::new(),
+        diagnostics: vec![],
+        stats: RunStats::default(),
+        duration: Duration::ZERO,
+    };
+    let error = evaluation.Result().unwrap_err();
+    assert_eq!(error.to_string(), "uncaught exception: 3");
+    assert_eq!(error.Thrown(), Some(&Integer { value: 3 }.into()));
+}
@@ -1,53 +1,598 @@
+use std::cmp::Ordering;
 use std::convert::TryInto;
+use std::rc::Rc;
 
 use crate::ast::BlockStatement;
+use crate::ast::CallExpression;
+use crate::ast::DestructurePattern;
 use crate::ast::ExpressionEnum;
+use crate::ast::HashLiteral;
+use crate::ast::Identifier;
 use crate::ast::IfExpression;
+use crate::ast::ImportExpression;
+use crate::ast::IndexExpression;
+use crate::ast::MatchExpression;
+use crate::ast::MatchPattern;
+use crate::ast::Node;
 use crate::ast::NodeEnum;
 use crate::ast::Program;
+use crate::ast::SliceExpression;
 use crate::ast::StatementEnum;
+use crate::ast::TryExpression;
+use crate::ast::UpdateExpression;
+use crate::builtins;
+use crate::capability::Capability;
+use crate::edition::Edition;
+use crate::environment::Env;
+use crate::environment::Environment;
+use crate::lexer::Lexer;
+use crate::macro_expansion;
+use crate::module::Loader;
+use crate::object::Array;
 use crate::object::Boolean;
 use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Frame;
+use crate::object::Function;
+use crate::object::Hash;
+use crate::object::HashKey;
 use crate::object::Integer;
+use crate::object::Macro;
+use crate::object::Module;
 use crate::object::Null;
 use crate::object::Object;
 use crate::object::ObjectEnum;
 use crate::object::ObjectKind;
 use crate::object::ReturnValue;
+use crate::object::Str;
+use crate::object::TailCall;
+use crate::object::Thrown;
 use crate::object::Truthy;
+use crate::object::NULL;
+use crate::parser::Parser;
+use crate::runtime::Division;
+use crate::runtime::Runtime;
+use crate::warning::Warning;
+use crate::warning::WarningCode;
 
-pub fn Eval(node: NodeEnum) -> Option<ObjectEnum> {
+/// Returns the error that should end the evaluation in `env`, if it was
+/// cancelled or ran out of its limits.
+fn checkRuntime(env: &Env) -> Option<ObjectEnum> {
+    let runtime = Rc::clone(env.borrow().Runtime());
+    runtime.Check()
+}
+
+/// Under strict truthiness, returns the error for using `value` as the
+/// `what` when it isn't a boolean. Otherwise that only warns.
+fn checkBoolean(value: &ObjectEnum, what: &str, env: &Env) -> Option<ObjectEnum> {
+    let runtime = Rc::clone(env.borrow().Runtime());
+    checkBooleanIn(value, what, &runtime)
+}
+
+pub(crate) fn checkBooleanIn(
+    value: &ObjectEnum,
+    what: &str,
+    runtime: &Runtime,
+) -> Option<ObjectEnum> {
+    if value.Type() == ObjectKind::BOOLEAN {
+        return None;
+    }
+    if !runtime.Edition().StrictTruthiness() {
+        runtime.Warnings().Warn(Warning {
+            code: WarningCode::Truthiness,
+            message: format!(
+                "{} is {:?}, not a BOOLEAN; edition {} requires one",
+                what,
+                value.Type(),
+                Edition::LATEST
+            ),
+            span: None,
+        });
+        return None;
+    }
+    Some(
+        Error::new(
+            ErrorKind::TypeError,
+            format!("{} must be a BOOLEAN, got {:?}", what, value.Type()),
+        )
+        .with("type", typeName(value))
+        .into(),
+    )
+}
+
+pub fn Eval(node: NodeEnum, env: &Env) -> Option<ObjectEnum> {
     match node {
-        NodeEnum::Program(p) => evalProgram(p),
-        NodeEnum::Statement(StatementEnum::Expression(e)) => Eval(e.expression.into()),
-        NodeEnum::Statement(StatementEnum::Block(b)) => evalBlockStatement(b),
+        NodeEnum::Program(p) => evalProgram(p, env),
+        NodeEnum::Statement(StatementEnum::Expression(e)) => Eval(e.expression.into(), env),
+        NodeEnum::Statement(StatementEnum::Block(b)) => evalBlockStatement(b, env, false),
         NodeEnum::Statement(StatementEnum::Return(r)) => {
-            let val = Eval(r.returnValue.into())?;
+            let val = Eval(r.returnValue.into(), env)?;
+            if isError(&val) {
+                return Some(val);
+            }
             Some(ReturnValue(Box::new(val)).into())
         }
+        NodeEnum::Statement(StatementEnum::Let(l)) => {
+            let val = Eval(l.value.into(), env)?;
+            if isError(&val) {
+                return Some(val);
+            }
+            env.borrow_mut().Set(&l.name.value, val);
+            None
+        }
+        NodeEnum::Statement(StatementEnum::Destructure(d)) => {
+            let val = Eval(d.value.into(), env)?;
+            if isError(&val) {
+                return Some(val);
+            }
+            evalDestructure(d.pattern, val, env)
+        }
+        NodeEnum::Statement(StatementEnum::Throw(t)) => {
+            let val = Eval(t.value.into(), env)?;
+            if isError(&val) {
+                return Some(val);
+            }
+            Some(Thrown(Box::new(val)).into())
+        }
+        NodeEnum::Statement(StatementEnum::Defer(d)) => {
+            env.borrow_mut().Defer(d.expression);
+            None
+        }
         NodeEnum::Expression(ExpressionEnum::IntegerLiteral(i)) => {
             Some(Integer { value: i.value }.into())
         }
+        NodeEnum::Expression(ExpressionEnum::StringLiteral(s)) => Some(
+            Str {
+                value: s.value.to_string(),
+            }
+            .into(),
+        ),
         NodeEnum::Expression(ExpressionEnum::Boolean(b)) => Some(Boolean { value: b.value }.into()),
+        NodeEnum::Expression(ExpressionEnum::NullLiteral(_)) => Some(NULL),
         NodeEnum::Expression(ExpressionEnum::PrefixExpression(p)) => {
-            let right = Eval((*p.right).into())?;
+            let right = Eval((*p.right).into(), env)?;
+            if isError(&right) {
+                return Some(right);
+            }
+            if p.operator == "!" {
+                if let Some(err) = checkBoolean(&right, "operand of !", env) {
+                    return Some(err);
+                }
+            }
             Some(evalPrefixExpression(&p.operator, right))
         }
         NodeEnum::Expression(ExpressionEnum::InfixExpression(i)) => {
-            let left = Eval((*i.left).into())?;
-            let right = Eval((*i.right).into())?;
-            Some(evalInfixExpression(&i.operator, left, right))
+            let left = Eval((*i.left).into(), env)?;
+            if isError(&left) {
+                return Some(left);
+            }
+            let right = Eval((*i.right).into(), env)?;
+            if isError(&right) {
+                return Some(right);
+            }
+            let division = env.borrow().Runtime().Division();
+            Some(evalInfixExpression(&i.operator, left, right, division))
+        }
+        NodeEnum::Expression(ExpressionEnum::UpdateExpression(u)) => {
+            Some(evalUpdateExpression(u, env))
         }
-        NodeEnum::Expression(ExpressionEnum::IfExpression(i)) => evalIfExpression(i),
-        _ => Some(Null.into()),
+        NodeEnum::Expression(ExpressionEnum::IfExpression(i)) => evalIfExpression(i, env, false),
+        NodeEnum::Expression(ExpressionEnum::MatchExpression(m)) => {
+            evalMatchExpression(m, env, false)
+        }
+        NodeEnum::Expression(ExpressionEnum::TryExpression(t)) => evalTryExpression(t, env, false),
+        NodeEnum::Expression(ExpressionEnum::ImportExpression(i)) => {
+            Some(evalImportExpression(i, env))
+        }
+        NodeEnum::Expression(ExpressionEnum::Identifier(i)) => Some(evalIdentifier(i, env)),
+        NodeEnum::Expression(ExpressionEnum::FunctionLiteral(f)) => Some(
+            Function {
+                parameters: f.parameters,
+                body: Rc::new(*f.body),
+                env: Rc::clone(env),
+            }
+            .into(),
+        ),
+        NodeEnum::Expression(ExpressionEnum::MacroLiteral(m)) => Some(
+            Macro {
+                parameters: m.parameters,
+                body: Rc::new(*m.body),
+                env: Rc::clone(env),
+            }
+            .into(),
+        ),
+        NodeEnum::Expression(ExpressionEnum::CallExpression(c)) => {
+            evalCallExpression(c, env, false)
+        }
+        NodeEnum::Expression(ExpressionEnum::ArrayLiteral(a)) => {
+            match evalExpressions(a.elements, env) {
+                Ok(elements) => Some(Array { elements }.into()),
+                Err(err) => Some(err),
+            }
+        }
+        NodeEnum::Expression(ExpressionEnum::HashLiteral(h)) => Some(evalHashLiteral(h, env)),
+        NodeEnum::Expression(ExpressionEnum::IndexExpression(i)) => evalIndexExpression(i, env),
+        NodeEnum::Expression(ExpressionEnum::SliceExpression(s)) => evalSliceExpression(s, env),
+        NodeEnum::Expression(ExpressionEnum::KeywordArgument(_)) => Some(
+            Error::new(
+                ErrorKind::TypeError,
+                "keyword arguments are only allowed in argument lists",
+            )
+            .into(),
+        ),
+        // the parser only allows spreading inside array literals and argument
+        // lists, which expand it themselves
+        NodeEnum::Expression(ExpressionEnum::SpreadExpression(_)) => Some(
+            Error::new(
+                ErrorKind::TypeError,
+                "spread is only allowed in array literals and argument lists",
+            )
+            .into(),
+        ),
     }
 }
 
+fn evalIdentifier(i: Identifier, env: &Env) -> ObjectEnum {
+    if let Some(val) = env.borrow().Get(&i.value) {
+        return val;
+    }
+    let runtime = Rc::clone(env.borrow().Runtime());
+    match builtins::Available(&i.value, &runtime) {
+        Some(builtin) => builtin.into(),
+        None => Error::new(
+            ErrorKind::NameError,
+            format!("identifier not found: {}", i.value),
+        )
+        .with("name", Str { value: i.value })
+        .into(),
+    }
+}
+
+/// Evaluates `node` in tail position in a function body, where a call's
+/// result would be returned as is. Such calls become `TailCall`s, which
+/// `applyFunction` makes without growing the host stack.
+fn evalTail(node: NodeEnum, env: &Env) -> Option<ObjectEnum> {
+    match node {
+        NodeEnum::Statement(StatementEnum::Expression(e)) => evalTail(e.expression.into(), env),
+        NodeEnum::Statement(StatementEnum::Block(b)) => evalBlockStatement(b, env, true),
+        NodeEnum::Statement(StatementEnum::Return(r)) => {
+            let val = evalTail(r.returnValue.into(), env)?;
+            if isError(&val) {
+                return Some(val);
+            }
+            Some(ReturnValue(Box::new(val)).into())
+        }
+        NodeEnum::Expression(ExpressionEnum::IfExpression(i)) => evalIfExpression(i, env, true),
+        NodeEnum::Expression(ExpressionEnum::MatchExpression(m)) => {
+            evalMatchExpression(m, env, true)
+        }
+        NodeEnum::Expression(ExpressionEnum::TryExpression(t)) => evalTryExpression(t, env, true),
+        NodeEnum::Expression(ExpressionEnum::CallExpression(c)) => evalCallExpression(c, env, true),
+        node => Eval(node, env),
+    }
+}
+
+/// In tail position, calls to Monkey functions are handed back as a `TailCall`
+/// instead of being made.
+fn evalCallExpression(c: CallExpression, env: &Env, tail: bool) -> Option<ObjectEnum> {
+    // `quote` takes its argument as code, unless it has been rebound
+    if let ExpressionEnum::Identifier(i) = &*c.function {
+        if i.value == "quote" && env.borrow().Get("quote").is_none() {
+            return Some(macro_expansion::Quote(c.arguments, env));
+        }
+        // and `assert` shows the code of its condition
+        if i.value == "assert" && env.borrow().Get("assert").is_none() {
+            let source = c.arguments.first().map(Node::String);
+            let args = match evalExpressions(c.arguments, env) {
+                Ok(args) => args,
+                Err(err) => return Some(err),
+            };
+            let runtime = Rc::clone(env.borrow().Runtime());
+            return Some(builtins::Assert(args, source.as_deref(), &runtime));
+        }
+        // and `eval` runs its code where it is called
+        if i.value == "eval" && env.borrow().Get("eval").is_none() {
+            return Some(match evalExpressions(c.arguments, env) {
+                Ok(args) => evalCode(args, env),
+                Err(err) => err,
+            });
+        }
+    }
+    let call = c.function.Position();
+    let name = match &*c.function {
+        ExpressionEnum::Identifier(i) => Some(i.value.clone()),
+        _ => None,
+    };
+    let function = Eval((*c.function).into(), env)?;
+    if isError(&function) {
+        return Some(function);
+    }
+    // in the order they are written, which the parser makes positional
+    // arguments first
+    let mut args = vec![];
+    let mut keywords = vec![];
+    for arg in c.arguments {
+        match arg {
+            ExpressionEnum::KeywordArgument(k) => {
+                let value = Eval((*k.value).into(), env).unwrap_or(NULL);
+                if isError(&value) {
+                    return Some(value);
+                }
+                keywords.push((k.name.value, value));
+            }
+            arg if !keywords.is_empty() => {
+                return Some(
+                    Error::new(
+                        ErrorKind::ArgumentError,
+                        format!("positional argument after keyword arguments: {}", arg),
+                    )
+                    .into(),
+                )
+            }
+            arg => match evalExpressions(vec![arg], env) {
+                Ok(values) => args.extend(values),
+                Err(err) => return Some(err),
+            },
+        }
+    }
+    if !keywords.is_empty() {
+        args = match bindKeywordArguments(&function, args, keywords) {
+            Ok(args) => args,
+            Err(err) => return Some(err),
+        };
+    }
+    Some(match function {
+        ObjectEnum::Function(f) => {
+            let frame = Frame {
+                function: name.unwrap_or_else(|| format!("fn at {}", f.body.token.position)),
+                call,
+            };
+            if tail {
+                return Some(
+                    TailCall {
+                        function: Box::new(f.into()),
+                        args,
+                        frame,
+                    }
+                    .into(),
+                );
+            }
+            let runtime = Rc::clone(env.borrow().Runtime());
+            let mut result = applyFunction(f.into(), args, &runtime);
+            unwind(&mut result, frame);
+            result
+        }
+        function => {
+            let runtime = Rc::clone(env.borrow().Runtime());
+            let caller = runtime.SetCallSite(call);
+            let result = applyFunction(function, args, &runtime);
+            runtime.SetCallSite(caller);
+            result
+        }
+    })
+}
+
+/// Puts the values of `keywords` in the place of the parameters they name,
+/// after the `positional` arguments. Every parameter must end up with exactly
+/// one argument.
+fn bindKeywordArguments(
+    function: &ObjectEnum,
+    positional: Vec<ObjectEnum>,
+    keywords: Vec<(String, ObjectEnum)>,
+) -> Result<Vec<ObjectEnum>, ObjectEnum> {
+    let parameters = match function {
+        ObjectEnum::Function(f) => &f.parameters,
+        ObjectEnum::Builtin(b) => {
+            return Err(Error::new(
+                ErrorKind::ArgumentError,
+                format!("builtin {} doesn't take keyword arguments", b.name),
+            )
+            .into())
+        }
+        // calling it reports that it isn't a function
+        _ => return Ok(positional),
+    };
+    if positional.len() > parameters.len() {
+        return Err(wrongArgumentCount(
+            parameters.len(),
+            positional.len() + keywords.len(),
+        ));
+    }
+
+    let mut args: Vec<Option<ObjectEnum>> = positional.into_iter().map(Some).collect();
+    args.resize(parameters.len(), None);
+    for (name, value) in keywords {
+        let arg = match parameters.iter().position(|p| p.value == name) {
+            Some(index) => &mut args[index],
+            None => {
+                return Err(Error::new(
+                    ErrorKind::ArgumentError,
+                    format!("unexpected keyword argument: {}", name),
+                )
+                .with("name", Str { value: name })
+                .into())
+            }
+        };
+        if arg.is_some() {
+            return Err(Error::new(
+                ErrorKind::ArgumentError,
+                format!("argument given twice: {}", name),
+            )
+            .with("name", Str { value: name })
+            .into());
+        }
+        *arg = Some(value);
+    }
+
+    args.into_iter()
+        .zip(parameters)
+        .map(|(arg, parameter)| {
+            arg.ok_or_else(|| {
+                Error::new(
+                    ErrorKind::ArgumentError,
+                    format!("missing argument: {}", parameter.value),
+                )
+                .with(
+                    "name",
+                    Str {
+                        value: parameter.value.clone(),
+                    },
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn wrongArgumentCount(want: usize, got: usize) -> ObjectEnum {
+    Error::new(
+        ErrorKind::ArgumentError,
+        format!("wrong number of arguments: want={}, got={}", want, got),
+    )
+    .with("want", Integer { value: want as i64 })
+    .with("got", Integer { value: got as i64 })
+    .into()
+}
+
+/// Evaluates expressions left to right, stopping at the first error. Spread
+/// expressions contribute the elements of their array.
+fn evalExpressions(exps: Vec<ExpressionEnum>, env: &Env) -> Result<Vec<ObjectEnum>, ObjectEnum> {
+    let mut result = vec![];
+    for exp in exps {
+        let spread = matches!(exp, ExpressionEnum::SpreadExpression(_));
+        let exp = match exp {
+            ExpressionEnum::SpreadExpression(s) => *s.value,
+            exp => exp,
+        };
+        let evaluated = Eval(exp.into(), env).unwrap_or(NULL);
+        if isError(&evaluated) {
+            return Err(evaluated);
+        }
+        match evaluated {
+            ObjectEnum::Array(a) if spread => result.extend(a.elements),
+            ObjectEnum::HostArray(a) if spread => result.extend(a.ToArray().elements),
+            other if spread => {
+                return Err(Error::new(
+                    ErrorKind::TypeError,
+                    format!("cannot spread {:?}, only an ARRAY", other.Type()),
+                )
+                .with("type", typeName(&other))
+                .into())
+            }
+            other => result.push(other),
+        }
+    }
+    Ok(result)
+}
+
+pub(crate) fn applyFunction(
+    function: ObjectEnum,
+    args: Vec<ObjectEnum>,
+    runtime: &Runtime,
+) -> ObjectEnum {
+    if let Some(err) = runtime.EnterCall() {
+        return err;
+    }
+    let result = callFunction(function, args, runtime);
+    runtime.ExitCall();
+    result
+}
+
+fn callFunction(
+    mut function: ObjectEnum,
+    mut args: Vec<ObjectEnum>,
+    runtime: &Runtime,
+) -> ObjectEnum {
+    // calls in tail position of the body come back as a `TailCall` and are
+    // made by the next iteration rather than by recursing; the last of them
+    // stands in the trace for the ones it replaced
+    let mut tail: Option<Frame> = None;
+    let mut result = loop {
+        let f: Function = match function {
+            ObjectEnum::Function(f) => f,
+            ObjectEnum::Builtin(builtin) => break runtime.CallBuiltin(&builtin, args),
+            other => {
+                break Error::new(
+                    ErrorKind::TypeError,
+                    format!("not a function: {:?}", other.Type()),
+                )
+                .with("type", typeName(&other))
+                .into()
+            }
+        };
+        if f.parameters.len() != args.len() {
+            break wrongArgumentCount(f.parameters.len(), args.len());
+        }
+
+        let fenv = Environment::NewEnclosed(&f.env);
+        if let Some(err) = checkRuntime(&fenv) {
+            break err;
+        }
+        for (param, arg) in f.parameters.iter().zip(args) {
+            fenv.borrow_mut().Set(&param.value, arg);
+        }
+        let body: StatementEnum = (*f.body).clone().into();
+        let evaluated = match evalTail(body.into(), &fenv) {
+            Some(ObjectEnum::ReturnValue(ReturnValue(rv))) => Some(*rv),
+            evaluated => evaluated,
+        };
+        let result = match evaluated {
+            Some(ObjectEnum::TailCall(call)) if !fenv.borrow().HasDeferred() => {
+                function = *call.function;
+                args = call.args;
+                tail = Some(call.frame);
+                Environment::Release(fenv);
+                continue;
+            }
+            // deferred expressions run after the call returns, so this frame
+            // has to stay around for it
+            Some(ObjectEnum::TailCall(call)) => {
+                let mut result = applyFunction(*call.function, call.args, runtime);
+                unwind(&mut result, call.frame);
+                runDeferred(Some(result), &fenv)
+            }
+            evaluated => runDeferred(evaluated, &fenv),
+        };
+        Environment::Release(fenv);
+        break result.unwrap_or(NULL);
+    };
+    if let Some(frame) = tail {
+        unwind(&mut result, frame);
+    }
+    result
+}
+
+/// Records in `result`, if it is an error, that it came out of the call
+/// `frame`. Exiting isn't a failure, so it gets no trace.
+fn unwind(result: &mut ObjectEnum, frame: Frame) {
+    if let ObjectEnum::Error(err) = result {
+        if err.kind != ErrorKind::Exit {
+            err.Unwind(frame);
+        }
+    }
+}
+
+/// Runs the expressions deferred in `env`, most recent first. They run even if
+/// `result` is an error; an error from a deferred expression replaces a
+/// successful result but never an earlier error.
+fn runDeferred(mut result: Option<ObjectEnum>, env: &Env) -> Option<ObjectEnum> {
+    let deferred = env.borrow_mut().TakeDeferred();
+    for exp in deferred {
+        let evaluated = Eval(exp.into(), env);
+        match (&result, evaluated) {
+            (Some(result), _) if isError(result) => {}
+            (_, Some(err)) if isError(&err) => result = Some(err),
+            _ => {}
+        }
+    }
+    result
+}
+
 fn evalPrefixExpression(operator: &str, right: ObjectEnum) -> ObjectEnum {
     match operator {
         "!" => evalBangOperatorExpression(right),
         "-" => evalMinusPrefixOperatorExpression(right),
-        _ => Error(format!("unknown operator: {}{:?}", operator, right.Type())).into(),
+        _ => unknownPrefixOperator(operator, &right),
     }
 }
 
@@ -67,14 +612,33 @@ fn evalBangOperatorExpression(right: ObjectEnum) -> ObjectEnum {
 
 fn evalMinusPrefixOperatorExpression(right: ObjectEnum) -> ObjectEnum {
     match right {
-        ObjectEnum::Integer(Integer { value }) => Integer { value: -value }.into(),
-        _ => Error(format!("unknown operator: -{:?}", right.Type())).into(),
+        ObjectEnum::Integer(Integer { value }) => match value.checked_neg() {
+            Some(value) => Integer { value }.into(),
+            None => Error::new(
+                ErrorKind::ArithmeticError,
+                format!("-({}) is out of range", value),
+            )
+            .into(),
+        },
+        _ => unknownPrefixOperator("-", &right),
     }
 }
 
-fn evalInfixExpression(operator: &str, left: ObjectEnum, right: ObjectEnum) -> ObjectEnum {
-    if left.Type() == ObjectKind::INTEGER && right.Type() == ObjectKind::INTEGER {
-        evalIntegerInfixExpression(operator, left, right)
+fn evalInfixExpression(
+    operator: &str,
+    left: ObjectEnum,
+    right: ObjectEnum,
+    division: Division,
+) -> ObjectEnum {
+    if let "<" | ">" = operator {
+        evalComparisonExpression(operator, left, right)
+    } else if left.Type() == ObjectKind::INTEGER && right.Type() == ObjectKind::INTEGER {
+        evalIntegerInfixExpression(operator, left, right, division)
+    } else if left.Type() == ObjectKind::STRING
+        && right.Type() == ObjectKind::STRING
+        && operator == "+"
+    {
+        evalStringConcatenation(left, right)
     } else if operator == "==" {
         Boolean {
             value: left == right,
@@ -86,104 +650,693 @@ fn evalInfixExpression(operator: &str, left: ObjectEnum, right: ObjectEnum) -> O
         }
         .into()
     } else if left.Type() != right.Type() {
-        Error(format!(
-            "type mismatch: {:?} {} {:?}",
-            left.Type(),
-            operator,
-            right.Type()
-        ))
-        .into()
+        typeMismatch(&left, operator, &right)
     } else {
-        Error(format!(
-            "unknown operator: {:?} {} {:?}",
-            left.Type(),
-            operator,
-            right.Type()
-        ))
-        .into()
+        unknownInfixOperator(&left, operator, &right)
     }
 }
 
-fn evalIntegerInfixExpression(operator: &str, left: ObjectEnum, right: ObjectEnum) -> ObjectEnum {
+fn evalIntegerInfixExpression(
+    operator: &str,
+    left: ObjectEnum,
+    right: ObjectEnum,
+    division: Division,
+) -> ObjectEnum {
     let left: Integer = left.try_into().unwrap();
     let right: Integer = right.try_into().unwrap();
     match operator {
-        "+" => Integer {
-            value: left.value + right.value,
+        "+" | "-" | "*" => {
+            let value = match operator {
+                "+" => left.value.checked_add(right.value),
+                "-" => left.value.checked_sub(right.value),
+                _ => left.value.checked_mul(right.value),
+            };
+            match value {
+                Some(value) => Integer { value }.into(),
+                None => outOfRange(left.value, operator, right.value),
+            }
         }
-        .into(),
-        "-" => Integer {
-            value: left.value - right.value,
+        "/" | "%" => match divide(left.value, right.value, division) {
+            Some((quotient, _)) if operator == "/" => Integer { value: quotient }.into(),
+            Some((_, remainder)) => Integer { value: remainder }.into(),
+            None if right.value == 0 => {
+                Error::new(ErrorKind::ArithmeticError, "division by zero").into()
+            }
+            None => outOfRange(left.value, operator, right.value),
+        },
+        "==" => Boolean {
+            value: left.value == right.value,
         }
         .into(),
-        "*" => Integer {
-            value: left.value * right.value,
+        "!=" => Boolean {
+            value: left.value != right.value,
         }
         .into(),
-        "/" => Integer {
-            value: left.value / right.value,
+        _ => unknownInfixOperator(&left.into(), operator, &right.into()),
+    }
+}
+
+/// The error for `left operator right` not fitting in an integer.
+fn outOfRange(left: i64, operator: &str, right: i64) -> ObjectEnum {
+    Error::new(
+        ErrorKind::ArithmeticError,
+        format!("{} {} {} is out of range", left, operator, right),
+    )
+    .into()
+}
+
+/// The quotient and remainder of `a / b`, rounded the way `division` says.
+/// `None` for a zero `b`, or a quotient that doesn't fit.
+fn divide(a: i64, b: i64, division: Division) -> Option<(i64, i64)> {
+    let (quotient, remainder) = (a.checked_div(b)?, a.checked_rem(b)?);
+    match division {
+        Division::Floor if remainder != 0 && (remainder < 0) != (b < 0) => {
+            Some((quotient - 1, remainder + b))
         }
-        .into(),
-        "<" => Boolean {
-            value: left.value < right.value,
+        _ => Some((quotient, remainder)),
+    }
+}
+
+/// Joins two strings. Interpolations go through `str` first, which is what
+/// makes `"n = ${n}"` work for any `n`.
+fn evalStringConcatenation(left: ObjectEnum, right: ObjectEnum) -> ObjectEnum {
+    let left: Str = left.try_into().unwrap();
+    let right: Str = right.try_into().unwrap();
+    Str {
+        value: left.value + &right.value,
+    }
+    .into()
+}
+
+fn evalUpdateExpression(u: UpdateExpression, env: &Env) -> ObjectEnum {
+    let old = match evalIdentifier(u.target.clone(), env) {
+        ObjectEnum::Integer(i) => i,
+        ObjectEnum::Error(e) => return e.into(),
+        other => {
+            return Error::new(
+                ErrorKind::TypeError,
+                format!("{} requires an INTEGER, got {:?}", u.operator, other.Type()),
+            )
+            .with("operator", Str { value: u.operator })
+            .with("type", typeName(&other))
+            .into()
         }
-        .into(),
-        ">" => Boolean {
-            value: left.value > right.value,
+    };
+    let (operator, value) = match u.operator.as_str() {
+        "++" => ("+", old.value.checked_add(1)),
+        _ => ("-", old.value.checked_sub(1)),
+    };
+    let new = match value {
+        Some(value) => Integer { value },
+        None => return outOfRange(old.value, operator, 1),
+    };
+    env.borrow_mut().Assign(&u.target.value, new.into());
+    if u.prefix {
+        new.into()
+    } else {
+        old.into()
+    }
+}
+
+fn evalComparisonExpression(operator: &str, left: ObjectEnum, right: ObjectEnum) -> ObjectEnum {
+    if left.Type() != right.Type() {
+        return typeMismatch(&left, operator, &right);
+    }
+    match left.Compare(&right) {
+        Some(ordering) => Boolean {
+            value: match operator {
+                "<" => ordering == Ordering::Less,
+                _ => ordering == Ordering::Greater,
+            },
         }
         .into(),
-        "==" => Boolean {
-            value: left.value == right.value,
+        None => unknownInfixOperator(&left, operator, &right),
+    }
+}
+
+/// Whether `obj` aborts the evaluation it comes out of: an error, or a value
+/// being thrown.
+pub(crate) fn isError(obj: &ObjectEnum) -> bool {
+    matches!(obj.Type(), ObjectKind::ERROR | ObjectKind::THROWN)
+}
+
+fn evalIndexExpression(i: IndexExpression, env: &Env) -> Option<ObjectEnum> {
+    let left = Eval((*i.left).into(), env)?;
+    if isError(&left) {
+        return Some(left);
+    }
+    let index = Eval((*i.index).into(), env)?;
+    if isError(&index) {
+        return Some(index);
+    }
+    Some(match (left, index) {
+        (ObjectEnum::Hash(h), index) => match index.HashKey() {
+            Some(key) => h.pairs.get(&key).cloned().unwrap_or(NULL),
+            None => unusableHashKey(&index),
+        },
+        (ObjectEnum::Array(a), ObjectEnum::Integer(index)) => {
+            resolveIndex(index.value, a.elements.len())
+                .map(|i| a.elements[i].clone())
+                .unwrap_or(NULL)
         }
-        .into(),
-        "!=" => Boolean {
-            value: left.value != right.value,
+        (ObjectEnum::HostArray(a), ObjectEnum::Integer(index)) => {
+            resolveIndex(index.value, a.Len())
+                .and_then(|i| a.Get(i))
+                .unwrap_or(NULL)
+        }
+        (ObjectEnum::String(s), ObjectEnum::Integer(index)) => {
+            resolveIndex(index.value, s.value.chars().count())
+                .and_then(|i| s.value.chars().nth(i))
+                .map(|c| {
+                    Str {
+                        value: c.to_string(),
+                    }
+                    .into()
+                })
+                .unwrap_or(NULL)
+        }
+        (ObjectEnum::Module(m), ObjectEnum::String(name)) => match m.members.get(&name.value) {
+            Some(member) => member.clone(),
+            None => Error::new(
+                ErrorKind::NameError,
+                format!("module {} has no member {}", m.name, name.value),
+            )
+            .with("name", name)
+            .into(),
+        },
+        (ObjectEnum::Array(_) | ObjectEnum::HostArray(_) | ObjectEnum::String(_), index) => {
+            invalidIndex(&index)
         }
+        (ObjectEnum::Module(_), index) => Error::new(
+            ErrorKind::TypeError,
+            format!("module member must be a STRING, got {:?}", index.Type()),
+        )
+        .with("type", typeName(&index))
         .into(),
-        _ => Error(format!(
+        (other, _) => unsupportedIndex(&other),
+    })
+}
+
+/// Slices arrays and strings (by character). Negative bounds count from the
+/// end, and bounds are then clamped to the sequence, so out-of-range bounds
+/// never fail; a start past the end gives an empty result.
+fn evalSliceExpression(s: SliceExpression, env: &Env) -> Option<ObjectEnum> {
+    let left = Eval((*s.left).into(), env)?;
+    if isError(&left) {
+        return Some(left);
+    }
+    let mut bounds = [None, None];
+    for (bound, exp) in bounds.iter_mut().zip(vec![s.start, s.end]) {
+        if let Some(exp) = exp {
+            match Eval((*exp).into(), env)? {
+                ObjectEnum::Integer(i) => *bound = Some(i.value),
+                err if isError(&err) => return Some(err),
+                other => return Some(invalidIndex(&other)),
+            }
+        }
+    }
+    let [start, end] = bounds;
+
+    Some(match left {
+        ObjectEnum::Array(a) => {
+            let (start, end) = sliceRange(start, end, a.elements.len());
+            Array {
+                elements: a.elements[start..end].to_vec(),
+            }
+            .into()
+        }
+        ObjectEnum::HostArray(a) => {
+            let (start, end) = sliceRange(start, end, a.Len());
+            a.Slice(start, end).into()
+        }
+        ObjectEnum::String(s) => {
+            let (start, end) = sliceRange(start, end, s.value.chars().count());
+            Str {
+                value: s.value.chars().skip(start).take(end - start).collect(),
+            }
+            .into()
+        }
+        other => unsupportedIndex(&other),
+    })
+}
+
+/// Turns an index into a position in a sequence of `len` elements, counting
+/// negative indices from the end (so -1 is the last element).
+fn resolveIndex(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    if (0..len as i64).contains(&index) {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+fn sliceRange(start: Option<i64>, end: Option<i64>, len: usize) -> (usize, usize) {
+    let clamp = |bound: i64| {
+        let bound = if bound < 0 { bound + len as i64 } else { bound };
+        bound.clamp(0, len as i64) as usize
+    };
+    let end = end.map_or(len, clamp);
+    let start = start.map_or(0, clamp).min(end);
+    (start, end)
+}
+
+fn evalHashLiteral(h: HashLiteral, env: &Env) -> ObjectEnum {
+    let mut hash = Hash::default();
+    for (key, value) in h.pairs {
+        let key = Eval(key.into(), env).unwrap_or(NULL);
+        if isError(&key) {
+            return key;
+        }
+        let key = match key.HashKey() {
+            Some(hashKey) => hashKey,
+            None => return unusableHashKey(&key),
+        };
+        let value = Eval(value.into(), env).unwrap_or(NULL);
+        if isError(&value) {
+            return value;
+        }
+        hash.pairs.insert(key, value);
+    }
+    hash.into()
+}
+
+/// Binds the names in `pattern` to the parts of `val`. Returns the error
+/// explaining why `val` doesn't fit the pattern, if it doesn't.
+fn evalDestructure(pattern: DestructurePattern, val: ObjectEnum, env: &Env) -> Option<ObjectEnum> {
+    let val = match val {
+        ObjectEnum::HostArray(a) => a.ToArray().into(),
+        val => val,
+    };
+    let values = match (&pattern, val) {
+        (DestructurePattern::Array(names), ObjectEnum::Array(a)) => {
+            if a.elements.len() != names.len() {
+                return Some(
+                    Error::new(
+                        ErrorKind::DestructureError,
+                        format!(
+                            "cannot destructure an ARRAY of {} elements into {} names",
+                            a.elements.len(),
+                            names.len()
+                        ),
+                    )
+                    .with(
+                        "want",
+                        Integer {
+                            value: names.len() as i64,
+                        },
+                    )
+                    .with(
+                        "got",
+                        Integer {
+                            value: a.elements.len() as i64,
+                        },
+                    )
+                    .into(),
+                );
+            }
+            a.elements
+        }
+        (DestructurePattern::Hash(names), ObjectEnum::Hash(h)) => {
+            let mut values = vec![];
+            for name in names {
+                match h.pairs.get(&HashKey::String(name.value.clone())) {
+                    Some(value) => values.push(value.clone()),
+                    None => {
+                        return Some(
+                            Error::new(
+                                ErrorKind::DestructureError,
+                                format!("cannot destructure HASH without key \"{}\"", name.value),
+                            )
+                            .with(
+                                "key",
+                                Str {
+                                    value: name.value.clone(),
+                                },
+                            )
+                            .into(),
+                        )
+                    }
+                }
+            }
+            values
+        }
+        (pattern, other) => {
+            let want = match pattern {
+                DestructurePattern::Array(_) => ObjectKind::ARRAY,
+                DestructurePattern::Hash(_) => ObjectKind::HASH,
+            };
+            return Some(
+                Error::new(
+                    ErrorKind::DestructureError,
+                    format!("cannot destructure {:?} as {:?}", other.Type(), want),
+                )
+                .with("type", typeName(&other))
+                .into(),
+            );
+        }
+    };
+    for (name, value) in pattern.Names().iter().zip(values) {
+        env.borrow_mut().Set(&name.value, value);
+    }
+    None
+}
+
+pub(crate) fn unusableHashKey(key: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::TypeError,
+        format!("unusable as hash key: {:?}", key.Type()),
+    )
+    .with("type", typeName(key))
+    .into()
+}
+
+fn invalidIndex(index: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::TypeError,
+        format!("index must be an INTEGER, got {:?}", index.Type()),
+    )
+    .with("type", typeName(index))
+    .into()
+}
+
+fn unsupportedIndex(left: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::TypeError,
+        format!("index operator not supported: {:?}", left.Type()),
+    )
+    .with("type", typeName(left))
+    .into()
+}
+
+pub(crate) fn typeName(obj: &ObjectEnum) -> Str {
+    Str {
+        value: format!("{:?}", obj.Type()),
+    }
+}
+
+fn typeMismatch(left: &ObjectEnum, operator: &str, right: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::TypeError,
+        format!(
+            "type mismatch: {:?} {} {:?}",
+            left.Type(),
+            operator,
+            right.Type()
+        ),
+    )
+    .with("left", typeName(left))
+    .with(
+        "operator",
+        Str {
+            value: operator.into(),
+        },
+    )
+    .with("right", typeName(right))
+    .into()
+}
+
+fn unknownInfixOperator(left: &ObjectEnum, operator: &str, right: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::OperatorError,
+        format!(
             "unknown operator: {:?} {} {:?}",
             left.Type(),
             operator,
             right.Type()
-        ))
-        .into(),
-    }
+        ),
+    )
+    .with("left", typeName(left))
+    .with(
+        "operator",
+        Str {
+            value: operator.into(),
+        },
+    )
+    .with("right", typeName(right))
+    .into()
 }
 
-fn evalIfExpression(i: IfExpression) -> Option<ObjectEnum> {
-    let condition = Eval((*i.condition).into())?;
-    if condition.isTruthy() {
-        let consequence_stmt: StatementEnum = (*i.consequence).into();
-        Eval(consequence_stmt.into())
+fn unknownPrefixOperator(operator: &str, right: &ObjectEnum) -> ObjectEnum {
+    Error::new(
+        ErrorKind::OperatorError,
+        format!("unknown operator: {}{:?}", operator, right.Type()),
+    )
+    .with(
+        "operator",
+        Str {
+            value: operator.into(),
+        },
+    )
+    .with("right", typeName(right))
+    .into()
+}
+
+fn evalIfExpression(i: IfExpression, env: &Env, tail: bool) -> Option<ObjectEnum> {
+    let condition = Eval((*i.condition).into(), env)?;
+    if isError(&condition) {
+        Some(condition)
+    } else if let Some(err) = checkBoolean(&condition, "condition", env) {
+        Some(err)
+    } else if condition.isTruthy() {
+        evalBlockStatement(*i.consequence, env, tail)
     } else if let Some(alternative) = i.alternative {
-        let alternative_stmt: StatementEnum = (*alternative).into();
-        Eval(alternative_stmt.into())
+        evalBlockStatement(*alternative, env, tail)
     } else {
-        Some(Null.into())
+        Some(NULL)
+    }
+}
+
+/// Evaluates the body, and the handler if the body throws or fails with a
+/// catchable error. The handler sees what was thrown, or the error as a
+/// hash, bound to the name in `catch`. The body is never in tail position,
+/// since a call made after leaving it would escape the handler.
+fn evalTryExpression(t: TryExpression, env: &Env, tail: bool) -> Option<ObjectEnum> {
+    let caught = match evalBlockStatement(*t.body, env, false) {
+        Some(ObjectEnum::Thrown(Thrown(value))) => *value,
+        Some(ObjectEnum::Error(err)) if err.kind.IsCatchable() => err.ToHash().into(),
+        result => return result,
+    };
+    env.borrow_mut().Set(&t.name.value, caught);
+    evalBlockStatement(*t.handler, env, tail)
+}
+
+/// Evaluates the file the path names into a module, in a global environment
+/// of its own sharing this one's runtime. A file is only evaluated the first
+/// time it is imported.
+fn evalImportExpression(i: ImportExpression, env: &Env) -> ObjectEnum {
+    let position = i.Position();
+    let name = match Eval((*i.path).into(), env) {
+        Some(ObjectEnum::String(name)) => name.value,
+        Some(err) if isError(&err) => return err,
+        other => {
+            let other = other.unwrap_or(NULL);
+            return Error::new(
+                ErrorKind::TypeError,
+                format!("import path must be a STRING, got {:?}", other.Type()),
+            )
+            .with("type", typeName(&other))
+            .into();
+        }
+    };
+    let importError = |message: String| -> ObjectEnum {
+        Error::new(ErrorKind::ImportError, message)
+            .with(
+                "name",
+                Str {
+                    value: name.clone(),
+                },
+            )
+            .into()
+    };
+
+    let runtime = Rc::clone(env.borrow().Runtime());
+    if let Some(err) = runtime.CheckAllowed(Capability::Io, "import") {
+        return err;
+    }
+    if Loader::Escapes(&name) {
+        if let Some(err) = runtime.CheckAllowed(Capability::Fs, "import") {
+            return err;
+        }
+    }
+    runtime.Audit(
+        Capability::Io,
+        "import",
+        &[Str {
+            value: name.clone(),
+        }
+        .into()],
+        position,
+    );
+    let platform = runtime.Platform();
+    let path = match runtime.Loader().Resolve(&name, &*platform) {
+        Some(path) => path,
+        None => return importError(format!("module not found: {}", name)),
+    };
+    if let Some(module) = runtime.Loader().Loaded(&path) {
+        return module.into();
+    }
+    if let Some(cycle) = runtime.Loader().Cycle(&path, &name) {
+        return importError(format!("import cycle: {}", cycle));
+    }
+    let source = match platform.ReadFile(&path) {
+        Ok(source) => source,
+        Err(err) => return importError(format!("could not read {}: {}", name, err)),
+    };
+    let mut p = Parser::WithEdition(Lexer::New(&source), runtime.Edition());
+    let program = p.ParseProgram();
+    for warning in p.warnings.Take() {
+        // the span is in the other file
+        runtime.Warnings().Warn(Warning {
+            span: None,
+            ..warning
+        });
+    }
+    if let Some(error) = p.errors.first() {
+        return importError(format!("could not parse {}: {}", name, error));
+    }
+
+    let moduleEnv = Environment::WithRuntime(Rc::clone(&runtime));
+    runtime.Loader().StartLoading(path, &name);
+    runtime.EnterModule(&name);
+    let result = Eval(program.into(), &moduleEnv);
+    runtime.ExitModule(&name);
+    if let Some(err) = result.filter(isError) {
+        runtime.Loader().FinishLoading(None);
+        return err;
+    }
+    let members = moduleEnv
+        .borrow()
+        .Bindings()
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let module = Module {
+        name,
+        members: Rc::new(members),
+    };
+    runtime.Loader().FinishLoading(Some(module.clone()));
+    module.into()
+}
+
+/// `eval(code)`: the value of the program in the string `code`, run in `env`,
+/// so it sees and can bind the same names as the code around it. Like
+/// `quote`, it is only a call by name, not a value.
+fn evalCode(args: Vec<ObjectEnum>, env: &Env) -> ObjectEnum {
+    let [code]: [ObjectEnum; 1] = match args.try_into() {
+        Ok(args) => args,
+        Err(args) => return wrongArgumentCount(1, args.len()),
+    };
+    let code = match code {
+        ObjectEnum::String(s) => s.value,
+        other => return builtins::unsupportedArgument("eval", &other),
+    };
+    let runtime = Rc::clone(env.borrow().Runtime());
+    let mut p = Parser::WithEdition(Lexer::New(&code), runtime.Edition());
+    let program = p.ParseProgram();
+    for warning in p.warnings.Take() {
+        // the span is in the string
+        runtime.Warnings().Warn(Warning {
+            span: None,
+            ..warning
+        });
+    }
+    if let Some(error) = p.errors.first() {
+        return Error::new(
+            ErrorKind::SyntaxError,
+            format!("can't parse the code passed to `eval`: {}", error),
+        )
+        .with("code", Str { value: code })
+        .into();
+    }
+    // the statements run as if written where `eval` is, not as a program:
+    // what is deferred runs when the caller returns, and a throw can be
+    // caught around the call
+    let mut result = NULL;
+    for stmt in program.statements {
+        match checkRuntime(env).or_else(|| Eval(stmt.into(), env)) {
+            Some(ObjectEnum::ReturnValue(ReturnValue(rv))) => return *rv,
+            Some(err) if isError(&err) => return err,
+            Some(value) => result = value,
+            None => result = NULL,
+        }
     }
+    result
 }
 
-fn evalProgram(program: Program) -> Option<ObjectEnum> {
+/// Evaluates the body of the first arm whose pattern equals the subject, or
+/// returns null if no arm matches.
+fn evalMatchExpression(m: MatchExpression, env: &Env, tail: bool) -> Option<ObjectEnum> {
+    let subject = Eval((*m.subject).into(), env)?;
+    if isError(&subject) {
+        return Some(subject);
+    }
+    for arm in m.arms {
+        let matched = match arm.pattern {
+            MatchPattern::Wildcard(_) => true,
+            MatchPattern::Literal(literal) => Eval(literal.into(), env).as_ref() == Some(&subject),
+        };
+        if matched {
+            let body: NodeEnum = (*arm.body).into();
+            return if tail {
+                evalTail(body, env)
+            } else {
+                Eval(body, env)
+            };
+        }
+    }
+    Some(NULL)
+}
+
+fn evalProgram(program: Program, env: &Env) -> Option<ObjectEnum> {
+    evalProgramReporting(program, env, &mut |_| {})
+}
+
+/// Evaluates `program` like `Eval` does, calling `report` with the value of
+/// each top-level statement as it finishes without an error.
+pub(crate) fn evalProgramReporting(
+    program: Program,
+    env: &Env,
+    report: &mut dyn FnMut(Option<&ObjectEnum>),
+) -> Option<ObjectEnum> {
     let mut result: Option<ObjectEnum> = None;
     for stmt in program.statements {
-        result = Eval(stmt.into());
+        result = checkRuntime(env).or_else(|| Eval(stmt.into(), env));
 
         match result {
-            Some(ObjectEnum::ReturnValue(ReturnValue(rv))) => return Some(*rv),
-            Some(ObjectEnum::Error(_)) => return result,
-            _ => {}
+            Some(ObjectEnum::ReturnValue(ReturnValue(rv))) => {
+                result = Some(*rv);
+                report(result.as_ref());
+                break;
+            }
+            Some(ref err) if isError(err) => break,
+            _ => report(result.as_ref()),
         }
     }
 
-    result
+    match runDeferred(result, env) {
+        Some(ObjectEnum::Thrown(thrown)) => Some(thrown.Uncaught().into()),
+        result => result,
+    }
 }
 
-fn evalBlockStatement(bs: BlockStatement) -> Option<ObjectEnum> {
+/// With `tail` set, the block is in tail position: so is its last statement,
+/// and a `return` anywhere in it.
+fn evalBlockStatement(bs: BlockStatement, env: &Env, tail: bool) -> Option<ObjectEnum> {
     let mut result: Option<ObjectEnum> = None;
-    for statement in bs.statements {
-        result = Eval(statement.into());
+    let last = bs.statements.len().saturating_sub(1);
+    for (i, statement) in bs.statements.into_iter().enumerate() {
+        let inTail = tail && (i == last || matches!(statement, StatementEnum::Return(_)));
+        result = checkRuntime(env).or_else(|| {
+            if inTail {
+                evalTail(statement.into(), env)
+            } else {
+                Eval(statement.into(), env)
+            }
+        });
         match &result {
-            Some(ObjectEnum::ReturnValue(_)) | Some(ObjectEnum::Error(_)) => return result,
+            Some(ObjectEnum::ReturnValue(_)) => return result,
+            Some(ref err) if isError(err) => return result,
             _ => {}
         }
     }
@@ -1,17 +1,31 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::ast::Node;
+use crate::edition::Edition;
+use crate::environment::Environment;
 use crate::lexer::Lexer;
-use crate::object::Null;
+use crate::object::ErrorKind;
 use crate::object::Object;
 use crate::object::ObjectEnum;
+use crate::object::NULL;
 use crate::parser::Parser;
 
+use super::isError;
 use super::Eval;
+use crate::runtime::Limit;
+use crate::runtime::Limits;
+use crate::runtime::Runtime;
+use crate::warning::WarningCode;
 
 fn testEval(input: &str) -> Option<ObjectEnum> {
     let l = Lexer::New(input);
     let mut p = Parser::New(l);
     let program = p.ParseProgram();
+    let env = Environment::New();
 
-    Eval(program.into())
+    Eval(program.into(), &env)
 }
 
 fn testIntegerObject(obj: ObjectEnum, expected: i64) {
@@ -53,6 +67,32 @@ fn EvalIntegerExpression() {
         let evaluated = testEval(input);
         testIntegerObject(evaluated.unwrap(), expected);
     }
+
+    let tests = vec![
+        (
+            "9223372036854775807 + 1",
+            "9223372036854775807 + 1 is out of range",
+        ),
+        (
+            "-9223372036854775807 - 2",
+            "-9223372036854775807 - 2 is out of range",
+        ),
+        (
+            "4611686018427387904 * 2",
+            "4611686018427387904 * 2 is out of range",
+        ),
+        (
+            "let min = -9223372036854775807 - 1; -min",
+            "-(-9223372036854775808) is out of range",
+        ),
+    ];
+    for (input, expected) in tests {
+        testErrorObject(
+            testEval(input).unwrap(),
+            ErrorKind::ArithmeticError,
+            expected,
+        );
+    }
 }
 
 #[test]
@@ -101,7 +141,7 @@ fn EvalBangOperator() {
 }
 
 fn testNullObject(obj: ObjectEnum) {
-    assert_eq!(obj, Null.into())
+    assert_eq!(obj, NULL)
 }
 
 #[test]
@@ -159,7 +199,12 @@ fn ErrorHandling() {
         ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
         ("-true", "unknown operator: -BOOLEAN"),
         ("true + false;", "unknown operator: BOOLEAN + BOOLEAN"),
+        (r#""Hello" - "World""#, "unknown operator: STRING - STRING"),
+        (r#"1 + "a""#, "type mismatch: INTEGER + STRING"),
         ("5; true + false; 5;", "unknown operator: BOOLEAN + BOOLEAN"),
+        ("1 < true", "type mismatch: INTEGER < BOOLEAN"),
+        ("false > 0", "type mismatch: BOOLEAN > INTEGER"),
+        ("true < false", "unknown operator: BOOLEAN < BOOLEAN"),
         (
             "if (10 > 1) { true + false; }",
             "unknown operator: BOOLEAN + BOOLEAN",
@@ -168,6 +213,9 @@ fn ErrorHandling() {
             "if (10 > 1) { if (10 > 1) { return true + false; } return 1; }",
             "unknown operator: BOOLEAN + BOOLEAN",
         ),
+        ("foobar", "identifier not found: foobar"),
+        ("5(1)", "not a function: INTEGER"),
+        ("fn(x) { x }()", "wrong number of arguments: want=1, got=0"),
     ];
     for (input, expected) in tests {
         let evaluated = testEval(input).unwrap();
@@ -176,6 +224,2159 @@ fn ErrorHandling() {
             ObjectEnum::Error(e) => e,
             _ => panic!("no error message, got={}", evaluated.Inspect()),
         };
-        assert_eq!(err_obj.0.as_str(), expected);
+        assert_eq!(err_obj.message.as_str(), expected);
+    }
+}
+
+#[test]
+fn ErrorKindsAndData() {
+    let tests = vec![
+        ("5 + true", ErrorKind::TypeError, Some("INTEGER"), "+"),
+        ("-true", ErrorKind::OperatorError, None, "-"),
+        (
+            "(true + false) * 1",
+            ErrorKind::OperatorError,
+            Some("BOOLEAN"),
+            "+",
+        ),
+    ];
+    for (input, kind, left, operator) in tests {
+        let err = match testEval(input).unwrap() {
+            ObjectEnum::Error(e) => e,
+            other => panic!("no error object, got={}", other.Inspect()),
+        };
+        assert_eq!(err.kind, kind);
+        assert_eq!(
+            err.data.get("left").map(|l| l.Inspect()),
+            left.map(String::from)
+        );
+        assert_eq!(err.data["operator"].Inspect(), operator);
+    }
+    assert_eq!(ErrorKind::TypeError.to_string(), "TypeError");
+}
+
+fn testStringObject(obj: ObjectEnum, expected: &str) {
+    let result = match obj {
+        ObjectEnum::String(s) => s,
+        _ => panic!("testStringObject: obj was {}", obj.Inspect()),
+    };
+    assert_eq!(result.value, expected);
+}
+
+#[test]
+fn StringLiteral() {
+    let evaluated = testEval(r#""Hello World!""#);
+    testStringObject(evaluated.unwrap(), "Hello World!");
+}
+
+#[test]
+fn StringConcatenation() {
+    let evaluated = testEval(r#""Hello" + " " + "World!""#);
+    testStringObject(evaluated.unwrap(), "Hello World!");
+}
+
+#[test]
+fn StringComparison() {
+    let tests = vec![
+        (r#""a" == "a""#, true),
+        (r#""a" != "a""#, false),
+        (r#""a" < "b""#, true),
+        (r#""b" > "ab""#, true),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input);
+        testBooleanObject(evaluated.unwrap(), expected);
+    }
+}
+
+#[test]
+fn StringInterpolation() {
+    let tests = vec![
+        (r#""two is ${1 + 1}""#, "two is 2"),
+        (r#""${true} or ${"not" + " ${false}"}!""#, "true or not false!"),
+        (r#""${"}"}{}""#, "}{}"),
+        (r#""escaped \${1}""#, "escaped ${1}"),
+        (r#""${[1, "a"]}""#, "[1, a]"),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input);
+        testStringObject(evaluated.unwrap(), expected);
+    }
+
+    // only interpolations are turned into strings: `+` joins strings alone,
+    // whichever side the other operand is on
+    let tests = vec![
+        (r#""a" + 1"#, "type mismatch: STRING + INTEGER"),
+        (r#"1 + "a""#, "type mismatch: INTEGER + STRING"),
+    ];
+    for (input, expected) in tests {
+        testErrorObject(testEval(input).unwrap(), ErrorKind::TypeError, expected);
+    }
+}
+
+#[test]
+fn LetStatements() {
+    let tests = vec![
+        ("let a = 5; a;", 5),
+        ("let a = 5 * 5; a;", 25),
+        ("let a = 5; let b = a; b;", 5),
+        ("let a = 5; let b = a; let c = a + b + 5; c;", 15),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(testEval(input).unwrap(), expected);
+    }
+}
+
+#[test]
+fn FunctionObject() {
+    let evaluated = testEval("fn(x) { x + 2; };").unwrap();
+    let function = match evaluated {
+        ObjectEnum::Function(f) => f,
+        other => panic!("object is not Function. got={}", other.Inspect()),
+    };
+    assert_eq!(function.parameters.len(), 1);
+    assert_eq!(function.parameters[0].String(), "x");
+    assert_eq!(function.body.String(), "(x + 2)");
+}
+
+#[test]
+fn FunctionApplication() {
+    let tests = vec![
+        ("let identity = fn(x) { x; }; identity(5);", 5),
+        ("let identity = fn(x) { return x; }; identity(5);", 5),
+        ("let double = fn(x) { x * 2; }; double(5);", 10),
+        ("let add = fn(x, y) { x + y; }; add(5, 5);", 10),
+        ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", 20),
+        ("fn(x) { x; }(5)", 5),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(testEval(input).unwrap(), expected);
+    }
+}
+
+#[test]
+fn Closures() {
+    let input = "
+        let newAdder = fn(x) {
+            fn(y) { x + y };
+        };
+
+        let addTwo = newAdder(2);
+        addTwo(2);";
+    testIntegerObject(testEval(input).unwrap(), 4);
+}
+
+#[test]
+fn ArrowFunctions() {
+    let tests = vec![
+        ("let inc = |x| x + 1; inc(4)", 5),
+        ("(|| 7)()", 7),
+        ("let add = |x| |y| x + y; add(2)(3)", 5),
+        ("let apply = fn(f, x) { f(x) }; apply(|n| n * n, 6)", 36),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(testEval(input).unwrap(), expected);
+    }
+}
+
+#[test]
+fn DeferStatements() {
+    let tests = vec![
+        // deferred expressions see the function's bindings as of its exit
+        ("fn() { defer y; let y = 1; 2 }()", Ok(2)),
+        (
+            "fn() { defer 1 + true; 5 }()",
+            Err("type mismatch: INTEGER + BOOLEAN"),
+        ),
+        // most recent first, and the first error wins
+        (
+            "fn() { defer 1 + true; defer -false; 5 }()",
+            Err("unknown operator: -BOOLEAN"),
+        ),
+        (
+            "fn() { defer y; return 1 + true; }()",
+            Err("type mismatch: INTEGER + BOOLEAN"),
+        ),
+        (
+            "let f = fn() { defer z; 1 }; let g = fn() { f() }; 3",
+            Ok(3),
+        ),
+        ("defer y; let y = 3; y", Ok(3)),
+        ("defer y; 3", Err("identifier not found: y")),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input).unwrap();
+        match expected {
+            Ok(integer) => testIntegerObject(evaluated, integer),
+            Err(message) => match evaluated {
+                ObjectEnum::Error(e) => assert_eq!(e.message, message),
+                other => panic!("no error message, got={}", other.Inspect()),
+            },
+        }
+    }
+}
+
+#[test]
+fn TailCalls() {
+    // deep enough to overflow the host stack if every call recursed
+    let tests = vec![
+        (
+            "let countdown = fn(n) { if (n == 0) { 0 } else { countdown(n - 1) } }; countdown(100000)",
+            0,
+        ),
+        (
+            "let sum = fn(n, acc) { if (n == 0) { return acc; } return sum(n - 1, acc + n); }; sum(100000, 0)",
+            5000050000,
+        ),
+        (
+            "let even = fn(n) { match n { 0 => 1, _ => odd(n - 1) } };
+             let odd = fn(n) { match n { 0 => 0, _ => even(n - 1) } };
+             even(100001)",
+            0,
+        ),
+        ("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(10)", 10),
+        ("let f = fn(g) { g(2) }; f(fn(x) { x * 3 })", 6),
+        // the call has to return before the frame's deferred expressions run
+        (
+            "let n = 0; let g = fn() { n }; let f = fn() { defer n++; g() }; f()",
+            0,
+        ),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(testEval(input).unwrap(), expected);
+    }
+
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(1000),
+        time: None,
+        depth: None,
+    }));
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let mut p = Parser::New(Lexer::New("let loop = fn() { loop() }; loop()"));
+    testErrorObject(
+        Eval(p.ParseProgram().into(), &env).unwrap(),
+        ErrorKind::LimitExceeded,
+        "evaluation exceeded 1000 steps",
+    );
+}
+
+#[test]
+fn NullLiteral() {
+    testNullObject(testEval("null").unwrap());
+    testNullObject(testEval("let x = null; x").unwrap());
+    testNullObject(testEval("if (null) { 1 }").unwrap());
+    testNullObject(testEval("if (null) { 1 } else { null }").unwrap());
+
+    let tests = vec![
+        ("null == null", true),
+        ("null != null", false),
+        ("!null", true),
+        ("null == false", false),
+        ("if (false) { 1 } == null", true),
+    ];
+    for (input, expected) in tests {
+        testBooleanObject(testEval(input).unwrap(), expected);
+    }
+}
+
+fn testErrorObject(obj: ObjectEnum, kind: ErrorKind, message: &str) {
+    match obj {
+        ObjectEnum::Error(e) => {
+            assert_eq!(e.kind, kind);
+            assert_eq!(e.message, message);
+        }
+        other => panic!("no error object, got={}", other.Inspect()),
+    }
+}
+
+#[test]
+fn Cancellation() {
+    let runtime = Rc::new(Runtime::New());
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let run = |input: &str| {
+        let mut p = Parser::New(Lexer::New(input));
+        Eval(p.ParseProgram().into(), &env).unwrap()
+    };
+
+    testIntegerObject(run("let f = fn(x) { x * 2 }; f(2)"), 4);
+
+    runtime.Cancellation().Cancel();
+    testErrorObject(run("f(3)"), ErrorKind::Interrupted, "interrupted");
+
+    runtime.Reset();
+    testIntegerObject(run("f(3)"), 6);
+}
+
+#[test]
+fn EvalMatchExpression() {
+    let tests = vec![
+        ("match 1 { 1 => 10, 2 => 20, _ => 30 }", Some(10)),
+        ("match 1 + 1 { 1 => 10, 2 => 20, _ => 30 }", Some(20)),
+        ("match 5 { 1 => 10, 2 => 20, _ => 30 }", Some(30)),
+        ("match 5 { 1 => 10 }", None),
+        ("match -1 { 1 => 10, -1 => 20 }", Some(20)),
+        (r#"match "a" { "b" => 1, "a" => 2, }"#, Some(2)),
+        ("match true { false => 1, true => 2 }", Some(2)),
+        ("match null { 0 => 1, false => 2, null => 3 }", Some(3)),
+        ("match 1 { true => 1, 1 => 2 }", Some(2)),
+        ("match 2 { 2 => { let x = 5; x * 2 }, _ => 0 }", Some(10)),
+        (
+            "fn(x) { match x { 1 => { return 7; }, _ => 0 }; 8 }(1)",
+            Some(7),
+        ),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input).unwrap();
+        match expected {
+            Some(integer) => testIntegerObject(evaluated, integer),
+            None => testNullObject(evaluated),
+        }
+    }
+}
+
+const COUNTDOWN: &str = "
+    let countdown = fn(n) { if (n == 0) { 0 } else { countdown(n - 1) } };
+    countdown(20)";
+
+#[test]
+fn FuelLimit() {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(10),
+        time: None,
+        depth: None,
+    }));
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let mut p = Parser::New(Lexer::New(COUNTDOWN));
+    let evaluated = Eval(p.ParseProgram().into(), &env).unwrap();
+    testErrorObject(
+        evaluated,
+        ErrorKind::LimitExceeded,
+        "evaluation exceeded 10 steps",
+    );
+}
+
+#[test]
+fn TimeLimit() {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: None,
+        time: Some(Duration::from_secs(0)),
+        depth: None,
+    }));
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let mut p = Parser::New(Lexer::New(COUNTDOWN));
+    let evaluated = Eval(p.ParseProgram().into(), &env).unwrap();
+    testErrorObject(
+        evaluated,
+        ErrorKind::LimitExceeded,
+        "evaluation exceeded 0ns",
+    );
+}
+
+#[test]
+fn DepthLimit() {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: None,
+        time: None,
+        depth: Some(5),
+    }));
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let eval = |input| Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env);
+    eval("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }");
+
+    testIntegerObject(eval("f(4)").unwrap(), 4);
+    testErrorObject(
+        eval("f(100)").unwrap(),
+        ErrorKind::StackOverflow,
+        "stack overflow: max depth 5 exceeded",
+    );
+    // the depth unwinds with the error, and tail calls don't add to it
+    testIntegerObject(eval("f(4)").unwrap(), 4);
+    testIntegerObject(
+        eval("let g = fn(n) { if (n == 0) { 0 } else { g(n - 1) } }; g(1000)").unwrap(),
+        0,
+    );
+}
+
+#[test]
+fn LimitEscalation() {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(10),
+        time: None,
+        depth: None,
+    }));
+    let asked = Rc::new(RefCell::new(vec![]));
+    let handler_asked = Rc::clone(&asked);
+    runtime.OnLimitExceeded(move |limit| {
+        handler_asked.borrow_mut().push(limit);
+        true
+    });
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let mut p = Parser::New(Lexer::New(COUNTDOWN));
+    let evaluated = Eval(p.ParseProgram().into(), &env).unwrap();
+
+    // the evaluation picks up where it stopped rather than starting over
+    testIntegerObject(evaluated, 0);
+    assert!(asked.borrow().len() > 1);
+    assert!(asked.borrow().iter().all(|l| *l == Limit::Fuel(10)));
+}
+
+#[test]
+fn UpdateExpressions() {
+    let tests = vec![
+        ("let i = 1; i++", 1),
+        ("let i = 1; i++; i", 2),
+        ("let i = 1; ++i", 2),
+        ("let i = 1; i--; i", 0),
+        ("let i = 5; --i + i--", 8),
+        ("let i = 5; --i + i--; i", 3),
+        ("let c = 0; let inc = fn() { c++ }; inc(); inc(); c", 2),
+        ("let c = 0; let f = fn(c) { c++; c }; f(10) + c", 11),
+        // `--` only decrements an identifier it's next to
+        ("5--3", 8),
+        ("let x = 2; 5--x; x", 2),
+        ("--3", 3),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(testEval(input).unwrap(), expected);
+    }
+
+    testErrorObject(
+        testEval("let b = true; b++").unwrap(),
+        ErrorKind::TypeError,
+        "++ requires an INTEGER, got BOOLEAN",
+    );
+    testErrorObject(
+        testEval("--x").unwrap(),
+        ErrorKind::NameError,
+        "identifier not found: x",
+    );
+    testErrorObject(
+        testEval("let i = 9223372036854775807; i++").unwrap(),
+        ErrorKind::ArithmeticError,
+        "9223372036854775807 + 1 is out of range",
+    );
+    testErrorObject(
+        testEval("let i = -9223372036854775807 - 1; --i").unwrap(),
+        ErrorKind::ArithmeticError,
+        "-9223372036854775808 - 1 is out of range",
+    );
+}
+
+#[test]
+fn BuiltinFunctions() {
+    testIntegerObject(testEval(r#"len("")"#).unwrap(), 0);
+    testIntegerObject(testEval(r#"len("four")"#).unwrap(), 4);
+    testIntegerObject(testEval(r#"len("héllo")"#).unwrap(), 5);
+    testIntegerObject(testEval(r#"let len = fn(x) { 7 }; len("a")"#).unwrap(), 7);
+    testNullObject(testEval(r#"puts("hello")"#).unwrap());
+    testErrorObject(
+        testEval("len(1)").unwrap(),
+        ErrorKind::TypeError,
+        "argument to `len` not supported, got INTEGER",
+    );
+    testErrorObject(
+        testEval(r#"len("one", "two")"#).unwrap(),
+        ErrorKind::ArgumentError,
+        "wrong number of arguments: want=1, got=2",
+    );
+}
+
+#[test]
+fn HigherOrderBuiltins() {
+    let tests = vec![
+        ("map([1, 2, 3], |x| x * 2)", "[2, 4, 6]"),
+        ("map([], |x| x)", "[]"),
+        ("filter([1, 2, 3, 4], |x| x > 2)", "[3, 4]"),
+        ("reduce([1, 2, 3], 10, |acc, x| acc + x)", "16"),
+        ("reduce([], 10, |acc, x| acc + x)", "10"),
+        (
+            "let add = fn(a) { fn(b) { a + b } }; map([1, 2], add(10))",
+            "[11, 12]",
+        ),
+        ("map([[1], [2, 3]], len)", "[1, 2]"),
+        ("reduce(map([1, 2, 3], |x| x * x), 0, |a, b| a + b)", "14"),
+        (
+            "map(1, |x| x)",
+            "ERROR: argument to `map` not supported, got INTEGER",
+        ),
+        ("map([1], 5)", "ERROR: not a function: INTEGER"),
+        (
+            "map([1])",
+            "ERROR: wrong number of arguments: want=2, got=1",
+        ),
+        (
+            "map([1], |a, b| a)",
+            "ERROR: wrong number of arguments: want=2, got=1",
+        ),
+        (
+            "map([1], |x| x + true)",
+            "ERROR: type mismatch: INTEGER + BOOLEAN",
+        ),
+        (
+            "try { filter([1, 2], fn(x) { throw x * 10 }) } catch (e) { e }",
+            "10",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetEdition(Edition::V2);
+    let env = Environment::WithRuntime(runtime);
+    let mut p = Parser::WithEdition(Lexer::New("filter([1], |x| x);"), Edition::V2);
+    testErrorObject(
+        Eval(p.ParseProgram().into(), &env).unwrap(),
+        ErrorKind::TypeError,
+        "result of the `filter` predicate must be a BOOLEAN, got INTEGER",
+    );
+}
+
+#[test]
+fn MathBuiltins() {
+    let tests = vec![
+        ("abs(-5)", "5"),
+        ("abs(5)", "5"),
+        (
+            "abs(-9223372036854775807 - 1)",
+            "ERROR: result of `abs` is out of range",
+        ),
+        ("min(3, 1, 2)", "1"),
+        ("max(3, 1, 2)", "3"),
+        ("max([4, 9, 2])", "9"),
+        ("min(7)", "7"),
+        (r#"min("b", "a", "c")"#, "a"),
+        ("min([])", "ERROR: `min` needs at least one value"),
+        (
+            r#"max(1, "a")"#,
+            "ERROR: arguments to `max` can't be compared: INTEGER and STRING",
+        ),
+        ("pow(2, 10)", "1024"),
+        ("pow(-3, 3)", "-27"),
+        ("pow(5, 0)", "1"),
+        (
+            "pow(2, -1)",
+            "ERROR: exponent of `pow` must not be negative",
+        ),
+        ("pow(2, 64)", "ERROR: result of `pow` is out of range"),
+        (
+            r#"pow(2, "a")"#,
+            "ERROR: argument to `pow` not supported, got STRING",
+        ),
+        ("pow(2)", "ERROR: wrong number of arguments: want=2, got=1"),
+        ("sqrt(16)", "4"),
+        ("sqrt(15)", "3"),
+        ("sqrt(0)", "0"),
+        ("sqrt(9223372036854775807)", "3037000499"),
+        ("sqrt(-1)", "ERROR: argument to `sqrt` must not be negative"),
+        ("floor(7)", "7"),
+        ("ceil(-7)", "-7"),
+        (
+            r#"floor("a")"#,
+            "ERROR: argument to `floor` not supported, got STRING",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn HashBuiltins() {
+    let tests = vec![
+        (r#"keys({"b": 1, "a": 2})"#, "[a, b]"),
+        (r#"values({"b": 1, "a": 2})"#, "[2, 1]"),
+        ("keys({})", "[]"),
+        (r#"has({"a": null}, "a")"#, "true"),
+        (r#"has({"a": 1}, "b")"#, "false"),
+        ("has({1: 1}, 1)", "true"),
+        (r#"delete({"a": 1, "b": 2}, "a")"#, "{b: 2}"),
+        (r#"delete({"a": 1}, "b")"#, "{a: 1}"),
+        (r#"let h = {"a": 1}; delete(h, "a"); h"#, "{a: 1}"),
+        (
+            r#"merge({"a": 1, "b": 2}, {"b": 3, "c": 4})"#,
+            "{a: 1, b: 3, c: 4}",
+        ),
+        ("has({}, [])", "ERROR: unusable as hash key: ARRAY"),
+        (
+            "keys([1])",
+            "ERROR: argument to `keys` not supported, got ARRAY",
+        ),
+        (
+            "merge({}, 1)",
+            "ERROR: argument to `merge` not supported, got INTEGER",
+        ),
+        (
+            "delete({})",
+            "ERROR: wrong number of arguments: want=2, got=1",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[cfg(feature = "i18n")]
+#[test]
+fn I18nBuiltins() {
+    let tests = vec![
+        ("format_number(1234567)", "1,234,567"),
+        ("format_number(-1000)", "-1,000"),
+        ("format_number(999)", "999"),
+        (
+            r#"format_number(1234567, {"locale": "de-DE"})"#,
+            "1.234.567",
+        ),
+        (
+            r#"format_number(1234, {"locale": "fr", "decimals": 2})"#,
+            "1\u{202f}234,00",
+        ),
+        (r#"format_number(1234, {"thousands_sep": "'"})"#, "1'234"),
+        (r#"format_number(12, {"decimals": 1})"#, "12.0"),
+        (
+            r#"format_number(1, {"locale": "xx"})"#,
+            "ERROR: unknown locale `xx`",
+        ),
+        (
+            r#"format_number(1, {"decimals": -1})"#,
+            "ERROR: option `decimals` to `format_number` can't be -1",
+        ),
+        (
+            r#"format_number(1, {"digits": 1})"#,
+            "ERROR: unknown option `digits` to `format_number`",
+        ),
+        (
+            r#"format_number("1")"#,
+            "ERROR: argument to `format_number` not supported, got STRING",
+        ),
+        (r#"upper("straße")"#, "STRASSE"),
+        (r#"upper("istanbul")"#, "ISTANBUL"),
+        (r#"upper("istanbul", "tr")"#, "\u{130}STANBUL"),
+        (r#"lower("ISPARTA", "tr-TR")"#, "\u{131}sparta"),
+        (r#"lower("İzmir", "tr")"#, "izmir"),
+        (r#"lower("ISPARTA")"#, "isparta"),
+        (r#"upper("a", "xx")"#, "ERROR: unknown locale `xx`"),
+        (
+            "upper(1)",
+            "ERROR: argument to `upper` not supported, got INTEGER",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn RegexBuiltins() {
+    let tests = vec![
+        (r#"re_match("b+", "abbc")"#, "{0: bb}"),
+        (r#"re_match("x", "abc")"#, "null"),
+        (
+            r#"re_match("(?P<key>\w+)=(\d+)?", "a=")"#,
+            "{0: a=, 1: a, 2: null, key: a}",
+        ),
+        (
+            r#"re_find_all("(\d)(\d)", "12 34 5")"#,
+            "[{0: 12, 1: 1, 2: 2}, {0: 34, 1: 3, 2: 4}]",
+        ),
+        (r#"re_find_all("x", "abc")"#, "[]"),
+        (r#"re_replace("(\w)(\d)", "a1 b2", "$2$1")"#, "1a 2b"),
+        (
+            r#"re_replace("(?P<n>\d+)", "1 and 22", "<\${n}>")"#,
+            "<1> and <22>",
+        ),
+        (
+            r#"re_replace("\d+", "1 and 22", fn(m) { str(int(m[0]) * 2) })"#,
+            "2 and 44",
+        ),
+        (
+            r#"re_replace("\d", "1", fn(m) { 1 })"#,
+            "ERROR: result of the `re_replace` replacement must be a STRING, got INTEGER",
+        ),
+        (
+            r#"re_replace("\d", "1", fn(m) { m[0] - 1 })"#,
+            "ERROR: type mismatch: STRING - INTEGER",
+        ),
+        (
+            r#"re_match("(", "")"#,
+            "ERROR: invalid pattern passed to `re_match`: regex parse error:\n    (\n    ^\nerror: unclosed group",
+        ),
+        (
+            r#"re_match(1, "")"#,
+            "ERROR: argument to `re_match` not supported, got INTEGER",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn ConversionBuiltins() {
+    let tests = vec![
+        ("type(5)", "INTEGER"),
+        (r#"type("a")"#, "STRING"),
+        ("type([])", "ARRAY"),
+        ("type(fn() {})", "FUNCTION"),
+        ("type(len)", "BUILTIN"),
+        ("type(null)", "NULL"),
+        (r#"int("5")"#, "5"),
+        (r#"int("-42")"#, "-42"),
+        ("int(7)", "7"),
+        ("int(true)", "1"),
+        (
+            r#"int("five")"#,
+            r#"ERROR: can't convert "five" to INTEGER"#,
+        ),
+        (
+            r#"int("99999999999999999999")"#,
+            r#"ERROR: can't convert "99999999999999999999" to INTEGER"#,
+        ),
+        ("int([1])", "ERROR: can't convert ARRAY to INTEGER"),
+        ("str(5)", "5"),
+        (r#"str(5) + "1""#, "51"),
+        ("str([1, true])", "[1, true]"),
+        // like `if`
+        ("bool(0)", "true"),
+        ("bool(-3)", "true"),
+        ("bool(null)", "false"),
+        ("bool(false)", "false"),
+        (r#"bool("")"#, "true"),
+        (r#"bool("false")"#, "true"),
+        ("bool([])", "true"),
+        ("bool({})", "true"),
+        ("bool(0) == !!0", "true"),
+        ("int()", "ERROR: wrong number of arguments: want=1, got=0"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn FormatBuiltin() {
+    let tests = vec![
+        (r#"format("x=%d y=%s", 1, "a")"#, "x=1 y=a"),
+        (r#"format("no directives")"#, "no directives"),
+        (r#"format("100%%")"#, "100%"),
+        (r#"format("%s and %s", [1, 2], true)"#, "[1, 2] and true"),
+        (r#"format("[%5d]", 42)"#, "[   42]"),
+        (r#"format("[%-5d]", 42)"#, "[42   ]"),
+        (r#"format("[%05d]", -42)"#, "[-0042]"),
+        (r#"format("[%4s|%-4s]", "ab", "cd")"#, "[  ab|cd  ]"),
+        (r#"format("%x %x", 255, -16)"#, "ff -10"),
+        (
+            r#"format("%d", "a")"#,
+            "ERROR: `format`: %d needs an INTEGER, got STRING",
+        ),
+        (
+            r#"format("%d %d", 1)"#,
+            "ERROR: `format`: directive 2 has no argument",
+        ),
+        (
+            r#"format("%d", 1, 2, 3)"#,
+            "ERROR: `format`: 2 more arguments than directives",
+        ),
+        (
+            r#"format("%q", 1)"#,
+            "ERROR: `format`: unknown directive %q",
+        ),
+        (
+            r#"format("50%")"#,
+            "ERROR: `format`: format string ends in a directive",
+        ),
+        (
+            r#"format("%999999999999d", 1)"#,
+            "ERROR: `format`: width 999999999999 is more than 4096",
+        ),
+        ("format()", "ERROR: `format`: missing the format string"),
+        (
+            "format(1)",
+            "ERROR: argument to `format` not supported, got INTEGER",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+    assert_eq!(
+        testEval(r#"format("[%4096d]", 1)"#).unwrap().Inspect(),
+        format!("[{:>4096}]", 1)
+    );
+
+    let platform = Rc::new(crate::platform::MemoryPlatform::New());
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    let env = Environment::WithRuntime(runtime);
+    let input = r#"printf("%-3s|%3d\n", "a", 7); printf("end")"#;
+    Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+    assert_eq!(platform.Output(), "a  |  7\nend");
+}
+
+#[test]
+fn AssertBuiltin() {
+    let tests = vec![
+        ("assert(1 < 2)", "null"),
+        ("assert(true, \"fine\")", "null"),
+        (
+            "let x = 1; assert(x > 2)",
+            "ERROR: assertion failed: (x > 2)",
+        ),
+        (
+            r#"let x = 1; assert(x == 2, "x is " + str(x))"#,
+            "ERROR: assertion failed: (x == 2): x is 1",
+        ),
+        (
+            r#"try { assert(false) } catch (e) { [e["kind"], e["expression"]] }"#,
+            "[AssertionError, false]",
+        ),
+        // passed around, it can't show the code
+        (
+            "let check = assert; check(false)",
+            "ERROR: assertion failed",
+        ),
+        ("map([true, false], assert)", "ERROR: assertion failed"),
+        ("let assert = fn(c) { 5 }; assert(false)", "5"),
+        ("assert(y)", "ERROR: identifier not found: y"),
+        (
+            "assert()",
+            "ERROR: wrong number of arguments: want=2, got=0",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn RandomBuiltins() {
+    let tests = vec![
+        (
+            "rand_seed(7); let a = [rand(), rand_int(1, 6)]; rand_seed(7); a == [rand(), rand_int(1, 6)]",
+            "true",
+        ),
+        ("rand() > -1", "true"),
+        ("rand_int(3, 3)", "3"),
+        (
+            "let rolls = map([1, 2, 3, 4, 5, 6, 7, 8], |x| rand_int(-2, 2)); [len(filter(rolls, |r| r < -2)), len(filter(rolls, |r| r > 2))]",
+            "[0, 0]",
+        ),
+        (
+            "type(rand_int(-9223372036854775807 - 1, 9223372036854775807))",
+            "INTEGER",
+        ),
+        ("rand_int(2, 1)", "ERROR: `rand_int` got an empty range: 2 > 1"),
+        (r#"rand_int(1, "6")"#, "ERROR: argument to `rand_int` not supported, got STRING"),
+        (r#"rand_seed("x")"#, "ERROR: argument to `rand_seed` not supported, got STRING"),
+        ("rand(1)", "ERROR: wrong number of arguments: want=0, got=1"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    // every runtime has its own generator, which the host can seed
+    let draw = |seed| {
+        let runtime = Rc::new(Runtime::New());
+        runtime.SeedRandom(seed);
+        let env = Environment::WithRuntime(runtime);
+        let input = "[rand_int(1, 100), rand_int(1, 100), rand_int(1, 100)]";
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env)
+            .unwrap()
+            .Inspect()
+    };
+    assert_eq!(draw(42), draw(42));
+    assert_ne!(draw(42), draw(43));
+}
+
+#[test]
+fn IntegerDivision() {
+    use crate::runtime::Division;
+
+    // input, truncating, flooring
+    let tests = vec![
+        ("7 / 2", "3", "3"),
+        ("-7 / 2", "-3", "-4"),
+        ("7 / -2", "-3", "-4"),
+        ("-7 / -2", "3", "3"),
+        ("-8 / 2", "-4", "-4"),
+        ("7 % 3", "1", "1"),
+        ("-7 % 3", "-1", "2"),
+        ("7 % -3", "1", "-2"),
+        ("-7 % -3", "-1", "-1"),
+        ("-6 % 3", "0", "0"),
+        ("1 + 10 % 4 * 3", "7", "7"),
+        (
+            "1 / 0",
+            "ERROR: division by zero",
+            "ERROR: division by zero",
+        ),
+        (
+            "1 % 0",
+            "ERROR: division by zero",
+            "ERROR: division by zero",
+        ),
+        (
+            "(-9223372036854775807 - 1) / -1",
+            "ERROR: -9223372036854775808 / -1 is out of range",
+            "ERROR: -9223372036854775808 / -1 is out of range",
+        ),
+        (
+            r#""a" % 2"#,
+            "ERROR: type mismatch: STRING % INTEGER",
+            "ERROR: type mismatch: STRING % INTEGER",
+        ),
+    ];
+    for (input, truncated, floored) in tests {
+        for (division, expected) in [(Division::Truncate, truncated), (Division::Floor, floored)] {
+            let runtime = Rc::new(Runtime::New());
+            runtime.SetDivision(division);
+            let env = Environment::WithRuntime(runtime);
+            let program = Parser::New(Lexer::New(input)).ParseProgram();
+            let result = Eval(program.into(), &env).unwrap();
+            assert_eq!(result.Inspect(), expected, "{} ({:?})", input, division);
+        }
+    }
+    assert_eq!(Runtime::New().Division(), Division::Truncate);
+}
+
+#[test]
+fn TimeBuiltins() {
+    use crate::capability::Capability;
+    use crate::platform::MemoryPlatform;
+    use crate::runtime::Limits;
+
+    let platform = Rc::new(MemoryPlatform::New());
+    platform.Advance(Duration::from_millis(1500));
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        time: Some(Duration::from_secs(10)),
+        ..Limits::default()
+    }));
+    runtime.SetPlatform(platform.clone());
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let run = |input: &str| {
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env)
+            .unwrap()
+            .Inspect()
+    };
+
+    assert_eq!(run("[now(), clock()]"), "[1500, 1500]");
+    assert_eq!(
+        run("let start = clock(); sleep(250); clock() - start"),
+        "250"
+    );
+    assert_eq!(run("now()"), "1750");
+    assert_eq!(
+        run("sleep(-1)"),
+        "ERROR: argument to `sleep` must not be negative"
+    );
+    assert_eq!(
+        run("now(1)"),
+        "ERROR: wrong number of arguments: want=0, got=1"
+    );
+    // sleeping doesn't outlast the time limit
+    assert_eq!(run("sleep(60000); 1"), "ERROR: evaluation exceeded 10s");
+    runtime.Reset();
+    assert_eq!(run("clock()"), "11500");
+
+    runtime.Deny(Capability::Time);
+    assert!(!runtime.IsAllowed(Capability::Time));
+    assert_eq!(
+        run("clock()"),
+        "ERROR: `clock` needs the time capability, which is denied"
+    );
+    assert_eq!(
+        run(r#"try { sleep(1) } catch (e) { [e["kind"], e["capability"]] }"#),
+        "[PermissionDenied, time]"
+    );
+    assert_eq!(run("rand_seed(1); rand_int(1, 1)"), "1");
+
+    runtime.Deny(Capability::Io);
+    runtime.Deny(Capability::Random);
+    assert_eq!(
+        run(r#"import "m.mk""#),
+        "ERROR: `import` needs the io capability, which is denied"
+    );
+    assert_eq!(
+        run("rand()"),
+        "ERROR: `rand` needs the random capability, which is denied"
+    );
+    runtime.Allow(Capability::Time);
+    assert_eq!(run("type(now())"), "INTEGER");
+}
+
+#[test]
+fn FileBuiltins() {
+    use std::path::Path;
+
+    use crate::capability::Capability;
+    use crate::platform::MemoryPlatform;
+    use crate::platform::Platform;
+
+    let platform = Rc::new(MemoryPlatform::New());
+    platform.AddFile("/data/in.txt", "hello");
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let run = |input: &str| {
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env)
+            .unwrap()
+            .Inspect()
+    };
+
+    // they don't exist until the host allows them
+    assert!(!runtime.IsAllowed(Capability::Fs));
+    assert_eq!(
+        run(r#"read_file("/data/in.txt")"#),
+        "ERROR: identifier not found: read_file"
+    );
+    runtime.Allow(Capability::Fs);
+    assert_eq!(run(r#"read_file("/data/in.txt")"#), "hello");
+    assert_eq!(
+        run(r#"write_file("/data/out.txt", "a"); append_file("/data/out.txt", "b")"#),
+        "null"
+    );
+    assert_eq!(platform.ReadFile(Path::new("/data/out.txt")).unwrap(), "ab");
+    assert_eq!(
+        run(r#"append_file("new.txt", "c"); read_file("/new.txt")"#),
+        "c"
+    );
+    assert_eq!(run(r#"list_dir("/data")"#), "[in.txt, out.txt]");
+    assert_eq!(run(r#"list_dir("/")"#), "[data, new.txt]");
+    assert_eq!(
+        run(r#"try { read_file("missing") } catch (e) { [e["kind"], e["path"]] }"#),
+        "[IoError, missing]"
+    );
+    assert_eq!(
+        run(r#"list_dir("nowhere")"#),
+        "ERROR: can't list nowhere: no such file: nowhere"
+    );
+    assert_eq!(
+        run("write_file(1, 2)"),
+        "ERROR: argument to `write_file` not supported, got INTEGER"
+    );
+
+    // one kept from before is denied again along with the others
+    runtime.Deny(Capability::Fs);
+    assert_eq!(
+        run(r#"let read = read_file; read("/data/in.txt")"#),
+        "ERROR: identifier not found: read_file"
+    );
+    runtime.Allow(Capability::Fs);
+    run("let read = read_file; 0");
+    runtime.Deny(Capability::Fs);
+    assert_eq!(
+        run(r#"read("/data/in.txt")"#),
+        "ERROR: `read_file` needs the fs capability, which is denied"
+    );
+}
+
+#[test]
+fn EnvBuiltins() {
+    use crate::capability::Capability;
+    use crate::platform::MemoryPlatform;
+
+    let platform = Rc::new(MemoryPlatform::New());
+    platform.SetVar("HOME", "/home/monkey");
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let run = |input: &str| {
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env)
+            .unwrap()
+            .Inspect()
+    };
+
+    assert_eq!(run(r#"getenv("HOME")"#), "/home/monkey");
+    assert_eq!(run(r#"getenv("UNSET")"#), "null");
+    assert_eq!(run(r#"setenv("MODE", "fast"); getenv("MODE")"#), "fast");
+    assert_eq!(
+        run(r#"setenv("A=B", "1")"#),
+        r#"ERROR: can't set the environment variable "A=B" to "1""#
+    );
+    assert_eq!(
+        run(r#"setenv("MODE", 1)"#),
+        "ERROR: argument to `setenv` not supported, got INTEGER"
+    );
+
+    runtime.Deny(Capability::Env);
+    assert_eq!(
+        run(r#"getenv("HOME")"#),
+        "ERROR: `getenv` needs the env capability, which is denied"
+    );
+}
+
+#[test]
+fn HostArrays() {
+    use crate::object::HostArray;
+
+    let env = Environment::New();
+    env.borrow_mut()
+        .Set("ids", HostArray::from(vec![10, 20, 30]).into());
+    env.borrow_mut().Set(
+        "names",
+        HostArray::from(vec!["ann".to_string(), "bob".to_string()]).into(),
+    );
+    let run = |input: &str| {
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env)
+            .unwrap()
+            .Inspect()
+    };
+
+    let tests = vec![
+        ("ids[1]", "20"),
+        ("ids[-1]", "30"),
+        ("ids[3]", "null"),
+        ("len(ids)", "3"),
+        ("ids[1:]", "[20, 30]"),
+        ("ids", "[10, 20, 30]"),
+        ("type(ids)", "ARRAY"),
+        ("map(ids, |x| x / 10)", "[1, 2, 3]"),
+        ("max(ids)", "30"),
+        ("[0, ...ids]", "[0, 10, 20, 30]"),
+        ("let [a, b] = names; b", "bob"),
+        (r#"names[0] + "!""#, "ann!"),
+        ("ids == ids", "true"),
+        ("ids[true]", "ERROR: index must be an INTEGER, got BOOLEAN"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run(input), expected, "{}", input);
+    }
+}
+
+#[test]
+fn StringViewBuiltins() {
+    let tests = vec![
+        (r#"chars("abc")"#, "[a, b, c]"),
+        (r#"bytes("abc")"#, "[97, 98, 99]"),
+        (r#"graphemes("abc")"#, "[a, b, c]"),
+        (r#"chars("")"#, "[]"),
+        // é, as e and a combining acute accent
+        (r#"len(chars("é"))"#, "2"),
+        (r#"len(bytes("é"))"#, "3"),
+        (r#"graphemes("é")"#, "[é]"),
+        // a thumbs up with a skin tone
+        (r#"len(chars("👍🏽"))"#, "2"),
+        (r#"len(bytes("👍🏽"))"#, "8"),
+        (r#"len(graphemes("👍🏽"))"#, "1"),
+        (r#"bytes("ü")"#, "[195, 188]"),
+        (
+            "chars(1)",
+            "ERROR: argument to `chars` not supported, got INTEGER",
+        ),
+        (
+            r#"graphemes("a", "b")"#,
+            "ERROR: wrong number of arguments: want=1, got=2",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn EvalCall() {
+    let tests =
+        vec![
+        (r#"eval("1 + 2")"#, "3"),
+        (r#"let x = 4; eval("x * x")"#, "16"),
+        (r#"eval("let y = 5;"); y"#, "5"),
+        (r#"let f = fn(a) { eval("a + 1") }; f(1)"#, "2"),
+        (r#"eval("let f = fn(n) { if (n > 0) { f(n - 1) } else { 9 } }; f(3)")"#, "9"),
+        (r#"eval("")"#, "null"),
+        (r#"eval("eval(\"7\")")"#, "7"),
+        (
+            r#"eval("1 +")"#,
+            "ERROR: can't parse the code passed to `eval`: no prefix parse function for EOF found",
+        ),
+        (
+            r#"try { eval("let = 1") } catch (e) { [e["kind"], e["code"]] }"#,
+            "[SyntaxError, let = 1]",
+        ),
+        (r#"eval("1 + true")"#, "ERROR: type mismatch: INTEGER + BOOLEAN"),
+        ("eval(1)", "ERROR: argument to `eval` not supported, got INTEGER"),
+        (
+            r#"eval("1", "2")"#,
+            "ERROR: wrong number of arguments: want=1, got=2",
+        ),
+        // only a call by name, like `quote`
+        ("let e = eval; 1", "ERROR: identifier not found: eval"),
+        ("let eval = fn(x) { x }; eval(3)", "3"),
+        (r#"try { eval("throw 5") } catch (e) { e }"#, "5"),
+        (r#"eval("return 6; 7")"#, "6"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    // what the caller deferred waits for the caller to return
+    let platform = Rc::new(crate::platform::MemoryPlatform::New());
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    let env = Environment::WithRuntime(runtime);
+    let input = r#"fn() { defer puts("deferred"); eval("1"); puts("after eval"); 2 }()"#;
+    let result = Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env);
+    assert_eq!(result.unwrap().Inspect(), "2");
+    assert_eq!(platform.Output(), "after eval\ndeferred\n");
+}
+
+#[test]
+fn ExitBuiltin() {
+    let tests = vec![
+        ("exit(3); 1", Some(3)),
+        ("exit()", Some(0)),
+        ("let f = fn() { exit(2) }; [1, f(), 3]", Some(2)),
+        // `catch` doesn't stop it
+        ("try { exit(1) } catch (e) { 5 }", Some(1)),
+        ("1 / 0", None),
+        ("1", None),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input).unwrap();
+        assert_eq!(evaluated.ExitCode(), expected, "{}", input);
+    }
+    assert_eq!(
+        testEval("exit(4)").unwrap().Inspect(),
+        "ERROR: exited with status 4"
+    );
+    assert_eq!(
+        testEval("exit(256)").unwrap().Inspect(),
+        "ERROR: status passed to `exit` must be from 0 to 255, got 256"
+    );
+    assert_eq!(
+        testEval(r#"exit("1")"#).unwrap().Inspect(),
+        "ERROR: argument to `exit` not supported, got STRING"
+    );
+}
+
+#[test]
+fn SortBuiltin() {
+    let tests = vec![
+        ("sort([3, 1, 2])", "[1, 2, 3]"),
+        (r#"sort(["b", "c", "a"])"#, "[a, b, c]"),
+        ("sort([])", "[]"),
+        ("let a = [2, 1]; sort(a); a", "[2, 1]"),
+        ("sort([3, 1, 2], fn(a, b) { b - a })", "[3, 2, 1]"),
+        (
+            "sort([[2, 1], [1, 2], [2, 2], [1, 1]], |a, b| a[0] - b[0])",
+            "[[1, 2], [1, 1], [2, 1], [2, 2]]",
+        ),
+        ("sort([1, 2, 3], fn(a, b) { 1 })", "[3, 2, 1]"),
+        (
+            r#"sort([1, "a"])"#,
+            "ERROR: elements passed to `sort` can't be compared: INTEGER and STRING",
+        ),
+        (
+            "sort([true, false])",
+            "ERROR: argument to `sort` not supported, got BOOLEAN",
+        ),
+        (
+            "sort(1)",
+            "ERROR: argument to `sort` not supported, got INTEGER",
+        ),
+        (
+            "sort([1, 2], fn(a, b) { a < b })",
+            "ERROR: result of the `sort` comparator must be an INTEGER, got BOOLEAN",
+        ),
+        (
+            "sort([1, 2], fn(a, b) { a + x })",
+            "ERROR: identifier not found: x",
+        ),
+        ("sort()", "ERROR: wrong number of arguments: want=2, got=0"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn Secrets() {
+    let tests = vec![
+        (r#"secret("hunter2")"#, "<secret>"),
+        (r#"secret(secret(1))"#, "<secret>"),
+        (r#""pw: ${secret("hunter2")}""#, "pw: <secret>"),
+        (r#"[secret("hunter2")]"#, "[<secret>]"),
+        (r#"secure_compare("abc", "abc")"#, "true"),
+        (r#"secure_compare("abc", "abd")"#, "false"),
+        (r#"secure_compare("abc", "ab")"#, "false"),
+        (r#"secure_compare("", "")"#, "true"),
+        (r#"secure_compare(secret("abc"), "abc")"#, "true"),
+        (r#"secure_compare("abc", secret("abc"))"#, "true"),
+        (r#"secure_compare(secret("abc"), secret("xyz"))"#, "false"),
+        (r#"let s = secret("abc"); s == s"#, "true"),
+        (r#"secret("abc") == secret("abc")"#, "false"),
+        (
+            r#"secure_compare(secret(1), "1")"#,
+            "ERROR: argument to `secure_compare` not supported, got SECRET",
+        ),
+        (
+            r#"secure_compare("a")"#,
+            "ERROR: wrong number of arguments: want=2, got=1",
+        ),
+        (r#"-secret(5)"#, "ERROR: unknown operator: -SECRET"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    let secret = testEval(r#"secret("hunter2")"#).unwrap();
+    assert!(!format!("{:?}", secret).contains("hunter2"));
+
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(Rc::new(crate::platform::MemoryPlatform::New()));
+    let logged = Rc::new(RefCell::new(String::new()));
+    let sink = Rc::clone(&logged);
+    runtime.SetAuditSink(move |entry| *sink.borrow_mut() += &format!("{:?}", entry.args));
+    let env = Environment::WithRuntime(runtime);
+    let input = r#"puts(secret("hunter2"))"#;
+    Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+    assert!(!logged.borrow().is_empty());
+    assert!(!logged.borrow().contains("hunter2"));
+}
+
+#[test]
+fn RecordAndReplayHostCalls() {
+    use crate::object::Builtin;
+    use crate::object::Integer;
+    use std::cell::Cell;
+
+    let input = "let a = now(); let b = now(); b - a + len(\"xy\")";
+    let run = |runtime: &Rc<Runtime>, clock: Rc<Cell<i64>>| {
+        let env = Environment::WithRuntime(Rc::clone(runtime));
+        env.borrow_mut().Set(
+            "now",
+            Builtin::new("now", move |_, _| {
+                clock.set(clock.get() + 10);
+                Integer { value: clock.get() }.into()
+            })
+            .into(),
+        );
+        let mut p = Parser::New(Lexer::New(input));
+        Eval(p.ParseProgram().into(), &env).unwrap()
+    };
+
+    let runtime = Rc::new(Runtime::New());
+    runtime.Record();
+    testIntegerObject(run(&runtime, Rc::new(Cell::new(0))), 12);
+    let calls = runtime.TakeCalls();
+    assert_eq!(
+        calls.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+        vec!["now", "now", "len"]
+    );
+
+    // the replay never reaches the host's clock
+    let clock = Rc::new(Cell::new(1000));
+    runtime.Replay(calls.clone());
+    testIntegerObject(run(&runtime, Rc::clone(&clock)), 12);
+    assert_eq!(clock.get(), 1000);
+    assert!(runtime.TakeCalls().is_empty());
+
+    runtime.Replay(calls[..2].to_vec());
+    testErrorObject(
+        run(&runtime, clock),
+        ErrorKind::ReplayError,
+        "replay diverged: expected no more calls, got len(xy)",
+    );
+}
+
+#[test]
+fn ReplayRunsTheFunctionsGivenToBuiltins() {
+    use crate::object::Builtin;
+    use crate::object::Integer;
+    use std::cell::Cell;
+
+    let input = "let n = 0; \
+                 let xs = map([1, 2], fn(x) { n++; x * now() }); \
+                 let ys = sort(filter(xs, fn(x) { x > 0 }), fn(a, b) { n++; b - a }); \
+                 [n, ys]";
+    let run = |runtime: &Rc<Runtime>, clock: Rc<Cell<i64>>| {
+        let env = Environment::WithRuntime(Rc::clone(runtime));
+        env.borrow_mut().Set(
+            "now",
+            Builtin::new("now", move |_, _| {
+                clock.set(clock.get() + 10);
+                Integer { value: clock.get() }.into()
+            })
+            .into(),
+        );
+        let mut p = Parser::New(Lexer::New(input));
+        Eval(p.ParseProgram().into(), &env).unwrap()
+    };
+
+    let runtime = Rc::new(Runtime::New());
+    runtime.Record();
+    let recorded = run(&runtime, Rc::new(Cell::new(0)));
+    assert_eq!(recorded.Inspect(), "[3, [40, 10]]");
+    let calls = runtime.TakeCalls();
+    assert_eq!(
+        calls.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+        vec!["map", "now", "now", "filter", "sort"]
+    );
+
+    // the functions run again, with what they call served from the recording
+    let clock = Rc::new(Cell::new(1000));
+    runtime.Replay(calls);
+    assert_eq!(run(&runtime, Rc::clone(&clock)), recorded);
+    assert_eq!(clock.get(), 1000);
+    assert!(runtime.TakeCalls().is_empty());
+}
+
+#[test]
+fn ArrayLiterals() {
+    let result = testEval("[1, 2 * 2, 3 + 3]").unwrap();
+    assert_eq!(result.Inspect(), "[1, 4, 6]");
+    testIntegerObject(testEval("len([1, [2, 3], 4])").unwrap(), 3);
+    testBooleanObject(testEval("[1, [2]] == [1, [2]]").unwrap(), true);
+}
+
+#[test]
+fn IndexExpressions() {
+    let tests = vec![
+        ("[1, 2, 3][0]", Some(1)),
+        ("[1, 2, 3][1 + 1]", Some(3)),
+        ("let i = 0; [1][i]", Some(1)),
+        (
+            "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2]",
+            Some(6),
+        ),
+        ("[1, 2, 3][3]", None),
+        ("[1, 2, 3][-1]", Some(3)),
+        ("[1, 2, 3][-3]", Some(1)),
+        ("[1, 2, 3][-4]", None),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input).unwrap();
+        match expected {
+            Some(value) => testIntegerObject(evaluated, value),
+            None => testNullObject(evaluated),
+        }
+    }
+
+    testStringObject(testEval(r#""héllo"[1]"#).unwrap(), "é");
+    testNullObject(testEval(r#""abc"[3]"#).unwrap());
+    testStringObject(testEval(r#""héllo"[-4]"#).unwrap(), "é");
+    testNullObject(testEval(r#""abc"[-4]"#).unwrap());
+    testErrorObject(
+        testEval(r#"[1]["0"]"#).unwrap(),
+        ErrorKind::TypeError,
+        "index must be an INTEGER, got STRING",
+    );
+    testErrorObject(
+        testEval("1[0]").unwrap(),
+        ErrorKind::TypeError,
+        "index operator not supported: INTEGER",
+    );
+}
+
+#[test]
+fn SliceExpressions() {
+    let tests = vec![
+        ("[1, 2, 3, 4][1:3]", "[2, 3]"),
+        ("[1, 2, 3, 4][2:]", "[3, 4]"),
+        ("[1, 2, 3, 4][:2]", "[1, 2]"),
+        ("[1, 2, 3, 4][:]", "[1, 2, 3, 4]"),
+        ("[1, 2, 3, 4][1:10]", "[2, 3, 4]"),
+        ("[1, 2, 3, 4][-5:2]", "[1, 2]"),
+        ("[1, 2, 3, 4][3:1]", "[]"),
+        ("[1, 2, 3, 4][7:]", "[]"),
+        ("[1, 2, 3, 4][-2:]", "[3, 4]"),
+        ("[1, 2, 3, 4][:-1]", "[1, 2, 3]"),
+        ("[1, 2, 3, 4][-3:-1]", "[2, 3]"),
+        ("[1, 2, 3, 4][-1:-3]", "[]"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    testStringObject(testEval(r#""héllo"[1:3]"#).unwrap(), "él");
+    testStringObject(testEval(r#""hello"[2:]"#).unwrap(), "llo");
+    testStringObject(testEval(r#""hello"[4:99]"#).unwrap(), "o");
+    testStringObject(testEval(r#""hello"[3:2]"#).unwrap(), "");
+    testStringObject(testEval(r#""héllo"[-4:-2]"#).unwrap(), "él");
+    testErrorObject(
+        testEval("[1][true:]").unwrap(),
+        ErrorKind::TypeError,
+        "index must be an INTEGER, got BOOLEAN",
+    );
+    testErrorObject(
+        testEval("[1][:x]").unwrap(),
+        ErrorKind::NameError,
+        "identifier not found: x",
+    );
+}
+
+#[test]
+fn SpreadExpressions() {
+    let tests = vec![
+        ("let xs = [1, 2]; [...xs, 3]", "[1, 2, 3]"),
+        ("[...[], ...[1], 2, ...[3, [4]]]", "[1, 2, 3, [4]]"),
+        (
+            "let add = fn(a, b, c) { a + b + c }; add(...[1, 2], 3)",
+            "6",
+        ),
+        (
+            "let add = fn(a, b) { a + b }; let args = [2, 3]; add(...args)",
+            "5",
+        ),
+        ("len(...[\"four\"])", "4"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    testErrorObject(
+        testEval("[...1]").unwrap(),
+        ErrorKind::TypeError,
+        "cannot spread INTEGER, only an ARRAY",
+    );
+    testErrorObject(
+        testEval("let f = fn(a) { a }; f(...[1, 2])").unwrap(),
+        ErrorKind::ArgumentError,
+        "wrong number of arguments: want=1, got=2",
+    );
+}
+
+#[test]
+fn HashLiterals() {
+    let input = r#"
+        let two = "two";
+        {"one": 10 - 9, two: 1 + 1, "thr" + "ee": 6 / 2, 4: 4, true: 5, false: 6}
+    "#;
+    assert_eq!(
+        testEval(input).unwrap().Inspect(),
+        "{4: 4, one: 1, three: 3, two: 2, false: 6, true: 5}"
+    );
+    testIntegerObject(testEval(r#"len({"a": 1, "a": 2})"#).unwrap(), 1);
+    testBooleanObject(testEval(r#"{"a": [1]} == {"a": [1]}"#).unwrap(), true);
+    testErrorObject(
+        testEval("{fn(x) { x }: 1}").unwrap(),
+        ErrorKind::TypeError,
+        "unusable as hash key: FUNCTION",
+    );
+}
+
+#[test]
+fn HashIndexExpressions() {
+    let tests = vec![
+        (r#"{"foo": 5}["foo"]"#, Some(5)),
+        (r#"{"foo": 5}["bar"]"#, None),
+        (r#"let key = "foo"; {"foo": 5}[key]"#, Some(5)),
+        (r#"{}["foo"]"#, None),
+        ("{5: 5}[5]", Some(5)),
+        ("{true: 5}[true]", Some(5)),
+        ("{false: 5}[false]", Some(5)),
+    ];
+    for (input, expected) in tests {
+        let evaluated = testEval(input).unwrap();
+        match expected {
+            Some(value) => testIntegerObject(evaluated, value),
+            None => testNullObject(evaluated),
+        }
+    }
+    testErrorObject(
+        testEval(r#"{"name": "Monkey"}[fn(x) { x }]"#).unwrap(),
+        ErrorKind::TypeError,
+        "unusable as hash key: FUNCTION",
+    );
+}
+
+#[test]
+fn DestructuringLet() {
+    let tests = vec![
+        ("let [a, b] = [1, 2]; a * 10 + b", 12),
+        ("let pair = [3, [4]]; let [a, b] = pair; a + b[0]", 7),
+        (r#"let {x, y} = {"x": 1, "y": 2, "z": 3}; x * 10 + y"#, 12),
+        (r#"let point = fn() { {"x": 5} }; let {x} = point(); x"#, 5),
+        (
+            "let swap = fn(p) { let [a, b] = p; [b, a] }; swap([1, 2])[0]",
+            2,
+        ),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(testEval(input).unwrap(), expected);
+    }
+
+    let tests = vec![
+        (
+            "let [a, b] = [1];",
+            "cannot destructure an ARRAY of 1 elements into 2 names",
+        ),
+        (
+            "let [a] = [1, 2];",
+            "cannot destructure an ARRAY of 2 elements into 1 names",
+        ),
+        (
+            r#"let {x, y} = {"x": 1};"#,
+            r#"cannot destructure HASH without key "y""#,
+        ),
+        (
+            "let {x} = {1: 1};",
+            r#"cannot destructure HASH without key "x""#,
+        ),
+        ("let [a] = 1;", "cannot destructure INTEGER as ARRAY"),
+        ("let {a} = [1];", "cannot destructure ARRAY as HASH"),
+    ];
+    for (input, expected) in tests {
+        testErrorObject(
+            testEval(input).unwrap(),
+            ErrorKind::DestructureError,
+            expected,
+        );
+    }
+    testErrorObject(
+        testEval("let [a] = [b];").unwrap(),
+        ErrorKind::NameError,
+        "identifier not found: b",
+    );
+}
+
+#[test]
+fn KeywordArguments() {
+    let define = "let box = fn(width, height, depth) { width * 100 + height * 10 + depth };";
+    let tests = vec![
+        ("box(1, 2, 3)", 123),
+        ("box(width: 1, height: 2, depth: 3)", 123),
+        ("box(depth: 3, width: 1, height: 2)", 123),
+        ("box(1, depth: 3, height: 2)", 123),
+        ("box(...[1, 2], depth: 3)", 123),
+    ];
+    for (input, expected) in tests {
+        testIntegerObject(
+            testEval(&format!("{} {}", define, input)).unwrap(),
+            expected,
+        );
+    }
+
+    let tests = vec![
+        ("box(1, 2, width: 3)", "argument given twice: width"),
+        ("box(1, 2, size: 3)", "unexpected keyword argument: size"),
+        ("box(1, depth: 3)", "missing argument: height"),
+        (
+            "box(1, 2, 3, 4, depth: 5)",
+            "wrong number of arguments: want=3, got=5",
+        ),
+        (
+            r#"len(x: "a")"#,
+            "builtin len doesn't take keyword arguments",
+        ),
+    ];
+    for (input, expected) in tests {
+        testErrorObject(
+            testEval(&format!("{} {}", define, input)).unwrap(),
+            ErrorKind::ArgumentError,
+            expected,
+        );
+    }
+    testErrorObject(
+        testEval("let f = fn(a) { a }; f(a: b)").unwrap(),
+        ErrorKind::NameError,
+        "identifier not found: b",
+    );
+
+    // arguments are evaluated in the order they are written
+    let input = "let c = 0; let next = fn() { c++; c }; box(next(), depth: next(), height: next())";
+    testIntegerObject(testEval(&format!("{} {}", define, input)).unwrap(), 132);
+}
+
+#[test]
+fn PositionalArgumentsAfterKeywords() {
+    use crate::ast::CallExpression;
+    use crate::ast::ExpressionEnum;
+    use crate::ast::ExpressionStatement;
+    use crate::ast::StatementEnum;
+
+    // the parser doesn't allow them, but a tree made some other way can have
+    // them
+    let mut program =
+        Parser::New(Lexer::New("let f = fn(a, b) { a }; f(a: 1, b: 2)")).ParseProgram();
+    let call = match program.statements.pop() {
+        Some(StatementEnum::Expression(ExpressionStatement {
+            expression: ExpressionEnum::CallExpression(call),
+            ..
+        })) => call,
+        other => panic!("not a call: {:?}", other),
+    };
+    let mut arguments = call.arguments;
+    match arguments.pop() {
+        Some(ExpressionEnum::KeywordArgument(k)) => arguments.push(*k.value),
+        other => panic!("not a keyword argument: {:?}", other),
+    }
+    let call: ExpressionEnum = CallExpression { arguments, ..call }.into();
+    program.statements.push(call.into());
+
+    let env = Environment::New();
+    testErrorObject(
+        Eval(program.into(), &env).unwrap(),
+        ErrorKind::ArgumentError,
+        "positional argument after keyword arguments: 2",
+    );
+}
+
+#[test]
+fn StrictTruthiness() {
+    let tests = vec![
+        ("if (1 < 2) { 10 }", None),
+        ("!true == false", None),
+        (
+            "if (1) { 10 }",
+            Some("condition must be a BOOLEAN, got INTEGER"),
+        ),
+        (
+            "if (null) { 10 }",
+            Some("condition must be a BOOLEAN, got NULL"),
+        ),
+        ("!!0", Some("operand of ! must be a BOOLEAN, got INTEGER")),
+    ];
+    for (input, expected) in tests {
+        let runtime = Rc::new(Runtime::New());
+        let env = Environment::WithRuntime(Rc::clone(&runtime));
+        let eval = || Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+        assert!(!isError(&eval()), "{}", input);
+
+        runtime.SetEdition(Edition::V2);
+        match expected {
+            None => assert!(!isError(&eval()), "{}", input),
+            Some(message) => testErrorObject(eval(), ErrorKind::TypeError, message),
+        }
+    }
+}
+
+#[test]
+fn TruthinessWarnings() {
+    let runtime = Rc::new(Runtime::New());
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let input = "let f = fn(x) { if (x) { 1 } else { 2 } }; f(true); f(0); f(1); !f";
+    Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env);
+    let warnings = runtime.Warnings().Take();
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| w.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "condition is INTEGER, not a BOOLEAN; edition 2 requires one",
+            "operand of ! is FUNCTION, not a BOOLEAN; edition 2 requires one",
+        ]
+    );
+    assert!(warnings.iter().all(|w| w.code == WarningCode::Truthiness));
+
+    runtime.Warnings().Suppress(WarningCode::Truthiness);
+    Eval(Parser::New(Lexer::New("!0")).ParseProgram().into(), &env);
+    assert!(runtime.Warnings().Get().is_empty());
+}
+
+#[test]
+fn TryCatch() {
+    let tests = vec![
+        ("try { 1 } catch (e) { 2 }", "1"),
+        ("try { throw 5; 1 } catch (e) { e + 1 }", "6"),
+        (
+            "let f = fn(x) { if (x > 2) { throw [x] } x }; try { f(1) + f(3) } catch (e) { e }",
+            "[3]",
+        ),
+        (
+            r#"try { 1 + true } catch (e) { e["message"] }"#,
+            "type mismatch: INTEGER + BOOLEAN",
+        ),
+        (r#"try { len(1) } catch (e) { e["kind"] }"#, "TypeError"),
+        (r#"try { x } catch (e) { e["name"] }"#, "x"),
+        (
+            "try { try { throw 1 } catch (e) { throw e + 1 } } catch (e) { e }",
+            "2",
+        ),
+        (
+            "let f = fn() { defer 1 + true; throw 1 }; try { f() } catch (e) { e }",
+            "1",
+        ),
+        (
+            "let f = fn() { try { return 1 } catch (e) { 2 }; 3 }; f()",
+            "1",
+        ),
+        ("throw 5", "ERROR: uncaught exception: 5"),
+        (
+            r#"let f = fn() { throw "x" }; f(); 1"#,
+            "ERROR: uncaught exception: x",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
+    }
+
+    // running out of fuel can't be caught
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(50),
+        time: None,
+        depth: None,
+    }));
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let input = "let loop = fn() { loop() }; try { loop() } catch (e) { 1 }";
+    testErrorObject(
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap(),
+        ErrorKind::LimitExceeded,
+        "evaluation exceeded 50 steps",
+    );
+}
+
+#[test]
+fn Imports() {
+    let dir = std::env::temp_dir().join(format!("monkey-imports-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let files = [
+        ("math.mk", "let square = fn(x) { x * x }; let loaded = 1;"),
+        (
+            "uses.mk",
+            r#"let math = import "math.mk"; let cube = fn(x) { x * math["square"](x) };"#,
+        ),
+        ("a.mk", r#"let b = import "b.mk";"#),
+        ("b.mk", r#"let a = import "a.mk";"#),
+        ("broken.mk", "let = 1;"),
+        ("throws.mk", "throw 7;"),
+    ];
+    for (name, source) in files {
+        std::fs::write(dir.join(name), source).unwrap();
+    }
+
+    let tests =
+        vec![
+        (r#"let m = import "math.mk"; m["square"](4)"#, "16"),
+        (r#"import "uses.mk"["cube"](3)"#, "27"),
+        (r#"import "math.mk""#, "module math.mk"),
+        (
+            r#"let m = import "math.mk"; m["cube"]"#,
+            "ERROR: module math.mk has no member cube",
+        ),
+        (r#"import "math.mk"[0]"#, "ERROR: module member must be a STRING, got INTEGER"),
+        (r#"import "nope.mk""#, "ERROR: module not found: nope.mk"),
+        (r#"import 5"#, "ERROR: import path must be a STRING, got INTEGER"),
+        (
+            r#"import "a.mk""#,
+            "ERROR: import cycle: a.mk -> b.mk -> a.mk",
+        ),
+        (
+            r#"import "broken.mk""#,
+            "ERROR: could not parse broken.mk: expected next token to be IDENT, got ASSIGN instead",
+        ),
+        (r#"import "throws.mk""#, "ERROR: uncaught exception: 7"),
+        (
+            r#"try { import "nope.mk" } catch (e) { e["kind"] }"#,
+            "ImportError",
+        ),
+    ];
+    for (input, expected) in tests {
+        let runtime = Rc::new(Runtime::New());
+        runtime.Loader().SetSearchPath(vec![dir.clone()]);
+        let env = Environment::WithRuntime(Rc::clone(&runtime));
+        let evaluated = Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+        assert_eq!(evaluated.Inspect(), expected, "{}", input);
+    }
+
+    // a module is only evaluated once, however often it is imported
+    let runtime = Rc::new(Runtime::New());
+    runtime.Loader().SetSearchPath(vec![dir.clone()]);
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let input = r#"let a = import "math.mk"; let b = import "math.mk"; a == b"#;
+    let evaluated = Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+    testBooleanObject(evaluated, true);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ImportsOutsideTheSearchPath() {
+    use crate::capability::Capability;
+    use crate::platform::MemoryPlatform;
+
+    let platform = Rc::new(MemoryPlatform::New());
+    platform.AddFile("lib/m.mk", "let x = 1;");
+    platform.AddFile("secret.mk", "let x = 2;");
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform);
+    runtime.Loader().SetSearchPath(vec!["/lib".into()]);
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let eval =
+        |input: &str| Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+
+    testIntegerObject(eval(r#"import "./m.mk"["x"]"#), 1);
+    for input in [r#"import "../secret.mk""#, r#"import "/secret.mk""#] {
+        testErrorObject(
+            eval(input),
+            ErrorKind::PermissionDenied,
+            "`import` needs the fs capability, which is denied",
+        );
+    }
+    runtime.Allow(Capability::Fs);
+    testIntegerObject(eval(r#"import "../secret.mk"["x"]"#), 2);
+    testIntegerObject(eval(r#"import "/secret.mk"["x"]"#), 2);
+}
+
+#[test]
+fn RunsOnMemoryPlatform() {
+    use crate::object::Builtin;
+    use crate::platform::MemoryPlatform;
+
+    let platform = Rc::new(MemoryPlatform::New());
+    platform.AddFile(
+        "lib/greet.mk",
+        r#"let hello = fn(name) { "hello " + name };"#,
+    );
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: None,
+        time: Some(Duration::from_secs(5)),
+        depth: None,
+    }));
+    runtime.SetPlatform(platform.clone());
+    runtime.Loader().SetSearchPath(vec!["/lib".into()]);
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let clock = Rc::clone(&platform);
+    env.borrow_mut().Set(
+        "sleep",
+        Builtin::new("sleep", move |_, _| {
+            clock.Advance(Duration::from_secs(3));
+            NULL
+        })
+        .into(),
+    );
+
+    let input = r#"let greet = import "greet.mk"; puts(greet["hello"]("world"), 1);"#;
+    let evaluated = Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+    assert_eq!(evaluated, NULL);
+    assert_eq!(platform.TakeOutput(), "hello world\n1\n");
+
+    // time only passes when the platform's clock is advanced
+    let input = "sleep(); puts(1); sleep(); puts(2);";
+    let evaluated = Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+    testErrorObject(
+        evaluated,
+        ErrorKind::LimitExceeded,
+        "evaluation exceeded 5s",
+    );
+    assert_eq!(platform.Output(), "1\n");
+}
+
+#[test]
+fn AuditsCapabilityUse() {
+    use crate::capability::Capability;
+    use crate::object::Str;
+    use crate::platform::MemoryPlatform;
+    use std::time::SystemTime;
+
+    let platform = Rc::new(MemoryPlatform::New());
+    platform.AddFile("m.mk", "let x = 1;");
+    platform.Advance(Duration::from_secs(60));
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    let entries = Rc::new(RefCell::new(vec![]));
+    let sink = Rc::clone(&entries);
+    runtime.SetAuditSink(move |entry| sink.borrow_mut().push(entry.clone()));
+
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let input = r#"puts("hi", 2); len("not audited"); import "m.mk"; import "m.mk";"#;
+    Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+
+    let summary: Vec<_> = entries
+        .borrow()
+        .iter()
+        .map(|e| {
+            let call = (e.call.line, e.call.column);
+            (e.capability, e.name.clone(), e.args.len(), call)
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Capability::Io, "puts".to_string(), 2, (1, 1)),
+            (Capability::Io, "import".to_string(), 1, (1, 36)),
+            (Capability::Io, "import".to_string(), 1, (1, 51)),
+        ]
+    );
+    assert_eq!(
+        entries.borrow()[1].args,
+        vec![Str {
+            value: "m.mk".into()
+        }
+        .into()]
+    );
+    assert_eq!(
+        entries.borrow()[0].time,
+        SystemTime::UNIX_EPOCH + Duration::from_secs(60)
+    );
+
+    // a builtin called by another is where that one is called
+    let input = "let xs = [1];\nfn() { map(xs, puts) }()";
+    Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap();
+    let call = entries.borrow()[3].call;
+    assert_eq!((call.line, call.column), (2, 8));
+
+    // a replay doesn't reach the host, so there's nothing to audit
+    runtime.Record();
+    Eval(
+        Parser::New(Lexer::New("puts(1)")).ParseProgram().into(),
+        &env,
+    )
+    .unwrap();
+    runtime.Replay(runtime.TakeCalls());
+    Eval(
+        Parser::New(Lexer::New("puts(1)")).ParseProgram().into(),
+        &env,
+    )
+    .unwrap();
+    assert_eq!(entries.borrow().len(), 5);
+}
+
+#[test]
+fn Quotas() {
+    use crate::platform::MemoryPlatform;
+    use crate::platform::Platform;
+    use crate::runtime::Quotas;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    /// Takes two seconds to write anything.
+    struct SlowOutput(MemoryPlatform);
+
+    impl Platform for SlowOutput {
+        fn Now(&self) -> Duration {
+            self.0.Now()
+        }
+        fn SystemTime(&self) -> std::time::SystemTime {
+            self.0.SystemTime()
+        }
+        fn Sleep(&self, time: Duration) {
+            self.0.Sleep(time)
+        }
+        fn Random(&self) -> u64 {
+            self.0.Random()
+        }
+        fn Write(&self, text: &str) {
+            self.0.Advance(Duration::from_secs(2));
+            self.0.Write(text)
+        }
+        fn ReadLine(&self) -> Option<String> {
+            self.0.ReadLine()
+        }
+        fn ReadFile(&self, path: &Path) -> std::io::Result<String> {
+            self.0.ReadFile(path)
+        }
+        fn WriteFile(&self, path: &Path, contents: &str) -> std::io::Result<()> {
+            self.0.WriteFile(path, contents)
+        }
+        fn AppendFile(&self, path: &Path, contents: &str) -> std::io::Result<()> {
+            self.0.AppendFile(path, contents)
+        }
+        fn ReadDir(&self, path: &Path) -> std::io::Result<Vec<String>> {
+            self.0.ReadDir(path)
+        }
+        fn IsFile(&self, path: &Path) -> bool {
+            self.0.IsFile(path)
+        }
+        fn Canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+            self.0.Canonicalize(path)
+        }
+        fn Var(&self, name: &str) -> Option<String> {
+            self.0.Var(name)
+        }
+        fn SetVar(&self, name: &str, value: &str) {
+            self.0.SetVar(name, value)
+        }
+    }
+
+    let platform = Rc::new(SlowOutput(MemoryPlatform::New()));
+    platform
+        .0
+        .AddFile("slow.mk", "let a = 1; puts(a); let b = 2;");
+    platform.0.AddFile("fast.mk", "let a = 1;");
+    let runtime = Rc::new(Runtime::New());
+    runtime.SetPlatform(platform.clone());
+    runtime.SetQuotas(Quotas {
+        calls: maplit::hashmap! { "puts".to_string() => 2 },
+        moduleTime: maplit::hashmap! { "slow.mk".to_string() => Duration::from_secs(1) },
+    });
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let run = |input: &str| {
+        runtime.Reset();
+        Eval(Parser::New(Lexer::New(input)).ParseProgram().into(), &env).unwrap()
+    };
+
+    testNullObject(run("puts(1); puts(2);"));
+    testErrorObject(
+        run("puts(1); puts(2); try { puts(3) } catch (e) { 0 }"),
+        ErrorKind::LimitExceeded,
+        "quota exceeded: more than 2 calls to puts",
+    );
+    assert_eq!(platform.0.TakeOutput(), "1\n2\n1\n2\n");
+
+    testErrorObject(
+        run(r#"import "slow.mk""#),
+        ErrorKind::LimitExceeded,
+        "quota exceeded: module slow.mk ran for more than 1s",
+    );
+    // the time spent outside the module doesn't count against it
+    assert_eq!(
+        run(r#"puts(0); import "fast.mk""#).Inspect(),
+        "module fast.mk"
+    );
+}
+
+#[test]
+fn StackTraces() {
+    let input = "let inner = fn(x) { x + true };
+let outer = fn(x) { inner(x) + 1 };
+outer(1)";
+    assert_eq!(
+        testEval(input).unwrap().Inspect(),
+        "ERROR: type mismatch: INTEGER + BOOLEAN
+  in inner, called at 2:21
+  in outer, called at 3:1"
+    );
+
+    // a call in tail position replaces its caller, so only the last of a
+    // chain of them is there
+    let input = "let inner = fn(x) { x + true };
+let outer = fn(x) { inner(x) };
+[outer][0](1)";
+    assert_eq!(
+        testEval(input).unwrap().Inspect(),
+        "ERROR: type mismatch: INTEGER + BOOLEAN
+  in inner, called at 2:21
+  in fn at 2:19, called at 3:8"
+    );
+
+    let input = "let g = fn(x) { x + true };
+let h = fn(x) { g(x) };
+let f = fn(x) { h(x) };
+f(1)";
+    assert_eq!(
+        testEval(input).unwrap().Inspect(),
+        "ERROR: type mismatch: INTEGER + BOOLEAN
+  in g, called at 2:17
+  in f, called at 4:1"
+    );
+
+    let input = "let f = fn(n) { if (n == 0) { 1 + true } else { 1 + f(n - 1) } }; f(20)";
+    let evaluated = testEval(input).unwrap();
+    match &evaluated {
+        ObjectEnum::Error(err) => {
+            let trace = err.trace.as_ref().unwrap();
+            assert_eq!((trace.frames.len(), trace.elided), (16, 5));
+        }
+        other => panic!("not an error: {}", other.Inspect()),
+    }
+    assert!(evaluated.Inspect().ends_with("\n  ... and 5 more calls"));
+
+    assert_eq!(
+        testEval("let f = fn() { exit(3) }; f()").unwrap().Inspect(),
+        "ERROR: exited with status 3"
+    );
+}
+
+#[test]
+fn RawStrings() {
+    let tests = vec![
+        (r#"r"C:\temp\new""#, r"C:\temp\new"),
+        (r#"let x = 1; r"${x}\n""#, r"${x}\n"),
+        (r#"r"" + "${1}""#, "1"),
+        (r#"match (r"\d") { r"\d" => "raw", _ => "other" }"#, "raw"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(testEval(input).unwrap().Inspect(), expected, "{}", input);
     }
 }
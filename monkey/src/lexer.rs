@@ -1,30 +1,108 @@
+use std::fmt::Display;
+use std::io;
+use std::io::BufRead;
 use std::iter::Peekable;
+use std::ops::Range;
 use std::str::CharIndices;
 
 use crate::token::LookupIdent;
+use crate::token::Position;
 use crate::token::Token;
 use crate::token::TokenKind;
 
+/// A character no token starts with, or a string the source ends in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    /// The byte range of the character.
+    pub span: Range<usize>,
+    pub position: Position,
+    pub found: String,
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if IsUnterminatedString(&self.found) {
+            return write!(f, "unterminated string at {}", self.position);
+        }
+        write!(
+            f,
+            "unexpected character `{}` at {}",
+            self.found, self.position
+        )
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// All the tokens of `input`, the last of them EOF, or the first character
+/// that doesn't start a token.
+pub fn Tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    Lexer::New(input)
+        .map(|token| match token.kind {
+            TokenKind::ILLEGAL => Err(LexError {
+                span: token.Span(),
+                position: token.position,
+                found: token.literal,
+            }),
+            _ => Ok(token),
+        })
+        .collect()
+}
+
 pub struct Lexer<'src> {
     input: &'src str,
     input_chars: Peekable<CharIndices<'src>>,
     ch: char,
     pos: usize,
+    /// Whether EOF has been returned by `next`.
+    done: bool,
+    /// The line and column of `ch`.
+    line: usize,
+    column: usize,
+    /// The offset of `input` in the whole source.
+    base: usize,
+    /// The kind of the token returned last, which tells `--` apart from two
+    /// minuses.
+    previous: Option<TokenKind>,
 }
 
 impl<'src> Lexer<'src> {
     pub fn New(input: &'src str) -> Self {
+        Self::At(
+            input,
+            Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+        )
+    }
+
+    /// A lexer for `input` as part of a larger source, where it starts at
+    /// `start`, so its tokens have positions in that source.
+    pub(crate) fn At(input: &'src str, start: Position) -> Self {
         let mut lexer = Self {
             input_chars: input.char_indices().peekable(),
             input,
             ch: '\0',
             pos: 0,
+            done: false,
+            line: start.line,
+            column: start.column.saturating_sub(1),
+            base: start.offset,
+            previous: None,
         };
         lexer.readChar();
         lexer
     }
 
     fn readChar(&mut self) {
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         let (pos, ch) = self.input_chars.next().unwrap_or((self.input.len(), '\0'));
         self.pos = pos;
         self.ch = ch;
@@ -47,6 +125,20 @@ impl<'src> Lexer<'src> {
 
     pub fn NextToken(&mut self) -> Token {
         self.skipWhitespace();
+        let position = Position {
+            line: self.line,
+            column: self.column,
+            offset: self.base + self.pos,
+        };
+        let token = Token {
+            position,
+            ..self.readToken()
+        };
+        self.previous = Some(token.kind);
+        token
+    }
+
+    fn readToken(&mut self) -> Token {
         let tok = match self.ch {
             ';' => self.singleCharToken(TokenKind::SEMICOLON),
             '(' => self.singleCharToken(TokenKind::LPAREN),
@@ -54,10 +146,34 @@ impl<'src> Lexer<'src> {
             '{' => self.singleCharToken(TokenKind::LBRACE),
             '}' => self.singleCharToken(TokenKind::RBRACE),
             ',' => self.singleCharToken(TokenKind::COMMA),
-            '+' => self.singleCharToken(TokenKind::PLUS),
-            '-' => self.singleCharToken(TokenKind::MINUS),
+            ':' => self.singleCharToken(TokenKind::COLON),
+            '|' => self.singleCharToken(TokenKind::PIPE),
+            '[' => self.singleCharToken(TokenKind::LBRACKET),
+            ']' => self.singleCharToken(TokenKind::RBRACKET),
+            '.' if self.input[self.pos..].starts_with("...") => {
+                self.readChar();
+                self.readChar();
+                Token::New(TokenKind::ELLIPSIS, "...")
+            }
+            '+' => {
+                if self.peekChar() == '+' {
+                    self.readChar();
+                    self.twoCharToken(TokenKind::INCREMENT)
+                } else {
+                    self.singleCharToken(TokenKind::PLUS)
+                }
+            }
+            '-' => {
+                if self.peekChar() == '-' && self.decrements() {
+                    self.readChar();
+                    self.twoCharToken(TokenKind::DECREMENT)
+                } else {
+                    self.singleCharToken(TokenKind::MINUS)
+                }
+            }
             '*' => self.singleCharToken(TokenKind::STAR),
             '/' => self.singleCharToken(TokenKind::SLASH),
+            '%' => self.singleCharToken(TokenKind::PERCENT),
             '<' => self.singleCharToken(TokenKind::LESS),
             '>' => self.singleCharToken(TokenKind::GREATER),
             '!' => {
@@ -72,23 +188,34 @@ impl<'src> Lexer<'src> {
                 if self.peekChar() == '=' {
                     self.readChar();
                     self.twoCharToken(TokenKind::EQ)
+                } else if self.peekChar() == '>' {
+                    self.readChar();
+                    self.twoCharToken(TokenKind::FAT_ARROW)
                 } else {
                     self.singleCharToken(TokenKind::ASSIGN)
                 }
             }
-            '\0' => Token {
-                kind: TokenKind::EOF,
-                literal: String::default(),
-            },
-            ch if ch.is_alphabetic() => {
+            '"' => {
+                let start = self.pos;
+                let contents = self.readString();
+                self.closeString(start, Token::New(TokenKind::STRING, contents))
+            }
+            'r' if self.input[self.pos..].starts_with("r\"") => {
+                let start = self.pos;
+                self.readChar();
+                let contents = self.readRawString();
+                self.closeString(start, Token::New(TokenKind::RAW_STRING, contents))
+            }
+            '\0' => Token::New(TokenKind::EOF, ""),
+            ch if isIdentStart(ch) => {
                 let literal = self.readIdentifier();
                 let kind = LookupIdent(&literal);
-                return Token { kind, literal };
+                return Token::New(kind, literal);
             }
-            ch if ch.is_digit(10) => {
+            ch if ch.is_ascii_digit() => {
                 let literal = self.readNumber();
                 let kind = TokenKind::INT;
-                return Token { kind, literal };
+                return Token::New(kind, literal);
             }
             _ => self.singleCharToken(TokenKind::ILLEGAL),
         };
@@ -97,12 +224,33 @@ impl<'src> Lexer<'src> {
         tok
     }
 
+    /// Whether the `--` at `ch` is a decrement rather than two minuses, as
+    /// it always was before there were decrements: it is one after an
+    /// identifier, and before one unless it follows some other operand. So
+    /// `x--` and `--x` decrement, and `5--3` and `5--x` subtract a negation.
+    fn decrements(&self) -> bool {
+        match self.previous {
+            Some(TokenKind::IDENT) => true,
+            Some(
+                TokenKind::INT
+                | TokenKind::STRING
+                | TokenKind::RAW_STRING
+                | TokenKind::TRUE
+                | TokenKind::FALSE
+                | TokenKind::NULL
+                | TokenKind::RPAREN
+                | TokenKind::RBRACKET,
+            ) => false,
+            _ => self.input[self.pos + 2..]
+                .chars()
+                .next()
+                .is_some_and(isIdentStart),
+        }
+    }
+
     fn singleCharToken(&self, kind: TokenKind) -> Token {
         if self.pos < self.input.len() {
-            Token {
-                kind,
-                literal: self.input[self.pos..self.pos + 1].to_string(),
-            }
+            Token::New(kind, &self.input[self.pos..self.pos + self.ch.len_utf8()])
         } else {
             unreachable!()
         }
@@ -110,10 +258,7 @@ impl<'src> Lexer<'src> {
 
     fn twoCharToken(&self, kind: TokenKind) -> Token {
         if self.pos < self.input.len() {
-            Token {
-                kind,
-                literal: self.input[self.pos - 1..self.pos + 1].to_string(),
-            }
+            Token::New(kind, &self.input[self.pos - 1..self.pos + 1])
         } else {
             unreachable!()
         }
@@ -121,20 +266,257 @@ impl<'src> Lexer<'src> {
 
     fn readIdentifier(&mut self) -> String {
         let pos = self.pos;
-        while self.ch.is_alphanumeric() {
+        while isIdentContinue(self.ch) {
+            self.readChar();
+        }
+        self.input[pos..self.pos].to_string()
+    }
+
+    /// Reads the raw contents of a string literal, leaving `self.ch` on the
+    /// closing quote. Escapes are left in place; interpolated `${...}` segments
+    /// are lexed as code so that braces and quotes inside them don't end the
+    /// string early.
+    fn readString(&mut self) -> String {
+        let pos = self.pos + 1;
+        self.readChar();
+        while self.ch != '"' && self.ch != '\0' {
+            let ch = self.ch;
+            match ch {
+                '\\' => {
+                    self.readChar();
+                    self.readChar();
+                }
+                '$' if self.peekChar() == '{' => {
+                    self.readChar();
+                    self.readChar();
+                    self.skipInterpolation();
+                }
+                _ => self.readChar(),
+            }
+        }
+        self.input[pos..self.pos].to_string()
+    }
+
+    /// The string token lexed from `start`, or if the input ended before its
+    /// closing quote, an ILLEGAL token with the rest of the input.
+    fn closeString(&self, start: usize, token: Token) -> Token {
+        match self.ch {
+            '"' => token,
+            _ => Token::New(TokenKind::ILLEGAL, &self.input[start..self.pos]),
+        }
+    }
+
+    /// Reads the contents of a raw string literal, which end at the next
+    /// quote, leaving `self.ch` on the closing quote.
+    fn readRawString(&mut self) -> String {
+        let pos = self.pos + 1;
+        self.readChar();
+        while self.ch != '"' && self.ch != '\0' {
             self.readChar();
         }
         self.input[pos..self.pos].to_string()
     }
 
+    /// Lexes tokens until the brace closing an interpolation is consumed.
+    /// Returns false if the input ran out first.
+    fn skipInterpolation(&mut self) -> bool {
+        let mut depth = 0usize;
+        loop {
+            match self.NextToken().kind {
+                TokenKind::LBRACE => depth += 1,
+                TokenKind::RBRACE if depth == 0 => return true,
+                TokenKind::RBRACE => depth -= 1,
+                TokenKind::EOF => return false,
+                _ => {}
+            }
+        }
+    }
+
     fn readNumber(&mut self) -> String {
         let pos = self.pos;
-        while self.ch.is_digit(10) {
+        while self.ch.is_ascii_digit() {
             self.readChar();
         }
         self.input[pos..self.pos].to_string()
     }
 }
 
+/// The tokens of the input, the last of them EOF.
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.done {
+            return None;
+        }
+        let token = self.NextToken();
+        self.done = token.kind == TokenKind::EOF;
+        Some(token)
+    }
+}
+
+/// A lexer that reads its source as it goes, a line at a time, keeping only
+/// what it hasn't lexed yet. It gives the same tokens as a `Lexer` over the
+/// whole source. If reading fails the tokens end there, as if the source did.
+pub struct StreamLexer<R> {
+    reader: R,
+    /// What has been read but not lexed.
+    buffer: String,
+    /// Where `buffer` starts in the source.
+    start: Position,
+    /// Whether there is nothing more to read.
+    read: bool,
+    error: Option<io::Error>,
+    /// Whether EOF has been returned by `next`.
+    done: bool,
+    previous: Option<TokenKind>,
+}
+
+impl<R: BufRead> StreamLexer<R> {
+    pub fn New(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            start: Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+            read: false,
+            error: None,
+            done: false,
+            previous: None,
+        }
+    }
+
+    /// Why reading the source failed, if it did.
+    pub fn Error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn NextToken(&mut self) -> Token {
+        loop {
+            let mut lexer = Lexer::At(&self.buffer, self.start);
+            lexer.previous = self.previous;
+            let token = lexer.NextToken();
+            let end = token.End();
+            let lexed = end.offset - self.start.offset;
+            // a token that ends before what has been read can't go on in what
+            // hasn't
+            if self.read || (token.kind != TokenKind::EOF && lexed < self.buffer.len()) {
+                self.buffer.drain(..lexed.min(self.buffer.len()));
+                self.start = end;
+                self.previous = Some(token.kind);
+                return token;
+            }
+            self.readLine();
+        }
+    }
+
+    fn readLine(&mut self) {
+        match self.reader.read_line(&mut self.buffer) {
+            Ok(0) => self.read = true,
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                self.error = Some(err);
+                self.read = true;
+            }
+        }
+    }
+}
+
+/// The tokens of the source, the last of them EOF.
+impl<R: BufRead> Iterator for StreamLexer<R> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.done {
+            return None;
+        }
+        let token = self.NextToken();
+        self.done = token.kind == TokenKind::EOF;
+        Some(token)
+    }
+}
+
+/// Whether an ILLEGAL token with `literal` is a string the input ended in,
+/// rather than a character no token starts with.
+pub(crate) fn IsUnterminatedString(literal: &str) -> bool {
+    literal.starts_with('"') || literal.starts_with("r\"")
+}
+
+fn isIdentStart(ch: char) -> bool {
+    ch == '_' || unicode_ident::is_xid_start(ch)
+}
+
+fn isIdentContinue(ch: char) -> bool {
+    unicode_ident::is_xid_continue(ch)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum StringSegment<'src> {
+    Text(String),
+    /// The source of an interpolation, which starts `offset` bytes into the
+    /// raw contents.
+    Code {
+        offset: usize,
+        source: &'src str,
+    },
+}
+
+/// Splits the raw contents of a string literal into text (with escapes
+/// processed) and the source of each `${...}` interpolation. The result always
+/// starts with a `Text` segment, which may be empty.
+pub(crate) fn StringSegments(raw: &str) -> Result<Vec<StringSegment<'_>>, String> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut rest = raw;
+
+    while let Some(ch) = rest.chars().next() {
+        rest = &rest[ch.len_utf8()..];
+        match ch {
+            '\\' => {
+                let escaped = rest.chars().next();
+                rest = &rest[escaped.map_or(0, char::len_utf8)..];
+                match escaped {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some(escaped @ ('"' | '\\' | '$')) => text.push(escaped),
+                    Some(other) => {
+                        text.push('\\');
+                        text.push(other);
+                    }
+                    None => text.push('\\'),
+                }
+            }
+            '$' if rest.starts_with('{') => {
+                rest = &rest[1..];
+                let mut lexer = Lexer::New(rest);
+                if !lexer.skipInterpolation() {
+                    return Err(format!("unterminated interpolation in \"{}\"", raw));
+                }
+                // the lexer stops one character past the closing brace
+                let end = lexer.pos - 1;
+                if !text.is_empty() || segments.is_empty() {
+                    segments.push(StringSegment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(StringSegment::Code {
+                    offset: raw.len() - rest.len(),
+                    source: &rest[..end],
+                });
+                rest = &rest[end + 1..];
+            }
+            _ => text.push(ch),
+        }
+    }
+
+    if !text.is_empty() || segments.is_empty() {
+        segments.push(StringSegment::Text(text));
+    }
+    Ok(segments)
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,8 +1,14 @@
+use std::io::BufReader;
+
 use crate::token::TokenKind;
 use rstest::fixture;
 use rstest::rstest;
 
 use super::Lexer;
+use super::StreamLexer;
+use super::StringSegment;
+use super::StringSegments;
+use super::Tokenize;
 
 struct NextTokenTests {
     lexer: Lexer<'static>,
@@ -12,7 +18,7 @@ struct NextTokenTests {
 
 #[fixture]
 fn nextTokenInput() -> NextTokenTests {
-    let input = "
+    let input = r#"
         let five = 5;
         let ten = 10;
         let add = fn(x, y) {
@@ -30,7 +36,9 @@ fn nextTokenInput() -> NextTokenTests {
 
         10 == 10;
         10 != 9;
-        ";
+        "foobar"
+        "foo bar"
+        "#;
 
     NextTokenTests {
         lexer: Lexer::New(input),
@@ -40,6 +48,7 @@ fn nextTokenInput() -> NextTokenTests {
             "add", "(", "five", ",", "ten", ")", ";", "!", "-", "/", "*", "5", ";", "5", "<", "10",
             ">", "5", ";", "if", "(", "5", "<", "10", ")", "{", "return", "true", ";", "}", "else",
             "{", "return", "false", ";", "}", "10", "==", "10", ";", "10", "!=", "9", ";",
+            "foobar", "foo bar",
         ],
         expectedKinds: vec![
             TokenKind::LET,
@@ -115,6 +124,8 @@ fn nextTokenInput() -> NextTokenTests {
             TokenKind::NOT_EQ,
             TokenKind::INT,
             TokenKind::SEMICOLON,
+            TokenKind::STRING,
+            TokenKind::STRING,
         ],
     }
 }
@@ -135,3 +146,311 @@ fn next_token(mut nextTokenInput: NextTokenTests) {
     assert_eq!(tok.kind, TokenKind::EOF);
     assert_eq!(tok.literal, "");
 }
+
+#[test]
+fn InterpolatedString() {
+    let mut lexer = Lexer::New(r#""a ${f("}", {})} b" c"#);
+    let tok = lexer.NextToken();
+    assert_eq!(tok.kind, TokenKind::STRING);
+    assert_eq!(tok.literal, r#"a ${f("}", {})} b"#);
+    let tok = lexer.NextToken();
+    assert_eq!(tok.kind, TokenKind::IDENT);
+    assert_eq!(tok.literal, "c");
+}
+
+#[test]
+fn SplitStringSegments() {
+    let tests = vec![
+        ("", vec![StringSegment::Text("".into())]),
+        ("plain", vec![StringSegment::Text("plain".into())]),
+        (
+            r#"tab\t quote\" dollar\${x}"#,
+            vec![StringSegment::Text("tab\t quote\" dollar${x}".into())],
+        ),
+        (
+            "hello ${name}!",
+            vec![
+                StringSegment::Text("hello ".into()),
+                StringSegment::Code {
+                    offset: 8,
+                    source: "name",
+                },
+                StringSegment::Text("!".into()),
+            ],
+        ),
+        (
+            "${a}${ b + 1 }",
+            vec![
+                StringSegment::Text("".into()),
+                StringSegment::Code {
+                    offset: 2,
+                    source: "a",
+                },
+                StringSegment::Code {
+                    offset: 6,
+                    source: " b + 1 ",
+                },
+            ],
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(StringSegments(input).unwrap(), expected);
+    }
+
+    assert!(StringSegments("oops ${1 + ").is_err());
+}
+
+#[test]
+fn UnicodeIdentifiers() {
+    let mut lexer = Lexer::New("let 名前 = café_2 + _x; € ü");
+    let expected = vec![
+        (TokenKind::LET, "let"),
+        (TokenKind::IDENT, "名前"),
+        (TokenKind::ASSIGN, "="),
+        (TokenKind::IDENT, "café_2"),
+        (TokenKind::PLUS, "+"),
+        (TokenKind::IDENT, "_x"),
+        (TokenKind::SEMICOLON, ";"),
+        (TokenKind::ILLEGAL, "€"),
+        (TokenKind::IDENT, "ü"),
+        (TokenKind::EOF, ""),
+    ];
+    for (kind, literal) in expected {
+        let tok = lexer.NextToken();
+        assert_eq!(tok.kind, kind);
+        assert_eq!(tok.literal, literal);
+    }
+}
+
+#[test]
+fn MatchTokens() {
+    let mut lexer = Lexer::New("match x { _ => 1 }");
+    let expected = vec![
+        (TokenKind::MATCH, "match"),
+        (TokenKind::IDENT, "x"),
+        (TokenKind::LBRACE, "{"),
+        (TokenKind::IDENT, "_"),
+        (TokenKind::FAT_ARROW, "=>"),
+        (TokenKind::INT, "1"),
+        (TokenKind::RBRACE, "}"),
+        (TokenKind::EOF, ""),
+    ];
+    for (kind, literal) in expected {
+        let tok = lexer.NextToken();
+        assert_eq!(tok.kind, kind);
+        assert_eq!(tok.literal, literal);
+    }
+}
+
+#[test]
+fn Pipes() {
+    let mut lexer = Lexer::New("|x, y| || x");
+    let expected = vec![
+        (TokenKind::PIPE, "|"),
+        (TokenKind::IDENT, "x"),
+        (TokenKind::COMMA, ","),
+        (TokenKind::IDENT, "y"),
+        (TokenKind::PIPE, "|"),
+        (TokenKind::PIPE, "|"),
+        (TokenKind::PIPE, "|"),
+        (TokenKind::IDENT, "x"),
+        (TokenKind::EOF, ""),
+    ];
+    for (kind, literal) in expected {
+        let tok = lexer.NextToken();
+        assert_eq!(tok.kind, kind);
+        assert_eq!(tok.literal, literal);
+    }
+}
+
+#[test]
+fn UpdateOperators() {
+    let mut lexer = Lexer::New("i++ - --j");
+    let expected = vec![
+        (TokenKind::IDENT, "i"),
+        (TokenKind::INCREMENT, "++"),
+        (TokenKind::MINUS, "-"),
+        (TokenKind::DECREMENT, "--"),
+        (TokenKind::IDENT, "j"),
+        (TokenKind::EOF, ""),
+    ];
+    for (kind, literal) in expected {
+        let tok = lexer.NextToken();
+        assert_eq!(tok.kind, kind);
+        assert_eq!(tok.literal, literal);
+    }
+}
+
+#[test]
+fn MinusMinus() {
+    use TokenKind::*;
+    let tests = vec![
+        ("5--3", vec![INT, MINUS, MINUS, INT]),
+        ("5--x", vec![INT, MINUS, MINUS, IDENT]),
+        ("f()--1", vec![IDENT, LPAREN, RPAREN, MINUS, MINUS, INT]),
+        ("--3", vec![MINUS, MINUS, INT]),
+        ("x--", vec![IDENT, DECREMENT]),
+        ("1 + --x", vec![INT, PLUS, DECREMENT, IDENT]),
+        ("{}\n--x", vec![LBRACE, RBRACE, DECREMENT, IDENT]),
+    ];
+    for (input, expected) in tests {
+        let kinds = |tokens: &mut dyn Iterator<Item = crate::token::Token>| -> Vec<TokenKind> {
+            tokens.map(|t| t.kind).filter(|k| *k != EOF).collect()
+        };
+        assert_eq!(kinds(&mut Lexer::New(input)), expected, "{}", input);
+        let mut stream = StreamLexer::New(BufReader::new(input.as_bytes()));
+        assert_eq!(kinds(&mut stream), expected, "{}", input);
+    }
+}
+
+#[test]
+fn Brackets() {
+    let mut lexer = Lexer::New("[1, 2][0:1] [...xs] .");
+    let expected = vec![
+        (TokenKind::LBRACKET, "["),
+        (TokenKind::INT, "1"),
+        (TokenKind::COMMA, ","),
+        (TokenKind::INT, "2"),
+        (TokenKind::RBRACKET, "]"),
+        (TokenKind::LBRACKET, "["),
+        (TokenKind::INT, "0"),
+        (TokenKind::COLON, ":"),
+        (TokenKind::INT, "1"),
+        (TokenKind::RBRACKET, "]"),
+        (TokenKind::LBRACKET, "["),
+        (TokenKind::ELLIPSIS, "..."),
+        (TokenKind::IDENT, "xs"),
+        (TokenKind::RBRACKET, "]"),
+        (TokenKind::ILLEGAL, "."),
+        (TokenKind::EOF, ""),
+    ];
+    for (kind, literal) in expected {
+        let tok = lexer.NextToken();
+        assert_eq!(tok.kind, kind);
+        assert_eq!(tok.literal, literal);
+    }
+}
+
+#[test]
+fn Positions() {
+    let mut lexer = Lexer::New("let 名前 = 1;\n  x\n\n\"a\nb\" y");
+    let expected = vec![
+        ("let", 1, 1, 0),
+        ("名前", 1, 5, 4),
+        // columns count chars, offsets bytes
+        ("=", 1, 8, 11),
+        ("1", 1, 10, 13),
+        (";", 1, 11, 14),
+        ("x", 2, 3, 18),
+        ("a\nb", 4, 1, 21),
+        ("y", 5, 4, 27),
+        ("", 5, 5, 28),
+    ];
+    for (literal, line, column, offset) in expected {
+        let tok = lexer.NextToken();
+        assert_eq!(tok.literal, literal);
+        assert_eq!(
+            (tok.position.line, tok.position.column, tok.position.offset),
+            (line, column, offset),
+            "{}",
+            literal
+        );
+    }
+}
+
+#[test]
+fn Iterates() {
+    let tokens: Vec<_> = Lexer::New(r#"let s = "hi";"#).collect();
+    let kinds: Vec<_> = tokens.iter().map(|tok| tok.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::LET,
+            TokenKind::IDENT,
+            TokenKind::ASSIGN,
+            TokenKind::STRING,
+            TokenKind::SEMICOLON,
+            TokenKind::EOF
+        ]
+    );
+    // a string's span has its quotes
+    assert_eq!(tokens[3].Span(), 8..12);
+    assert_eq!(tokens[5].Span(), 13..13);
+}
+
+#[test]
+fn Streams() {
+    let input = "let s = \"a\nb ${x +\n1}\";\nlet t = s\n== s; ...xs  ";
+    let streamed: Vec<_> =
+        StreamLexer::New(BufReader::with_capacity(1, input.as_bytes())).collect();
+    let lexed: Vec<_> = Lexer::New(input).collect();
+    assert_eq!(streamed.len(), lexed.len());
+    for (streamed, lexed) in streamed.iter().zip(&lexed) {
+        assert_eq!(
+            (streamed.kind, &streamed.literal, streamed.position),
+            (lexed.kind, &lexed.literal, lexed.position)
+        );
+    }
+
+    // reading stops at invalid UTF-8, as if the source ended there
+    let mut lexer = StreamLexer::New(&b"let x = 1;\nlet \xff"[..]);
+    let kinds: Vec<_> = lexer.by_ref().map(|tok| tok.kind).collect();
+    assert_eq!(kinds.last(), Some(&TokenKind::EOF));
+    assert_eq!(kinds.len(), 6);
+    assert_eq!(
+        lexer.Error().map(std::io::Error::kind),
+        Some(std::io::ErrorKind::InvalidData)
+    );
+}
+
+#[test]
+fn RawStrings() {
+    let tokens: Vec<_> = Lexer::New(r#"r"a\n${b}" bar"c" r"#).collect();
+    let lexed: Vec<_> = tokens
+        .iter()
+        .map(|tok| (tok.kind, tok.literal.as_str(), tok.Span()))
+        .collect();
+    assert_eq!(
+        lexed,
+        vec![
+            (TokenKind::RAW_STRING, r"a\n${b}", 0..10),
+            (TokenKind::IDENT, "bar", 11..14),
+            (TokenKind::STRING, "c", 14..17),
+            (TokenKind::IDENT, "r", 18..19),
+            (TokenKind::EOF, "", 19..19),
+        ]
+    );
+}
+
+#[test]
+fn UnterminatedStrings() {
+    for (input, literal) in [
+        ("\"unterminated\nxx", "\"unterminated\nxx"),
+        ("let s = r\"abc", "r\"abc"),
+        ("\"a ${\"b\"} \\\"", "\"a ${\"b\"} \\\""),
+    ] {
+        let start = input.len() - literal.len();
+        let lexed: Vec<_> = Lexer::New(input).collect();
+        let streamed: Vec<_> =
+            StreamLexer::New(BufReader::with_capacity(1, input.as_bytes())).collect();
+        for tokens in [&lexed, &streamed] {
+            let tokens: Vec<_> = tokens[tokens.len() - 2..]
+                .iter()
+                .map(|tok| (tok.kind, tok.literal.as_str(), tok.Span()))
+                .collect();
+            assert_eq!(
+                tokens,
+                vec![
+                    (TokenKind::ILLEGAL, literal, start..input.len()),
+                    (TokenKind::EOF, "", input.len()..input.len()),
+                ],
+                "{:?}",
+                input
+            );
+        }
+    }
+    assert_eq!(
+        Tokenize("x + \"abc").unwrap_err().to_string(),
+        "unterminated string at 1:5"
+    );
+}
@@ -2,8 +2,29 @@
 #![allow(non_camel_case_types)]
 
 pub mod ast;
+mod builtins;
+pub mod callgraph;
+pub mod capability;
+pub mod compiler;
+pub mod continuation;
+pub mod cst;
+pub mod diagnostics;
+pub mod edition;
+pub mod environment;
+pub mod evaluation;
 pub mod evaluator;
 pub mod lexer;
+pub mod macro_expansion;
+pub mod module;
 pub mod object;
+pub mod optimize;
 pub mod parser;
+pub mod platform;
+pub mod pool;
+pub mod printer;
+pub mod runtime;
+pub mod sandbox;
+pub mod speculation;
+pub mod stats;
 pub mod token;
+pub mod warning;
@@ -0,0 +1,200 @@
+//! Macros, which run between parsing and evaluation. `DefineMacros` takes the
+//! top-level `let name = macro(...) { ... }` statements out of a program and
+//! binds the macros; `ExpandMacros` then replaces every call to one with the
+//! code it returns. A macro gets its arguments as quotes of the code passed
+//! and returns a quote, usually made with `quote`, in which `unquote(x)` is
+//! replaced by the value of `x` turned back into code.
+
+use std::rc::Rc;
+
+use crate::ast::Boolean;
+use crate::ast::ExpressionEnum;
+use crate::ast::IntegerLiteral;
+use crate::ast::Modify;
+use crate::ast::NodeEnum;
+use crate::ast::NullLiteral;
+use crate::ast::Program;
+use crate::ast::Span;
+use crate::ast::StatementEnum;
+use crate::ast::StringLiteral;
+use crate::environment::Env;
+use crate::environment::Environment;
+use crate::evaluator::isError;
+use crate::evaluator::typeName;
+use crate::evaluator::wrongArgumentCount;
+use crate::evaluator::Eval;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Macro;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Quote as QuoteObject;
+use crate::object::ReturnValue;
+use crate::object::NULL;
+use crate::token::Token;
+use crate::token::TokenKind;
+
+/// Removes the macro definitions from the top level of `program`, binding the
+/// macros they define in `env`.
+pub fn DefineMacros(program: &mut Program, env: &Env) {
+    program.statements.retain(|stmt| match stmt {
+        StatementEnum::Let(l) => match &l.value {
+            ExpressionEnum::MacroLiteral(m) => {
+                let definition = Macro {
+                    parameters: m.parameters.clone(),
+                    body: Rc::new((*m.body).clone()),
+                    env: Rc::clone(env),
+                };
+                env.borrow_mut().Set(&l.name.value, definition.into());
+                false
+            }
+            _ => true,
+        },
+        _ => true,
+    });
+}
+
+/// Replaces every call of a macro bound in `env` with the code the macro
+/// returns for it. Fails with the first error a macro runs into, or returns
+/// instead of a quote.
+pub fn ExpandMacros(program: Program, env: &Env) -> Result<Program, ObjectEnum> {
+    let mut failure = None;
+    let expanded = Modify(program.into(), &mut |exp| {
+        if failure.is_some() {
+            return exp;
+        }
+        let (definition, arguments) = match exp {
+            ExpressionEnum::CallExpression(c) => match macroCalled(&c.function, env) {
+                Some(definition) => (definition, c.arguments),
+                None => return c.into(),
+            },
+            exp => return exp,
+        };
+        match expand(definition, arguments) {
+            Ok(exp) => exp,
+            Err(err) => {
+                failure = Some(err);
+                NullLiteral {
+                    token: Token::New(TokenKind::NULL, "null"),
+                    span: Span::default(),
+                }
+                .into()
+            }
+        }
+    });
+    match (failure, expanded) {
+        (Some(err), _) => Err(err),
+        (None, NodeEnum::Program(program)) => Ok(program),
+        (None, _) => unreachable!("modifying a program gives a program"),
+    }
+}
+
+fn macroCalled(function: &ExpressionEnum, env: &Env) -> Option<Macro> {
+    match function {
+        ExpressionEnum::Identifier(i) => match env.borrow().Get(&i.value) {
+            Some(ObjectEnum::Macro(m)) => Some(m),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expand(definition: Macro, arguments: Vec<ExpressionEnum>) -> Result<ExpressionEnum, ObjectEnum> {
+    if arguments.len() != definition.parameters.len() {
+        return Err(wrongArgumentCount(
+            definition.parameters.len(),
+            arguments.len(),
+        ));
+    }
+    let macroEnv = Environment::NewEnclosed(&definition.env);
+    for (parameter, argument) in definition.parameters.iter().zip(arguments) {
+        macroEnv.borrow_mut().Set(
+            &parameter.value,
+            QuoteObject {
+                node: Box::new(argument),
+            }
+            .into(),
+        );
+    }
+    let body = StatementEnum::Block((*definition.body).clone());
+    let result = match Eval(body.into(), &macroEnv).unwrap_or(NULL) {
+        ObjectEnum::ReturnValue(ReturnValue(value)) => *value,
+        result => result,
+    };
+    match result {
+        ObjectEnum::Quote(quote) => Ok(*quote.node),
+        err if isError(&err) => Err(err),
+        other => Err(Error::new(
+            ErrorKind::TypeError,
+            format!("macro must return a QUOTE, got {:?}", other.Type()),
+        )
+        .with("type", typeName(&other))
+        .into()),
+    }
+}
+
+/// What `quote(exp)` evaluates to: `exp` as code, with its `unquote` calls
+/// evaluated in `env` and replaced by their values.
+pub(crate) fn Quote(mut arguments: Vec<ExpressionEnum>, env: &Env) -> ObjectEnum {
+    if arguments.len() != 1 {
+        return wrongArgumentCount(1, arguments.len());
+    }
+    let node = Modify(arguments.remove(0).into(), &mut |exp| match exp {
+        ExpressionEnum::CallExpression(c) if isUnquote(&c.function) && c.arguments.len() == 1 => {
+            let value = Eval(c.arguments[0].clone().into(), env).unwrap_or(NULL);
+            match toNode(value) {
+                Some(node) => node,
+                None => c.into(),
+            }
+        }
+        exp => exp,
+    });
+    match node {
+        NodeEnum::Expression(node) => QuoteObject {
+            node: Box::new(node),
+        }
+        .into(),
+        _ => unreachable!("modifying an expression gives an expression"),
+    }
+}
+
+fn isUnquote(function: &ExpressionEnum) -> bool {
+    matches!(function, ExpressionEnum::Identifier(i) if i.value == "unquote")
+}
+
+/// `value` as the code that evaluates to it, if there is such code.
+fn toNode(value: ObjectEnum) -> Option<ExpressionEnum> {
+    Some(match value {
+        ObjectEnum::Integer(i) => IntegerLiteral {
+            token: Token::New(TokenKind::INT, i.value.to_string()),
+            span: Span::default(),
+            value: i.value,
+        }
+        .into(),
+        ObjectEnum::String(s) => StringLiteral {
+            token: Token::New(TokenKind::STRING, String::new()),
+            span: Span::default(),
+            value: s.value.into(),
+        }
+        .into(),
+        ObjectEnum::Boolean(b) => Boolean {
+            token: match b.value {
+                true => Token::New(TokenKind::TRUE, "true"),
+                false => Token::New(TokenKind::FALSE, "false"),
+            },
+            span: Span::default(),
+            value: b.value,
+        }
+        .into(),
+        ObjectEnum::Null(_) => NullLiteral {
+            token: Token::New(TokenKind::NULL, "null"),
+            span: Span::default(),
+        }
+        .into(),
+        ObjectEnum::Quote(quote) => *quote.node,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests;
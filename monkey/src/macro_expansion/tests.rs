@@ -0,0 +1,183 @@
+use crate::ast::Node;
+use crate::ast::Program;
+use crate::environment::Env;
+use crate::environment::Environment;
+use crate::evaluator::Eval;
+use crate::lexer::Lexer;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::parser::Parser;
+
+use super::DefineMacros;
+use super::ExpandMacros;
+
+fn parse(input: &str) -> Program {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "{}: {:?}", input, p.errors);
+    program
+}
+
+fn eval(input: &str, env: &Env) -> ObjectEnum {
+    Eval(parse(input).into(), env).unwrap()
+}
+
+#[test]
+fn Quotes() {
+    let tests = vec![
+        ("quote(5)", "QUOTE(5)"),
+        ("quote(5 + 8)", "QUOTE((5 + 8))"),
+        ("quote(foobar)", "QUOTE(foobar)"),
+        ("quote(foobar + barfoo)", "QUOTE((foobar + barfoo))"),
+        (
+            "quote(1, 2)",
+            "ERROR: wrong number of arguments: want=1, got=2",
+        ),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(
+            eval(input, &Environment::New()).Inspect(),
+            expected,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn Unquotes() {
+    let tests = vec![
+        ("quote(unquote(4))", "QUOTE(4)"),
+        ("quote(unquote(4 + 4))", "QUOTE(8)"),
+        ("quote(8 + unquote(4 + 4))", "QUOTE((8 + 8))"),
+        ("quote(unquote(4 + 4) + 8)", "QUOTE((8 + 8))"),
+        ("let foobar = 8; quote(foobar)", "QUOTE(foobar)"),
+        ("let foobar = 8; quote(unquote(foobar))", "QUOTE(8)"),
+        ("quote(unquote(true))", "QUOTE(true)"),
+        ("quote(unquote(true == false))", "QUOTE(false)"),
+        ("quote(unquote(null))", "QUOTE(null)"),
+        (r#"quote(unquote("a" + "b") + x)"#, "QUOTE((ab + x))"),
+        ("quote(unquote(quote(4 + 4)))", "QUOTE((4 + 4))"),
+        (
+            "let quotedInfixExpression = quote(4 + 4); \
+             quote(unquote(4 + 4) + unquote(quotedInfixExpression))",
+            "QUOTE((8 + (4 + 4)))",
+        ),
+        // values that aren't code stay unquoted
+        ("quote(unquote([1]))", "QUOTE(unquote([1]))"),
+        // a binding called `quote` is an ordinary function
+        ("let quote = fn(x) { x * 2 }; quote(4)", "8"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(
+            eval(input, &Environment::New()).Inspect(),
+            expected,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn DefinesMacros() {
+    let input = "
+    let number = 1;
+    let function = fn(x, y) { x + y };
+    let mymacro = macro(x, y) { x + y; };
+    ";
+    let env = Environment::New();
+    let mut program = parse(input);
+    DefineMacros(&mut program, &env);
+
+    assert_eq!(program.statements.len(), 2);
+    assert!(env.borrow().Get("number").is_none());
+    assert!(env.borrow().Get("function").is_none());
+    let mymacro = env.borrow().Get("mymacro");
+    match mymacro {
+        Some(ObjectEnum::Macro(m)) => {
+            assert_eq!(m.parameters.len(), 2);
+            assert_eq!(m.parameters[0].value, "x");
+            assert_eq!(m.parameters[1].value, "y");
+            assert_eq!(m.body.String(), "(x + y)");
+        }
+        other => panic!("mymacro isn't a macro: {:?}", other),
+    }
+}
+
+#[test]
+fn ExpandsMacros() {
+    let tests = vec![
+        (
+            "let infixExpression = macro() { quote(1 + 2); }; infixExpression();",
+            "(1 + 2)",
+        ),
+        (
+            "let reverse = macro(a, b) { quote(unquote(b) - unquote(a)); }; reverse(2 + 2, 10 - 5);",
+            "(10 - 5) - (2 + 2)",
+        ),
+        (
+            r#"
+            let unless = macro(condition, consequence, alternative) {
+                quote(if (!(unquote(condition))) {
+                    unquote(consequence);
+                } else {
+                    unquote(alternative);
+                });
+            };
+            unless(10 > 5, puts("not greater"), puts("greater"));
+            "#,
+            r#"if (!(10 > 5)) { puts("not greater") } else { puts("greater") }"#,
+        ),
+        (
+            "let twice = macro(x) { quote(unquote(x) + unquote(x)); }; let f = fn() { twice(3) };",
+            "let f = fn() { 3 + 3 };",
+        ),
+    ];
+    for (input, expected) in tests {
+        let env = Environment::New();
+        let mut program = parse(input);
+        DefineMacros(&mut program, &env);
+        let expanded = ExpandMacros(program, &env).unwrap();
+        assert_eq!(expanded.String(), parse(expected).String(), "{}", input);
+    }
+}
+
+#[test]
+fn ExpansionErrors() {
+    let tests = vec![
+        (
+            "let m = macro(x) { 5 }; m(1);",
+            "ERROR: macro must return a QUOTE, got INTEGER",
+        ),
+        (
+            "let m = macro(x) { quote(x) }; m();",
+            "ERROR: wrong number of arguments: want=1, got=0",
+        ),
+        (
+            "let m = macro() { nope }; m();",
+            "ERROR: identifier not found: nope",
+        ),
+    ];
+    for (input, expected) in tests {
+        let env = Environment::New();
+        let mut program = parse(input);
+        DefineMacros(&mut program, &env);
+        let err = ExpandMacros(program, &env).unwrap_err();
+        assert_eq!(err.Inspect(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn ExpandedProgramsEvaluate() {
+    let input = "
+    let unless = macro(condition, consequence, alternative) {
+        quote(if (!(unquote(condition))) { unquote(consequence) } else { unquote(alternative) });
+    };
+    unless(10 > 5, 1, 2)";
+    let macros = Environment::New();
+    let mut program = parse(input);
+    DefineMacros(&mut program, &macros);
+    let expanded = ExpandMacros(program, &macros).unwrap();
+    let evaluated = Eval(expanded.into(), &Environment::New()).unwrap();
+    assert_eq!(evaluated.Inspect(), "2");
+}
@@ -0,0 +1,97 @@
+//! Finding and keeping track of the files scripts `import`. Evaluating them is
+//! up to the evaluator; the loader only knows where they are, which ones have
+//! been loaded, and which ones are being loaded, to catch import cycles.
+
+use std::collections::HashMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::object::Module;
+use crate::platform::Platform;
+
+#[derive(Debug)]
+pub struct Loader {
+    searchPath: Vec<PathBuf>,
+    /// Modules already evaluated, by canonical path. Importing one again gives
+    /// the same module without evaluating it again.
+    loaded: HashMap<PathBuf, Module>,
+    /// Modules being evaluated, outermost first, with the names they were
+    /// imported by.
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self {
+            searchPath: vec![PathBuf::from(".")],
+            loaded: HashMap::new(),
+            loading: vec![],
+        }
+    }
+}
+
+impl Loader {
+    pub fn New() -> Self {
+        Self::default()
+    }
+
+    /// The directories relative imports are looked up in, in order. Defaults
+    /// to the current directory.
+    pub fn SearchPath(&self) -> &[PathBuf] {
+        &self.searchPath
+    }
+
+    pub fn SetSearchPath(&mut self, searchPath: Vec<PathBuf>) {
+        self.searchPath = searchPath;
+    }
+
+    /// The canonical path of the file `name` refers to: `name` itself if it is
+    /// absolute, otherwise `name` in the first directory of the search path
+    /// that has it on `platform`.
+    pub(crate) fn Resolve(&self, name: &str, platform: &dyn Platform) -> Option<PathBuf> {
+        let name = Path::new(name);
+        if name.is_absolute() {
+            return platform.Canonicalize(name).ok();
+        }
+        self.searchPath
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| platform.IsFile(path))
+            .and_then(|path| platform.Canonicalize(&path).ok())
+    }
+
+    /// Whether `name` can refer to a file outside the search path, by being
+    /// absolute or going up with `..`. Importing one needs `Fs`.
+    pub(crate) fn Escapes(name: &str) -> bool {
+        Path::new(name)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    }
+
+    pub(crate) fn Loaded(&self, path: &Path) -> Option<Module> {
+        self.loaded.get(path).cloned()
+    }
+
+    /// The chain of imports leading back to `path`, if it is being loaded,
+    /// like `a.mk -> b.mk -> a.mk`.
+    pub(crate) fn Cycle(&self, path: &Path, name: &str) -> Option<String> {
+        let start = self.loading.iter().position(|(p, _)| p == path)?;
+        let names = self.loading[start..].iter().map(|(_, name)| name.as_str());
+        Some(itertools::join(names.chain(Some(name)), " -> "))
+    }
+
+    pub(crate) fn StartLoading(&mut self, path: PathBuf, name: &str) {
+        self.loading.push((path, name.to_string()));
+    }
+
+    /// Records the end of the innermost load, and the module it produced if
+    /// it succeeded.
+    pub(crate) fn FinishLoading(&mut self, module: Option<Module>) {
+        if let Some((path, _)) = self.loading.pop() {
+            if let Some(module) = module {
+                self.loaded.insert(path, module);
+            }
+        }
+    }
+}
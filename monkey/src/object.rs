@@ -1,5 +1,18 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::rc::Rc;
+
 use enum_dispatch::enum_dispatch;
 
+use crate::ast::BlockStatement;
+use crate::ast::ExpressionEnum;
+use crate::ast::Identifier;
+use crate::ast::Node;
+use crate::environment::Env;
+use crate::runtime::Runtime;
+use crate::token::Position;
+
 pub(crate) trait Truthy {
     fn isTruthy(&self) -> bool;
 }
@@ -17,26 +30,60 @@ impl Truthy for ObjectEnum {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ObjectKind {
     INTEGER,
+    STRING,
     BOOLEAN,
     NULL,
     RETURN_VALUE,
     ERROR,
+    FUNCTION,
+    BUILTIN,
+    ARRAY,
+    HASH,
+    TAIL_CALL,
+    THROWN,
+    MODULE,
+    QUOTE,
+    MACRO,
+    SECRET,
 }
 
 #[enum_dispatch]
 #[derive(Debug, Clone, PartialEq)]
 pub enum ObjectEnum {
     Integer(Integer),
+    String(Str),
     Boolean(Boolean),
     Null(Null),
     ReturnValue(ReturnValue),
     Error(Error),
+    Function(Function),
+    Builtin(Builtin),
+    Array(Array),
+    Hash(Hash),
+    TailCall(TailCall),
+    Thrown(Thrown),
+    Module(Module),
+    Quote(Quote),
+    Macro(Macro),
+    Secret(Secret),
+    HostArray(HostArray),
 }
 
 #[enum_dispatch(ObjectEnum)]
 pub trait Object: std::fmt::Debug + Clone + PartialEq {
     fn Type(&self) -> ObjectKind;
     fn Inspect(&self) -> String;
+
+    /// The comparison protocol behind `<` and `>`.
+    ///
+    /// Only called with an `other` of the same `ObjectKind`; comparisons across
+    /// types are rejected by the evaluator before reaching this hook. Returning
+    /// `None` means the type has no ordering. Implementations must be a total
+    /// order so that sorting anything comparable is predictable (and stable,
+    /// since equal elements keep their relative order).
+    fn Compare(&self, _other: &ObjectEnum) -> Option<Ordering> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -52,6 +99,35 @@ impl Object for Integer {
     fn Inspect(&self) -> String {
         self.value.to_string()
     }
+
+    fn Compare(&self, other: &ObjectEnum) -> Option<Ordering> {
+        match other {
+            ObjectEnum::Integer(other) => Some(self.value.cmp(&other.value)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Str {
+    pub value: String,
+}
+
+impl Object for Str {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::STRING
+    }
+
+    fn Inspect(&self) -> String {
+        self.value.clone()
+    }
+
+    fn Compare(&self, other: &ObjectEnum) -> Option<Ordering> {
+        match other {
+            ObjectEnum::String(other) => Some(self.value.cmp(&other.value)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -72,6 +148,9 @@ impl Object for Boolean {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Null;
 
+/// The one null value, produced by `null` and by anything that has no value.
+pub const NULL: ObjectEnum = ObjectEnum::Null(Null);
+
 impl Object for Null {
     fn Type(&self) -> ObjectKind {
         ObjectKind::NULL
@@ -82,6 +161,192 @@ impl Object for Null {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array {
+    pub elements: Vec<ObjectEnum>,
+}
+
+impl Object for Array {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::ARRAY
+    }
+
+    fn Inspect(&self) -> String {
+        format!(
+            "[{}]",
+            itertools::join(self.elements.iter().map(|e| e.Inspect()), ", ")
+        )
+    }
+}
+
+/// A read-only array a host passes in without converting it: each element
+/// becomes an object only when a script reaches it. Indexing and `len` don't
+/// convert anything else, and a slice converts only what it keeps; everything
+/// else that takes an array converts all of it first. Scripts see it as an
+/// `ARRAY`, though it only `==` another host array with the same elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostArray {
+    elements: Rc<HostElements>,
+}
+
+#[derive(Debug, PartialEq)]
+enum HostElements {
+    Integers(Vec<i64>),
+    Strings(Vec<String>),
+}
+
+impl From<Vec<i64>> for HostArray {
+    fn from(elements: Vec<i64>) -> Self {
+        Self {
+            elements: Rc::new(HostElements::Integers(elements)),
+        }
+    }
+}
+
+impl From<Vec<String>> for HostArray {
+    fn from(elements: Vec<String>) -> Self {
+        Self {
+            elements: Rc::new(HostElements::Strings(elements)),
+        }
+    }
+}
+
+impl HostArray {
+    pub fn Len(&self) -> usize {
+        match &*self.elements {
+            HostElements::Integers(elements) => elements.len(),
+            HostElements::Strings(elements) => elements.len(),
+        }
+    }
+
+    /// The element at `index`, if there is one.
+    pub fn Get(&self, index: usize) -> Option<ObjectEnum> {
+        match &*self.elements {
+            HostElements::Integers(elements) => {
+                elements.get(index).map(|&value| Integer { value }.into())
+            }
+            HostElements::Strings(elements) => elements.get(index).map(|value| {
+                Str {
+                    value: value.clone(),
+                }
+                .into()
+            }),
+        }
+    }
+
+    /// The elements from `start` up to `end` as a script's array.
+    pub fn Slice(&self, start: usize, end: usize) -> Array {
+        Array {
+            elements: (start..end).filter_map(|i| self.Get(i)).collect(),
+        }
+    }
+
+    pub fn ToArray(&self) -> Array {
+        self.Slice(0, self.Len())
+    }
+}
+
+impl Object for HostArray {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::ARRAY
+    }
+
+    fn Inspect(&self) -> String {
+        self.ToArray().Inspect()
+    }
+}
+
+/// The value of a hash key. Only integers, strings and booleans can be keys.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+}
+
+impl HashKey {
+    pub fn ToObject(&self) -> ObjectEnum {
+        match self {
+            Self::Integer(value) => Integer { value: *value }.into(),
+            Self::String(value) => Str {
+                value: value.clone(),
+            }
+            .into(),
+            Self::Boolean(value) => Boolean { value: *value }.into(),
+        }
+    }
+}
+
+impl ObjectEnum {
+    /// The key this object is stored under in a hash, if it can be one.
+    pub fn HashKey(&self) -> Option<HashKey> {
+        match self {
+            Self::Integer(i) => Some(HashKey::Integer(i.value)),
+            Self::String(s) => Some(HashKey::String(s.value.clone())),
+            Self::Boolean(b) => Some(HashKey::Boolean(b.value)),
+            _ => None,
+        }
+    }
+
+    /// The status a script asked to exit with, if this is the result of it
+    /// calling `exit`.
+    pub fn ExitCode(&self) -> Option<i32> {
+        match self {
+            Self::Error(err) if err.kind == ErrorKind::Exit => match err.data.get("code") {
+                Some(Self::Integer(code)) => code.value.try_into().ok(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A map from keys to values. Pairs are kept in key order, so hashes inspect
+/// the same way every time.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Hash {
+    pub pairs: BTreeMap<HashKey, ObjectEnum>,
+}
+
+impl Object for Hash {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::HASH
+    }
+
+    fn Inspect(&self) -> String {
+        format!(
+            "{{{}}}",
+            itertools::join(
+                self.pairs.iter().map(|(k, v)| format!(
+                    "{}: {}",
+                    k.ToObject().Inspect(),
+                    v.Inspect()
+                )),
+                ", "
+            )
+        )
+    }
+}
+
+/// The top-level bindings of an imported file, looked up by indexing with
+/// their names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// The name it was first imported by.
+    pub name: String,
+    pub members: Rc<BTreeMap<String, ObjectEnum>>,
+}
+
+impl Object for Module {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::MODULE
+    }
+
+    fn Inspect(&self) -> String {
+        format!("module {}", self.name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReturnValue(pub(crate) Box<ObjectEnum>);
 
@@ -95,8 +360,194 @@ impl Object for ReturnValue {
     }
 }
 
+/// A value on its way from `throw` to the nearest `catch`. Like `ReturnValue`,
+/// it never escapes the evaluator: uncaught, it becomes an `Uncaught` error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thrown(pub(crate) Box<ObjectEnum>);
+
+impl Thrown {
+    /// The value thrown.
+    pub fn Value(&self) -> &ObjectEnum {
+        &self.0
+    }
+
+    /// The error the value becomes when nothing catches it, with the value as
+    /// its `value`.
+    pub(crate) fn Uncaught(self) -> Error {
+        Error::new(
+            ErrorKind::Uncaught,
+            format!("uncaught exception: {}", self.0.Inspect()),
+        )
+        .with("value", *self.0)
+    }
+}
+
+impl Object for Thrown {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::THROWN
+    }
+
+    fn Inspect(&self) -> String {
+        format!("thrown {}", self.0.Inspect())
+    }
+}
+
+/// A call in tail position, handed back to the caller's `applyFunction` to be
+/// made there. Like `ReturnValue`, it never escapes the evaluator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TailCall {
+    pub(crate) function: Box<ObjectEnum>,
+    pub(crate) args: Vec<ObjectEnum>,
+    /// The call, for the trace of an error coming out of it.
+    pub(crate) frame: Frame,
+}
+
+impl Object for TailCall {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::TAIL_CALL
+    }
+
+    fn Inspect(&self) -> String {
+        format!("tail call of {}", self.function.Inspect())
+    }
+}
+
+/// What went wrong, for embedders that want to match on errors rather than
+/// their messages. The script-visible name of a kind is its variant name.
+#[derive(strum_macros::Display, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Operands of incompatible types, e.g. `5 + true`.
+    TypeError,
+    /// An operator that isn't defined for its operand types, e.g. `-true`.
+    OperatorError,
+    /// An identifier that isn't bound in any enclosing environment.
+    NameError,
+    /// A call with the wrong number of arguments.
+    ArgumentError,
+    /// Evaluation was cancelled through its `CancellationToken`.
+    Interrupted,
+    /// Evaluation ran out of fuel or time.
+    LimitExceeded,
+    /// Calls nested deeper than `Limits::depth` allows.
+    StackOverflow,
+    /// A value that doesn't have the shape a destructuring `let` expects.
+    DestructureError,
+    /// A replayed run made a builtin call the recording doesn't have.
+    ReplayError,
+    /// A value thrown with `throw` that no `catch` caught.
+    Uncaught,
+    /// A module that can't be found, read, parsed or evaluated, or that
+    /// imports itself.
+    ImportError,
+    /// A condition given to `assert` that doesn't hold.
+    AssertionError,
+    /// Dividing by zero, or a quotient too large for an integer.
+    ArithmeticError,
+    /// Using a capability the runtime denies.
+    PermissionDenied,
+    /// A file that can't be read or written, or a directory that can't be
+    /// listed.
+    IoError,
+    /// Code given to `eval` that doesn't parse.
+    SyntaxError,
+    /// Not a failure: the script called `exit`, with its status as `code`.
+    Exit,
+}
+
+impl ErrorKind {
+    /// Whether `catch` can handle errors of this kind. Those that stop a
+    /// script from outside, like running out of fuel, can't be caught, or the
+    /// script could carry on regardless.
+    pub fn IsCatchable(self) -> bool {
+        !matches!(
+            self,
+            Self::Interrupted | Self::LimitExceeded | Self::ReplayError | Self::Exit
+        )
+    }
+}
+
+/// How many of the calls an error came out of its trace keeps.
+const TRACE_DEPTH: usize = 16;
+
+/// The calls an error came out of, innermost first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// Up to `TRACE_DEPTH` of the calls. A call in tail position replaces its
+    /// caller, so of a chain of them only the last is there, inside the call
+    /// that started the chain.
+    pub frames: Vec<Frame>,
+    /// How many calls there were beyond those in `frames`.
+    pub elided: usize,
+}
+
+/// A call an error came out of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The name the function was called by, or where it was defined if it
+    /// wasn't called by name.
+    pub function: String,
+    /// Where the call is.
+    pub call: Position,
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub struct Error(pub(crate) String);
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Extra details about the error, keyed by name.
+    pub data: BTreeMap<String, ObjectEnum>,
+    /// The calls the error came out of, if it came out of any. Boxed so
+    /// that errors, and so all objects, stay small.
+    pub trace: Option<Box<Trace>>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            data: BTreeMap::new(),
+            trace: None,
+        }
+    }
+
+    /// Records that the error came out of the call `frame`, which is outside
+    /// those recorded already.
+    pub(crate) fn Unwind(&mut self, frame: Frame) {
+        let trace = self.trace.get_or_insert_with(Box::default);
+        if trace.frames.len() < TRACE_DEPTH {
+            trace.frames.push(frame);
+        } else {
+            trace.elided += 1;
+        }
+    }
+
+    pub fn with(mut self, key: &str, value: impl Into<ObjectEnum>) -> Self {
+        self.data.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// The error as a script sees it once caught: a hash of its data, plus
+    /// its `kind` and `message`.
+    pub fn ToHash(&self) -> Hash {
+        let mut hash = Hash::default();
+        for (key, value) in &self.data {
+            hash.pairs
+                .insert(HashKey::String(key.clone()), value.clone());
+        }
+        let kind = Str {
+            value: self.kind.to_string(),
+        };
+        let message = Str {
+            value: self.message.clone(),
+        };
+        hash.pairs
+            .insert(HashKey::String("kind".into()), kind.into());
+        hash.pairs
+            .insert(HashKey::String("message".into()), message.into());
+        hash
+    }
+}
 
 impl Object for Error {
     fn Type(&self) -> ObjectKind {
@@ -104,6 +555,204 @@ impl Object for Error {
     }
 
     fn Inspect(&self) -> String {
-        format!("ERROR: {}", self.0)
+        let mut out = format!("ERROR: {}", self.message);
+        if let Some(trace) = &self.trace {
+            for frame in &trace.frames {
+                out += &format!("\n  in {}, called at {}", frame.function, frame.call);
+            }
+            if trace.elided > 0 {
+                out += &format!("\n  ... and {} more calls", trace.elided);
+            }
+        }
+        out
+    }
+}
+
+#[derive(Clone)]
+pub struct Function {
+    pub parameters: Vec<Identifier>,
+    pub body: Rc<BlockStatement>,
+    pub env: Env,
+}
+
+impl std::fmt::Debug for Function {
+    // the environment can contain this function, so it's left out
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Function({})", self.Inspect())
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body) && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+impl Object for Function {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::FUNCTION
+    }
+
+    fn Inspect(&self) -> String {
+        format!(
+            "fn({}) {{\n{}\n}}",
+            itertools::join(&self.parameters, ", "),
+            self.body
+        )
+    }
+}
+
+/// Code as a value, made by `quote` and taken apart by `unquote`.
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub node: Box<ExpressionEnum>,
+}
+
+impl PartialEq for Quote {
+    fn eq(&self, other: &Self) -> bool {
+        self.node.String() == other.node.String()
+    }
+}
+
+impl Object for Quote {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::QUOTE
+    }
+
+    fn Inspect(&self) -> String {
+        format!("QUOTE({})", self.node)
+    }
+}
+
+/// Like a `Function`, but called with its arguments unevaluated, as quotes,
+/// and returning the quote of the code to put in the place of the call.
+#[derive(Clone)]
+pub struct Macro {
+    pub parameters: Vec<Identifier>,
+    pub body: Rc<BlockStatement>,
+    pub env: Env,
+}
+
+impl std::fmt::Debug for Macro {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Macro({})", self.Inspect())
+    }
+}
+
+impl PartialEq for Macro {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body) && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+impl Object for Macro {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::MACRO
+    }
+
+    fn Inspect(&self) -> String {
+        format!(
+            "macro({}) {{\n{}\n}}",
+            itertools::join(&self.parameters, ", "),
+            self.body
+        )
+    }
+}
+
+/// A value that scripts can pass around and compare with `secure_compare`,
+/// but not look into: it prints as `<secret>`, in errors and audit entries
+/// too, and the host can't read it back either. Two secrets are only `==`
+/// if they are the same secret.
+#[derive(Clone)]
+pub struct Secret(Rc<ObjectEnum>);
+
+impl Secret {
+    pub fn New(value: ObjectEnum) -> Self {
+        match value {
+            ObjectEnum::Secret(secret) => secret,
+            value => Self(Rc::new(value)),
+        }
+    }
+
+    pub(crate) fn Value(&self) -> &ObjectEnum {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret(..)")
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Object for Secret {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::SECRET
+    }
+
+    fn Inspect(&self) -> String {
+        "<secret>".into()
+    }
+}
+
+/// Whether `a` and `b` are equal, in time that depends only on the longer
+/// one's length, not on where they first differ.
+pub fn SecureEq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= usize::from(x ^ y);
+    }
+    diff == 0
+}
+
+pub type BuiltinFunction = Rc<dyn Fn(&Runtime, Vec<ObjectEnum>) -> ObjectEnum>;
+
+/// A function implemented in Rust, either one of the standard builtins or one
+/// the host binds into an environment.
+#[derive(Clone)]
+pub struct Builtin {
+    pub name: String,
+    pub func: BuiltinFunction,
+}
+
+impl Builtin {
+    pub fn new(
+        name: impl Into<String>,
+        func: impl Fn(&Runtime, Vec<ObjectEnum>) -> ObjectEnum + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            func: Rc::new(func),
+        }
+    }
+}
+
+impl std::fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Builtin({})", self.name)
+    }
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Rc::ptr_eq(&self.func, &other.func)
+    }
+}
+
+impl Object for Builtin {
+    fn Type(&self) -> ObjectKind {
+        ObjectKind::BUILTIN
+    }
+
+    fn Inspect(&self) -> String {
+        format!("builtin function {}", self.name)
     }
 }
@@ -0,0 +1,275 @@
+//! Passes that rewrite a program into one that evaluates the same way but
+//! does less work. They run after macro expansion, since a macro gets the
+//! code it is called with as written.
+
+use std::rc::Rc;
+
+use crate::ast::BlockStatement;
+use crate::ast::Boolean;
+use crate::ast::CallExpression;
+use crate::ast::ExpressionEnum;
+use crate::ast::ExpressionStatement;
+use crate::ast::Fold;
+use crate::ast::IfExpression;
+use crate::ast::InfixExpression;
+use crate::ast::IntegerLiteral;
+use crate::ast::Node;
+use crate::ast::NullLiteral;
+use crate::ast::PrefixExpression;
+use crate::ast::Program;
+use crate::ast::RebuildExpression;
+use crate::ast::Span;
+use crate::ast::StatementEnum;
+use crate::ast::StringLiteral;
+use crate::token::Position;
+use crate::token::Token;
+use crate::token::TokenKind;
+
+impl Program {
+    /// Replaces the operations on literals with their results, innermost
+    /// first, so `2 * 3 + x` becomes `6 + x`. An operation is only folded
+    /// when it can't fail or depend on the runtime: one that overflows,
+    /// divides by zero, mixes types or rounds a negative quotient (which
+    /// depends on the runtime's `Division`) is left to fail or round when it
+    /// runs.
+    pub fn FoldConstants(self) -> Self {
+        ConstantFolder.FoldProgram(self)
+    }
+
+    /// Removes the code that can never run or does nothing: the statements
+    /// after a `return` or `throw` in the same block, the branches an `if` on
+    /// a literal condition doesn't take, and literals whose value isn't used.
+    /// The branch it does take replaces the `if` where that keeps the value
+    /// of the block, which doesn't have a scope of its own.
+    pub fn EliminateDeadCode(self) -> Self {
+        DeadCodeEliminator.FoldProgram(self)
+    }
+
+    /// Folds constants and then eliminates dead code, which the folding can
+    /// turn conditions into literals for.
+    pub fn Optimize(self) -> Self {
+        self.FoldConstants().EliminateDeadCode()
+    }
+}
+
+struct ConstantFolder;
+
+impl Fold for ConstantFolder {
+    fn FoldExpression(&mut self, exp: ExpressionEnum) -> ExpressionEnum {
+        if takesCode(&exp) {
+            return exp;
+        }
+        match RebuildExpression(self, exp) {
+            ExpressionEnum::PrefixExpression(p) => foldPrefix(&p).unwrap_or_else(|| p.into()),
+            ExpressionEnum::InfixExpression(i) => foldInfix(&i).unwrap_or_else(|| i.into()),
+            ExpressionEnum::CallExpression(c) => foldStr(&c).unwrap_or_else(|| c.into()),
+            exp => exp,
+        }
+    }
+}
+
+/// Whether `exp` is a call of `quote` or `assert`, whose arguments are code
+/// the call can see: folding them would change the quote, or what a failed
+/// assertion shows.
+fn takesCode(exp: &ExpressionEnum) -> bool {
+    match exp {
+        ExpressionEnum::CallExpression(c) => matches!(
+            &*c.function,
+            ExpressionEnum::Identifier(i) if i.value == "quote" || i.value == "assert"
+        ),
+        _ => false,
+    }
+}
+
+/// A value a literal can stand for.
+enum Constant {
+    Int(i64),
+    Str(Rc<str>),
+    Bool(bool),
+}
+
+impl Constant {
+    fn Of(exp: &ExpressionEnum) -> Option<Self> {
+        match exp {
+            ExpressionEnum::IntegerLiteral(i) => Some(Self::Int(i.value)),
+            ExpressionEnum::StringLiteral(s) => Some(Self::Str(Rc::clone(&s.value))),
+            ExpressionEnum::Boolean(b) => Some(Self::Bool(b.value)),
+            _ => None,
+        }
+    }
+
+    /// The literal for the constant, at `position` and spanning the code it
+    /// stands for.
+    fn Literal(self, position: Position, span: Span) -> ExpressionEnum {
+        let (kind, literal) = match &self {
+            Self::Int(value) => (TokenKind::INT, value.to_string()),
+            Self::Str(_) => (TokenKind::STRING, String::new()),
+            Self::Bool(true) => (TokenKind::TRUE, "true".into()),
+            Self::Bool(false) => (TokenKind::FALSE, "false".into()),
+        };
+        let token = Token {
+            position,
+            ..Token::New(kind, literal)
+        };
+        match self {
+            Self::Int(value) => IntegerLiteral { token, span, value }.into(),
+            Self::Str(value) => StringLiteral { token, span, value }.into(),
+            Self::Bool(value) => Boolean { token, span, value }.into(),
+        }
+    }
+}
+
+fn foldPrefix(p: &PrefixExpression) -> Option<ExpressionEnum> {
+    let value = match (p.operator.as_str(), Constant::Of(&p.right)?) {
+        ("-", Constant::Int(value)) => Constant::Int(value.checked_neg()?),
+        ("!", Constant::Bool(value)) => Constant::Bool(!value),
+        _ => return None,
+    };
+    Some(value.Literal(p.Position(), p.span))
+}
+
+fn foldInfix(i: &InfixExpression) -> Option<ExpressionEnum> {
+    use Constant::*;
+
+    let value = match (
+        Constant::Of(&i.left)?,
+        i.operator.as_str(),
+        Constant::Of(&i.right)?,
+    ) {
+        (Int(a), "+", Int(b)) => Int(a.checked_add(b)?),
+        (Int(a), "-", Int(b)) => Int(a.checked_sub(b)?),
+        (Int(a), "*", Int(b)) => Int(a.checked_mul(b)?),
+        // both ways of rounding agree when nothing is negative
+        (Int(a), "/", Int(b)) if a >= 0 && b > 0 => Int(a / b),
+        (Int(a), "%", Int(b)) if a >= 0 && b > 0 => Int(a % b),
+        (Int(a), "<", Int(b)) => Bool(a < b),
+        (Int(a), ">", Int(b)) => Bool(a > b),
+        (Int(a), "==", Int(b)) => Bool(a == b),
+        (Int(a), "!=", Int(b)) => Bool(a != b),
+        (Bool(a), "==", Bool(b)) => Bool(a == b),
+        (Bool(a), "!=", Bool(b)) => Bool(a != b),
+        (Str(a), "==", Str(b)) => Bool(a == b),
+        (Str(a), "!=", Str(b)) => Bool(a != b),
+        (Str(a), "+", Str(b)) => Str(format!("{}{}", a, b).into()),
+        _ => return None,
+    };
+    Some(value.Literal(i.left.Position(), i.span))
+}
+
+/// A call of `str` on a constant, which is what an interpolation of one turns
+/// into.
+fn foldStr(c: &CallExpression) -> Option<ExpressionEnum> {
+    let argument = match (&*c.function, c.arguments.as_slice()) {
+        (ExpressionEnum::Identifier(f), [argument]) if f.value == "str" => argument,
+        _ => return None,
+    };
+    let value = match Constant::Of(argument)? {
+        Constant::Int(value) => value.to_string().into(),
+        Constant::Str(value) => value,
+        Constant::Bool(value) => value.to_string().into(),
+    };
+    Some(Constant::Str(value).Literal(c.Position(), c.span))
+}
+
+struct DeadCodeEliminator;
+
+impl Fold for DeadCodeEliminator {
+    fn FoldProgram(&mut self, program: Program) -> Program {
+        Program {
+            statements: self.live(program.statements),
+        }
+    }
+
+    fn FoldBlock(&mut self, block: BlockStatement) -> BlockStatement {
+        BlockStatement {
+            token: block.token,
+            span: block.span,
+            statements: self.live(block.statements),
+        }
+    }
+
+    fn FoldExpression(&mut self, exp: ExpressionEnum) -> ExpressionEnum {
+        if takesCode(&exp) {
+            return exp;
+        }
+        match RebuildExpression(self, exp) {
+            ExpressionEnum::IfExpression(i) => pruneIf(i),
+            exp => exp,
+        }
+    }
+}
+
+impl DeadCodeEliminator {
+    /// The statements of a block that can run, with the ifs that always take
+    /// their consequence replaced by it.
+    fn live(&mut self, statements: Vec<StatementEnum>) -> Vec<StatementEnum> {
+        let last = statements.len().saturating_sub(1);
+        let mut live = vec![];
+        for (n, stmt) in statements.into_iter().enumerate() {
+            match self.FoldStatement(stmt) {
+                // an empty block has no value, so it can only go if another
+                // statement comes after it
+                StatementEnum::Expression(ExpressionStatement {
+                    expression: ExpressionEnum::IfExpression(i),
+                    ..
+                }) if isLiteral(&i.condition, true)
+                    && i.alternative.is_none()
+                    && (n < last || !i.consequence.statements.is_empty()) =>
+                {
+                    live.extend(i.consequence.statements)
+                }
+                // like what is left of an `if (false)`, whose value would be
+                // thrown away
+                StatementEnum::Expression(e) if n < last && isConstant(&e.expression) => {}
+                stmt => live.push(stmt),
+            }
+            if let Some(StatementEnum::Return(_) | StatementEnum::Throw(_)) = live.last() {
+                break;
+            }
+        }
+        live
+    }
+}
+
+fn isConstant(exp: &ExpressionEnum) -> bool {
+    matches!(exp, ExpressionEnum::NullLiteral(_)) || Constant::Of(exp).is_some()
+}
+
+fn isLiteral(exp: &ExpressionEnum, value: bool) -> bool {
+    matches!(exp, ExpressionEnum::Boolean(b) if b.value == value)
+}
+
+/// `i` without the branch it never takes, if its condition is a literal.
+fn pruneIf(i: IfExpression) -> ExpressionEnum {
+    if isLiteral(&i.condition, true) {
+        IfExpression {
+            alternative: None,
+            ..i
+        }
+        .into()
+    } else if !isLiteral(&i.condition, false) {
+        i.into()
+    } else if let Some(alternative) = i.alternative {
+        IfExpression {
+            condition: Box::new(
+                Constant::Bool(true).Literal(i.condition.Position(), i.condition.Span().into()),
+            ),
+            consequence: alternative,
+            alternative: None,
+            ..i
+        }
+        .into()
+    } else {
+        NullLiteral {
+            token: Token {
+                position: i.token.position,
+                ..Token::New(TokenKind::NULL, "null")
+            },
+            span: i.span,
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests;
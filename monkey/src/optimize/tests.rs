@@ -0,0 +1,107 @@
+use crate::ast::Node;
+use crate::ast::Program;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+fn parse(input: &str) -> Program {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "parser errors: {:?}", p.errors);
+    program
+}
+
+#[test]
+fn FoldsConstants() {
+    let tests = vec![
+        ("2 * 3 + x", "(6 + x)"),
+        ("x + 2 * 3", "(x + 6)"),
+        ("-(1 + 2)", "-3"),
+        ("!(1 < 2)", "false"),
+        ("1 == 1 != false", "true"),
+        (r#""a" + "b" == "ab""#, "true"),
+        (r#""n = ${1 + 2}!""#, "n = 3!"),
+        ("7 / 2 + 7 % 2", "4"),
+        ("fn(x) { x * (2 + 2) }", "fn(x)(x * 4)"),
+        ("[1 + 1][0 + 0]", "([2][0])"),
+        // only the negation overflows
+        ("-(-9223372036854775807 - 1)", "(--9223372036854775808)"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(parse(input).FoldConstants().String(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn LeavesWhatCouldFail() {
+    // overflow, division by zero and type errors are left to happen at
+    // runtime, and so is rounding, which `Division` decides
+    let tests = vec![
+        ("9223372036854775807 + 1", "(9223372036854775807 + 1)"),
+        ("1 / 0", "(1 / 0)"),
+        ("-7 / 2", "(-7 / 2)"),
+        ("7 % -2", "(7 % -2)"),
+        ("1 + true", "(1 + true)"),
+        ("!1", "(!1)"),
+        (r#""a" < "b""#, "(a < b)"),
+        ("quote(1 + 2)", "quote((1 + 2))"),
+        ("assert(1 + 1 == 2)", "assert(((1 + 1) == 2))"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(parse(input).FoldConstants().String(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn EliminatesDeadCode() {
+    let tests = vec![
+        ("return 1; puts(2);", "return 1;"),
+        ("fn() { throw 1; 2 }", "fn()throw 1;"),
+        ("fn() { if (x) { return 1; 2 } 3 }", "fn()ifx return 1;3"),
+        ("if (true) { 1 } else { 2 }", "1"),
+        ("if (false) { 1 } else { 2 }", "2"),
+        ("if (false) { 1 }; 2", "2"),
+        ("if (false) { 1 }", "null"),
+        ("if (true) { return 1; }; 2", "return 1;"),
+        ("let x = if (false) { 1 } else { 2 };", "let x = iftrue 2;"),
+        // an empty consequence has no value, unlike every statement
+        ("if (true) { }", "iftrue "),
+        ("if (x) { 1 } else { 2 }", "ifx 1 else 2"),
+        ("1; \"a\"; x; 2", "x2"),
+        ("quote(if (true) { 1 })", "quote(iftrue 1)"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(
+            parse(input).EliminateDeadCode().String(),
+            expected,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn OptimizedProgramsEvaluateTheSame() {
+    use crate::environment::Environment;
+    use crate::evaluator::Eval;
+    use crate::object::Object;
+
+    let tests = vec![
+        "let f = fn(n) { if (1 < 2) { return n * (2 + 3); puts(n) } }; f(2)",
+        "let x = 1; if (2 > 3) { let x = 0; } else { let x = 2 * 2; }; x",
+        r#"let greet = fn(name) { "hi ${name}, ${1 + 1}" }; greet("you")"#,
+        "let f = fn() { if (true) { } }; f()",
+        "if (false) { 1 }",
+        "-7 / 2",
+    ];
+    for input in tests {
+        let evaluate = |program: Program| {
+            Eval(program.into(), &Environment::New()).map(|result| result.Inspect())
+        };
+        assert_eq!(
+            evaluate(parse(input).Optimize()),
+            evaluate(parse(input)),
+            "{}",
+            input
+        );
+    }
+}
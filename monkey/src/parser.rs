@@ -1,21 +1,57 @@
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::ast::ArrayLiteral;
 use crate::ast::BlockStatement;
 use crate::ast::Boolean;
 use crate::ast::CallExpression;
+use crate::ast::DeferStatement;
+use crate::ast::DestructurePattern;
+use crate::ast::DestructureStatement;
 use crate::ast::ExpressionEnum;
 use crate::ast::ExpressionStatement;
 use crate::ast::FunctionLiteral;
+use crate::ast::HashLiteral;
 use crate::ast::Identifier;
 use crate::ast::IfExpression;
+use crate::ast::ImportExpression;
+use crate::ast::IndexExpression;
 use crate::ast::InfixExpression;
 use crate::ast::IntegerLiteral;
+use crate::ast::KeywordArgument;
 use crate::ast::LetStatement;
+use crate::ast::MacroLiteral;
+use crate::ast::MatchArm;
+use crate::ast::MatchExpression;
+use crate::ast::MatchPattern;
+use crate::ast::Node;
+use crate::ast::NullLiteral;
 use crate::ast::PrefixExpression;
 use crate::ast::Program;
 use crate::ast::ReturnStatement;
+use crate::ast::SliceExpression;
+use crate::ast::Span;
+use crate::ast::SpreadExpression;
 use crate::ast::StatementEnum;
+use crate::ast::StringLiteral;
+use crate::ast::ThrowStatement;
+use crate::ast::TryExpression;
+use crate::ast::UpdateExpression;
+use crate::diagnostics::Diagnostic;
+use crate::edition::Edition;
+use crate::lexer::IsUnterminatedString;
 use crate::lexer::Lexer;
+use crate::lexer::StringSegment;
+use crate::lexer::StringSegments;
+use crate::token::Position;
 use crate::token::Token;
 use crate::token::TokenKind;
+use crate::warning::Warning;
+use crate::warning::WarningCode;
+use crate::warning::Warnings;
 
 #[cfg(test)]
 const TRACE: bool = true;
@@ -24,7 +60,7 @@ const TRACE: bool = false;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[allow(clippy::upper_case_acronyms)]
-enum Precedence {
+pub(crate) enum Precedence {
     _ZERO,
     LOWEST,
     EQUALS,
@@ -32,7 +68,17 @@ enum Precedence {
     SUM,
     PRODUCT,
     PREFIX,
+    POSTFIX,
     CALL,
+    INDEX,
+}
+
+impl Precedence {
+    /// How tightly the operator `kind` binds its operands when it comes
+    /// after one, `LOWEST` if it doesn't.
+    pub(crate) fn Of(kind: TokenKind) -> Self {
+        kind.binary_precedence()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -40,11 +86,22 @@ enum Precedence {
 enum PrefixDispatcher {
     PARSE_IDENTIFIER,
     PARSE_INTEGER_LITERAL,
+    PARSE_STRING_LITERAL,
+    PARSE_RAW_STRING_LITERAL,
     PARSE_PREFIX_EXPRESSION,
+    PARSE_PREFIX_UPDATE,
     PARSE_BOOLEAN,
+    PARSE_NULL,
     PARSE_GROUPED_EXPRESSION,
     PARSE_IF_EXPRESSION,
+    PARSE_MATCH_EXPRESSION,
+    PARSE_TRY_EXPRESSION,
+    PARSE_IMPORT_EXPRESSION,
     PARSE_FUNCTION_LITERAL,
+    PARSE_MACRO_LITERAL,
+    PARSE_ARROW_FUNCTION,
+    PARSE_ARRAY_LITERAL,
+    PARSE_HASH_LITERAL,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -52,6 +109,8 @@ enum PrefixDispatcher {
 enum InfixDispatcher {
     PARSE_INFIX_EXPRESSION,
     PARSE_CALL_EXPRESSION,
+    PARSE_POSTFIX_UPDATE,
+    PARSE_INDEX_EXPRESSION,
 }
 
 trait TokenTypeExt {
@@ -65,11 +124,22 @@ impl TokenTypeExt for TokenKind {
         match self {
             Self::IDENT => Some(PrefixDispatcher::PARSE_IDENTIFIER),
             Self::INT => Some(PrefixDispatcher::PARSE_INTEGER_LITERAL),
+            Self::STRING => Some(PrefixDispatcher::PARSE_STRING_LITERAL),
+            Self::RAW_STRING => Some(PrefixDispatcher::PARSE_RAW_STRING_LITERAL),
             Self::BANG | Self::MINUS => Some(PrefixDispatcher::PARSE_PREFIX_EXPRESSION),
+            Self::INCREMENT | Self::DECREMENT => Some(PrefixDispatcher::PARSE_PREFIX_UPDATE),
             Self::TRUE | Self::FALSE => Some(PrefixDispatcher::PARSE_BOOLEAN),
+            Self::NULL => Some(PrefixDispatcher::PARSE_NULL),
             Self::LPAREN => Some(PrefixDispatcher::PARSE_GROUPED_EXPRESSION),
             Self::IF => Some(PrefixDispatcher::PARSE_IF_EXPRESSION),
+            Self::MATCH => Some(PrefixDispatcher::PARSE_MATCH_EXPRESSION),
+            Self::TRY => Some(PrefixDispatcher::PARSE_TRY_EXPRESSION),
+            Self::IMPORT => Some(PrefixDispatcher::PARSE_IMPORT_EXPRESSION),
             Self::FUNCTION => Some(PrefixDispatcher::PARSE_FUNCTION_LITERAL),
+            Self::MACRO => Some(PrefixDispatcher::PARSE_MACRO_LITERAL),
+            Self::PIPE => Some(PrefixDispatcher::PARSE_ARROW_FUNCTION),
+            Self::LBRACKET => Some(PrefixDispatcher::PARSE_ARRAY_LITERAL),
+            Self::LBRACE => Some(PrefixDispatcher::PARSE_HASH_LITERAL),
             _ => None,
         }
     }
@@ -80,11 +150,14 @@ impl TokenTypeExt for TokenKind {
             | Self::MINUS
             | Self::STAR
             | Self::SLASH
+            | Self::PERCENT
             | Self::LESS
             | Self::GREATER
             | Self::EQ
             | Self::NOT_EQ => Some(InfixDispatcher::PARSE_INFIX_EXPRESSION),
             Self::LPAREN => Some(InfixDispatcher::PARSE_CALL_EXPRESSION),
+            Self::INCREMENT | Self::DECREMENT => Some(InfixDispatcher::PARSE_POSTFIX_UPDATE),
+            Self::LBRACKET => Some(InfixDispatcher::PARSE_INDEX_EXPRESSION),
             _ => None,
         }
     }
@@ -99,30 +172,85 @@ impl TokenTypeExt for TokenKind {
             Self::MINUS => Precedence::SUM,
             Self::STAR => Precedence::PRODUCT,
             Self::SLASH => Precedence::PRODUCT,
+            Self::PERCENT => Precedence::PRODUCT,
             Self::LPAREN => Precedence::CALL,
+            Self::LBRACKET => Precedence::INDEX,
+            Self::INCREMENT => Precedence::POSTFIX,
+            Self::DECREMENT => Precedence::POSTFIX,
             _ => Precedence::LOWEST,
         }
     }
 }
 
-pub struct Parser<'src> {
-    lexer: Lexer<'src>,
+/// A syntax error, and where in the source the parser found it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte range of the source the error is about.
+    pub span: Range<usize>,
+    /// What the parser would have accepted there, if it wanted one thing.
+    pub expected: Option<String>,
+    /// What was there instead.
+    pub found: String,
+    pub message: String,
+}
+
+impl ParseError {
+    /// The error with the line of `source` it is on, and the offending part
+    /// of that line underlined.
+    pub fn Render(&self, source: &str) -> String {
+        Diagnostic::from(self).Render(source, false)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the tokens a `Tokens` iterator yields, usually a `Lexer`. If it
+/// ends without an EOF token, one is made up after its last token.
+pub struct Parser<Tokens> {
+    tokens: Tokens,
+    /// The tokens after `peekToken` that have been looked at already.
+    ahead: VecDeque<Token>,
+    /// Where the last token taken from `tokens` ends.
+    end: Position,
 
     curToken: Token,
     peekToken: Token,
+    /// How many `{` are open, counting the current token.
+    braces: usize,
 
-    pub errors: Vec<String>,
+    pub errors: Vec<ParseError>,
+    pub warnings: Warnings,
     indentLevel: usize,
+    edition: Edition,
+    /// The values of the string literals parsed so far, so repeated
+    /// literals can share them.
+    strings: HashSet<Rc<str>>,
 }
 
-impl<'src> Parser<'src> {
-    pub fn New(lexer: Lexer<'src>) -> Self {
+impl<Tokens: Iterator<Item = Token>> Parser<Tokens> {
+    pub fn New(tokens: Tokens) -> Self {
+        Self::WithEdition(tokens, Edition::default())
+    }
+
+    pub fn WithEdition(tokens: Tokens, edition: Edition) -> Self {
         let mut p = Self {
-            lexer,
+            tokens,
+            ahead: VecDeque::new(),
+            end: Position::default(),
             curToken: Token::default(),
             peekToken: Token::default(),
+            braces: 0,
             errors: Vec::default(),
+            warnings: Warnings::default(),
             indentLevel: 0,
+            edition,
+            strings: HashSet::default(),
         };
 
         p.nextToken();
@@ -136,9 +264,9 @@ impl<'src> Parser<'src> {
         let mut statements = vec![];
 
         while self.curToken.kind != TokenKind::EOF {
-            let stmt = self.parseStatement();
-            if let Some(stmt) = stmt {
-                statements.push(stmt);
+            match self.parseStatement() {
+                Some(stmt) => statements.push(stmt),
+                None => self.synchronize(0),
             }
             self.nextToken();
         }
@@ -178,6 +306,8 @@ impl<'src> Parser<'src> {
         let out = match self.curToken.kind {
             TokenKind::LET => self.parseLetStatement(),
             TokenKind::RETURN => self.parseReturnStatement().map(Into::into),
+            TokenKind::DEFER => self.parseDeferStatement().map(Into::into),
+            TokenKind::THROW => self.parseThrowStatement().map(Into::into),
             _ => self.parseExpressionStatement().map(Into::into),
         };
 
@@ -189,14 +319,18 @@ impl<'src> Parser<'src> {
         self.trace_begin("let statement");
         let token = self.curToken.clone();
 
+        if self.peekTokenIs(TokenKind::LBRACKET) || self.peekTokenIs(TokenKind::LBRACE) {
+            self.nextToken();
+            let stmt = self.parseDestructureStatement(token);
+            self.trace_end("let statement");
+            return stmt.map(Into::into);
+        }
+
         if !self.expectPeek(TokenKind::IDENT) {
             return None;
         }
 
-        let name = Identifier {
-            token: self.curToken.clone(),
-            value: self.curToken.literal.clone(),
-        };
+        let name = self.parseIdentifier();
 
         if !self.expectPeek(TokenKind::ASSIGN) {
             return None;
@@ -206,12 +340,62 @@ impl<'src> Parser<'src> {
 
         let value = self.parseExpression(Precedence::LOWEST)?;
 
-        if self.peekTokenIs(TokenKind::SEMICOLON) {
+        self.endStatement();
+
+        self.trace_end("let statement");
+        Some(
+            LetStatement {
+                span: self.spanFrom(token.position.offset),
+                token,
+                name,
+                value,
+            }
+            .into(),
+        )
+    }
+
+    /// Parses the rest of `let [a, b] = value;` or `let {a, b} = value;`, from
+    /// the opening bracket or brace.
+    fn parseDestructureStatement(&mut self, token: Token) -> Option<DestructureStatement> {
+        self.trace_begin("destructure statement");
+        let array = self.curTokenIs(TokenKind::LBRACKET);
+        let end = if array {
+            TokenKind::RBRACKET
+        } else {
+            TokenKind::RBRACE
+        };
+
+        let mut names = vec![];
+        loop {
+            if !self.expectPeek(TokenKind::IDENT) {
+                return None;
+            }
+            names.push(self.parseIdentifier());
+            if !self.peekTokenIs(TokenKind::COMMA) {
+                break;
+            }
             self.nextToken();
         }
+        if !self.expectPeek(end) || !self.expectPeek(TokenKind::ASSIGN) {
+            return None;
+        }
+        self.nextToken();
 
-        self.trace_end("let statement");
-        Some(LetStatement { token, name, value }.into())
+        let value = self.parseExpression(Precedence::LOWEST)?;
+
+        self.endStatement();
+
+        self.trace_end("destructure statement");
+        Some(DestructureStatement {
+            span: self.spanFrom(token.position.offset),
+            token,
+            pattern: if array {
+                DestructurePattern::Array(names)
+            } else {
+                DestructurePattern::Hash(names)
+            },
+            value,
+        })
     }
 
     fn parseReturnStatement(&mut self) -> Option<ReturnStatement> {
@@ -221,12 +405,48 @@ impl<'src> Parser<'src> {
 
         let returnValue = self.parseExpression(Precedence::LOWEST)?;
 
-        if self.peekTokenIs(TokenKind::SEMICOLON) {
-            self.nextToken();
-        }
+        self.endStatement();
 
         self.trace_end("return statement");
-        Some(ReturnStatement { token, returnValue })
+        Some(ReturnStatement {
+            span: self.spanFrom(token.position.offset),
+            token,
+            returnValue,
+        })
+    }
+
+    fn parseDeferStatement(&mut self) -> Option<DeferStatement> {
+        self.trace_begin("defer statement");
+        let token = self.curToken.clone();
+        self.nextToken();
+
+        let expression = self.parseExpression(Precedence::LOWEST)?;
+
+        self.endStatement();
+
+        self.trace_end("defer statement");
+        Some(DeferStatement {
+            span: self.spanFrom(token.position.offset),
+            token,
+            expression,
+        })
+    }
+
+    fn parseThrowStatement(&mut self) -> Option<ThrowStatement> {
+        self.trace_begin("throw statement");
+        let token = self.curToken.clone();
+        self.nextToken();
+
+        let value = self.parseExpression(Precedence::LOWEST)?;
+
+        self.endStatement();
+
+        self.trace_end("throw statement");
+        Some(ThrowStatement {
+            span: self.spanFrom(token.position.offset),
+            token,
+            value,
+        })
     }
 
     fn parseExpressionStatement(&mut self) -> Option<ExpressionStatement> {
@@ -235,25 +455,70 @@ impl<'src> Parser<'src> {
 
         let expression = self.parseExpression(Precedence::LOWEST)?;
 
+        self.endStatement();
+
+        self.trace_end("expression statement");
+        Some(ExpressionStatement {
+            span: self.spanFrom(token.position.offset),
+            token,
+            expression,
+        })
+    }
+
+    /// Consumes the semicolon ending a statement, if there is one. Editions
+    /// that require semicolons only allow leaving it out after a block and
+    /// before the end of a block or of the program.
+    fn endStatement(&mut self) {
         if self.peekTokenIs(TokenKind::SEMICOLON) {
             self.nextToken();
+        } else if !self.curTokenIs(TokenKind::RBRACE)
+            && !self.peekTokenIs(TokenKind::RBRACE)
+            && !self.peekTokenIs(TokenKind::EOF)
+        {
+            if self.edition.RequiresSemicolons() {
+                self.errors.push(ParseError {
+                    span: self.peekToken.Span(),
+                    expected: Some(format!("{:?}", TokenKind::SEMICOLON)),
+                    found: format!("{:?}", self.peekToken.kind),
+                    message: format!(
+                        "expected SEMICOLON after statement, got {:?} instead",
+                        self.peekToken.kind
+                    ),
+                });
+            } else {
+                self.warnings.Warn(Warning {
+                    code: WarningCode::MissingSemicolon,
+                    message: "statement without a semicolon; edition 2 requires one".into(),
+                    span: Some(self.curToken.End().offset..self.curToken.End().offset),
+                });
+            }
         }
-
-        self.trace_end("expression statement");
-        Some(ExpressionStatement { token, expression })
     }
 
     fn dispatchPrefix(&mut self, dispatcher: PrefixDispatcher) -> Option<ExpressionEnum> {
         match dispatcher {
             PrefixDispatcher::PARSE_IDENTIFIER => Some(self.parseIdentifier().into()),
             PrefixDispatcher::PARSE_INTEGER_LITERAL => self.parseIntegerLiteral().map(Into::into),
+            PrefixDispatcher::PARSE_STRING_LITERAL => self.parseStringLiteral(),
+            PrefixDispatcher::PARSE_RAW_STRING_LITERAL => Some(self.parseRawStringLiteral().into()),
             PrefixDispatcher::PARSE_PREFIX_EXPRESSION => {
                 self.parsePrefixExpression().map(Into::into)
             }
+            PrefixDispatcher::PARSE_PREFIX_UPDATE => self.parsePrefixUpdate().map(Into::into),
             PrefixDispatcher::PARSE_BOOLEAN => Some(self.parseBoolean().into()),
+            PrefixDispatcher::PARSE_NULL => Some(self.parseNull().into()),
             PrefixDispatcher::PARSE_GROUPED_EXPRESSION => self.parseGroupedExpression(),
             PrefixDispatcher::PARSE_IF_EXPRESSION => self.parseIfExpression().map(Into::into),
+            PrefixDispatcher::PARSE_MATCH_EXPRESSION => self.parseMatchExpression().map(Into::into),
+            PrefixDispatcher::PARSE_TRY_EXPRESSION => self.parseTryExpression().map(Into::into),
+            PrefixDispatcher::PARSE_IMPORT_EXPRESSION => {
+                self.parseImportExpression().map(Into::into)
+            }
             PrefixDispatcher::PARSE_FUNCTION_LITERAL => self.parseFunctionLiteral().map(Into::into),
+            PrefixDispatcher::PARSE_MACRO_LITERAL => self.parseMacroLiteral().map(Into::into),
+            PrefixDispatcher::PARSE_ARROW_FUNCTION => self.parseArrowFunction().map(Into::into),
+            PrefixDispatcher::PARSE_ARRAY_LITERAL => self.parseArrayLiteral().map(Into::into),
+            PrefixDispatcher::PARSE_HASH_LITERAL => self.parseHashLiteral().map(Into::into),
         }
     }
 
@@ -264,7 +529,7 @@ impl<'src> Parser<'src> {
             return None;
         }
 
-        let parameters = self.parseFunctionParameters()?;
+        let parameters = self.parseFunctionParameters(TokenKind::RPAREN)?;
         if !self.expectPeek(TokenKind::LBRACE) {
             return None;
         }
@@ -272,36 +537,87 @@ impl<'src> Parser<'src> {
         let body = self.parseBlockStatement();
         self.trace_end("function literal");
         Some(FunctionLiteral {
+            span: self.spanFrom(token.position.offset),
+            token,
+            parameters,
+            body: Box::new(body),
+        })
+    }
+
+    fn parseMacroLiteral(&mut self) -> Option<MacroLiteral> {
+        self.trace_begin("macro literal");
+        let token = self.curToken.clone();
+        if !self.expectPeek(TokenKind::LPAREN) {
+            return None;
+        }
+
+        let parameters = self.parseFunctionParameters(TokenKind::RPAREN)?;
+        if !self.expectPeek(TokenKind::LBRACE) {
+            return None;
+        }
+
+        let body = self.parseBlockStatement();
+        self.trace_end("macro literal");
+        Some(MacroLiteral {
+            span: self.spanFrom(token.position.offset),
             token,
             parameters,
             body: Box::new(body),
         })
     }
 
-    fn parseFunctionParameters(&mut self) -> Option<Vec<Identifier>> {
+    /// Parses `|params| body`, shorthand for `fn(params) { body }` where the
+    /// body is a single expression.
+    fn parseArrowFunction(&mut self) -> Option<FunctionLiteral> {
+        self.trace_begin("arrow function");
+        let token = self.curToken.clone();
+        let parameters = self.parseFunctionParameters(TokenKind::PIPE)?;
+        self.nextToken();
+        let expression = self.parseExpression(Precedence::LOWEST)?;
+        self.trace_end("arrow function");
+        // the block and statement wrapping the body span just its code
+        let body: Span = expression.Span().into();
+        Some(FunctionLiteral {
+            token: Token {
+                kind: TokenKind::FUNCTION,
+                literal: "fn".into(),
+                position: token.position,
+            },
+            span: self.spanFrom(token.position.offset),
+            parameters,
+            body: Box::new(BlockStatement {
+                token: token.clone(),
+                span: body,
+                statements: vec![ExpressionStatement {
+                    token,
+                    span: body,
+                    expression,
+                }
+                .into()],
+            }),
+        })
+    }
+
+    /// Parses the parameters after the opening token of a parameter list, up
+    /// to and including `end`.
+    fn parseFunctionParameters(&mut self, end: TokenKind) -> Option<Vec<Identifier>> {
         self.trace_begin("function parameters");
         let mut identifiers = vec![];
-        if self.peekTokenIs(TokenKind::RPAREN) {
+        if self.peekTokenIs(end) {
             self.nextToken();
             self.trace_end("function parameters");
             return Some(identifiers);
         }
 
         self.nextToken();
-        identifiers.push(Identifier {
-            token: self.curToken.clone(),
-            value: self.curToken.literal.clone(),
-        });
+        identifiers.push(self.parseIdentifier());
         while self.peekTokenIs(TokenKind::COMMA) {
             self.nextToken();
             self.nextToken();
-            identifiers.push(Identifier {
-                token: self.curToken.clone(),
-                value: self.curToken.literal.clone(),
-            });
+            identifiers.push(self.parseIdentifier());
         }
 
-        if !self.expectPeek(TokenKind::RPAREN) {
+        if !self.expectPeek(end) {
             return None;
         }
 
@@ -336,6 +652,7 @@ impl<'src> Parser<'src> {
 
         self.trace_end("if expression");
         Some(IfExpression {
+            span: self.spanFrom(token.position.offset),
             token,
             condition: Box::new(condition),
             consequence: Box::new(consequence),
@@ -343,31 +660,170 @@ impl<'src> Parser<'src> {
         })
     }
 
+    fn parseTryExpression(&mut self) -> Option<TryExpression> {
+        self.trace_begin("try expression");
+        let token = self.curToken.clone();
+        if !self.expectPeek(TokenKind::LBRACE) {
+            return None;
+        }
+        let body = self.parseBlockStatement();
+        if !self.expectPeek(TokenKind::CATCH)
+            || !self.expectPeek(TokenKind::LPAREN)
+            || !self.expectPeek(TokenKind::IDENT)
+        {
+            return None;
+        }
+        let name = self.parseIdentifier();
+        if !self.expectPeek(TokenKind::RPAREN) || !self.expectPeek(TokenKind::LBRACE) {
+            return None;
+        }
+        let handler = self.parseBlockStatement();
+
+        self.trace_end("try expression");
+        Some(TryExpression {
+            span: self.spanFrom(token.position.offset),
+            token,
+            body: Box::new(body),
+            name,
+            handler: Box::new(handler),
+        })
+    }
+
+    /// Parses `import path`. Nothing binds tighter than the path, so
+    /// `import "a.mk"["f"]` indexes the module, and a computed path needs
+    /// parentheses.
+    fn parseImportExpression(&mut self) -> Option<ImportExpression> {
+        self.trace_begin("import expression");
+        let token = self.curToken.clone();
+        self.nextToken();
+        let path = self.parseExpression(Precedence::INDEX)?;
+        self.trace_end("import expression");
+        Some(ImportExpression {
+            span: self.spanFrom(token.position.offset),
+            token,
+            path: Box::new(path),
+        })
+    }
+
+    fn parseMatchExpression(&mut self) -> Option<MatchExpression> {
+        self.trace_begin("match expression");
+        let token = self.curToken.clone();
+        self.nextToken();
+        let subject = self.parseExpression(Precedence::LOWEST)?;
+        if !self.expectPeek(TokenKind::LBRACE) {
+            return None;
+        }
+
+        let mut arms = vec![];
+        while !self.peekTokenIs(TokenKind::RBRACE) {
+            self.nextToken();
+            arms.push(self.parseMatchArm()?);
+            if !self.peekTokenIs(TokenKind::RBRACE) && !self.expectPeek(TokenKind::COMMA) {
+                return None;
+            }
+        }
+        self.nextToken();
+
+        self.trace_end("match expression");
+        Some(MatchExpression {
+            span: self.spanFrom(token.position.offset),
+            token,
+            subject: Box::new(subject),
+            arms,
+        })
+    }
+
+    fn parseMatchArm(&mut self) -> Option<MatchArm> {
+        self.trace_begin("match arm");
+        let pattern = self.parseMatchPattern()?;
+        if !self.expectPeek(TokenKind::FAT_ARROW) {
+            return None;
+        }
+        self.nextToken();
+        let body: StatementEnum = if self.curTokenIs(TokenKind::LBRACE) {
+            self.parseBlockStatement().into()
+        } else {
+            let token = self.curToken.clone();
+            let expression = self.parseExpression(Precedence::LOWEST)?;
+            ExpressionStatement {
+                span: self.spanFrom(token.position.offset),
+                token,
+                expression,
+            }
+            .into()
+        };
+        self.trace_end("match arm");
+        Some(MatchArm {
+            pattern,
+            body: Box::new(body),
+        })
+    }
+
+    fn parseMatchPattern(&mut self) -> Option<MatchPattern> {
+        self.trace_begin("match pattern");
+        let pattern = match self.curToken.kind {
+            TokenKind::IDENT if self.curToken.literal == "_" => {
+                MatchPattern::Wildcard(self.curToken.clone())
+            }
+            TokenKind::INT
+            | TokenKind::STRING
+            | TokenKind::RAW_STRING
+            | TokenKind::TRUE
+            | TokenKind::FALSE
+            | TokenKind::NULL => MatchPattern::Literal(self.parseExpression(Precedence::PREFIX)?),
+            TokenKind::MINUS if self.peekTokenIs(TokenKind::INT) => {
+                MatchPattern::Literal(self.parseExpression(Precedence::PREFIX)?)
+            }
+            kind => {
+                self.errors.push(ParseError {
+                    span: self.curToken.Span(),
+                    expected: Some("a literal or _".into()),
+                    found: format!("{:?}", kind),
+                    message: format!("expected a literal or _ as match pattern, got {:?}", kind),
+                });
+                return None;
+            }
+        };
+        self.trace_end("match pattern");
+        Some(pattern)
+    }
+
     fn parseBlockStatement(&mut self) -> BlockStatement {
         self.trace_begin("block statement");
         let token = self.curToken.clone();
         let mut statements = vec![];
+        let braces = self.braces;
         self.nextToken();
         while !self.curTokenIs(TokenKind::RBRACE) && !self.curTokenIs(TokenKind::EOF) {
-            let stmt = self.parseStatement();
-            if let Some(stmt) = stmt {
-                statements.push(stmt);
+            match self.parseStatement() {
+                Some(stmt) => statements.push(stmt),
+                None => {
+                    self.synchronize(braces);
+                    // the statement ran into the end of the block
+                    if self.braces < braces {
+                        break;
+                    }
+                }
             }
             self.nextToken();
         }
         self.trace_end("block statement");
-        BlockStatement { token, statements }
+        BlockStatement {
+            span: self.spanFrom(token.position.offset),
+            token,
+            statements,
+        }
     }
 
     fn parseGroupedExpression(&mut self) -> Option<ExpressionEnum> {
         self.trace_begin("grouped expression");
         self.nextToken();
-        let exp = self.parseExpression(Precedence::LOWEST);
+        let exp = self.parseExpression(Precedence::LOWEST)?;
         if !self.expectPeek(TokenKind::RPAREN) {
             return None;
         }
         self.trace_end("grouped expression");
-        exp
+        Some(exp)
     }
 
     fn parseBoolean(&mut self) -> Boolean {
@@ -375,22 +831,41 @@ impl<'src> Parser<'src> {
         self.trace_end("boolean literal");
         Boolean {
             token: self.curToken.clone(),
+            span: self.curToken.Span().into(),
             value: self.curTokenIs(TokenKind::TRUE),
         }
     }
 
+    fn parseNull(&mut self) -> NullLiteral {
+        self.trace_begin("null literal");
+        self.trace_end("null literal");
+        NullLiteral {
+            token: self.curToken.clone(),
+            span: self.curToken.Span().into(),
+        }
+    }
+
+    /// Parses the rest of an expression whose `left` operand starts at the
+    /// offset `from`, which is at the parenthesis of a grouped operand.
     fn dispatchInfix(
         &mut self,
         left: ExpressionEnum,
+        from: usize,
         dispatcher: InfixDispatcher,
     ) -> Option<ExpressionEnum> {
         match dispatcher {
-            InfixDispatcher::PARSE_INFIX_EXPRESSION => self.parseInfixExpression(left),
-            InfixDispatcher::PARSE_CALL_EXPRESSION => self.parseCallExpression(left),
+            InfixDispatcher::PARSE_INFIX_EXPRESSION => self.parseInfixExpression(left, from),
+            InfixDispatcher::PARSE_CALL_EXPRESSION => self.parseCallExpression(left, from),
+            InfixDispatcher::PARSE_POSTFIX_UPDATE => self.parsePostfixUpdate(left, from),
+            InfixDispatcher::PARSE_INDEX_EXPRESSION => self.parseIndexExpression(left, from),
         }
     }
 
-    fn parseCallExpression(&mut self, function: ExpressionEnum) -> Option<ExpressionEnum> {
+    fn parseCallExpression(
+        &mut self,
+        function: ExpressionEnum,
+        from: usize,
+    ) -> Option<ExpressionEnum> {
         self.trace_begin("call expression");
         let token = self.curToken.clone();
         let arguments = self.parseCallArguments()?;
@@ -398,6 +873,7 @@ impl<'src> Parser<'src> {
         Some(
             CallExpression {
                 token,
+                span: self.spanFrom(from),
                 function: Box::new(function),
                 arguments,
             }
@@ -405,33 +881,204 @@ impl<'src> Parser<'src> {
         )
     }
 
+    /// Parses the arguments of a call. Keyword arguments come after all the
+    /// positional ones and name each parameter at most once.
     fn parseCallArguments(&mut self) -> Option<Vec<ExpressionEnum>> {
         self.trace_begin("call arguments");
+        let args = self.parseExpressionList(TokenKind::RPAREN, true)?;
+        let mut named = std::collections::HashSet::new();
+        for arg in &args {
+            match arg {
+                ExpressionEnum::KeywordArgument(k) if !named.insert(&k.name.value) => {
+                    self.errors.push(ParseError {
+                        span: k.name.token.Span(),
+                        expected: None,
+                        found: k.name.value.clone(),
+                        message: format!("keyword argument repeated: {}", k.name.value),
+                    });
+                    return None;
+                }
+                ExpressionEnum::KeywordArgument(_) => {}
+                other if !named.is_empty() => {
+                    self.errors.push(ParseError {
+                        span: other.Span(),
+                        expected: Some("a keyword argument".into()),
+                        found: other.String(),
+                        message: format!("positional argument after keyword arguments: {}", other),
+                    });
+                    return None;
+                }
+                _ => {}
+            }
+        }
+        self.trace_end("call arguments");
+        Some(args)
+    }
+
+    /// Parses comma-separated expressions up to and including `end`, allowing
+    /// keyword arguments if `keywords` is set.
+    fn parseExpressionList(
+        &mut self,
+        end: TokenKind,
+        keywords: bool,
+    ) -> Option<Vec<ExpressionEnum>> {
+        self.trace_begin("expression list");
         let mut args = vec![];
 
-        if self.peekTokenIs(TokenKind::RPAREN) {
+        if self.peekTokenIs(end) {
             self.nextToken();
-            self.trace_end("call arguments");
+            self.trace_end("expression list");
             return Some(args);
         }
 
         self.nextToken();
-        args.push(self.parseExpression(Precedence::LOWEST)?);
+        args.push(self.parseListElement(keywords)?);
 
         while self.peekTokenIs(TokenKind::COMMA) {
             self.nextToken();
             self.nextToken();
-            args.push(self.parseExpression(Precedence::LOWEST)?);
+            args.push(self.parseListElement(keywords)?);
         }
 
-        if !self.expectPeek(TokenKind::RPAREN) {
+        if !self.expectPeek(end) {
             return None;
         }
 
-        self.trace_end("call arguments");
+        self.trace_end("expression list");
         Some(args)
     }
 
+    /// An element of an expression list, which may be spread with `...` or,
+    /// if `keywords` is set, be a keyword argument.
+    fn parseListElement(&mut self, keywords: bool) -> Option<ExpressionEnum> {
+        if keywords && self.curTokenIs(TokenKind::IDENT) && self.peekTokenIs(TokenKind::COLON) {
+            return self.parseKeywordArgument().map(Into::into);
+        }
+        if !self.curTokenIs(TokenKind::ELLIPSIS) {
+            return self.parseExpression(Precedence::LOWEST);
+        }
+        self.trace_begin("spread expression");
+        let token = self.curToken.clone();
+        self.nextToken();
+        let value = self.parseExpression(Precedence::LOWEST)?;
+        self.trace_end("spread expression");
+        Some(
+            SpreadExpression {
+                span: self.spanFrom(token.position.offset),
+                token,
+                value: Box::new(value),
+            }
+            .into(),
+        )
+    }
+
+    fn parseKeywordArgument(&mut self) -> Option<KeywordArgument> {
+        self.trace_begin("keyword argument");
+        let token = self.curToken.clone();
+        let name = self.parseIdentifier();
+        self.nextToken();
+        self.nextToken();
+        let value = self.parseExpression(Precedence::LOWEST)?;
+        self.trace_end("keyword argument");
+        Some(KeywordArgument {
+            span: self.spanFrom(token.position.offset),
+            token,
+            name,
+            value: Box::new(value),
+        })
+    }
+
+    fn parseArrayLiteral(&mut self) -> Option<ArrayLiteral> {
+        self.trace_begin("array literal");
+        let token = self.curToken.clone();
+        let elements = self.parseExpressionList(TokenKind::RBRACKET, false)?;
+        self.trace_end("array literal");
+        Some(ArrayLiteral {
+            span: self.spanFrom(token.position.offset),
+            token,
+            elements,
+        })
+    }
+
+    fn parseHashLiteral(&mut self) -> Option<HashLiteral> {
+        self.trace_begin("hash literal");
+        let token = self.curToken.clone();
+        let mut pairs = vec![];
+        while !self.peekTokenIs(TokenKind::RBRACE) {
+            self.nextToken();
+            let key = self.parseExpression(Precedence::LOWEST)?;
+            if !self.expectPeek(TokenKind::COLON) {
+                return None;
+            }
+            self.nextToken();
+            let value = self.parseExpression(Precedence::LOWEST)?;
+            pairs.push((key, value));
+            if !self.peekTokenIs(TokenKind::RBRACE) && !self.expectPeek(TokenKind::COMMA) {
+                return None;
+            }
+        }
+        self.nextToken();
+        self.trace_end("hash literal");
+        Some(HashLiteral {
+            span: self.spanFrom(token.position.offset),
+            token,
+            pairs,
+        })
+    }
+
+    /// Parses `left[index]`, or a slice `left[start:end]` with optional bounds.
+    fn parseIndexExpression(
+        &mut self,
+        left: ExpressionEnum,
+        from: usize,
+    ) -> Option<ExpressionEnum> {
+        self.trace_begin("index expression");
+        let token = self.curToken.clone();
+        let left = Box::new(left);
+
+        let mut start = None;
+        if !self.peekTokenIs(TokenKind::COLON) {
+            self.nextToken();
+            start = Some(Box::new(self.parseExpression(Precedence::LOWEST)?));
+        }
+
+        // the end bound of a slice, if it is one
+        let mut slice = None;
+        if self.peekTokenIs(TokenKind::COLON) {
+            self.nextToken();
+            let mut end = None;
+            if !self.peekTokenIs(TokenKind::RBRACKET) {
+                self.nextToken();
+                end = Some(Box::new(self.parseExpression(Precedence::LOWEST)?));
+            }
+            slice = Some(end);
+        }
+        if !self.expectPeek(TokenKind::RBRACKET) {
+            return None;
+        }
+
+        let span = self.spanFrom(from);
+        let exp = match slice {
+            Some(end) => SliceExpression {
+                token,
+                span,
+                left,
+                start,
+                end,
+            }
+            .into(),
+            None => IndexExpression {
+                token,
+                span,
+                left,
+                index: start?,
+            }
+            .into(),
+        };
+        self.trace_end("index expression");
+        Some(exp)
+    }
+
     fn parsePrefixExpression(&mut self) -> Option<PrefixExpression> {
         self.trace_begin("prefix expression");
         let token = self.curToken.clone();
@@ -440,17 +1087,71 @@ impl<'src> Parser<'src> {
         let right = self.parseExpression(Precedence::PREFIX)?;
         self.trace_end("prefix expression");
         Some(PrefixExpression {
+            span: self.spanFrom(token.position.offset),
             token,
             operator,
             right: Box::new(right),
         })
     }
 
+    fn parsePrefixUpdate(&mut self) -> Option<UpdateExpression> {
+        self.trace_begin("prefix update");
+        let token = self.curToken.clone();
+        self.nextToken();
+        let target = self.parseExpression(Precedence::PREFIX)?;
+        let target = self.updateTarget(&token, target)?;
+        self.trace_end("prefix update");
+        Some(UpdateExpression {
+            operator: token.literal.clone(),
+            span: self.spanFrom(token.position.offset),
+            token,
+            prefix: true,
+            target,
+        })
+    }
+
+    fn parsePostfixUpdate(&mut self, left: ExpressionEnum, from: usize) -> Option<ExpressionEnum> {
+        self.trace_begin("postfix update");
+        let token = self.curToken.clone();
+        let target = self.updateTarget(&token, left)?;
+        self.trace_end("postfix update");
+        Some(
+            UpdateExpression {
+                operator: token.literal.clone(),
+                span: self.spanFrom(from),
+                token,
+                prefix: false,
+                target,
+            }
+            .into(),
+        )
+    }
+
+    /// `++` and `--` can only update variables.
+    fn updateTarget(&mut self, operator: &Token, target: ExpressionEnum) -> Option<Identifier> {
+        match target {
+            ExpressionEnum::Identifier(ident) => Some(ident),
+            other => {
+                self.errors.push(ParseError {
+                    span: operator.Span(),
+                    expected: Some("an identifier".into()),
+                    found: other.String(),
+                    message: format!(
+                        "{} can only be applied to an identifier, got {}",
+                        operator.literal, other
+                    ),
+                });
+                None
+            }
+        }
+    }
+
     fn parseIdentifier(&mut self) -> Identifier {
         self.trace_begin("identifier");
         self.trace_end("identifier");
         Identifier {
             token: self.curToken.clone(),
+            span: self.curToken.Span().into(),
             value: self.curToken.literal.clone(),
         }
     }
@@ -460,15 +1161,167 @@ impl<'src> Parser<'src> {
         let token = self.curToken.clone();
 
         let value: i64 = self.curToken.literal.parse().ok().or_else(|| {
-            self.errors.push(format!(
-                r#"could not parse "{}" as integer"#,
-                self.curToken.literal
-            ));
+            self.errors.push(ParseError {
+                span: self.curToken.Span(),
+                expected: Some("an integer".into()),
+                found: self.curToken.literal.clone(),
+                message: format!(r#"could not parse "{}" as integer"#, self.curToken.literal),
+            });
             None
         })?;
 
         self.trace_end("integer literal");
-        Some(IntegerLiteral { token, value })
+        Some(IntegerLiteral {
+            span: self.spanFrom(token.position.offset),
+            token,
+            value,
+        })
+    }
+
+    /// Parses a string literal. Interpolated strings are desugared into a
+    /// left-associative chain of `+` starting with a string, so
+    /// `"a ${b} c"` becomes `(("a " + str(b)) + " c")`. `+` only joins
+    /// strings, so each interpolation goes through `str` to be appended as
+    /// it is printed.
+    fn parseStringLiteral(&mut self) -> Option<ExpressionEnum> {
+        self.trace_begin("string literal");
+        let raw = self.curToken.literal.clone();
+        let start = self.curToken.position;
+        // what an interpolation turns into spans the whole literal
+        let span: Span = self.curToken.Span().into();
+        let segments = match StringSegments(&raw) {
+            Ok(segments) => segments,
+            Err(message) => {
+                self.errors.push(ParseError {
+                    span: self.curToken.Span(),
+                    expected: None,
+                    found: raw,
+                    message,
+                });
+                return None;
+            }
+        };
+
+        let mut exp: Option<ExpressionEnum> = None;
+        for segment in segments {
+            let right = match segment {
+                StringSegment::Text(value) => StringLiteral {
+                    token: Token {
+                        kind: TokenKind::STRING,
+                        literal: String::new(),
+                        position: start,
+                    },
+                    span,
+                    value: self.intern(value),
+                }
+                .into(),
+                StringSegment::Code { offset, source } => {
+                    // the contents start after the opening quote
+                    let position = start.After("\"").After(&raw[..offset]);
+                    let value = self.parseInterpolation(source, position)?;
+                    CallExpression {
+                        token: Token {
+                            kind: TokenKind::LPAREN,
+                            literal: "(".into(),
+                            position,
+                        },
+                        span,
+                        function: Box::new(
+                            Identifier {
+                                token: Token {
+                                    kind: TokenKind::IDENT,
+                                    literal: "str".into(),
+                                    position,
+                                },
+                                span,
+                                value: "str".into(),
+                            }
+                            .into(),
+                        ),
+                        arguments: vec![value],
+                    }
+                    .into()
+                }
+            };
+            exp = Some(match exp {
+                None => right,
+                Some(left) => InfixExpression {
+                    token: Token {
+                        kind: TokenKind::PLUS,
+                        literal: "+".into(),
+                        position: start,
+                    },
+                    span,
+                    left: Box::new(left),
+                    operator: "+".into(),
+                    right: Box::new(right),
+                }
+                .into(),
+            });
+        }
+
+        self.trace_end("string literal");
+        exp
+    }
+
+    /// Parses a raw string literal, which has neither escapes nor
+    /// interpolations. It is the same literal as a string written without
+    /// them.
+    fn parseRawStringLiteral(&mut self) -> StringLiteral {
+        self.trace_begin("raw string literal");
+        let value = self.intern(self.curToken.literal.clone());
+        self.trace_end("raw string literal");
+        StringLiteral {
+            token: Token {
+                kind: TokenKind::STRING,
+                literal: String::new(),
+                position: self.curToken.position,
+            },
+            span: self.curToken.Span().into(),
+            value,
+        }
+    }
+
+    fn intern(&mut self, value: String) -> Rc<str> {
+        match self.strings.get(value.as_str()) {
+            Some(shared) => Rc::clone(shared),
+            None => {
+                let shared: Rc<str> = value.into();
+                self.strings.insert(Rc::clone(&shared));
+                shared
+            }
+        }
+    }
+
+    /// Parses the `code` of an interpolation, which starts at `start`.
+    fn parseInterpolation(&mut self, code: &str, start: Position) -> Option<ExpressionEnum> {
+        self.trace_begin("interpolation");
+        let mut p = Parser::WithEdition(Lexer::At(code, start), self.edition);
+        p.indentLevel = self.indentLevel;
+        p.strings = std::mem::take(&mut self.strings);
+        let exp = p.parseExpression(Precedence::LOWEST);
+        if exp.is_some() && !p.peekTokenIs(TokenKind::EOF) {
+            p.errors.push(ParseError {
+                span: p.peekToken.Span(),
+                expected: Some(format!("{:?}", TokenKind::EOF)),
+                found: format!("{:?}", p.peekToken.kind),
+                message: format!(
+                    "expected end of interpolation, got {:?} instead",
+                    p.peekToken.kind
+                ),
+            });
+        }
+        let failed = !p.errors.is_empty();
+        self.errors.append(&mut p.errors);
+        self.strings = std::mem::take(&mut p.strings);
+        for warning in p.warnings.Take() {
+            self.warnings.Warn(warning);
+        }
+        self.trace_end("interpolation");
+        if failed {
+            return None;
+        }
+        exp
     }
 
     fn parseExpression(&mut self, precedence: Precedence) -> Option<ExpressionEnum> {
@@ -477,6 +1330,7 @@ impl<'src> Parser<'src> {
             self.noPrefixParseFnError(self.curToken.kind);
             None
         })?;
+        let from = self.curToken.position.offset;
         let mut left_exp = self.dispatchPrefix(prefix_dispatcher)?;
         while !self.peekTokenIs(TokenKind::SEMICOLON) && precedence < self.peekPrecedence() {
             let infix_dispatcher = match self.peekToken.kind.infix_dispatcher() {
@@ -484,12 +1338,18 @@ impl<'src> Parser<'src> {
                 None => return Some(left_exp),
             };
             self.nextToken();
-            left_exp = self.dispatchInfix(left_exp, infix_dispatcher)?;
+            left_exp = self.dispatchInfix(left_exp, from, infix_dispatcher)?;
         }
         self.trace_end("expression");
         Some(left_exp)
     }
 
+    /// The span of a node from the offset `start` to the end of the current
+    /// token, the last one of the node.
+    fn spanFrom(&self, start: usize) -> Span {
+        (start..self.curToken.Span().end).into()
+    }
+
     fn curTokenIs(&self, kind: TokenKind) -> bool {
         self.curToken.kind == kind
     }
@@ -509,24 +1369,131 @@ impl<'src> Parser<'src> {
     }
 
     fn peekError(&mut self, kind: TokenKind) {
-        let msg = format!(
-            "expected next token to be {:?}, got {:?} instead",
-            kind, self.peekToken.kind
-        );
-        self.errors.push(msg);
+        self.errors.push(ParseError {
+            span: self.peekToken.Span(),
+            expected: Some(format!("{:?}", kind)),
+            found: format!("{:?}", self.peekToken.kind),
+            message: format!(
+                "expected next token to be {:?}, got {:?} instead",
+                kind, self.peekToken.kind
+            ),
+        });
     }
 
     fn noPrefixParseFnError(&mut self, kind: TokenKind) {
-        self.errors
-            .push(format!("no prefix parse function for {:?} found", kind));
+        if kind == TokenKind::ILLEGAL && IsUnterminatedString(&self.curToken.literal) {
+            return self.errors.push(ParseError {
+                span: self.curToken.Span(),
+                expected: Some("a closing `\"`".into()),
+                found: "the end of the input".into(),
+                message: "unterminated string".into(),
+            });
+        }
+        self.errors.push(ParseError {
+            span: self.curToken.Span(),
+            expected: Some("an expression".into()),
+            found: format!("{:?}", kind),
+            message: format!("no prefix parse function for {:?} found", kind),
+        });
+    }
+
+    /// Skips the rest of a statement that failed to parse, in a block with
+    /// `braces` open, so that a mistake is reported once and the statements
+    /// after it are still parsed. Stops at the statement's semicolon, before
+    /// a token that starts a statement or ends the block, or at the end of
+    /// the block if the statement already ran into it.
+    fn synchronize(&mut self, braces: usize) {
+        while !self.curTokenIs(TokenKind::EOF) && self.braces >= braces {
+            if self.braces == braces
+                && (self.curTokenIs(TokenKind::SEMICOLON)
+                    || matches!(
+                        self.peekToken.kind,
+                        TokenKind::LET
+                            | TokenKind::RETURN
+                            | TokenKind::DEFER
+                            | TokenKind::THROW
+                            | TokenKind::RBRACE
+                            | TokenKind::EOF
+                    ))
+            {
+                return;
+            }
+            self.nextToken();
+        }
+    }
+
+    /// The token `n` tokens after the current one, so `peekNth(1)` is
+    /// `peekToken`. Past the end, every token is EOF.
+    // nothing in the grammar needs to look further than `peekToken` yet
+    #[allow(dead_code)]
+    fn peekNth(&mut self, n: usize) -> &Token {
+        match n {
+            0 => &self.curToken,
+            1 => &self.peekToken,
+            n => {
+                while self.ahead.len() < n - 1 {
+                    let token = self.readToken();
+                    self.ahead.push_back(token);
+                }
+                &self.ahead[n - 2]
+            }
+        }
+    }
+
+    fn readToken(&mut self) -> Token {
+        let token = match self.tokens.next() {
+            Some(token) => token,
+            None => Token {
+                position: self.end,
+                ..Token::New(TokenKind::EOF, "")
+            },
+        };
+        self.end = token.End();
+        token
     }
 
     fn nextToken(&mut self) {
         self.curToken = self.peekToken.clone();
-        self.peekToken = self.lexer.NextToken();
+        match self.curToken.kind {
+            TokenKind::LBRACE => self.braces += 1,
+            TokenKind::RBRACE => self.braces = self.braces.saturating_sub(1),
+            _ => {}
+        }
+        self.peekToken = match self.ahead.pop_front() {
+            Some(token) => token,
+            None => self.readToken(),
+        };
+        if self.peekToken.kind != TokenKind::IDENT {
+            return;
+        }
+        if self.edition.IsReserved(&self.peekToken.literal) {
+            self.errors.push(ParseError {
+                span: self.peekToken.Span(),
+                expected: None,
+                found: self.peekToken.literal.clone(),
+                message: format!(
+                    "`{}` is reserved in edition {}",
+                    self.peekToken.literal, self.edition
+                ),
+            });
+        } else if Edition::LATEST.IsReserved(&self.peekToken.literal) {
+            self.warnings.Warn(Warning {
+                code: WarningCode::ReservedWord,
+                message: format!(
+                    "`{}` is reserved from edition {}",
+                    self.peekToken.literal,
+                    Edition::LATEST
+                ),
+                span: Some(self.peekToken.Span()),
+            });
+        }
     }
 
-    fn parseInfixExpression(&mut self, left: ExpressionEnum) -> Option<ExpressionEnum> {
+    fn parseInfixExpression(
+        &mut self,
+        left: ExpressionEnum,
+        from: usize,
+    ) -> Option<ExpressionEnum> {
         self.trace_begin("infix expression");
         let token = self.curToken.clone();
         let operator = self.curToken.literal.clone();
@@ -537,6 +1504,7 @@ impl<'src> Parser<'src> {
         Some(
             InfixExpression {
                 token,
+                span: self.spanFrom(from),
                 left: Box::new(left),
                 operator,
                 right: Box::new(right),
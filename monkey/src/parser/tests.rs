@@ -2,19 +2,34 @@ use matches::assert_matches;
 
 use super::*;
 
+use crate::ast::ArrayLiteral;
 use crate::ast::Boolean;
 use crate::ast::CallExpression;
+use crate::ast::DeferStatement;
+use crate::ast::DestructurePattern;
+use crate::ast::DestructureStatement;
 use crate::ast::ExpressionStatement;
 use crate::ast::FunctionLiteral;
+use crate::ast::HashLiteral;
 use crate::ast::IfExpression;
+use crate::ast::IndexExpression;
 use crate::ast::InfixExpression;
 use crate::ast::IntegerLiteral;
 use crate::ast::LetStatement;
+use crate::ast::MacroLiteral;
+use crate::ast::MatchExpression;
+use crate::ast::MatchPattern;
 use crate::ast::Node;
+use crate::ast::NullLiteral;
 use crate::ast::PrefixExpression;
 use crate::ast::ReturnStatement;
+use crate::ast::SliceExpression;
 use crate::ast::StatementEnum;
+use crate::ast::StringLiteral;
+use crate::ast::ThrowStatement;
+use crate::ast::TryExpression;
 use std::convert::TryInto;
+use std::rc::Rc;
 
 enum MonkeyLiteral {
     Int(i64),
@@ -66,7 +81,7 @@ fn testLiteralExpression(exp: ExpressionEnum, expected: MonkeyLiteral) {
     }
 }
 
-fn checkParserErrors(parser: &Parser) {
+fn checkParserErrors(parser: &Parser<Lexer>) {
     for error in parser.errors.iter() {
         eprintln!("parser error encountered: {}", error);
     }
@@ -95,7 +110,7 @@ fn LetStatements() {
     for (stmt, expectedName) in program
         .statements
         .into_iter()
-        .zip(expectedNames.into_iter())
+        .zip(expectedNames)
     {
         testLetStatement(stmt, expectedName)
     }
@@ -120,6 +135,113 @@ fn ReturnStatements() {
     }
 }
 
+#[test]
+fn DeferStatements() {
+    let input = "defer close(handle); defer 5";
+    let l = Lexer::New(input);
+    let mut p = Parser::New(l);
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    assert_eq!(program.statements.len(), 2);
+    let defer_stmt: DeferStatement = program.statements[0].clone().try_into().unwrap();
+    assert_eq!(defer_stmt.TokenLiteral(), "defer");
+    assert_eq!(defer_stmt.expression.String(), "close(handle)");
+    assert_eq!(program.String(), "defer close(handle);defer 5;");
+}
+
+#[test]
+fn ParseTryExpression() {
+    let input = r#"try { f(); throw "oops" } catch (e) { e }"#;
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    assert_eq!(program.statements.len(), 1);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let exp: TryExpression = stmt.expression.try_into().unwrap();
+    assert_eq!(exp.body.statements.len(), 2);
+    let throw: ThrowStatement = exp.body.statements[1].clone().try_into().unwrap();
+    assert_eq!(throw.value.String(), "oops");
+    assert_eq!(exp.name.value, "e");
+    assert_eq!(exp.handler.String(), "e");
+    assert_eq!(program.String(), "try f()throw oops; catch (e) e");
+
+    for (input, expected) in [
+        (
+            "try { 1 }",
+            "expected next token to be CATCH, got EOF instead",
+        ),
+        (
+            "try { 1 } catch { 2 }",
+            "expected next token to be LPAREN, got LBRACE instead",
+        ),
+        ("throw;", "no prefix parse function for SEMICOLON found"),
+    ] {
+        let mut p = Parser::New(Lexer::New(input));
+        p.ParseProgram();
+        assert_eq!(
+            p.errors.first().map(|e| e.message.as_str()),
+            Some(expected),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn ParseImportExpression() {
+    let tests = vec![
+        (r#"import "a.mk""#, "import a.mk"),
+        (r#"import "a.mk"["f"](1)"#, "(import a.mk[f])(1)"),
+        (r#"import ("lib/" + name)"#, "import (lib/ + name)"),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        checkParserErrors(&p);
+        assert_eq!(program.String(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn ParseArrowFunction() {
+    let tests = vec![
+        ("|x| x + 1", "fn(x)(x + 1)"),
+        ("|| 5", "fn()5"),
+        ("|a, b| a * b", "fn(a, b)(a * b)"),
+        ("|x| |y| x + y", "fn(x)fn(y)(x + y)"),
+        ("f(|x| x, 2)", "f(fn(x)x, 2)"),
+        ("let inc = |x| x + 1;", "let inc = fn(x)(x + 1);"),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        checkParserErrors(&p);
+        assert_eq!(program.String(), expected, "{}", input);
+    }
+
+    let mut p = Parser::New(Lexer::New("|x y| x"));
+    p.ParseProgram();
+    assert_eq!(
+        p.errors.first().map(|e| e.message.as_str()),
+        Some("expected next token to be PIPE, got IDENT instead")
+    );
+}
+
+#[test]
+fn ParseMacroLiteral() {
+    let input = "macro(x, y) { x + y; }";
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let literal: MacroLiteral = stmt.expression.try_into().unwrap();
+    assert_eq!(literal.parameters.len(), 2);
+    testLiteralExpression(literal.parameters[0].clone().into(), "x".into());
+    testLiteralExpression(literal.parameters[1].clone().into(), "y".into());
+    assert_eq!(literal.body.statements.len(), 1);
+    assert_eq!(literal.body.String(), "(x + y)");
+}
+
 #[test]
 fn IdentifierExpression() {
     let input = "foobar;";
@@ -216,6 +338,7 @@ fn OperatorPrecedence() {
         ("a * b * c", "((a * b) * c)"),
         ("a * b / c", "((a * b) / c)"),
         ("a + b / c", "(a + (b / c))"),
+        ("a + b % c * d", "(a + ((b % c) * d))"),
         ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
         ("3 + 4; -5 * 5", "(3 + 4)((-5) * 5)"),
         ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
@@ -246,6 +369,32 @@ fn OperatorPrecedence() {
             "add(a + b + c * d / f + g)",
             "add((((a + b) + ((c * d) / f)) + g))",
         ),
+        ("-i++", "(-(i++))"),
+        ("++i * 2", "((++i) * 2)"),
+        ("i-- - --j", "((i--) - (--j))"),
+        ("a + b++", "(a + (b++))"),
+        (
+            "a * [1, 2, 3, 4][b * c] * d",
+            "((a * ([1, 2, 3, 4][(b * c)])) * d)",
+        ),
+        (
+            "add(a * b[2], b[1], 2 * [1, 2][1])",
+            "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+        ),
+        ("-a[1:]", "(-(a[1:]))"),
+        ("s[:n - 1] + s[i:j]", "((s[:(n - 1)]) + (s[i:j]))"),
+        ("a[:][0]", "((a[:])[0])"),
+        ("f(1)(2)", "f(1)(2)"),
+        ("f()[0] * 2", "((f()[0]) * 2)"),
+        ("-fs[0](x)", "(-(fs[0])(x))"),
+        ("a * (b + c)[0]", "(a * ((b + c)[0]))"),
+        ("!f(x) == y[1]", "((!f(x)) == (y[1]))"),
+        ("[...xs, 4]", "[...xs, 4]"),
+        ("f(a, ...b + c)", "f(a, ...(b + c))"),
+        (
+            "draw(1, width: 2 * x, height: f(h: 3))",
+            "draw(1, width: (2 * x), height: f(h: 3))",
+        ),
     ];
 
     for (input, expected) in tests {
@@ -272,6 +421,19 @@ fn BooleanExpression() {
     assert_eq!(b.TokenLiteral(), "true");
 }
 
+#[test]
+fn NullExpression() {
+    let input = "null;";
+    let l = Lexer::New(input);
+    let mut p = Parser::New(l);
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    assert_eq!(program.statements.len(), 1);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let null: NullLiteral = stmt.expression.try_into().unwrap();
+    assert_eq!(null.TokenLiteral(), "null");
+}
+
 #[test]
 fn ParseIfExpression() {
     let input = "if (x < y) { x }";
@@ -371,7 +533,7 @@ fn FunctionParameters() {
         for (actual, expected) in function
             .parameters
             .into_iter()
-            .zip(expectedParams.into_iter())
+            .zip(expectedParams)
         {
             testLiteralExpression(actual.into(), expected.into());
         }
@@ -394,3 +556,560 @@ fn ParseCallExpression() {
     testInfixExpression(exp.arguments[1].clone(), 2.into(), "*", 3.into());
     testInfixExpression(exp.arguments[2].clone(), 4.into(), "+", 5.into());
 }
+
+#[test]
+fn StringLiteralExpression() {
+    let input = r#""hello world";"#;
+    let l = Lexer::New(input);
+    let mut p = Parser::New(l);
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let literal: StringLiteral = stmt.expression.try_into().unwrap();
+    assert_eq!(&*literal.value, "hello world");
+}
+
+#[test]
+fn SharedStringLiterals() {
+    let input = r#""a\tb"; "a\tb"; "x ${"a\tb"}"; "a	b"; "c";"#;
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let literals: Vec<Rc<str>> = program
+        .statements
+        .into_iter()
+        .map(|stmt| {
+            let stmt: ExpressionStatement = stmt.try_into().unwrap();
+            match stmt.expression {
+                ExpressionEnum::StringLiteral(literal) => literal.value,
+                ExpressionEnum::InfixExpression(exp) => {
+                    let call: CallExpression = (*exp.right).try_into().unwrap();
+                    let literal: StringLiteral = call.arguments[0].clone().try_into().unwrap();
+                    literal.value
+                }
+                exp => panic!("unexpected {:?}", exp),
+            }
+        })
+        .collect();
+    assert_eq!(&*literals[0], "a\tb");
+    for literal in &literals[1..4] {
+        assert!(Rc::ptr_eq(&literals[0], literal));
+    }
+    assert!(!Rc::ptr_eq(&literals[0], &literals[4]));
+}
+
+#[test]
+fn StringInterpolation() {
+    let tests = vec![
+        (r#""hello ${name}!""#, "((hello  + str(name)) + !)"),
+        (r#""${a}${b * 2}""#, "(( + str(a)) + str((b * 2)))"),
+        (r#""${"nested ${x}"}""#, "( + str((nested  + str(x))))"),
+    ];
+    for (input, expected) in tests {
+        let l = Lexer::New(input);
+        let mut p = Parser::New(l);
+        let program = p.ParseProgram();
+        checkParserErrors(&p);
+        assert_eq!(program.String(), expected);
+    }
+}
+
+#[test]
+fn Positions() {
+    let input = "let f = |x| x;\nputs(\"n = ${\n  n + 1}\");";
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let at = |position: Position| (position.line, position.column, position.offset);
+
+    let stmt: LetStatement = program.statements[0].clone().try_into().unwrap();
+    assert_eq!(at(stmt.Position()), (1, 1, 0));
+    assert_eq!(at(stmt.name.Position()), (1, 5, 4));
+    // an arrow function is where its parameters start
+    assert_eq!(at(stmt.value.Position()), (1, 9, 8));
+
+    let stmt: ExpressionStatement = program.statements[1].clone().try_into().unwrap();
+    assert_eq!(at(stmt.Position()), (2, 1, 15));
+    let call: CallExpression = stmt.expression.try_into().unwrap();
+    let concat: InfixExpression = call.arguments[0].clone().try_into().unwrap();
+    let str: CallExpression = (*concat.right).try_into().unwrap();
+    assert_eq!(at(str.Position()), (2, 13, 27));
+    let interpolated: InfixExpression = str.arguments[0].clone().try_into().unwrap();
+    assert_eq!(at(interpolated.left.Position()), (3, 3, 30));
+    assert_eq!(at(interpolated.Position()), (3, 5, 32));
+}
+
+#[test]
+fn Spans() {
+    let input = "let x = (a + b) * f(c)[0];\nif (x) { y } else { -z }\n\"s ${t}\" |u| u++";
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let source = |span: std::ops::Range<usize>| &input[span];
+
+    assert_eq!(source(program.Span()), input);
+
+    let stmt: LetStatement = program.statements[0].clone().try_into().unwrap();
+    assert_eq!(source(stmt.Span()), "let x = (a + b) * f(c)[0];");
+    assert_eq!(source(stmt.name.Span()), "x");
+    assert_eq!(source(stmt.value.Span()), "(a + b) * f(c)[0]");
+    let product: InfixExpression = stmt.value.try_into().unwrap();
+    assert_eq!(source(product.left.Span()), "a + b");
+    let index: IndexExpression = (*product.right).try_into().unwrap();
+    assert_eq!(source(index.left.Span()), "f(c)");
+    assert_eq!(source(index.index.Span()), "0");
+
+    let stmt: ExpressionStatement = program.statements[1].clone().try_into().unwrap();
+    assert_eq!(source(stmt.Span()), "if (x) { y } else { -z }");
+    let exp: IfExpression = stmt.expression.try_into().unwrap();
+    assert_eq!(source(exp.consequence.Span()), "{ y }");
+    let alternative = exp.alternative.unwrap();
+    assert_eq!(source(alternative.Span()), "{ -z }");
+    assert_eq!(source(alternative.statements[0].Span()), "-z");
+
+    // what an interpolation turns into spans the literal
+    let stmt: ExpressionStatement = program.statements[2].clone().try_into().unwrap();
+    assert_eq!(source(stmt.Span()), "\"s ${t}\"");
+    let concat: InfixExpression = stmt.expression.try_into().unwrap();
+    assert_eq!(source(concat.left.Span()), "\"s ${t}\"");
+    assert_eq!(source(concat.right.Span()), "\"s ${t}\"");
+    let str: CallExpression = (*concat.right).try_into().unwrap();
+    assert_eq!(source(str.arguments[0].Span()), "t");
+
+    let stmt: ExpressionStatement = program.statements[3].clone().try_into().unwrap();
+    let function: FunctionLiteral = stmt.expression.try_into().unwrap();
+    assert_eq!(source(function.Span()), "|u| u++");
+    assert_eq!(source(function.body.Span()), "u++");
+}
+
+#[test]
+fn StringInterpolationErrors() {
+    let tests = vec![
+        r#""${}""#,
+        r#""${1 2}""#,
+        r#""${x""#,
+    ];
+    for input in tests {
+        let l = Lexer::New(input);
+        let mut p = Parser::New(l);
+        p.ParseProgram();
+        assert!(!p.errors.is_empty(), "no errors for {}", input);
+    }
+}
+
+#[test]
+fn UnterminatedString() {
+    let mut p = Parser::New(Lexer::New("let s = \"abc;\nputs(s)"));
+    p.ParseProgram();
+    let error = &p.errors[0];
+    assert_eq!(error.message, "unterminated string");
+    assert_eq!(error.span, 8..21);
+}
+
+#[test]
+fn ParseMatchExpression() {
+    let input = r#"match x { 1 => a, "b" => { b }, -2 => c + 1, _ => null, }"#;
+    let l = Lexer::New(input);
+    let mut p = Parser::New(l);
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    assert_eq!(program.statements.len(), 1);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let exp: MatchExpression = stmt.expression.try_into().unwrap();
+    testIdentifier(*exp.subject.clone(), "x");
+    assert_eq!(exp.arms.len(), 4);
+    assert!(matches!(exp.arms[3].pattern, MatchPattern::Wildcard(_)));
+    assert_eq!(
+        exp.String(),
+        "match x { 1 => a, b => { b }, (-2) => (c + 1), _ => null }"
+    );
+}
+
+#[test]
+fn MatchExpressionErrors() {
+    let tests = vec![
+        (
+            "match x { y => 1 }",
+            "expected a literal or _ as match pattern, got IDENT",
+        ),
+        (
+            "match x { 1 2 }",
+            "expected next token to be FAT_ARROW, got INT instead",
+        ),
+        (
+            "match x { 1 => 2 3 => 4 }",
+            "expected next token to be COMMA, got INT instead",
+        ),
+    ];
+    for (input, expected) in tests {
+        let l = Lexer::New(input);
+        let mut p = Parser::New(l);
+        p.ParseProgram();
+        assert_eq!(p.errors.first().map(|e| e.message.as_str()), Some(expected));
+    }
+}
+
+#[test]
+fn UpdateExpressionErrors() {
+    let tests = vec![
+        ("5++", "++ can only be applied to an identifier, got 5"),
+        ("--f()", "-- can only be applied to an identifier, got f()"),
+        (
+            "(a + b)++",
+            "++ can only be applied to an identifier, got (a + b)",
+        ),
+    ];
+    for (input, expected) in tests {
+        let l = Lexer::New(input);
+        let mut p = Parser::New(l);
+        p.ParseProgram();
+        assert_eq!(p.errors.first().map(|e| e.message.as_str()), Some(expected));
+    }
+}
+
+#[test]
+fn ParseArrayLiteral() {
+    let input = "[1, 2 * 2, 3 + 3]";
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let array: ArrayLiteral = stmt.expression.try_into().unwrap();
+    assert_eq!(array.elements.len(), 3);
+    testIntegerLiteral(array.elements[0].clone(), 1);
+    testInfixExpression(array.elements[1].clone(), 2.into(), "*", 2.into());
+    testInfixExpression(array.elements[2].clone(), 3.into(), "+", 3.into());
+}
+
+#[test]
+fn ParseIndexExpression() {
+    let mut p = Parser::New(Lexer::New("myArray[1 + 1]"));
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+    let index: IndexExpression = stmt.expression.try_into().unwrap();
+    testIdentifier(*index.left, "myArray");
+    testInfixExpression(*index.index, 1.into(), "+", 1.into());
+}
+
+#[test]
+fn ParseSliceExpression() {
+    let tests: Vec<(&str, Option<i64>, Option<i64>)> = vec![
+        ("a[1:3]", Some(1), Some(3)),
+        ("a[2:]", Some(2), None),
+        ("a[:2]", None, Some(2)),
+        ("a[:]", None, None),
+    ];
+    for (input, start, end) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        checkParserErrors(&p);
+        let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+        let slice: SliceExpression = stmt.expression.try_into().unwrap();
+        testIdentifier(*slice.left, "a");
+        for (bound, expected) in [(slice.start, start), (slice.end, end)] {
+            match expected {
+                Some(value) => testIntegerLiteral(*bound.unwrap(), value),
+                None => assert!(bound.is_none()),
+            }
+        }
+    }
+
+    let mut p = Parser::New(Lexer::New("a[]"));
+    p.ParseProgram();
+    assert_eq!(
+        p.errors.first().map(|e| e.message.as_str()),
+        Some("no prefix parse function for RBRACKET found")
+    );
+}
+
+#[test]
+fn SpreadOutsideListIsAnError() {
+    for input in ["...xs", "let a = ...xs;", "[1][...xs]"] {
+        let mut p = Parser::New(Lexer::New(input));
+        p.ParseProgram();
+        assert_eq!(
+            p.errors.first().map(|e| e.message.as_str()),
+            Some("no prefix parse function for ELLIPSIS found"),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn ParseHashLiteral() {
+    let tests = vec![
+        ("{}", "{}"),
+        (r#"{"one": 1, "two": 2,}"#, "{one: 1, two: 2}"),
+        ("{1 + 1: 2 * 3, true: [x]}", "{(1 + 1): (2 * 3), true: [x]}"),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        checkParserErrors(&p);
+        let stmt: ExpressionStatement = program.statements[0].clone().try_into().unwrap();
+        let hash: HashLiteral = stmt.expression.try_into().unwrap();
+        assert_eq!(hash.String(), expected);
+    }
+
+    let mut p = Parser::New(Lexer::New("{1 2}"));
+    p.ParseProgram();
+    assert_eq!(
+        p.errors.first().map(|e| e.message.as_str()),
+        Some("expected next token to be COLON, got INT instead")
+    );
+}
+
+#[test]
+fn ParseDestructureStatement() {
+    let tests = vec![
+        ("let [a, b] = pair;", "let [a, b] = pair;", false),
+        ("let {x} = point", "let {x} = point;", true),
+        (
+            "let {x, y} = {\"x\": 1, \"y\": 2};",
+            "let {x, y} = {x: 1, y: 2};",
+            true,
+        ),
+    ];
+    for (input, expected, hash) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        checkParserErrors(&p);
+        assert_eq!(program.statements.len(), 1);
+        let stmt: DestructureStatement = program.statements[0].clone().try_into().unwrap();
+        assert_eq!(
+            matches!(stmt.pattern, DestructurePattern::Hash(_)),
+            hash,
+            "{}",
+            input
+        );
+        assert_eq!(stmt.String(), expected);
+    }
+
+    let tests = vec![
+        (
+            "let [] = x;",
+            "expected next token to be IDENT, got RBRACKET instead",
+        ),
+        (
+            "let [a, 1] = x;",
+            "expected next token to be IDENT, got INT instead",
+        ),
+        (
+            "let [a} = x;",
+            "expected next token to be RBRACKET, got RBRACE instead",
+        ),
+        (
+            "let {a} x;",
+            "expected next token to be ASSIGN, got IDENT instead",
+        ),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        p.ParseProgram();
+        assert_eq!(p.errors.first().map(|e| e.message.as_str()), Some(expected));
+    }
+}
+
+#[test]
+fn KeywordArgumentErrors() {
+    let tests = vec![
+        (
+            "f(a: 1, 2)",
+            "positional argument after keyword arguments: 2",
+        ),
+        (
+            "f(a: 1, ...xs)",
+            "positional argument after keyword arguments: ...xs",
+        ),
+        ("f(a: 1, a: 2)", "keyword argument repeated: a"),
+        (
+            "[a: 1]",
+            "expected next token to be RBRACKET, got COLON instead",
+        ),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        p.ParseProgram();
+        assert_eq!(
+            p.errors.first().map(|e| e.message.as_str()),
+            Some(expected),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn Editions() {
+    let tests = vec![
+        (Edition::V1, "let x = 1\nlet y = 2", None),
+        (Edition::V1, "let while = 1;", None),
+        (Edition::V2, "let x = 1; x", None),
+        (Edition::V2, "let f = fn() { 1 }\nf()", None),
+        (
+            Edition::V2,
+            "if (x) { y }\nlet z = fn() { return 1 };",
+            None,
+        ),
+        (
+            Edition::V2,
+            "let x = 1\nlet y = 2",
+            Some("expected SEMICOLON after statement, got LET instead"),
+        ),
+        (
+            Edition::V2,
+            "x + 1\nputs(x)",
+            Some("expected SEMICOLON after statement, got IDENT instead"),
+        ),
+        (
+            Edition::V2,
+            "let while = 1;",
+            Some("`while` is reserved in edition 2"),
+        ),
+        (
+            Edition::V2,
+            r#""${const}";"#,
+            Some("`const` is reserved in edition 2"),
+        ),
+    ];
+    for (edition, input, expected) in tests {
+        let mut p = Parser::WithEdition(Lexer::New(input), edition);
+        p.ParseProgram();
+        assert_eq!(
+            p.errors.first().map(|e| e.message.as_str()),
+            expected,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn DeprecationWarnings() {
+    let input = "let x = 1\nlet while = x;\nlet y = 2\n";
+    let mut p = Parser::New(Lexer::New(input));
+    p.ParseProgram();
+    assert!(p.errors.is_empty());
+    assert_eq!(
+        p.warnings.Get(),
+        &[
+            Warning {
+                code: WarningCode::MissingSemicolon,
+                message: "statement without a semicolon; edition 2 requires one".into(),
+                span: Some(9..9),
+            },
+            Warning {
+                code: WarningCode::ReservedWord,
+                message: "`while` is reserved from edition 2".into(),
+                span: Some(14..19),
+            },
+        ]
+    );
+
+    let mut p = Parser::New(Lexer::New(input));
+    p.warnings.Suppress(WarningCode::MissingSemicolon);
+    p.ParseProgram();
+    assert_eq!(
+        p.warnings.Get().iter().map(|w| w.code).collect::<Vec<_>>(),
+        vec![WarningCode::ReservedWord]
+    );
+    assert_eq!(
+        "reserved-word".parse::<WarningCode>(),
+        Ok(WarningCode::ReservedWord)
+    );
+}
+
+#[test]
+fn ParseErrors() {
+    let input = "let x = 1;\nlet y = (x + 2;\n";
+    let mut p = Parser::New(Lexer::New(input));
+    p.ParseProgram();
+    let error = p.errors.first().unwrap();
+    assert_eq!(error.span, 25..26);
+    assert_eq!(error.expected.as_deref(), Some("RPAREN"));
+    assert_eq!(error.found, "SEMICOLON");
+    assert_eq!(
+        error.Render(input),
+        "error: expected next token to be RPAREN, got SEMICOLON instead
+  --> 2:15
+  |
+2 | let y = (x + 2;
+  |               ^ expected RPAREN"
+    );
+
+    // the spans of errors in interpolations are spans in the whole source
+    let input = r#"puts("a ${1 2}")"#;
+    let mut p = Parser::New(Lexer::New(input));
+    p.ParseProgram();
+    let error = p.errors.first().unwrap();
+    assert_eq!(error.span, 12..13);
+    assert_eq!(&input[error.span.clone()], "2");
+
+    let mut p = Parser::New(Lexer::New("add(1, x: 2, 3)"));
+    p.ParseProgram();
+    let error = p.errors.first().unwrap();
+    assert_eq!(
+        error.Render("add(1, x: 2, 3)"),
+        "error: positional argument after keyword arguments: 3
+  --> 1:14
+  |
+1 | add(1, x: 2, 3)
+  |              ^ expected a keyword argument"
+    );
+}
+
+#[test]
+fn ErrorRecovery() {
+    let tests = [
+        ("let x = ; let y = 2; y", "let y = 2;y"),
+        ("let x 5; let y = 2;", "let y = 2;"),
+        (
+            "let f = fn() { let a = ; 1 }; let b = 2;",
+            "let f = fn()1;let b = 2;",
+        ),
+        ("if (x) { 1 + }; let c = 3;", "ifx let c = 3;"),
+        ("let h = { 1: }; let d = 4;", "let d = 4;"),
+        ("let x = (1 + ; if (y) { z } let e = 5;", "ify zlet e = 5;"),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::New(Lexer::New(input));
+        let program = p.ParseProgram();
+        assert_eq!(p.errors.len(), 1, "{}: {:?}", input, p.errors);
+        assert_eq!(program.String(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn ParsesAnyTokens() {
+    // the EOF is made up when the tokens run out
+    let tokens = Lexer::New("let x = 1 + 2; x").filter(|tok| tok.kind != TokenKind::EOF);
+    let mut p = Parser::New(tokens);
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "{:?}", p.errors);
+    assert_eq!(program.String(), "let x = (1 + 2);x");
+
+    let tokens = Lexer::New("f(1, 2").filter(|tok| tok.kind != TokenKind::EOF);
+    let mut p = Parser::New(tokens);
+    p.ParseProgram();
+    let error = p.errors.first().unwrap();
+    assert_eq!(error.found, "EOF");
+    assert_eq!(error.span, 6..6);
+}
+
+#[test]
+fn PeekNth() {
+    let mut p = Parser::New(Lexer::New("let x = 1;"));
+    assert_eq!(p.peekNth(0).literal, "let");
+    assert_eq!(p.peekNth(3).literal, "1");
+    assert_eq!(p.peekNth(1).literal, "x");
+    assert_eq!(p.peekNth(5).kind, TokenKind::EOF);
+    assert_eq!(p.peekNth(9).kind, TokenKind::EOF);
+    assert_eq!(p.peekNth(9).position.offset, 10);
+
+    // looking ahead doesn't change what is parsed
+    let program = p.ParseProgram();
+    checkParserErrors(&p);
+    assert_eq!(program.String(), "let x = 1;");
+}
@@ -0,0 +1,415 @@
+//! Everything the interpreter needs from the machine it runs on: a clock,
+//! random numbers, standard input and output, files and environment
+//! variables. Builtins and the runtime only reach the machine through a
+//! runtime's `Platform`, so swapping it for a `MemoryPlatform` lets a script
+//! run with no OS underneath, or with one a test controls.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+
+pub trait Platform {
+    /// Time elapsed since some fixed point, which never goes backwards.
+    fn Now(&self) -> Duration;
+
+    /// The wall-clock time, which can jump.
+    fn SystemTime(&self) -> SystemTime;
+
+    /// Blocks for `time`.
+    fn Sleep(&self, time: Duration);
+
+    fn Random(&self) -> u64;
+
+    /// Writes `text` to standard output.
+    fn Write(&self, text: &str);
+
+    /// The next line of standard input, without its line ending, or `None`
+    /// at the end of the input.
+    fn ReadLine(&self) -> Option<String>;
+
+    fn ReadFile(&self, path: &Path) -> io::Result<String>;
+
+    /// Creates or replaces the file at `path`.
+    fn WriteFile(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// Adds `contents` to the end of the file at `path`, creating it if it
+    /// doesn't exist.
+    fn AppendFile(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// The names of the entries of the directory at `path`, in any order.
+    fn ReadDir(&self, path: &Path) -> io::Result<Vec<String>>;
+
+    fn IsFile(&self, path: &Path) -> bool;
+
+    /// The absolute path `path` refers to, with `.` and `..` resolved.
+    fn Canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// The value of the environment variable `name`, if it is set.
+    fn Var(&self, name: &str) -> Option<String>;
+
+    /// Sets the environment variable `name`, which is neither empty nor
+    /// contains `=` or NUL, to `value`, which doesn't contain NUL.
+    fn SetVar(&self, name: &str, value: &str);
+}
+
+/// The machine the interpreter runs on, through the standard library.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdPlatform;
+
+static START: Lazy<Instant> = Lazy::new(Instant::now);
+
+impl Platform for StdPlatform {
+    fn Now(&self) -> Duration {
+        START.elapsed()
+    }
+
+    fn SystemTime(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn Sleep(&self, time: Duration) {
+        std::thread::sleep(time)
+    }
+
+    fn Random(&self) -> u64 {
+        // every `RandomState` is seeded differently
+        RandomState::new().build_hasher().finish()
+    }
+
+    fn Write(&self, text: &str) {
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    fn ReadLine(&self) -> Option<String> {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                Some(line)
+            }
+        }
+    }
+
+    fn ReadFile(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn WriteFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn AppendFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+            .write_all(contents.as_bytes())
+    }
+
+    fn ReadDir(&self, path: &Path) -> io::Result<Vec<String>> {
+        std::fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    fn IsFile(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn Canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn Var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn SetVar(&self, name: &str, value: &str) {
+        std::env::set_var(name, value)
+    }
+}
+
+/// A platform that only exists in memory: its clock stands still until it is
+/// advanced (or slept on), its random numbers come from a fixed seed, and its
+/// output, input, files and environment variables are whatever the host puts
+/// there.
+/// Its file system has only files; a directory is wherever some are. For
+/// tests, and for hosts without an OS, like wasm.
+#[derive(Debug)]
+pub struct MemoryPlatform {
+    clock: Cell<Duration>,
+    seed: Cell<u64>,
+    output: RefCell<String>,
+    input: RefCell<VecDeque<String>>,
+    /// By absolute path, with `.` and `..` resolved.
+    files: RefCell<HashMap<PathBuf, String>>,
+    vars: RefCell<HashMap<String, String>>,
+}
+
+impl Default for MemoryPlatform {
+    fn default() -> Self {
+        Self {
+            clock: Cell::default(),
+            seed: Cell::new(0x2545_f491_4f6c_dd1d),
+            output: RefCell::default(),
+            input: RefCell::default(),
+            files: RefCell::default(),
+            vars: RefCell::default(),
+        }
+    }
+}
+
+impl MemoryPlatform {
+    pub fn New() -> Self {
+        Self::default()
+    }
+
+    pub fn Advance(&self, time: Duration) {
+        self.clock.set(self.clock.get() + time);
+    }
+
+    /// Restarts the random numbers from `seed`, which mustn't be 0.
+    pub fn Seed(&self, seed: u64) {
+        self.seed.set(seed.max(1));
+    }
+
+    /// Everything written so far.
+    pub fn Output(&self) -> String {
+        self.output.borrow().clone()
+    }
+
+    /// Everything written so far, leaving the output empty.
+    pub fn TakeOutput(&self) -> String {
+        std::mem::take(&mut *self.output.borrow_mut())
+    }
+
+    /// Queues `line` to be read after the lines queued before it.
+    pub fn PushInput(&self, line: impl Into<String>) {
+        self.input.borrow_mut().push_back(line.into());
+    }
+
+    /// Creates or replaces the file at `path`. A relative path is relative to
+    /// the root.
+    pub fn AddFile(&self, path: impl AsRef<Path>, contents: impl Into<String>) {
+        self.files
+            .borrow_mut()
+            .insert(normalize(path.as_ref()), contents.into());
+    }
+
+    pub fn SetVar(&self, name: impl Into<String>, value: impl Into<String>) {
+        self.vars.borrow_mut().insert(name.into(), value.into());
+    }
+}
+
+/// `path` made absolute against the root, with `.` and `..` resolved.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normal.push(name),
+            Component::ParentDir => {
+                normal.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    normal
+}
+
+fn notFound(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no such file: {}", path.display()),
+    )
+}
+
+impl Platform for MemoryPlatform {
+    fn Now(&self) -> Duration {
+        self.clock.get()
+    }
+
+    /// The Unix epoch, plus however far the clock has been advanced.
+    fn SystemTime(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + self.clock.get()
+    }
+
+    /// Advances the clock, without blocking.
+    fn Sleep(&self, time: Duration) {
+        self.Advance(time);
+    }
+
+    fn Random(&self) -> u64 {
+        // xorshift64*
+        let mut x = self.seed.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.seed.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn Write(&self, text: &str) {
+        self.output.borrow_mut().push_str(text);
+    }
+
+    fn ReadLine(&self) -> Option<String> {
+        self.input.borrow_mut().pop_front()
+    }
+
+    fn ReadFile(&self, path: &Path) -> io::Result<String> {
+        self.files
+            .borrow()
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| notFound(path))
+    }
+
+    fn WriteFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.AddFile(path, contents);
+        Ok(())
+    }
+
+    fn AppendFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.files
+            .borrow_mut()
+            .entry(normalize(path))
+            .or_default()
+            .push_str(contents);
+        Ok(())
+    }
+
+    /// The directories are those the files are in.
+    fn ReadDir(&self, path: &Path) -> io::Result<Vec<String>> {
+        let dir = normalize(path);
+        let names: BTreeSet<String> = self
+            .files
+            .borrow()
+            .keys()
+            .filter_map(|file| file.strip_prefix(&dir).ok()?.components().next())
+            .map(|name| name.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if names.is_empty() {
+            return Err(notFound(path));
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    fn IsFile(&self, path: &Path) -> bool {
+        self.files.borrow().contains_key(&normalize(path))
+    }
+
+    fn Canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let normal = normalize(path);
+        if self.files.borrow().contains_key(&normal) {
+            Ok(normal)
+        } else {
+            Err(notFound(path))
+        }
+    }
+
+    fn Var(&self, name: &str) -> Option<String> {
+        self.vars.borrow().get(name).cloned()
+    }
+
+    fn SetVar(&self, name: &str, value: &str) {
+        MemoryPlatform::SetVar(self, name, value)
+    }
+}
+
+/// Another platform, except that its output is kept instead of written.
+pub(crate) struct Capture {
+    inner: Rc<dyn Platform>,
+    output: RefCell<String>,
+}
+
+impl Capture {
+    pub(crate) fn New(inner: Rc<dyn Platform>) -> Self {
+        Self {
+            inner,
+            output: RefCell::default(),
+        }
+    }
+
+    pub(crate) fn TakeOutput(&self) -> String {
+        self.output.take()
+    }
+}
+
+impl Platform for Capture {
+    fn Now(&self) -> Duration {
+        self.inner.Now()
+    }
+
+    fn SystemTime(&self) -> SystemTime {
+        self.inner.SystemTime()
+    }
+
+    fn Sleep(&self, time: Duration) {
+        self.inner.Sleep(time)
+    }
+
+    fn Random(&self) -> u64 {
+        self.inner.Random()
+    }
+
+    fn Write(&self, text: &str) {
+        self.output.borrow_mut().push_str(text);
+    }
+
+    fn ReadLine(&self) -> Option<String> {
+        self.inner.ReadLine()
+    }
+
+    fn ReadFile(&self, path: &Path) -> io::Result<String> {
+        self.inner.ReadFile(path)
+    }
+
+    fn WriteFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.inner.WriteFile(path, contents)
+    }
+
+    fn AppendFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.inner.AppendFile(path, contents)
+    }
+
+    fn ReadDir(&self, path: &Path) -> io::Result<Vec<String>> {
+        self.inner.ReadDir(path)
+    }
+
+    fn IsFile(&self, path: &Path) -> bool {
+        self.inner.IsFile(path)
+    }
+
+    fn Canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.inner.Canonicalize(path)
+    }
+
+    fn Var(&self, name: &str) -> Option<String> {
+        self.inner.Var(name)
+    }
+
+    fn SetVar(&self, name: &str, value: &str) {
+        self.inner.SetVar(name, value)
+    }
+}
@@ -0,0 +1,137 @@
+//! Interpreters kept warm for hosts that evaluate many short requests, like
+//! servers. Setting one up, binding host builtins and evaluating a prelude,
+//! happens once; each request then checks out an interpreter and returns it
+//! when done, and the pool puts it back the way setup left it.
+//!
+//! What is put back is the global environment's bindings, and the runtime's
+//! edition, division, quotas, denied capabilities, platform and
+//! per-evaluation state (see `Runtime::Reset`). Like with `speculation`,
+//! changes a request makes inside environments captured by closures, and
+//! the modules it imports, are not undone. Audit sinks and limit handlers
+//! are kept, so they belong in the setup.
+//!
+//! Not to be confused with the `pool` feature, which reuses bindings tables
+//! inside one interpreter.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use strum::IntoEnumIterator;
+
+use crate::capability::Capability;
+use crate::edition::Edition;
+use crate::environment::Env;
+use crate::environment::Snapshot;
+use crate::platform::Platform;
+use crate::runtime::Division;
+use crate::runtime::Quotas;
+
+pub struct Pool {
+    setup: Box<dyn Fn() -> Env>,
+    idle: RefCell<Vec<Warm>>,
+    /// How many idle interpreters are kept.
+    capacity: usize,
+}
+
+/// An interpreter, with what it is put back to.
+struct Warm {
+    env: Env,
+    bindings: Snapshot,
+    edition: Edition,
+    division: Division,
+    quotas: Quotas,
+    denied: Vec<Capability>,
+    platform: Rc<dyn Platform>,
+}
+
+impl Warm {
+    fn New(env: Env) -> Self {
+        let bindings = env.borrow().Snapshot();
+        let runtime = Rc::clone(env.borrow().Runtime());
+        Self {
+            env,
+            bindings,
+            edition: runtime.Edition(),
+            division: runtime.Division(),
+            quotas: runtime.Quotas(),
+            denied: Capability::iter()
+                .filter(|&c| !runtime.IsAllowed(c))
+                .collect(),
+            platform: runtime.Platform(),
+        }
+    }
+
+    fn sanitize(&self) {
+        let mut env = self.env.borrow_mut();
+        env.Restore(self.bindings.clone());
+        env.TakeDeferred();
+        let runtime = env.Runtime();
+        runtime.SetEdition(self.edition);
+        runtime.SetDivision(self.division);
+        runtime.SetQuotas(self.quotas.clone());
+        for capability in Capability::iter() {
+            match self.denied.contains(&capability) {
+                true => runtime.Deny(capability),
+                false => runtime.Allow(capability),
+            }
+        }
+        runtime.SetPlatform(Rc::clone(&self.platform));
+        runtime.TakeCalls();
+        runtime.Warnings().Take();
+        runtime.Reset();
+    }
+}
+
+impl Pool {
+    /// A pool of `capacity` interpreters, each made by `setup`, which returns
+    /// the global environment requests are evaluated in.
+    pub fn New(capacity: usize, setup: impl Fn() -> Env + 'static) -> Self {
+        let idle = (0..capacity).map(|_| Warm::New(setup())).collect();
+        Self {
+            setup: Box::new(setup),
+            idle: RefCell::new(idle),
+            capacity,
+        }
+    }
+
+    /// An idle interpreter, or a new one if there is none.
+    pub fn Get(&self) -> Pooled<'_> {
+        let warm = self.idle.borrow_mut().pop();
+        Pooled {
+            pool: self,
+            warm: Some(warm.unwrap_or_else(|| Warm::New((self.setup)()))),
+        }
+    }
+
+    /// How many interpreters are waiting to be checked out.
+    pub fn Idle(&self) -> usize {
+        self.idle.borrow().len()
+    }
+}
+
+/// A checked-out interpreter, returned to its pool when dropped.
+pub struct Pooled<'a> {
+    pool: &'a Pool,
+    warm: Option<Warm>,
+}
+
+impl Pooled<'_> {
+    /// The global environment to evaluate the request in.
+    pub fn Env(&self) -> &Env {
+        &self.warm.as_ref().unwrap().env
+    }
+}
+
+impl Drop for Pooled<'_> {
+    fn drop(&mut self) {
+        let warm = self.warm.take().unwrap();
+        let mut idle = self.pool.idle.borrow_mut();
+        if idle.len() < self.pool.capacity {
+            warm.sanitize();
+            idle.push(warm);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,82 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::edition::Edition;
+use crate::environment::Env;
+use crate::environment::Environment;
+use crate::evaluator::Eval;
+use crate::lexer::Lexer;
+use crate::object::Builtin;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::runtime::Runtime;
+
+use super::Pool;
+
+fn run(input: &str, env: &Env) -> String {
+    let mut p = Parser::New(Lexer::New(input));
+    Eval(p.ParseProgram().into(), env)
+        .map(|value| value.Inspect())
+        .unwrap_or_default()
+}
+
+fn pool(capacity: usize, setups: Rc<Cell<usize>>) -> Pool {
+    Pool::New(capacity, move || {
+        setups.set(setups.get() + 1);
+        let env = Environment::WithRuntime(Rc::new(Runtime::New()));
+        env.borrow_mut().Set(
+            "answer",
+            Builtin::new("answer", |_, _| Integer { value: 42 }.into()).into(),
+        );
+        run("let double = fn(x) { x * 2 }; let base = 10;", &env);
+        env
+    })
+}
+
+#[test]
+fn ReusesWarmInterpreters() {
+    let setups = Rc::new(Cell::new(0));
+    let pool = pool(2, Rc::clone(&setups));
+    assert_eq!((setups.get(), pool.Idle()), (2, 2));
+
+    for _ in 0..5 {
+        let interpreter = pool.Get();
+        assert_eq!(pool.Idle(), 1);
+        assert_eq!(run("double(base) + answer()", interpreter.Env()), "62");
+    }
+    assert_eq!((setups.get(), pool.Idle()), (2, 2));
+
+    // past the idle ones, new interpreters are made, and the extra are
+    // dropped on return
+    let checkedOut: Vec<_> = (0..3).map(|_| pool.Get()).collect();
+    assert_eq!((setups.get(), pool.Idle()), (3, 0));
+    drop(checkedOut);
+    assert_eq!(pool.Idle(), 2);
+}
+
+#[test]
+fn SanitizesOnReturn() {
+    let pool = pool(1, Rc::new(Cell::new(0)));
+    {
+        let interpreter = pool.Get();
+        let env = interpreter.Env();
+        run(
+            "let base = 1; let leaked = 5; let double = fn(x) { x };",
+            env,
+        );
+        let runtime = Rc::clone(env.borrow().Runtime());
+        runtime.SetEdition(Edition::V2);
+        runtime.Cancellation().Cancel();
+        runtime.Record();
+    }
+    let interpreter = pool.Get();
+    let env = interpreter.Env();
+    assert_eq!(run("double(base)", env), "20");
+    assert_eq!(run("leaked", env), "ERROR: identifier not found: leaked");
+    let runtime = Rc::clone(env.borrow().Runtime());
+    assert_eq!(runtime.Edition(), Edition::default());
+    assert!(!runtime.Cancellation().IsCancelled());
+    assert_eq!(runtime.TakeCalls(), vec![]);
+    assert_eq!(run("answer()", env), "42");
+}
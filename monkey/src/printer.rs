@@ -0,0 +1,376 @@
+//! Prints a syntax tree back as Monkey source, laid out the way people write
+//! it: a statement a line, blocks indented, and only the parentheses needed
+//! to parse the same way, so `(a + (b * c))` prints as `a + b * c`. It is
+//! what a formatter writes; `Node::String` shows the structure instead, with
+//! every operation in parentheses.
+
+use crate::ast::BlockStatement;
+use crate::ast::ExpressionEnum;
+use crate::ast::FunctionLiteral;
+use crate::ast::MatchPattern;
+use crate::ast::Program;
+use crate::ast::StatementEnum;
+use crate::parser::Precedence;
+use crate::token::TokenKind;
+
+/// How printed code is laid out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    /// What each level of nesting is indented by.
+    pub indent: String,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            indent: "    ".into(),
+        }
+    }
+}
+
+/// `program` as source, ending in a newline unless it is empty. Parsing it
+/// gives back the same tree, except that an interpolated string comes back
+/// as the `+`s it is made of. Arrow functions stay arrow functions.
+pub fn Print(program: &Program, style: &Style) -> String {
+    let mut printer = Printer {
+        style,
+        out: String::new(),
+        level: 0,
+    };
+    printer.statements(&program.statements);
+    if !program.statements.is_empty() {
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+struct Printer<'a> {
+    style: &'a Style,
+    out: String,
+    /// How deeply the code being printed is nested.
+    level: usize,
+}
+
+impl Printer<'_> {
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.level {
+            self.out += &self.style.indent;
+        }
+    }
+
+    /// The statements a line each. An `if`, `match` or `try` goes without a
+    /// semicolon, unless the statement after it would otherwise continue it,
+    /// like `(f)(x)` calling the `if`.
+    fn statements(&mut self, statements: &[StatementEnum]) {
+        let printed: Vec<String> = statements
+            .iter()
+            .map(|stmt| {
+                let out = std::mem::take(&mut self.out);
+                self.statement(stmt);
+                std::mem::replace(&mut self.out, out)
+            })
+            .collect();
+        for (i, text) in printed.iter().enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            self.out += text;
+            let continues = |next: &String| next.starts_with(&['(', '[', '-', '+'][..]);
+            if endsWithBlock(&statements[i]) && printed.get(i + 1).is_some_and(continues) {
+                self.out.push(';');
+            }
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) {
+        if block.statements.is_empty() {
+            self.out += "{}";
+            return;
+        }
+        self.out.push('{');
+        self.level += 1;
+        self.newline();
+        self.statements(&block.statements);
+        self.level -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn statement(&mut self, stmt: &StatementEnum) {
+        match stmt {
+            StatementEnum::Let(l) => {
+                self.out += &format!("let {} = ", l.name.value);
+                self.expression(&l.value);
+            }
+            StatementEnum::Destructure(d) => {
+                self.out += &format!("let {} = ", d.pattern);
+                self.expression(&d.value);
+            }
+            StatementEnum::Return(r) => {
+                self.out += "return ";
+                self.expression(&r.returnValue);
+            }
+            StatementEnum::Defer(d) => {
+                self.out += "defer ";
+                self.expression(&d.expression);
+            }
+            StatementEnum::Throw(t) => {
+                self.out += "throw ";
+                self.expression(&t.value);
+            }
+            StatementEnum::Expression(e) => self.expression(&e.expression),
+            StatementEnum::Block(b) => self.block(b),
+        }
+        if !endsWithBlock(stmt) {
+            self.out.push(';');
+        }
+    }
+
+    /// `exp` where any expression can go, like an argument.
+    fn expression(&mut self, exp: &ExpressionEnum) {
+        self.operand(exp, false)
+    }
+
+    fn operand(&mut self, exp: &ExpressionEnum, parens: bool) {
+        if parens {
+            self.out.push('(');
+        }
+        self.bare(exp);
+        if parens {
+            self.out.push(')');
+        }
+    }
+
+    fn list<'e>(&mut self, expressions: impl IntoIterator<Item = &'e ExpressionEnum>) {
+        for (i, exp) in expressions.into_iter().enumerate() {
+            if i > 0 {
+                self.out += ", ";
+            }
+            self.expression(exp);
+        }
+    }
+
+    fn bare(&mut self, exp: &ExpressionEnum) {
+        match exp {
+            ExpressionEnum::Identifier(i) => self.out += &i.value,
+            ExpressionEnum::IntegerLiteral(i) => self.out += &i.value.to_string(),
+            ExpressionEnum::StringLiteral(s) => self.out += &quoted(&s.value),
+            ExpressionEnum::Boolean(b) => self.out += &b.value.to_string(),
+            ExpressionEnum::NullLiteral(_) => self.out += "null",
+            ExpressionEnum::PrefixExpression(p) => {
+                self.out += &p.operator;
+                self.operand(&p.right, looser(&p.right, Precedence::POSTFIX));
+            }
+            ExpressionEnum::InfixExpression(i) => {
+                let precedence = Precedence::Of(i.token.kind);
+                self.operand(&i.left, looser(&i.left, precedence));
+                self.out += &format!(" {} ", i.operator);
+                // the operators are left-associative, so one as tight on the
+                // right is grouped
+                let right = matches!(self::precedence(&i.right), Some(p) if p <= precedence);
+                self.operand(&i.right, right);
+            }
+            ExpressionEnum::UpdateExpression(u) if u.prefix => {
+                self.out += &format!("{}{}", u.operator, u.target.value)
+            }
+            ExpressionEnum::UpdateExpression(u) => {
+                self.out += &format!("{}{}", u.target.value, u.operator)
+            }
+            ExpressionEnum::IfExpression(i) => {
+                self.out += "if (";
+                self.expression(&i.condition);
+                self.out += ") ";
+                self.block(&i.consequence);
+                if let Some(alternative) = &i.alternative {
+                    self.out += " else ";
+                    self.block(alternative);
+                }
+            }
+            ExpressionEnum::MatchExpression(m) => {
+                self.out += "match ";
+                self.expression(&m.subject);
+                if m.arms.is_empty() {
+                    self.out += " {}";
+                    return;
+                }
+                self.out += " {";
+                self.level += 1;
+                for arm in &m.arms {
+                    self.newline();
+                    match &arm.pattern {
+                        MatchPattern::Literal(e) => self.expression(e),
+                        MatchPattern::Wildcard(_) => self.out.push('_'),
+                    }
+                    self.out += " => ";
+                    match arm.body.as_ref() {
+                        StatementEnum::Block(b) => self.block(b),
+                        // a brace after the arrow starts a block, so a body
+                        // that would start with one goes in parentheses
+                        StatementEnum::Expression(e) => {
+                            let start = self.out.len();
+                            self.expression(&e.expression);
+                            if self.out[start..].starts_with('{') {
+                                self.out.insert(start, '(');
+                                self.out.push(')');
+                            }
+                        }
+                        other => self.statement(other),
+                    }
+                    self.out.push(',');
+                }
+                self.level -= 1;
+                self.newline();
+                self.out.push('}');
+            }
+            ExpressionEnum::FunctionLiteral(f) => match arrowBody(f) {
+                Some(body) => {
+                    self.out += &format!("|{}| ", itertools::join(&f.parameters, ", "));
+                    self.expression(body);
+                }
+                None => {
+                    self.out += &format!("fn({}) ", itertools::join(&f.parameters, ", "));
+                    self.block(&f.body);
+                }
+            },
+            ExpressionEnum::MacroLiteral(m) => {
+                self.out += &format!("macro({}) ", itertools::join(&m.parameters, ", "));
+                self.block(&m.body);
+            }
+            ExpressionEnum::CallExpression(c) => {
+                self.operand(&c.function, looser(&c.function, Precedence::CALL));
+                self.out.push('(');
+                self.list(&c.arguments);
+                self.out.push(')');
+            }
+            ExpressionEnum::ArrayLiteral(a) => {
+                self.out.push('[');
+                self.list(&a.elements);
+                self.out.push(']');
+            }
+            ExpressionEnum::IndexExpression(i) => {
+                self.operand(&i.left, looser(&i.left, Precedence::CALL));
+                self.out.push('[');
+                self.expression(&i.index);
+                self.out.push(']');
+            }
+            ExpressionEnum::SliceExpression(s) => {
+                self.operand(&s.left, looser(&s.left, Precedence::CALL));
+                self.out.push('[');
+                if let Some(start) = &s.start {
+                    self.expression(start);
+                }
+                self.out.push(':');
+                if let Some(end) = &s.end {
+                    self.expression(end);
+                }
+                self.out.push(']');
+            }
+            ExpressionEnum::SpreadExpression(s) => {
+                self.out += "...";
+                self.expression(&s.value);
+            }
+            ExpressionEnum::HashLiteral(h) => {
+                self.out.push('{');
+                for (i, (key, value)) in h.pairs.iter().enumerate() {
+                    if i > 0 {
+                        self.out += ", ";
+                    }
+                    self.expression(key);
+                    self.out += ": ";
+                    self.expression(value);
+                }
+                self.out.push('}');
+            }
+            ExpressionEnum::KeywordArgument(k) => {
+                self.out += &format!("{}: ", k.name.value);
+                self.expression(&k.value);
+            }
+            ExpressionEnum::TryExpression(t) => {
+                self.out += "try ";
+                self.block(&t.body);
+                self.out += &format!(" catch ({}) ", t.name.value);
+                self.block(&t.handler);
+            }
+            ExpressionEnum::ImportExpression(i) => {
+                // nothing binds tighter than the path
+                self.out += "import ";
+                self.operand(&i.path, precedence(&i.path).is_some());
+            }
+        }
+    }
+}
+
+/// How tightly `exp` holds together when printed without parentheses, or
+/// `None` if nothing can pull it apart, like a literal or an `if`.
+fn precedence(exp: &ExpressionEnum) -> Option<Precedence> {
+    match exp {
+        ExpressionEnum::InfixExpression(i) => Some(Precedence::Of(i.token.kind)),
+        ExpressionEnum::PrefixExpression(_) => Some(Precedence::PREFIX),
+        ExpressionEnum::UpdateExpression(u) if u.prefix => Some(Precedence::PREFIX),
+        ExpressionEnum::UpdateExpression(_) => Some(Precedence::POSTFIX),
+        // a folded constant can be negative
+        ExpressionEnum::IntegerLiteral(i) if i.value < 0 => Some(Precedence::PREFIX),
+        ExpressionEnum::CallExpression(_) => Some(Precedence::CALL),
+        // its body takes in everything after it
+        ExpressionEnum::FunctionLiteral(f) if arrowBody(f).is_some() => Some(Precedence::LOWEST),
+        ExpressionEnum::IndexExpression(_) | ExpressionEnum::SliceExpression(_) => {
+            Some(Precedence::INDEX)
+        }
+        _ => None,
+    }
+}
+
+/// The expression an arrow function like `|x| x + 1` was written with,
+/// which the parser puts in a block at the arrow function's first token.
+fn arrowBody(f: &FunctionLiteral) -> Option<&ExpressionEnum> {
+    match f.body.statements.as_slice() {
+        [StatementEnum::Expression(e)] if f.body.token.kind == TokenKind::PIPE => {
+            Some(&e.expression)
+        }
+        _ => None,
+    }
+}
+
+/// Whether `exp` needs parentheses where it has to hold together at
+/// `precedence`.
+fn looser(exp: &ExpressionEnum, precedence: Precedence) -> bool {
+    matches!(self::precedence(exp), Some(p) if p < precedence)
+}
+
+/// Whether the statement ends with the block of an `if`, `match` or `try`,
+/// which a semicolon is left out after.
+fn endsWithBlock(stmt: &StatementEnum) -> bool {
+    match stmt {
+        StatementEnum::Expression(e) => matches!(
+            e.expression,
+            ExpressionEnum::IfExpression(_)
+                | ExpressionEnum::MatchExpression(_)
+                | ExpressionEnum::TryExpression(_)
+        ),
+        StatementEnum::Block(_) => true,
+        _ => false,
+    }
+}
+
+/// `value` as a string literal, with the escapes it needs.
+fn quoted(value: &str) -> String {
+    let mut out = String::from('"');
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\t' => out += "\\t",
+            '\r' => out += "\\r",
+            '$' if chars.peek() == Some(&'{') => out += "\\$",
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+use super::*;
+use crate::ast::Diff;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+fn parse(input: &str) -> Program {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "parser errors: {:?}", p.errors);
+    program
+}
+
+#[test]
+fn PrintsMinimalParentheses() {
+    let tests = vec![
+        ("(a + (b * c))", "a + b * c;"),
+        ("(a + b) * c", "(a + b) * c;"),
+        ("a - (b - c)", "a - (b - c);"),
+        ("(a - b) - c", "a - b - c;"),
+        ("-(a + b)", "-(a + b);"),
+        ("-(-a)", "-(-a);"),
+        ("!f(x)[0]", "!f(x)[0];"),
+        ("(-a)[0]", "(-a)[0];"),
+        ("(a + b)(c)", "(a + b)(c);"),
+        ("a < b == (c > d)", "a < b == c > d;"),
+        ("import (\"a\" + x)", "import (\"a\" + x);"),
+        ("(import \"a\")[\"f\"]", "import \"a\"[\"f\"];"),
+        ("x++ + ++y", "x++ + ++y;"),
+        (r#""a\"b\\${x}\${y}""#, r#""a\"b\\" + str(x) + "\${y}";"#),
+        ("let f = |x| x * 2", "let f = |x| x * 2;"),
+        ("(|x| x)(1) + (|| 2)()", "(|x| x)(1) + (|| 2)();"),
+        ("let f = fn(x) { x * 2 }", "let f = fn(x) {\n    x * 2;\n};"),
+        ("f(...xs, k: {1: [2]})", "f(...xs, k: {1: [2]});"),
+        ("xs[1:][:2]", "xs[1:][:2];"),
+        ("let [a, b] = c", "let [a, b] = c;"),
+    ];
+    for (input, expected) in tests {
+        let printed = Print(&parse(input), &Style::default());
+        assert_eq!(printed, format!("{}\n", expected), "{}", input);
+    }
+}
+
+#[test]
+fn PrintsBlocksIndented() {
+    let input = "let f = fn(x) { if (x) { return 1; } else { g(); defer h() }; \
+                 match x { 1 => { 2 }, \"s\" => {}, -1 => ({1: 2}), _ => 3 } } \
+                 try { throw f } catch (e) { e }; [f][0]";
+    let expected = "\
+let f = fn(x) {
+\tif (x) {
+\t\treturn 1;
+\t} else {
+\t\tg();
+\t\tdefer h();
+\t}
+\tmatch x {
+\t\t1 => {
+\t\t\t2;
+\t\t},
+\t\t\"s\" => {},
+\t\t-1 => ({1: 2}),
+\t\t_ => 3,
+\t}
+};
+try {
+\tthrow f;
+} catch (e) {
+\te;
+};
+[f][0];
+";
+    let style = Style {
+        indent: "\t".into(),
+    };
+    assert_eq!(Print(&parse(input), &style), expected);
+}
+
+#[test]
+fn PrintedCodeParsesTheSame() {
+    let inputs = vec![
+        "let a = 1 + 2 * 3 - -4 / (5 % 6); a(b)(c)[d][e:f];",
+        "if (a) { b } else { c } (d); if (e) { f } -g; match (h) { _ => i } [j]",
+        "let f = fn(a, b) { let c = a + b; c * c }; f(1, 2) + f(3, 4) * 5",
+        "|x| |y| x + y; macro(x) { quote(unquote(x) + 1) }",
+        "try { import \"m\"[\"f\"](1) } catch (e) { throw e + \"!\"; }",
+        "!(a == b) != !c; --x; y--; {\"k\": [1, 2, ...z]}[\"k\"][0]",
+        "(a - b) - c; (-i); (f)(x)",
+        "match (1) { 1 => ({a: 1}) == 2, 2 => ({a: 1})[b], 3 => ({a: 1}) % x, _ => 0 }",
+    ];
+    for input in inputs {
+        let program = parse(input);
+        let printed = Print(&program, &Style::default());
+        let reparsed = parse(&printed);
+        assert_eq!(Diff(&program, &reparsed), None, "{}\n{}", input, printed);
+    }
+}
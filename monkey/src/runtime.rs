@@ -0,0 +1,850 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::builtins;
+use crate::capability::Capability;
+use crate::edition::Edition;
+use crate::evaluator;
+use crate::module::Loader;
+use crate::object::Builtin;
+use crate::object::Error;
+use crate::object::ErrorKind;
+use crate::object::Integer;
+use crate::object::Object;
+use crate::object::ObjectEnum;
+use crate::object::Str;
+use crate::object::NULL;
+use crate::platform::Platform;
+use crate::platform::StdPlatform;
+use crate::token::Position;
+use crate::warning::Warnings;
+
+/// How many emptied bindings tables a runtime keeps at most.
+#[cfg(feature = "pool")]
+const POOL_SIZE: usize = 64;
+
+/// A flag that aborts evaluation from another thread (or a signal handler).
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn New() -> Self {
+        Self::default()
+    }
+
+    pub fn Cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn IsCancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Clears a previous cancellation so the token can be used again.
+    pub fn Reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// How much work a single evaluation may do. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Evaluation steps, counted per statement and per function call.
+    pub fuel: Option<u64>,
+    /// Wall-clock time.
+    pub time: Option<Duration>,
+    /// How deeply function calls may nest. Calls in tail position don't
+    /// count, since they replace the call they are made from.
+    pub depth: Option<usize>,
+}
+
+/// The stack a nested call takes at most, with room to spare. Calls take
+/// about four times as much in an unoptimized build.
+const CALL_STACK_SIZE: usize = if cfg!(debug_assertions) {
+    256 * 1024
+} else {
+    64 * 1024
+};
+
+/// The stack an evaluation takes outside the calls it nests.
+const BASE_STACK_SIZE: usize = 1024 * 1024;
+
+impl Limits {
+    /// How much stack an evaluation needs for calls to nest as deep as
+    /// `depth` allows, so going deeper is a `StackOverflow` error rather than
+    /// a crash. `None` if the depth is unlimited.
+    pub const fn StackSize(&self) -> Option<usize> {
+        match self.depth {
+            Some(depth) => {
+                Some(BASE_STACK_SIZE.saturating_add(depth.saturating_mul(CALL_STACK_SIZE)))
+            }
+            None => None,
+        }
+    }
+}
+
+/// Limits on particular builtins and modules, on top of `Limits`. Running
+/// out of a quota is a `LimitExceeded` error; the limit handler isn't asked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quotas {
+    /// How many times each builtin may be called in one evaluation, by name.
+    pub calls: HashMap<String, u64>,
+    /// How long the top level of each module may run while it is imported,
+    /// by the name it is imported by. Calls to its functions from other
+    /// modules don't count.
+    pub moduleTime: HashMap<String, Duration>,
+}
+
+/// How `/` and `%` round the quotient when it isn't whole.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Division {
+    /// Towards zero, so a remainder has the sign of the dividend: `-7 / 2` is
+    /// -3 and `-7 % 2` is -1. Like Go, and so the book.
+    #[default]
+    Truncate,
+    /// Down, so a remainder has the sign of the divisor: `-7 / 2` is -4 and
+    /// `-7 % 2` is 1.
+    Floor,
+}
+
+/// How much work was done since the last `Reset`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunStats {
+    /// Evaluation steps, as counted against `Limits::fuel`.
+    pub steps: u64,
+    pub builtinCalls: u64,
+    /// How deeply function calls nested at most, counted like for
+    /// `Limits::depth`.
+    pub maxDepth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Fuel(u64),
+    Time(Duration),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fuel(steps) => write!(f, "{} steps", steps),
+            Self::Time(time) => write!(f, "{:?}", time),
+        }
+    }
+}
+
+/// Decides whether an evaluation that ran out of `Limit` gets another budget of
+/// the same size (`true`) or stops with a `LimitExceeded` error (`false`).
+pub type LimitHandler = Box<dyn FnMut(Limit) -> bool>;
+
+/// A builtin call and what it returned, as captured by `Runtime::Record`.
+/// Calls are in the order they start, so the calls a builtin like `map`
+/// makes through the functions it is given come right after its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostCall {
+    pub name: String,
+    pub args: Vec<ObjectEnum>,
+    pub result: ObjectEnum,
+}
+
+/// A use of a capability by a script, as given to an audit sink.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// When it started, by the platform's wall clock.
+    pub time: SystemTime,
+    pub capability: Capability,
+    /// The builtin called, or `import`.
+    pub name: String,
+    /// Where in the script the call or import is. A builtin called by another,
+    /// like `puts` given to `map`, is where that one is called.
+    pub call: Position,
+    /// Secrets stay wrapped, so what they hold never reaches the sink.
+    pub args: Vec<ObjectEnum>,
+}
+
+pub type AuditSink = Box<dyn FnMut(&AuditEntry)>;
+
+#[derive(Debug, Default)]
+enum CallLog {
+    #[default]
+    Off,
+    Recording(Vec<HostCall>),
+    Replaying(VecDeque<HostCall>),
+}
+
+/// State shared by every environment of one interpreter: the things that can
+/// stop an evaluation part way through.
+pub struct Runtime {
+    cancellation: CancellationToken,
+    limits: Limits,
+    fuel: Cell<Option<u64>>,
+    /// By the platform's clock.
+    deadline: Cell<Option<Duration>>,
+    onLimit: RefCell<Option<LimitHandler>>,
+    calls: RefCell<CallLog>,
+    audit: RefCell<Option<AuditSink>>,
+    denied: RefCell<BTreeSet<Capability>>,
+    quotas: RefCell<Quotas>,
+    /// How many times each builtin with a quota has been called.
+    callCounts: RefCell<HashMap<String, u64>>,
+    /// The modules being imported that have a time quota, with the names
+    /// they are imported by and their deadlines.
+    moduleDeadlines: RefCell<Vec<(String, Duration)>>,
+    /// How many builtin calls are running that a replay serves without
+    /// running them, so the calls they make mustn't be recorded.
+    servedDepth: Cell<usize>,
+    /// Where the builtin call being made is, for auditing.
+    callSite: Cell<Position>,
+    /// How many function calls are currently running.
+    depth: Cell<usize>,
+    stats: Cell<RunStats>,
+    edition: Cell<Edition>,
+    division: Cell<Division>,
+    warnings: RefCell<Warnings>,
+    loader: RefCell<Loader>,
+    platform: RefCell<Rc<dyn Platform>>,
+    /// The state of the random numbers scripts get, seeded from the platform
+    /// when the first one is drawn unless it was seeded before.
+    random: Cell<Option<u64>>,
+    /// Emptied bindings tables of finished calls, for the next calls to reuse.
+    #[cfg(feature = "pool")]
+    stores: RefCell<Vec<HashMap<String, ObjectEnum>>>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            cancellation: CancellationToken::default(),
+            limits: Limits::default(),
+            fuel: Cell::default(),
+            deadline: Cell::default(),
+            onLimit: RefCell::default(),
+            calls: RefCell::default(),
+            audit: RefCell::default(),
+            denied: RefCell::new(BTreeSet::from([Capability::Fs])),
+            quotas: RefCell::default(),
+            callCounts: RefCell::default(),
+            moduleDeadlines: RefCell::default(),
+            servedDepth: Cell::default(),
+            callSite: Cell::default(),
+            depth: Cell::default(),
+            stats: Cell::default(),
+            edition: Cell::default(),
+            division: Cell::default(),
+            warnings: RefCell::default(),
+            loader: RefCell::default(),
+            platform: RefCell::new(Rc::new(StdPlatform)),
+            random: Cell::default(),
+            #[cfg(feature = "pool")]
+            stores: RefCell::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Runtime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Runtime")
+            .field("cancellation", &self.cancellation)
+            .field("limits", &self.limits)
+            .field("fuel", &self.fuel)
+            .field("deadline", &self.deadline)
+            .field("depth", &self.depth)
+            .field("stats", &self.stats)
+            .field("calls", &self.calls)
+            .field("edition", &self.edition)
+            .field("division", &self.division)
+            .field("warnings", &self.warnings)
+            .field("loader", &self.loader)
+            .finish()
+    }
+}
+
+impl Runtime {
+    pub fn New() -> Self {
+        Self::default()
+    }
+
+    pub fn WithLimits(limits: Limits) -> Self {
+        let runtime = Self {
+            limits,
+            ..Self::default()
+        };
+        runtime.Reset();
+        runtime
+    }
+
+    pub fn Cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn Limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn Quotas(&self) -> Quotas {
+        self.quotas.borrow().clone()
+    }
+
+    /// Replaces the quotas, starting their counts over.
+    pub fn SetQuotas(&self, quotas: Quotas) {
+        *self.quotas.borrow_mut() = quotas;
+        self.callCounts.borrow_mut().clear();
+    }
+
+    /// Counts a call of the builtin `name`. Returns the error that should be
+    /// its result if that is one call too many.
+    fn countCall(&self, name: &str) -> Option<ObjectEnum> {
+        let max = *self.quotas.borrow().calls.get(name)?;
+        let mut counts = self.callCounts.borrow_mut();
+        let count = counts.entry(name.to_string()).or_default();
+        if *count >= max {
+            return Some(
+                Error::new(
+                    ErrorKind::LimitExceeded,
+                    format!("quota exceeded: more than {} calls to {}", max, name),
+                )
+                .with(
+                    "name",
+                    Str {
+                        value: name.to_string(),
+                    },
+                )
+                .with("calls", Integer { value: max as i64 })
+                .into(),
+            );
+        }
+        *count += 1;
+        None
+    }
+
+    /// Called by the evaluator as the top level of the module imported by
+    /// `name` starts running. Must be followed by `ExitModule`.
+    pub(crate) fn EnterModule(&self, name: &str) {
+        if let Some(&time) = self.quotas.borrow().moduleTime.get(name) {
+            self.moduleDeadlines
+                .borrow_mut()
+                .push((name.to_string(), self.now() + time));
+        }
+    }
+
+    pub(crate) fn ExitModule(&self, name: &str) {
+        let mut deadlines = self.moduleDeadlines.borrow_mut();
+        if deadlines.last().is_some_and(|(module, _)| module == name) {
+            deadlines.pop();
+        }
+    }
+
+    /// The error for the first module being imported that has run out of
+    /// time, if any.
+    fn checkModules(&self) -> Option<ObjectEnum> {
+        let deadlines = self.moduleDeadlines.borrow();
+        if deadlines.is_empty() {
+            return None;
+        }
+        let now = self.now();
+        let (name, _) = deadlines.iter().find(|(_, deadline)| now >= *deadline)?;
+        let time = self.quotas.borrow().moduleTime[name];
+        Some(
+            Error::new(
+                ErrorKind::LimitExceeded,
+                format!(
+                    "quota exceeded: module {} ran for more than {:?}",
+                    name, time
+                ),
+            )
+            .with(
+                "module",
+                Str {
+                    value: name.clone(),
+                },
+            )
+            .into(),
+        )
+    }
+
+    /// Installs the handler consulted when an evaluation runs out of a limit.
+    /// Without one, running out is always an error.
+    pub fn OnLimitExceeded(&self, handler: impl FnMut(Limit) -> bool + 'static) {
+        *self.onLimit.borrow_mut() = Some(Box::new(handler));
+    }
+
+    pub(crate) fn ReplaceLimitHandler(
+        &self,
+        handler: Option<LimitHandler>,
+    ) -> Option<LimitHandler> {
+        std::mem::replace(&mut *self.onLimit.borrow_mut(), handler)
+    }
+
+    /// The edition whose semantics scripts are evaluated with. Parsing them
+    /// with the same edition is up to the embedder.
+    pub fn Edition(&self) -> Edition {
+        self.edition.get()
+    }
+
+    pub fn SetEdition(&self, edition: Edition) {
+        self.edition.set(edition);
+    }
+
+    pub fn Division(&self) -> Division {
+        self.division.get()
+    }
+
+    pub fn SetDivision(&self, division: Division) {
+        self.division.set(division);
+    }
+
+    /// The warnings raised while evaluating, kept across evaluations until
+    /// taken.
+    pub fn Warnings(&self) -> RefMut<'_, Warnings> {
+        self.warnings.borrow_mut()
+    }
+
+    /// Where `import` finds modules, and the modules it has loaded.
+    pub fn Loader(&self) -> RefMut<'_, Loader> {
+        self.loader.borrow_mut()
+    }
+
+    /// What builtins and `import` use to reach the machine. Defaults to
+    /// `StdPlatform`.
+    pub fn Platform(&self) -> Rc<dyn Platform> {
+        Rc::clone(&self.platform.borrow())
+    }
+
+    /// Replaces the platform, restarting the clock of the time limit on the
+    /// new one.
+    pub fn SetPlatform(&self, platform: Rc<dyn Platform>) {
+        *self.platform.borrow_mut() = platform;
+        self.deadline
+            .set(self.limits.time.map(|time| self.now() + time));
+    }
+
+    fn now(&self) -> Duration {
+        self.platform.borrow().Now()
+    }
+
+    /// Sleeps for `time`, but not past the time limit, so the evaluation then
+    /// ends on time.
+    pub(crate) fn Sleep(&self, time: Duration) {
+        let time = match self.deadline.get() {
+            Some(deadline) => time.min(deadline.saturating_sub(self.now())),
+            None => time,
+        };
+        self.Platform().Sleep(time);
+    }
+
+    /// Makes the random numbers scripts get from here on the same every time
+    /// for the same `seed`.
+    pub fn SeedRandom(&self, seed: u64) {
+        // xorshift gets stuck on 0, so 0 seeds like 1
+        self.random.set(Some(seed.max(1)));
+    }
+
+    /// The next of the random numbers scripts get.
+    pub(crate) fn NextRandom(&self) -> u64 {
+        // xorshift64*
+        let mut x = match self.random.get() {
+            Some(x) => x,
+            None => self.platform.borrow().Random().max(1),
+        };
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.random.set(Some(x));
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Installs `sink` to be given an entry for every call of a builtin that
+    /// needs a capability, and every import, as it happens. Calls served by a
+    /// replay don't happen, so they aren't audited.
+    pub fn SetAuditSink(&self, sink: impl FnMut(&AuditEntry) + 'static) {
+        *self.audit.borrow_mut() = Some(Box::new(sink));
+    }
+
+    pub fn RemoveAuditSink(&self) {
+        *self.audit.borrow_mut() = None;
+    }
+
+    /// Makes every use of `capability` by a script a `PermissionDenied`
+    /// error, until it is allowed again. Everything but `Fs` is allowed at
+    /// first.
+    pub fn Deny(&self, capability: Capability) {
+        self.denied.borrow_mut().insert(capability);
+    }
+
+    pub fn Allow(&self, capability: Capability) {
+        self.denied.borrow_mut().remove(&capability);
+    }
+
+    pub fn IsAllowed(&self, capability: Capability) -> bool {
+        !self.denied.borrow().contains(&capability)
+    }
+
+    /// The error for a script using `capability` through `name`, if it is
+    /// denied.
+    pub(crate) fn CheckAllowed(&self, capability: Capability, name: &str) -> Option<ObjectEnum> {
+        if self.IsAllowed(capability) {
+            return None;
+        }
+        Some(
+            Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "`{}` needs the {} capability, which is denied",
+                    name, capability
+                ),
+            )
+            .with(
+                "capability",
+                Str {
+                    value: capability.to_string(),
+                },
+            )
+            .into(),
+        )
+    }
+
+    /// Makes `call` where the builtin calls from now on are, returning where
+    /// they were.
+    pub(crate) fn SetCallSite(&self, call: Position) -> Position {
+        self.callSite.replace(call)
+    }
+
+    pub(crate) fn Audit(
+        &self,
+        capability: Capability,
+        name: &str,
+        args: &[ObjectEnum],
+        call: Position,
+    ) {
+        // taken out while it runs, in case it uses this runtime
+        let mut sink = match self.audit.borrow_mut().take() {
+            Some(sink) => sink,
+            None => return,
+        };
+        sink(&AuditEntry {
+            time: self.platform.borrow().SystemTime(),
+            capability,
+            name: name.to_string(),
+            args: args.to_vec(),
+            call,
+        });
+        let mut audit = self.audit.borrow_mut();
+        if audit.is_none() {
+            *audit = Some(sink);
+        }
+    }
+
+    /// Starts recording every builtin call and its result, discarding any
+    /// previous recording or replay.
+    pub fn Record(&self) {
+        *self.calls.borrow_mut() = CallLog::Recording(vec![]);
+    }
+
+    /// Serves builtin calls from `calls` instead of calling the builtins, so
+    /// a recorded run can be reproduced without its host. A call that doesn't
+    /// match the next recorded one, by name and arguments, is a `ReplayError`;
+    /// functions match if they have the same parameters and body. Standard
+    /// builtins that are given functions and need no capability, like `map`,
+    /// run again, so the functions do too.
+    pub fn Replay(&self, calls: Vec<HostCall>) {
+        *self.calls.borrow_mut() = CallLog::Replaying(calls.into());
+    }
+
+    /// Stops recording or replaying. Returns the calls recorded, or those a
+    /// replay didn't get to.
+    pub fn TakeCalls(&self) -> Vec<HostCall> {
+        match std::mem::take(&mut *self.calls.borrow_mut()) {
+            CallLog::Off => vec![],
+            CallLog::Recording(calls) => calls,
+            CallLog::Replaying(calls) => calls.into(),
+        }
+    }
+
+    pub(crate) fn CallBuiltin(&self, builtin: &Builtin, args: Vec<ObjectEnum>) -> ObjectEnum {
+        self.updateStats(|stats| stats.builtinCalls += 1);
+        let capability = builtins::CapabilityOf(&builtin.name);
+        if let Some(err) = capability.and_then(|c| self.CheckAllowed(c, &builtin.name)) {
+            return err;
+        }
+        if let Some(err) = self.countCall(&builtin.name) {
+            return err;
+        }
+        let reruns = runsAgain(builtin, &args);
+        if matches!(*self.calls.borrow(), CallLog::Replaying(_)) {
+            return match self.nextReplayed(builtin, &args) {
+                // the calls of the functions it's given come next
+                Ok(_) if reruns => (builtin.func)(self, args),
+                Ok(call) => call.result,
+                Err(err) => err,
+            };
+        }
+
+        if let Some(capability) = capability {
+            self.Audit(capability, &builtin.name, &args, self.callSite.get());
+        }
+        // a builtin may call back into the evaluator; the calls it makes are
+        // only recorded if a replay runs it again, rather than skipping them
+        // by serving its result
+        let depth = self.servedDepth.get();
+        let recorded = match &mut *self.calls.borrow_mut() {
+            CallLog::Recording(calls) if depth == 0 => {
+                calls.push(HostCall {
+                    name: builtin.name.clone(),
+                    args: args.clone(),
+                    result: NULL,
+                });
+                Some(calls.len() - 1)
+            }
+            _ => None,
+        };
+        self.servedDepth.set(depth + usize::from(!reruns));
+        let result = (builtin.func)(self, args);
+        self.servedDepth.set(depth);
+        if let (Some(index), CallLog::Recording(calls)) = (recorded, &mut *self.calls.borrow_mut())
+        {
+            calls[index].result = result.clone();
+        }
+        result
+    }
+
+    /// Takes the recorded call a replay is at, if it is a call of `builtin`
+    /// with `args`, and otherwise gives the `ReplayError`.
+    fn nextReplayed(&self, builtin: &Builtin, args: &[ObjectEnum]) -> Result<HostCall, ObjectEnum> {
+        let mut log = self.calls.borrow_mut();
+        let calls = match &mut *log {
+            CallLog::Replaying(calls) => calls,
+            _ => unreachable!("only called while replaying"),
+        };
+        match calls.front() {
+            Some(call) if call.name == builtin.name && sameArguments(&call.args, args) => {
+                Ok(calls.pop_front().unwrap())
+            }
+            expected => {
+                let expected = match expected {
+                    Some(call) => describeCall(&call.name, &call.args),
+                    None => "no more calls".into(),
+                };
+                Err(Error::new(
+                    ErrorKind::ReplayError,
+                    format!(
+                        "replay diverged: expected {}, got {}",
+                        expected,
+                        describeCall(&builtin.name, args)
+                    ),
+                )
+                .with(
+                    "name",
+                    Str {
+                        value: builtin.name.clone(),
+                    },
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Calls `function` with `args`, for builtins that take functions. The
+    /// result may be an error or a thrown value, to be returned as it is.
+    pub fn Call(&self, function: ObjectEnum, args: Vec<ObjectEnum>) -> ObjectEnum {
+        evaluator::applyFunction(function, args, self)
+    }
+
+    /// A bindings table for a new environment.
+    pub(crate) fn TakeStore(&self) -> HashMap<String, ObjectEnum> {
+        #[cfg(feature = "pool")]
+        if let Some(store) = self.stores.borrow_mut().pop() {
+            return store;
+        }
+        HashMap::new()
+    }
+
+    /// Keeps `store`, which no environment uses anymore, for `TakeStore`.
+    #[cfg(feature = "pool")]
+    pub(crate) fn RecycleStore(&self, mut store: HashMap<String, ObjectEnum>) {
+        if self.stores.borrow().len() < POOL_SIZE {
+            // dropping the bindings may release environments, which recycle
+            // their own stores
+            store.clear();
+            let mut stores = self.stores.borrow_mut();
+            if stores.len() < POOL_SIZE {
+                stores.push(store);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "pool"))]
+    pub(crate) fn RecycleStore(&self, _store: HashMap<String, ObjectEnum>) {}
+
+    /// Prepares for a new evaluation: clears any cancellation, refills the fuel,
+    /// starts the quotas over and restarts the clock.
+    pub fn Reset(&self) {
+        self.cancellation.Reset();
+        self.depth.set(0);
+        self.stats.set(RunStats::default());
+        self.callCounts.borrow_mut().clear();
+        self.moduleDeadlines.borrow_mut().clear();
+        self.fuel.set(self.limits.fuel);
+        self.deadline
+            .set(self.limits.time.map(|time| self.now() + time));
+    }
+
+    /// The work done since the last `Reset`.
+    pub fn Stats(&self) -> RunStats {
+        self.stats.get()
+    }
+
+    fn updateStats(&self, update: impl FnOnce(&mut RunStats)) {
+        let mut stats = self.stats.get();
+        update(&mut stats);
+        self.stats.set(stats);
+    }
+
+    /// Called by the evaluator as a call starts. Returns the error that should
+    /// end the evaluation if the call would nest too deeply; otherwise the
+    /// call must be followed by `ExitCall`.
+    pub(crate) fn EnterCall(&self) -> Option<ObjectEnum> {
+        let depth = self.depth.get();
+        match self.limits.depth {
+            Some(max) if depth >= max => Some(
+                Error::new(
+                    ErrorKind::StackOverflow,
+                    format!("stack overflow: max depth {} exceeded", max),
+                )
+                .with("depth", Integer { value: max as i64 })
+                .into(),
+            ),
+            _ => {
+                self.depth.set(depth + 1);
+                self.updateStats(|stats| stats.maxDepth = stats.maxDepth.max(depth + 1));
+                None
+            }
+        }
+    }
+
+    pub(crate) fn ExitCall(&self) {
+        self.depth.set(self.depth.get().saturating_sub(1));
+    }
+
+    /// Called by the evaluator before every step. Returns the error that should
+    /// end the evaluation, if any.
+    pub(crate) fn Check(&self) -> Option<ObjectEnum> {
+        self.updateStats(|stats| stats.steps += 1);
+        if self.cancellation.IsCancelled() {
+            return Some(Error::new(ErrorKind::Interrupted, "interrupted").into());
+        }
+        if let Some(err) = self.checkModules() {
+            return Some(err);
+        }
+
+        let exceeded = match (self.fuel.get(), self.deadline.get()) {
+            (Some(0), _) => self.limits.fuel.map(Limit::Fuel),
+            (_, Some(deadline)) if self.now() >= deadline => self.limits.time.map(Limit::Time),
+            _ => None,
+        };
+        if let Some(fuel) = self.fuel.get() {
+            self.fuel.set(Some(fuel.saturating_sub(1)));
+        }
+
+        let limit = exceeded?;
+        // the handler is taken out while it runs, since it may suspend the
+        // evaluation (see `continuation`) or replace itself
+        let mut handler = self.onLimit.borrow_mut().take();
+        let proceed = match handler.as_mut() {
+            Some(handler) => handler(limit),
+            None => false,
+        };
+        if self.onLimit.borrow().is_none() {
+            *self.onLimit.borrow_mut() = handler;
+        }
+        if proceed {
+            match limit {
+                Limit::Fuel(steps) => self.fuel.set(Some(steps.saturating_sub(1))),
+                Limit::Time(time) => self.deadline.set(Some(self.now() + time)),
+            }
+            return None;
+        }
+
+        let err = Error::new(
+            ErrorKind::LimitExceeded,
+            format!("evaluation exceeded {}", limit),
+        );
+        Some(
+            match limit {
+                Limit::Fuel(steps) => err.with(
+                    "fuel",
+                    Integer {
+                        value: steps as i64,
+                    },
+                ),
+                Limit::Time(time) => err.with(
+                    "time",
+                    Str {
+                        value: format!("{:?}", time),
+                    },
+                ),
+            }
+            .into(),
+        )
+    }
+}
+
+/// Whether a replay runs a call of `builtin` again instead of serving its
+/// result: a standard builtin that needs no capability being given a
+/// function, which may have effects of its own.
+fn runsAgain(builtin: &Builtin, args: &[ObjectEnum]) -> bool {
+    builtins::Lookup(&builtin.name).is_some()
+        && builtins::CapabilityOf(&builtin.name).is_none()
+        && args
+            .iter()
+            .any(|arg| matches!(arg, ObjectEnum::Function(_) | ObjectEnum::Builtin(_)))
+}
+
+/// Whether the arguments of a call match those of a recorded one. Functions
+/// and secrets are compared by what they are rather than by identity, which
+/// a replay can't reproduce.
+fn sameArguments(recorded: &[ObjectEnum], args: &[ObjectEnum]) -> bool {
+    recorded.len() == args.len()
+        && recorded
+            .iter()
+            .zip(args)
+            .all(|(recorded, arg)| sameArgument(recorded, arg))
+}
+
+fn sameArgument(recorded: &ObjectEnum, arg: &ObjectEnum) -> bool {
+    match (recorded, arg) {
+        (ObjectEnum::Function(a), ObjectEnum::Function(b)) => {
+            a.parameters == b.parameters && a.body == b.body
+        }
+        (ObjectEnum::Builtin(a), ObjectEnum::Builtin(b)) => a.name == b.name,
+        (ObjectEnum::Secret(a), ObjectEnum::Secret(b)) => sameArgument(a.Value(), b.Value()),
+        (ObjectEnum::Array(a), ObjectEnum::Array(b)) => sameArguments(&a.elements, &b.elements),
+        (ObjectEnum::Hash(a), ObjectEnum::Hash(b)) => {
+            a.pairs.len() == b.pairs.len()
+                && a.pairs
+                    .iter()
+                    .zip(&b.pairs)
+                    .all(|((ka, va), (kb, vb))| ka == kb && sameArgument(va, vb))
+        }
+        _ => recorded == arg,
+    }
+}
+
+fn describeCall(name: &str, args: &[ObjectEnum]) -> String {
+    format!(
+        "{}({})",
+        name,
+        itertools::join(args.iter().map(|arg| arg.Inspect()), ", ")
+    )
+}
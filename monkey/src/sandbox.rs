@@ -0,0 +1,60 @@
+//! Evaluating snippets for hosts that show what each top-level statement
+//! gives, like a dashboard whose cells are the statements of one snippet.
+//! Running out of fuel or time then only loses the statements that didn't
+//! finish: those that did keep their values, and what was printed is kept.
+
+use std::rc::Rc;
+
+use crate::ast::Program;
+use crate::environment::Env;
+use crate::evaluator::evalProgramReporting;
+use crate::evaluator::isError;
+use crate::object::ErrorKind;
+use crate::object::ObjectEnum;
+use crate::platform::Capture;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnippetResult {
+    /// The value of each top-level statement that finished, in order. Those
+    /// without a value, like `let`, have `None`.
+    pub results: Vec<Option<ObjectEnum>>,
+    /// Everything the snippet wrote to standard output.
+    pub output: String,
+    /// Whether the snippet was stopped before its end by running out of fuel
+    /// or time, or by being cancelled, so that `results` is incomplete.
+    pub partial: bool,
+    /// The error the snippet ended with, if it did.
+    pub error: Option<ObjectEnum>,
+}
+
+/// Evaluates `program` in `env` with a fresh budget (see `Runtime::Reset`),
+/// keeping the value of every top-level statement and the output instead of
+/// only the value of the last one. The output goes to the snippet's result
+/// rather than to the runtime's platform.
+pub fn EvalSnippet(program: Program, env: &Env) -> SnippetResult {
+    let runtime = Rc::clone(env.borrow().Runtime());
+    let platform = runtime.Platform();
+    let capture = Rc::new(Capture::New(Rc::clone(&platform)));
+    runtime.SetPlatform(capture.clone());
+    runtime.Reset();
+
+    let mut results = vec![];
+    let result = evalProgramReporting(program, env, &mut |value| results.push(value.cloned()));
+    runtime.SetPlatform(platform);
+
+    let error = result.filter(isError);
+    let partial = matches!(
+        &error,
+        Some(ObjectEnum::Error(err))
+            if matches!(err.kind, ErrorKind::LimitExceeded | ErrorKind::Interrupted)
+    );
+    SnippetResult {
+        results,
+        output: capture.TakeOutput(),
+        partial,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests;
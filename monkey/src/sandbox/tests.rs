@@ -0,0 +1,97 @@
+use std::rc::Rc;
+
+use crate::environment::Env;
+use crate::environment::Environment;
+use crate::lexer::Lexer;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::platform::MemoryPlatform;
+use crate::runtime::Limits;
+use crate::runtime::Runtime;
+
+use super::EvalSnippet;
+use super::SnippetResult;
+
+fn snippet(input: &str, env: &Env) -> SnippetResult {
+    let mut p = Parser::New(Lexer::New(input));
+    EvalSnippet(p.ParseProgram(), env)
+}
+
+fn inspect(result: &SnippetResult) -> Vec<Option<String>> {
+    result
+        .results
+        .iter()
+        .map(|value| value.as_ref().map(|value| value.Inspect()))
+        .collect()
+}
+
+fn limited(fuel: u64) -> (Env, Rc<MemoryPlatform>) {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(fuel),
+        ..Limits::default()
+    }));
+    let platform = Rc::new(MemoryPlatform::New());
+    runtime.SetPlatform(platform.clone());
+    (Environment::WithRuntime(runtime), platform)
+}
+
+#[test]
+fn KeepsEveryResult() {
+    let (env, platform) = limited(1000);
+    let result = snippet(r#"let x = 2; x * 3; puts("hi"); "done""#, &env);
+    assert_eq!(
+        inspect(&result),
+        vec![
+            None,
+            Some("6".into()),
+            Some("null".into()),
+            Some("done".into())
+        ]
+    );
+    assert_eq!(result.output, "hi\n");
+    assert!(!result.partial);
+    assert_eq!(result.error, None);
+    // the output went to the snippet, not the platform
+    assert_eq!(platform.Output(), "");
+}
+
+#[test]
+fn PartialResultsOnLimit() {
+    let (env, _) = limited(50);
+    let input = r#"
+        1 + 1;
+        puts("before");
+        let loop = fn(n) { loop(n + 1) };
+        loop(0);
+        puts("after");
+        3
+    "#;
+    let result = snippet(input, &env);
+    assert_eq!(
+        inspect(&result),
+        vec![Some("2".into()), Some("null".into()), None]
+    );
+    assert_eq!(result.output, "before\n");
+    assert!(result.partial);
+    assert_eq!(
+        result.error.unwrap().Inspect(),
+        "ERROR: evaluation exceeded 50 steps\n  in loop, called at 4:28\n  in loop, called at 5:9"
+    );
+
+    // each snippet gets a fresh budget
+    let result = snippet("1; 2", &env);
+    assert_eq!(inspect(&result), vec![Some("1".into()), Some("2".into())]);
+    assert!(!result.partial);
+}
+
+#[test]
+fn ErrorsAreNotPartial() {
+    let (env, _) = limited(1000);
+    let result = snippet("1; x; 3", &env);
+    assert_eq!(inspect(&result), vec![Some("1".into())]);
+    assert!(!result.partial);
+    assert_eq!(
+        result.error.unwrap().Inspect(),
+        "ERROR: identifier not found: x"
+    );
+}
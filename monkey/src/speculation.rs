@@ -0,0 +1,85 @@
+//! Running a script without committing to its effects: the host evaluates it
+//! against a checkpoint of the environment, looks at the bindings it would
+//! add or change, and then commits them or rolls back to the checkpoint.
+//!
+//! Only the bindings of the environment the script runs in are tracked;
+//! changes a script makes inside the environments captured by existing
+//! closures are not rolled back.
+
+use crate::ast::NodeEnum;
+use crate::continuation::EvalResumable;
+use crate::continuation::Step;
+use crate::environment::Change;
+use crate::environment::Env;
+use crate::environment::Snapshot;
+use crate::object::ObjectEnum;
+use crate::runtime::Limit;
+
+pub struct Speculation {
+    env: Env,
+    checkpoint: Snapshot,
+    step: Step,
+}
+
+/// Starts evaluating `node` in `env` speculatively. Like `EvalResumable`, the
+/// evaluation suspends when it runs out of a limit.
+pub fn Speculate(node: NodeEnum, env: &Env) -> Speculation {
+    let checkpoint = env.borrow().Snapshot();
+    Speculation {
+        env: env.clone(),
+        checkpoint,
+        step: EvalResumable(node, env),
+    }
+}
+
+impl Speculation {
+    /// The limit the evaluation is suspended on, if it hasn't finished.
+    pub fn Suspended(&self) -> Option<Limit> {
+        match &self.step {
+            Step::Suspended(continuation) => Some(continuation.Limit()),
+            Step::Done(_) => None,
+        }
+    }
+
+    /// The result of the evaluation, once it has finished.
+    pub fn Result(&self) -> Option<&ObjectEnum> {
+        match &self.step {
+            Step::Done(result) => result.as_ref(),
+            Step::Suspended(_) => None,
+        }
+    }
+
+    pub fn Resume(self) -> Self {
+        let step = match self.step {
+            Step::Suspended(continuation) => continuation.Resume(),
+            done => done,
+        };
+        Self { step, ..self }
+    }
+
+    /// The bindings the evaluation has added or changed so far.
+    pub fn Changes(&self) -> Vec<Change> {
+        self.checkpoint.Changes(&self.env.borrow())
+    }
+
+    /// Keeps the evaluation's effects and returns its result. A suspended
+    /// evaluation can't be committed and is handed back unchanged.
+    // it is handed back by value so the host can keep resuming it
+    #[allow(clippy::result_large_err)]
+    pub fn Commit(self) -> Result<Option<ObjectEnum>, Self> {
+        match self.step {
+            Step::Done(result) => Ok(result),
+            Step::Suspended(_) => Err(self),
+        }
+    }
+
+    /// Abandons the evaluation, if it is still suspended, and puts the
+    /// environment back the way it was at the checkpoint.
+    pub fn Rollback(self) {
+        drop(self.step);
+        self.env.borrow_mut().Restore(self.checkpoint);
+    }
+}
+
+#[cfg(test)]
+mod tests;
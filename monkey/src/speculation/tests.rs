@@ -0,0 +1,97 @@
+use std::rc::Rc;
+
+use crate::environment::Change;
+use crate::environment::Env;
+use crate::environment::Environment;
+use crate::evaluator::Eval;
+use crate::lexer::Lexer;
+use crate::object::Integer;
+use crate::object::ObjectEnum;
+use crate::parser::Parser;
+use crate::runtime::Limits;
+use crate::runtime::Runtime;
+
+use super::Speculate;
+use super::Speculation;
+
+fn run(input: &str, env: &Env) -> Option<ObjectEnum> {
+    let mut p = Parser::New(Lexer::New(input));
+    Eval(p.ParseProgram().into(), env)
+}
+
+fn speculate(input: &str, env: &Env) -> Speculation {
+    let mut p = Parser::New(Lexer::New(input));
+    Speculate(p.ParseProgram().into(), env)
+}
+
+fn int(value: i64) -> ObjectEnum {
+    Integer { value }.into()
+}
+
+#[test]
+fn ReportsChanges() {
+    let env = Environment::New();
+    run("let x = 1; let z = 5;", &env);
+
+    let speculation = speculate("let y = x + 1; x++; let z = 5; y", &env);
+    assert_eq!(speculation.Result(), Some(&int(2)));
+    assert_eq!(
+        speculation.Changes(),
+        vec![
+            Change::Changed {
+                name: "x".into(),
+                old: int(1),
+                new: int(2),
+            },
+            Change::Added {
+                name: "y".into(),
+                value: int(2),
+            },
+        ]
+    );
+}
+
+#[test]
+fn CommitKeepsChanges() {
+    let env = Environment::New();
+    run("let x = 1;", &env);
+    let result = speculate("x++; x", &env).Commit().ok().unwrap();
+    assert_eq!(result, Some(int(2)));
+    assert_eq!(run("x", &env), Some(int(2)));
+}
+
+#[test]
+fn RollbackRestoresCheckpoint() {
+    let env = Environment::New();
+    run("let x = 1;", &env);
+    speculate("x++; let y = 3;", &env).Rollback();
+    assert_eq!(run("x", &env), Some(int(1)));
+    assert!(env.borrow().Get("y").is_none());
+}
+
+#[test]
+fn SuspendedSpeculation() {
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(3),
+        time: None,
+        depth: None,
+    }));
+    let env = Environment::WithRuntime(runtime);
+    run("let x = 0;", &env);
+
+    let mut speculation = speculate("x++; x++; x++; x++; x++; x", &env);
+    assert!(speculation.Suspended().is_some());
+    assert!(!speculation.Changes().is_empty());
+    speculation = match speculation.Commit() {
+        Ok(_) => panic!("committed a suspended speculation"),
+        Err(speculation) => speculation,
+    };
+    while speculation.Suspended().is_some() {
+        speculation = speculation.Resume();
+    }
+    assert_eq!(speculation.Result(), Some(&int(5)));
+
+    // rolling back a finished speculation still restores the checkpoint
+    speculation.Rollback();
+    assert_eq!(env.borrow().Get("x"), Some(int(0)));
+}
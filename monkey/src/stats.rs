@@ -0,0 +1,235 @@
+//! Size and complexity measures of a program, for keeping scripts reviewable.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::ast::BlockStatement;
+use crate::ast::ExpressionEnum;
+use crate::ast::Node;
+use crate::ast::Program;
+use crate::ast::StatementEnum;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProgramStats {
+    /// Statements of each kind, including those nested in blocks.
+    pub statements: BTreeMap<&'static str, usize>,
+    /// How deeply blocks nest; top-level statements are at depth 0.
+    pub maxDepth: usize,
+    /// Every function literal, in source order.
+    pub functions: Vec<FunctionStats>,
+    /// The statement-level expression with the most nodes.
+    pub longestExpression: Option<ExpressionSize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// The name it is bound to with `let`, if any.
+    pub name: Option<String>,
+    pub parameters: usize,
+    /// One plus the branches the body can take: each `if` and `try`, and each
+    /// arm of a `match` after the first. Nested functions count on their own.
+    pub complexity: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionSize {
+    pub nodes: usize,
+    pub text: String,
+}
+
+pub fn Analyze(program: &Program) -> ProgramStats {
+    let mut collector = Collector::default();
+    for stmt in &program.statements {
+        collector.statement(stmt, 0);
+    }
+    collector.stats
+}
+
+#[derive(Default)]
+struct Collector {
+    stats: ProgramStats,
+    /// Indices into `stats.functions` of the functions being walked,
+    /// innermost last.
+    open: Vec<usize>,
+}
+
+impl Collector {
+    fn statement(&mut self, stmt: &StatementEnum, depth: usize) {
+        let (kind, exp, name) = match stmt {
+            StatementEnum::Let(l) => ("let", &l.value, Some(&l.name.value)),
+            StatementEnum::Return(r) => ("return", &r.returnValue, None),
+            StatementEnum::Expression(e) => ("expression", &e.expression, None),
+            StatementEnum::Defer(d) => ("defer", &d.expression, None),
+            StatementEnum::Throw(t) => ("throw", &t.value, None),
+            StatementEnum::Destructure(d) => ("let", &d.value, None),
+            StatementEnum::Block(b) => return self.block(b, depth),
+        };
+        *self.stats.statements.entry(kind).or_default() += 1;
+        self.stats.maxDepth = self.stats.maxDepth.max(depth);
+
+        let nodes = self.expression(exp, depth, name.cloned());
+        if self
+            .stats
+            .longestExpression
+            .as_ref()
+            .is_none_or(|longest| nodes > longest.nodes)
+        {
+            self.stats.longestExpression = Some(ExpressionSize {
+                nodes,
+                text: exp.String(),
+            });
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement, depth: usize) {
+        for stmt in &block.statements {
+            self.statement(stmt, depth + 1);
+        }
+    }
+
+    fn branches(&mut self, count: usize) {
+        if let Some(&function) = self.open.last() {
+            self.stats.functions[function].complexity += count;
+        }
+    }
+
+    /// Walks `exp` and returns how many nodes it has. Blocks inside it are
+    /// measured as statements of their own.
+    fn expression(&mut self, exp: &ExpressionEnum, depth: usize, name: Option<String>) -> usize {
+        match exp {
+            ExpressionEnum::Identifier(_)
+            | ExpressionEnum::IntegerLiteral(_)
+            | ExpressionEnum::StringLiteral(_)
+            | ExpressionEnum::Boolean(_)
+            | ExpressionEnum::NullLiteral(_) => 1,
+            ExpressionEnum::UpdateExpression(_) => 2,
+            ExpressionEnum::PrefixExpression(p) => 1 + self.expression(&p.right, depth, None),
+            ExpressionEnum::ImportExpression(i) => 1 + self.expression(&i.path, depth, None),
+            ExpressionEnum::SpreadExpression(s) => 1 + self.expression(&s.value, depth, None),
+            ExpressionEnum::KeywordArgument(k) => 1 + self.expression(&k.value, depth, None),
+            ExpressionEnum::InfixExpression(i) => {
+                1 + self.expression(&i.left, depth, None) + self.expression(&i.right, depth, None)
+            }
+            ExpressionEnum::IfExpression(i) => {
+                self.branches(1);
+                let nodes = 1 + self.expression(&i.condition, depth, None);
+                self.block(&i.consequence, depth);
+                if let Some(alternative) = &i.alternative {
+                    self.block(alternative, depth);
+                }
+                nodes
+            }
+            ExpressionEnum::MatchExpression(m) => {
+                self.branches(m.arms.len().saturating_sub(1));
+                let mut nodes = 1 + self.expression(&m.subject, depth, None);
+                for arm in &m.arms {
+                    nodes += 1;
+                    match &*arm.body {
+                        StatementEnum::Expression(e) => {
+                            nodes += self.expression(&e.expression, depth, None)
+                        }
+                        body => self.statement(body, depth),
+                    }
+                }
+                nodes
+            }
+            ExpressionEnum::TryExpression(t) => {
+                self.branches(1);
+                self.block(&t.body, depth);
+                self.block(&t.handler, depth);
+                1
+            }
+            ExpressionEnum::FunctionLiteral(f) => {
+                self.stats.functions.push(FunctionStats {
+                    name,
+                    parameters: f.parameters.len(),
+                    complexity: 1,
+                });
+                self.open.push(self.stats.functions.len() - 1);
+                self.block(&f.body, depth);
+                self.open.pop();
+                1
+            }
+            ExpressionEnum::MacroLiteral(m) => {
+                self.block(&m.body, depth);
+                1
+            }
+            ExpressionEnum::CallExpression(c) => {
+                1 + self.expression(&c.function, depth, None)
+                    + self.expressions(&c.arguments, depth)
+            }
+            ExpressionEnum::ArrayLiteral(a) => 1 + self.expressions(&a.elements, depth),
+            ExpressionEnum::HashLiteral(h) => {
+                let mut nodes = 1;
+                for (key, value) in &h.pairs {
+                    nodes +=
+                        self.expression(key, depth, None) + self.expression(value, depth, None);
+                }
+                nodes
+            }
+            ExpressionEnum::IndexExpression(i) => {
+                1 + self.expression(&i.left, depth, None) + self.expression(&i.index, depth, None)
+            }
+            ExpressionEnum::SliceExpression(s) => {
+                let mut nodes = 1 + self.expression(&s.left, depth, None);
+                for bound in s.start.iter().chain(&s.end) {
+                    nodes += self.expression(bound, depth, None);
+                }
+                nodes
+            }
+        }
+    }
+
+    fn expressions(&mut self, exps: &[ExpressionEnum], depth: usize) -> usize {
+        exps.iter()
+            .map(|exp| self.expression(exp, depth, None))
+            .sum()
+    }
+}
+
+/// The longest expression is shown cut down to this many characters.
+const EXCERPT_LENGTH: usize = 60;
+
+impl Display for ProgramStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "statements: {} ({})",
+            self.statements.values().sum::<usize>(),
+            itertools::join(
+                self.statements
+                    .iter()
+                    .map(|(kind, count)| format!("{} {}", kind, count)),
+                ", "
+            )
+        )?;
+        writeln!(f, "max nesting depth: {}", self.maxDepth)?;
+        writeln!(f, "functions: {}", self.functions.len())?;
+        for function in &self.functions {
+            writeln!(
+                f,
+                "  {}: {} parameter{}, complexity {}",
+                function.name.as_deref().unwrap_or("<anonymous>"),
+                function.parameters,
+                if function.parameters == 1 { "" } else { "s" },
+                function.complexity
+            )?;
+        }
+        if let Some(longest) = &self.longestExpression {
+            let mut excerpt: String = longest.text.chars().take(EXCERPT_LENGTH).collect();
+            if longest.text.chars().count() > EXCERPT_LENGTH {
+                excerpt.push('…');
+            }
+            writeln!(
+                f,
+                "longest expression: {} nodes: {}",
+                longest.nodes,
+                excerpt.replace('\n', " ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+use maplit::btreemap;
+
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+use super::Analyze;
+use super::FunctionStats;
+use super::ProgramStats;
+
+fn analyze(input: &str) -> ProgramStats {
+    let mut p = Parser::New(Lexer::New(input));
+    let program = p.ParseProgram();
+    assert!(p.errors.is_empty(), "parser errors: {:?}", p.errors);
+    Analyze(&program)
+}
+
+#[test]
+fn EmptyProgram() {
+    let stats = analyze("");
+    assert_eq!(stats, ProgramStats::default());
+    assert_eq!(
+        stats.to_string(),
+        "statements: 0 ()\nmax nesting depth: 0\nfunctions: 0\n"
+    );
+}
+
+#[test]
+fn CountsStatementsAndFunctions() {
+    let input = r#"
+        let add = fn(a, b) { a + b };
+        let classify = fn(n) {
+            defer puts("done");
+            if (n < 0) {
+                return "negative";
+            }
+            match n {
+                0 => "zero",
+                1 => { let one = fn() { "one" }; one() },
+                _ => "many",
+            }
+        };
+        fn(x) { x }(classify(add(1, 2 * 3)));
+    "#;
+    let stats = analyze(input);
+
+    assert_eq!(
+        stats.statements,
+        btreemap! {"defer" => 1, "expression" => 7, "let" => 3, "return" => 1}
+    );
+    assert_eq!(stats.maxDepth, 3);
+    assert_eq!(
+        stats.functions,
+        vec![
+            FunctionStats {
+                name: Some("add".into()),
+                parameters: 2,
+                complexity: 1,
+            },
+            FunctionStats {
+                name: Some("classify".into()),
+                parameters: 1,
+                complexity: 4,
+            },
+            FunctionStats {
+                name: Some("one".into()),
+                parameters: 0,
+                complexity: 1,
+            },
+            FunctionStats {
+                name: None,
+                parameters: 1,
+                complexity: 1,
+            },
+        ]
+    );
+
+    let longest = stats.longestExpression.unwrap();
+    assert_eq!(longest.nodes, 10);
+    assert!(longest.text.ends_with("(classify(add(1, (2 * 3))))"));
+}
+
+#[test]
+fn Report() {
+    let stats = analyze("let f = fn(x) { if (x) { 1 } else { 2 } }; f(true)");
+    assert_eq!(
+        stats.to_string(),
+        "statements: 5 (expression 4, let 1)\n\
+         max nesting depth: 2\n\
+         functions: 1\n\
+         \x20 f: 1 parameter, complexity 2\n\
+         longest expression: 3 nodes: f(true)\n"
+    );
+}
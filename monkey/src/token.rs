@@ -1,51 +1,114 @@
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Range;
 
 use once_cell::sync::Lazy;
 use strum_macros::EnumIter;
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(EnumIter, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenKind {
     ILLEGAL,
     EOF,
 
     IDENT,
     INT,
+    STRING,
 
     ASSIGN,
     PLUS,
+    INCREMENT,
+    DECREMENT,
 
     COMMA,
     SEMICOLON,
+    COLON,
+    ELLIPSIS,
     BANG,
     MINUS,
     SLASH,
     STAR,
+    PERCENT,
 
     LESS,
     GREATER,
     EQ,
     NOT_EQ,
+    FAT_ARROW,
+    PIPE,
 
     LPAREN,
     RPAREN,
     LBRACE,
     RBRACE,
+    LBRACKET,
+    RBRACKET,
 
     FUNCTION,
+    MACRO,
     LET,
     IF,
     ELSE,
+    MATCH,
     RETURN,
+    DEFER,
+    THROW,
+    TRY,
+    CATCH,
+    IMPORT,
     TRUE,
     FALSE,
+    NULL,
+
+    // new kinds go last, so the discriminants fingerprints hash stay the same
+    /// A string written `r"..."`, whose contents are taken as they are.
+    RAW_STRING,
+}
+
+/// Where a token starts in its source. Lines and columns count from 1, columns
+/// in chars; the offset is in bytes. Tokens the parser makes up have the
+/// position of the code they stand for, and tokens made any other way are at
+/// line 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    /// Where `text` ends, if it starts here.
+    pub(crate) fn After(self, text: &str) -> Self {
+        let mut position = self;
+        for ch in text.chars() {
+            if ch == '\n' {
+                position.line += 1;
+                position.column = 1;
+            } else {
+                position.column += 1;
+            }
+        }
+        position.offset += text.len();
+        position
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub kind: TokenKind,
     pub(crate) literal: String,
+    pub position: Position,
 }
 
 impl Default for Token {
@@ -53,10 +116,59 @@ impl Default for Token {
         Self {
             kind: TokenKind::ILLEGAL,
             literal: String::default(),
+            position: Position::default(),
+        }
+    }
+}
+
+impl Token {
+    /// A token at no position.
+    pub(crate) fn New(kind: TokenKind, literal: impl Into<String>) -> Self {
+        Self {
+            kind,
+            literal: literal.into(),
+            position: Position::default(),
+        }
+    }
+
+    /// The text of the token: a string's contents without the quotes, and
+    /// what any other token was lexed from.
+    pub fn Literal(&self) -> &str {
+        &self.literal
+    }
+
+    /// Where the source the token was lexed from ends.
+    pub fn End(&self) -> Position {
+        match self.kind {
+            TokenKind::STRING => self.position.After("\"").After(&self.literal).After("\""),
+            TokenKind::RAW_STRING => self.position.After("r\"").After(&self.literal).After("\""),
+            _ => self.position.After(&self.literal),
         }
     }
+
+    /// The byte range of the source the token was lexed from.
+    pub fn Span(&self) -> Range<usize> {
+        self.position.offset..self.End().offset
+    }
 }
 
+// where a token is doesn't change what it means, so it's left out of the
+// syntax tree's fingerprint and of comparing trees
+impl Hash for Token {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.literal.hash(state);
+    }
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.literal == other.literal
+    }
+}
+
+impl Eq for Token {}
+
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -71,11 +183,19 @@ static KEYWORDS: Lazy<HashMap<&'static str, TokenKind>> = Lazy::new(|| {
     maplit::hashmap! {
         "let" => TokenKind::LET,
         "fn" => TokenKind::FUNCTION,
+        "macro" => TokenKind::MACRO,
         "if" => TokenKind::IF,
         "else" => TokenKind::ELSE,
+        "match" => TokenKind::MATCH,
         "return" => TokenKind::RETURN,
+        "defer" => TokenKind::DEFER,
+        "throw" => TokenKind::THROW,
+        "try" => TokenKind::TRY,
+        "catch" => TokenKind::CATCH,
+        "import" => TokenKind::IMPORT,
         "true" => TokenKind::TRUE,
         "false" => TokenKind::FALSE,
+        "null" => TokenKind::NULL,
     }
 });
 
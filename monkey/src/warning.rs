@@ -0,0 +1,72 @@
+//! Warnings about code that still works but is slated to change, mostly
+//! because a later edition gives it a different meaning. Each kind has a code
+//! by which it can be suppressed.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::ops::Range;
+
+use strum_macros::EnumString;
+
+#[derive(
+    strum_macros::Display, EnumString, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum WarningCode {
+    /// A statement without a semicolon, which edition 2 requires.
+    MissingSemicolon,
+    /// An identifier that edition 2 reserves as a keyword.
+    ReservedWord,
+    /// A condition or operand of `!` that isn't a boolean, which edition 2
+    /// doesn't accept.
+    Truthiness,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    /// The byte range of the source the warning is about. Warnings raised while
+    /// evaluating have none, since the AST doesn't keep positions.
+    pub span: Option<Range<usize>>,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "warning[{}]: {}", self.code, self.message)?;
+        if let Some(span) = &self.span {
+            write!(f, " (at {}..{})", span.start, span.end)?;
+        }
+        Ok(())
+    }
+}
+
+/// The warnings reported so far, leaving out suppressed codes and repeats.
+#[derive(Debug, Default, Clone)]
+pub struct Warnings {
+    reported: Vec<Warning>,
+    suppressed: BTreeSet<WarningCode>,
+}
+
+impl Warnings {
+    /// Stops reporting warnings with `code`, including ones reported already.
+    pub fn Suppress(&mut self, code: WarningCode) {
+        self.suppressed.insert(code);
+        self.reported.retain(|warning| warning.code != code);
+    }
+
+    pub fn Get(&self) -> &[Warning] {
+        &self.reported
+    }
+
+    /// Removes and returns the warnings reported so far. Suppressions stay.
+    pub fn Take(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.reported)
+    }
+
+    pub(crate) fn Warn(&mut self, warning: Warning) {
+        if !self.suppressed.contains(&warning.code) && !self.reported.contains(&warning) {
+            self.reported.push(warning);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Packaging a script with the interpreter into one executable. A bundle is
+//! a copy of this executable with the script appended, followed by a trailer
+//! holding its length; when this executable finds a trailer on itself, it
+//! runs the script instead of the REPL. The script is kept as source, and the
+//! edition it was bundled for goes on its first line. Only the script goes in,
+//! so one that imports modules, or might through `eval`, can't be bundled.
+
+use std::convert::TryInto;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::rc::Rc;
+
+use monkey::ast::Program;
+use monkey::capability;
+use monkey::capability::Capability;
+use monkey::edition::Edition;
+use monkey::environment::Environment;
+use monkey::evaluator::Eval;
+use monkey::lexer::Lexer;
+use monkey::macro_expansion::DefineMacros;
+use monkey::macro_expansion::ExpandMacros;
+use monkey::object::Object;
+use monkey::object::ObjectEnum;
+use monkey::parser::Parser;
+use monkey::runtime::Limits;
+use monkey::runtime::Runtime;
+
+use crate::commands;
+use crate::LIMITS;
+
+/// Ends every bundle, after the length of the script.
+const MAGIC: &[u8; 8] = b"MKBUNDLE";
+
+/// The length of the script as 8 little-endian bytes, then `MAGIC`.
+const TRAILER_LEN: usize = 16;
+
+/// A script found in an executable.
+pub(crate) struct Script {
+    pub(crate) edition: Edition,
+    pub(crate) source: String,
+}
+
+/// The script bundled into `executable`, if there is one.
+fn find(executable: &Path) -> io::Result<Option<Script>> {
+    let mut file = File::open(executable)?;
+    let len = file.metadata()?.len();
+    if len < TRAILER_LEN as u64 {
+        return Ok(None);
+    }
+    let mut trailer = [0; TRAILER_LEN];
+    file.seek(SeekFrom::End(-(TRAILER_LEN as i64)))?;
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let scriptLen = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+    let start = match (len - TRAILER_LEN as u64).checked_sub(scriptLen) {
+        Some(start) => start,
+        None => return Err(corrupt()),
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut payload = String::new();
+    file.take(scriptLen).read_to_string(&mut payload)?;
+    let (edition, source) = payload.split_once('\n').ok_or_else(corrupt)?;
+    let edition = edition.parse().map_err(|_| corrupt())?;
+    Ok(Some(Script {
+        edition,
+        source: source.into(),
+    }))
+}
+
+fn corrupt() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the bundled script is corrupt")
+}
+
+/// The script bundled into the running executable, if there is one.
+pub(crate) fn Embedded() -> io::Result<Option<Script>> {
+    find(&std::env::current_exe()?)
+}
+
+/// What in `program` may import a module: `import` itself, or `eval`, whose
+/// code might.
+fn importsBy(program: &Program) -> Option<&'static str> {
+    let names = capability::Required(program).remove(&Capability::Io)?;
+    ["import", "eval"]
+        .iter()
+        .copied()
+        .find(|name| names.contains(*name))
+}
+
+/// Writes to `output` an executable that runs the script at `path`. Returns
+/// false if the script doesn't parse or might import a module.
+pub(crate) fn Bundle(path: &str, output: &str, edition: Edition) -> io::Result<bool> {
+    let program = match commands::parse(path, edition)? {
+        Some(program) => program,
+        None => return Ok(false),
+    };
+    if let Some(how) = importsBy(&program) {
+        eprintln!(
+            "can't bundle {}: it may import modules through `{}`, and a bundle only has the script itself",
+            path, how
+        );
+        return Ok(false);
+    }
+    let source = fs::read_to_string(path)?;
+    // a bundle runs its script instead, so this is never one
+    let mut bundle = fs::read(std::env::current_exe()?)?;
+    let payload = format!("{}\n{}", edition, source);
+    bundle.extend_from_slice(payload.as_bytes());
+    bundle.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bundle.extend_from_slice(MAGIC);
+    fs::write(output, bundle)?;
+    fs::set_permissions(output, fs::Permissions::from_mode(0o755))?;
+    Ok(true)
+}
+
+/// Runs `script` to the end, returning the status to exit with: 1 if it
+/// doesn't parse or ends in an error, which is reported on stderr, or the
+/// one it passed to `exit`. It must run on a thread with `crate::STACK_SIZE`
+/// of stack, which is enough for the depth limit.
+pub(crate) fn Run(script: &Script) -> i32 {
+    // a script may take as long as it likes, but not recurse past the stack
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        depth: LIMITS.depth,
+        ..Limits::default()
+    }));
+    runtime.SetEdition(script.edition);
+    let mut p = Parser::WithEdition(Lexer::New(&script.source), script.edition);
+    let mut program = p.ParseProgram();
+    if !p.errors.is_empty() {
+        for error in &p.errors {
+            commands::Report(error.into(), &script.source);
+        }
+        return 1;
+    }
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    let macroEnv = Environment::WithRuntime(Rc::clone(&runtime));
+    DefineMacros(&mut program, &macroEnv);
+    let program = match ExpandMacros(program, &macroEnv) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("macro error: {}", err.Inspect());
+            return 1;
+        }
+    };
+    let evaluated = Eval(program.into(), &env);
+    if let Some(code) = evaluated.as_ref().and_then(ObjectEnum::ExitCode) {
+        return code;
+    }
+    match evaluated {
+        Some(ObjectEnum::Error(err)) => {
+            commands::Report((&err).into(), &script.source);
+            1
+        }
+        Some(thrown @ ObjectEnum::Thrown(_)) => {
+            eprintln!("{}", thrown.Inspect());
+            1
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,15 @@
+use monkey::lexer::Lexer;
+use monkey::parser::Parser;
+
+use super::importsBy;
+
+fn imports(input: &str) -> Option<&'static str> {
+    importsBy(&Parser::New(Lexer::New(input)).ParseProgram())
+}
+
+#[test]
+fn ImportsAreFound() {
+    assert_eq!(imports(r#"puts(len("abc"))"#), None);
+    assert_eq!(imports(r#"import "x""#), Some("import"));
+    assert_eq!(imports(r#"eval("import \"x\"")"#), Some("eval"));
+}
@@ -0,0 +1,155 @@
+//! Subcommands that analyze a script without running it.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+
+use monkey::ast::Program;
+use monkey::callgraph::CallGraph;
+use monkey::capability;
+use monkey::diagnostics::Diagnostic;
+use monkey::edition::Edition;
+use monkey::lexer::Lexer;
+use monkey::lexer::StreamLexer;
+use monkey::parser::Parser;
+use monkey::stats::Analyze;
+
+/// Writes `diagnostic` about `source` to stderr, in color if `Color` says so.
+pub(crate) fn Report(diagnostic: Diagnostic, source: &str) {
+    eprintln!("{}", diagnostic.Render(source, Color(&io::stderr())));
+}
+
+/// Writes `diagnostic` about `source` to `o`, in color if `color` is set.
+pub(crate) fn ReportTo(
+    o: &mut dyn Write,
+    diagnostic: Diagnostic,
+    source: &str,
+    color: bool,
+) -> io::Result<()> {
+    writeln!(o, "{}", diagnostic.Render(source, color))
+}
+
+/// Whether diagnostics written to `stream` should be in color: if it is a
+/// terminal and `NO_COLOR` isn't set.
+pub(crate) fn Color(stream: &impl IsTerminal) -> bool {
+    stream.is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+/// Parses the script at `path`, or standard input if it is `-`, reporting
+/// parse errors on stderr.
+pub(crate) fn parse(path: &str, edition: Edition) -> io::Result<Option<Program>> {
+    if path == "-" {
+        return parseStdin(edition);
+    }
+    let source = fs::read_to_string(path)?;
+    let mut p = Parser::WithEdition(Lexer::New(&source), edition);
+    let program = p.ParseProgram();
+    for warning in p.warnings.Take() {
+        Report((&warning).into(), &source);
+    }
+    if !p.errors.is_empty() {
+        for error in &p.errors {
+            Report(error.into(), &source);
+        }
+        return Ok(None);
+    }
+    Ok(Some(program))
+}
+
+/// Parses standard input as it is read, so a large script piped in isn't
+/// held in memory as well as its syntax tree. Without the source, errors are
+/// reported by where they are rather than with the code.
+fn parseStdin(edition: Edition) -> io::Result<Option<Program>> {
+    let mut lexer = StreamLexer::New(io::stdin().lock());
+    let mut p = Parser::WithEdition(&mut lexer, edition);
+    let program = p.ParseProgram();
+    let (errors, warnings) = (p.errors, p.warnings.Take());
+    if let Some(err) = lexer.Error() {
+        return Err(io::Error::new(err.kind(), err.to_string()));
+    }
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!(
+                "error: {} (at {}..{})",
+                error, error.span.start, error.span.end
+            );
+        }
+        return Ok(None);
+    }
+    Ok(Some(program))
+}
+
+/// Prints the statistics report for the script at `path`. Returns false if the
+/// script doesn't parse.
+pub(crate) fn Stats(path: &str, edition: Edition, o: &mut dyn io::Write) -> io::Result<bool> {
+    let program = match parse(path, edition)? {
+        Some(program) => program,
+        None => return Ok(false),
+    };
+    write!(o, "{}", Analyze(&program))?;
+    Ok(true)
+}
+
+/// Prints the syntax tree of the script at `path` as JSON. Returns false if
+/// the script doesn't parse.
+pub(crate) fn Ast(path: &str, edition: Edition, o: &mut dyn io::Write) -> io::Result<bool> {
+    let program = match parse(path, edition)? {
+        Some(program) => program,
+        None => return Ok(false),
+    };
+    serde_json::to_writer_pretty(&mut *o, &program)?;
+    writeln!(o)?;
+    Ok(true)
+}
+
+/// Prints the call graph of the script at `path`, as DOT or as JSON. Returns
+/// false if the script doesn't parse.
+pub(crate) fn CallGraph(
+    path: &str,
+    json: bool,
+    edition: Edition,
+    o: &mut dyn io::Write,
+) -> io::Result<bool> {
+    let program = match parse(path, edition)? {
+        Some(program) => program,
+        None => return Ok(false),
+    };
+    let graph = CallGraph::Build(&program);
+    if json {
+        writeln!(o, "{}", graph.ToJson())?;
+    } else {
+        write!(o, "{}", graph.ToDot())?;
+    }
+    Ok(true)
+}
+
+/// Prints the capabilities the script at `path` needs, one per line with the
+/// builtins needing it. Returns false if the script doesn't parse.
+pub(crate) fn Capabilities(
+    path: &str,
+    edition: Edition,
+    o: &mut dyn io::Write,
+) -> io::Result<bool> {
+    let program = match parse(path, edition)? {
+        Some(program) => program,
+        None => return Ok(false),
+    };
+    let capabilities = capability::Required(&program);
+    if capabilities.is_empty() {
+        writeln!(o, "no capabilities required")?;
+    }
+    for (capability, names) in capabilities {
+        writeln!(
+            o,
+            "{}: {}",
+            capability,
+            names.into_iter().collect::<Vec<_>>().join(", ")
+        )?;
+    }
+    Ok(true)
+}
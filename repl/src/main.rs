@@ -1,12 +1,146 @@
 #![allow(non_snake_case)]
 
+use std::env;
 use std::io;
+use std::process;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
 
 use io::stdin;
 use io::stdout;
+use monkey::edition::Edition;
+use monkey::runtime::Limits;
+use monkey::runtime::Runtime;
 
+mod bundle;
+mod commands;
+mod remote;
 mod repl;
 
+const USAGE: &str =
+    "usage: repl [--edition <edition>] [--history <depth>] [--optimize] [stats <file> \
+                     | ast <file> | callgraph [--json] <file> | capabilities <file> \
+                     | bundle <file> -o <output> | --remote <host:port | unix:path>]";
+
+/// Holds the token remote sessions must start with.
+const TOKEN_VAR: &str = "MONKEY_REPL_TOKEN";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+const LIMITS: Limits = Limits {
+    fuel: None,
+    time: Some(Duration::from_secs(1)),
+    depth: Some(1000),
+};
+
+/// Each level of a Monkey call takes tens of kilobytes of host stack, far
+/// more than the main thread has for the depth limit.
+const STACK_SIZE: usize = match LIMITS.StackSize() {
+    Some(size) => size,
+    None => 64 * 1024 * 1024,
+};
+
 fn main() -> io::Result<()> {
-    repl::Start(&mut stdin(), &mut stdout())
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)?
+        .join()
+        .unwrap()
+}
+
+fn run() -> io::Result<()> {
+    if let Some(script) = bundle::Embedded()? {
+        process::exit(bundle::Run(&script));
+    }
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let edition = match args[..] {
+        ["--edition", edition, ..] => {
+            let edition: Edition = edition.parse().unwrap_or_else(|_| usage());
+            args.drain(..2);
+            edition
+        }
+        _ => Edition::default(),
+    };
+    let history = match args[..] {
+        ["--history", depth, ..] => {
+            let depth: usize = depth.parse().unwrap_or_else(|_| usage());
+            args.drain(..2);
+            depth
+        }
+        _ => repl::HISTORY,
+    };
+    let optimize = match args[..] {
+        ["--optimize", ..] => {
+            args.drain(..1);
+            true
+        }
+        _ => false,
+    };
+    let remote = match args[..] {
+        ["--remote", address] => {
+            args.clear();
+            Some(address.to_string())
+        }
+        _ => None,
+    };
+    let succeeded = match args[..] {
+        [] => None,
+        ["stats", path] => Some(commands::Stats(path, edition, &mut stdout())?),
+        ["ast", path] => Some(commands::Ast(path, edition, &mut stdout())?),
+        ["callgraph", path] => Some(commands::CallGraph(path, false, edition, &mut stdout())?),
+        ["callgraph", "--json", path] => {
+            Some(commands::CallGraph(path, true, edition, &mut stdout())?)
+        }
+        ["capabilities", path] => Some(commands::Capabilities(path, edition, &mut stdout())?),
+        ["bundle", path, "-o", output] => Some(bundle::Bundle(path, output, edition)?),
+        _ => usage(),
+    };
+    match succeeded {
+        Some(true) => return Ok(()),
+        Some(false) => process::exit(1),
+        None => {}
+    }
+
+    let runtime = Rc::new(Runtime::WithLimits(LIMITS));
+    runtime.SetEdition(edition);
+
+    let interrupt = runtime.Cancellation().clone();
+    ctrlc::set_handler(move || interrupt.Cancel()).map_err(io::Error::other)?;
+
+    if let Some(address) = remote {
+        let token = match env::var(TOKEN_VAR) {
+            Ok(token) if !token.is_empty() => token,
+            _ => {
+                eprintln!("--remote needs a token in {}", TOKEN_VAR);
+                process::exit(2);
+            }
+        };
+        // a remote operator can't answer prompts on this terminal, so
+        // running out of a limit is always an error
+        return remote::Serve(&address, &token, runtime, history, optimize);
+    }
+
+    let color = commands::Color(&stdout());
+    let status = repl::Start(
+        Box::new(stdin()),
+        &mut stdout(),
+        runtime,
+        history,
+        optimize,
+        color,
+        true,
+    )?;
+    if status != 0 {
+        process::exit(status);
+    }
+    Ok(())
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,250 @@
+//! Serving the REPL over a socket, so an operator can inspect a running
+//! interpreter. The protocol is the REPL's own, one line per input, after a
+//! first line holding the token the server was started with. Connections are
+//! served one at a time, all with the same runtime, and one that doesn't send
+//! the token within `HANDSHAKE_TIMEOUT` is dropped so it can't hold up the
+//! others. What a session's scripts print goes to its connection.
+
+use std::cell::RefCell;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use monkey::object::SecureEq;
+use monkey::platform::Platform;
+use monkey::runtime::Runtime;
+
+use crate::repl;
+
+/// Tokens longer than this are rejected without reading the rest.
+const MAX_TOKEN_LEN: usize = 1024;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Listens on `address`, either `unix:<path>` or a TCP `host:port`, until
+/// listening fails. Each session can undo up to `history` inputs, and
+/// optimizes them if `optimize` is set.
+pub(crate) fn Serve(
+    address: &str,
+    token: &str,
+    runtime: Rc<Runtime>,
+    history: usize,
+    optimize: bool,
+) -> io::Result<()> {
+    match address.strip_prefix("unix:") {
+        Some(path) => {
+            let listener = UnixListener::bind(path)?;
+            eprintln!("serving the REPL on {}", address);
+            for stream in listener.incoming() {
+                report(session(
+                    Box::new(stream?),
+                    token,
+                    &runtime,
+                    history,
+                    optimize,
+                ));
+            }
+        }
+        None => {
+            let listener = TcpListener::bind(address)?;
+            eprintln!("serving the REPL on {}", listener.local_addr()?);
+            for stream in listener.incoming() {
+                report(session(
+                    Box::new(stream?),
+                    token,
+                    &runtime,
+                    history,
+                    optimize,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A connection going wrong only ends that connection.
+fn report(result: io::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("remote session failed: {}", err);
+    }
+}
+
+/// A stream a session is served over.
+trait Connection: Read + Write {
+    fn SetReadTimeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Another handle on the same stream.
+    fn TryClone(&self) -> io::Result<Box<dyn Connection>>;
+}
+
+impl Connection for TcpStream {
+    fn SetReadTimeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+
+    fn TryClone(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+}
+
+impl Connection for UnixStream {
+    fn SetReadTimeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+
+    fn TryClone(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+}
+
+fn session(
+    connection: Box<dyn Connection>,
+    token: &str,
+    runtime: &Rc<Runtime>,
+    history: usize,
+    optimize: bool,
+) -> io::Result<()> {
+    let (mut i, mut o) = (connection.TryClone()?, connection.TryClone()?);
+    let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+    let given = readLine(
+        &mut i,
+        &mut || match deadline.checked_duration_since(Instant::now()) {
+            Some(left) if !left.is_zero() => connection.SetReadTimeout(Some(left)),
+            _ => Err(io::ErrorKind::TimedOut.into()),
+        },
+    )
+    .map_err(|err| match err.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            io::Error::new(io::ErrorKind::TimedOut, "the token wasn't sent in time")
+        }
+        _ => err,
+    })?;
+    connection.SetReadTimeout(None)?;
+    let given = given.as_deref().unwrap_or_default();
+    if !SecureEq(given.as_bytes(), token.as_bytes()) {
+        writeln!(o, "unauthorized")?;
+        return Ok(());
+    }
+    let host = runtime.Platform();
+    runtime.SetPlatform(Rc::new(SessionPlatform {
+        inner: Rc::clone(&host),
+        connection: RefCell::new(connection),
+    }));
+    // `exit` only ends the session
+    let result = repl::Start(
+        Box::new(i),
+        &mut o,
+        Rc::clone(runtime),
+        history,
+        optimize,
+        false,
+        false,
+    );
+    runtime.SetPlatform(host);
+    result?;
+    Ok(())
+}
+
+/// The host's platform, except that output goes to the session's connection.
+/// The connection's input is the REPL's, so scripts have none.
+struct SessionPlatform {
+    inner: Rc<dyn Platform>,
+    connection: RefCell<Box<dyn Connection>>,
+}
+
+impl Platform for SessionPlatform {
+    fn Now(&self) -> Duration {
+        self.inner.Now()
+    }
+
+    fn SystemTime(&self) -> SystemTime {
+        self.inner.SystemTime()
+    }
+
+    fn Sleep(&self, time: Duration) {
+        self.inner.Sleep(time)
+    }
+
+    fn Random(&self) -> u64 {
+        self.inner.Random()
+    }
+
+    fn Write(&self, text: &str) {
+        // a client that went away ends the session at the next prompt
+        let _ = self.connection.borrow_mut().write_all(text.as_bytes());
+    }
+
+    fn ReadLine(&self) -> Option<String> {
+        None
+    }
+
+    fn ReadFile(&self, path: &Path) -> io::Result<String> {
+        self.inner.ReadFile(path)
+    }
+
+    fn WriteFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.inner.WriteFile(path, contents)
+    }
+
+    fn AppendFile(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.inner.AppendFile(path, contents)
+    }
+
+    fn ReadDir(&self, path: &Path) -> io::Result<Vec<String>> {
+        self.inner.ReadDir(path)
+    }
+
+    fn IsFile(&self, path: &Path) -> bool {
+        self.inner.IsFile(path)
+    }
+
+    fn Canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.inner.Canonicalize(path)
+    }
+
+    fn Var(&self, name: &str) -> Option<String> {
+        self.inner.Var(name)
+    }
+
+    fn SetVar(&self, name: &str, value: &str) {
+        self.inner.SetVar(name, value)
+    }
+}
+
+/// Reads one line a byte at a time, so nothing after it is consumed before
+/// the REPL reads it, calling `beforeRead` before each byte. `None` if it is
+/// too long.
+fn readLine(
+    i: &mut dyn Read,
+    beforeRead: &mut dyn FnMut() -> io::Result<()>,
+) -> io::Result<Option<String>> {
+    let mut line = vec![];
+    let mut byte = [0];
+    loop {
+        beforeRead()?;
+        if i.read(&mut byte)? == 0 || byte[0] == b'\n' {
+            break;
+        }
+        if line.len() == MAX_TOKEN_LEN {
+            return Ok(None);
+        }
+        line.push(byte[0]);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(String::from_utf8(line).ok())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,44 @@
+use std::io::Read;
+use std::io::Write;
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+
+use monkey::runtime::Runtime;
+
+use super::session;
+
+/// What a client sending `input` over a connection gets back.
+fn serve(input: &str) -> String {
+    let (mut client, server) = UnixStream::pair().unwrap();
+    client.write_all(input.as_bytes()).unwrap();
+    client.shutdown(Shutdown::Write).unwrap();
+    session(
+        Box::new(server),
+        "token",
+        &Rc::new(Runtime::New()),
+        0,
+        false,
+    )
+    .unwrap();
+    let mut output = String::new();
+    client.read_to_string(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn ClientGetsOutputAndDiagnostics() {
+    let output = serve("token\nputs(\"hi\")\nlet = 1\n:undo\n");
+    assert!(output.starts_with(">> hi\nnull\n>> "), "{}", output);
+    assert!(
+        output.contains("expected next token to be IDENT, got ASSIGN instead"),
+        "{}",
+        output
+    );
+    assert!(output.contains("can only undo 0 inputs"), "{}", output);
+}
+
+#[test]
+fn WrongTokenIsUnauthorized() {
+    assert_eq!(serve("nope\n"), "unauthorized\n");
+}
@@ -1,41 +1,144 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::io;
+use std::rc::Rc;
 
 use io::BufRead;
 use io::BufReader;
+use monkey::environment::Environment;
+use monkey::environment::Snapshot;
 use monkey::evaluator::Eval;
 use monkey::lexer::Lexer;
+use monkey::macro_expansion::DefineMacros;
+use monkey::macro_expansion::ExpandMacros;
 use monkey::object::Object;
+use monkey::object::ObjectEnum;
 use monkey::parser::Parser;
+use monkey::runtime::Limit;
+use monkey::runtime::Runtime;
+
+use crate::commands;
 
 const PROMPT: &str = ">> ";
 
-pub(crate) fn Start(i: &mut dyn io::Read, o: &mut dyn io::Write) -> io::Result<()> {
-    let mut scanner = BufReader::new(i);
+/// How many inputs `:undo` can go back by default.
+pub(crate) const HISTORY: usize = 100;
+
+/// Runs the read-eval-print loop. Cancelling the runtime aborts the input being
+/// evaluated and returns to the prompt with the environment intact. `:undo n`
+/// puts the environment back the way it was before the last `n` inputs that
+/// changed it (1 if left out), out of the last `history` of them. With
+/// `optimize`, each input is optimized before it runs. Returns the
+/// status a script passed to `exit`, which ends the loop, or 0 at the end of
+/// the input. Diagnostics go to `o` with the results, in color if `color` is
+/// set. With `ask`, an input that runs out of a limit asks on standard error
+/// whether to keep going, and the answer is read from `i` like the inputs.
+pub(crate) fn Start(
+    i: Box<dyn io::Read>,
+    o: &mut dyn io::Write,
+    runtime: Rc<Runtime>,
+    history: usize,
+    optimize: bool,
+    color: bool,
+    ask: bool,
+) -> io::Result<i32> {
+    let scanner = Rc::new(RefCell::new(BufReader::new(i)));
+    if ask {
+        let scanner = Rc::clone(&scanner);
+        runtime.OnLimitExceeded(move |limit| {
+            askToContinue(limit, &mut *scanner.borrow_mut(), &mut io::stderr()).unwrap_or(false)
+        });
+    }
     let mut line = String::new();
+    let env = Environment::WithRuntime(Rc::clone(&runtime));
+    // macros are kept apart from the values they expand to
+    let macroEnv = Environment::WithRuntime(Rc::clone(&runtime));
+    // the environments before each input, the latest last
+    let mut snapshots: VecDeque<(Snapshot, Snapshot)> = VecDeque::new();
 
     loop {
         line.clear();
         o.write_all(PROMPT.as_bytes())?;
         o.flush()?;
 
-        if scanner.read_line(&mut line)? == 0 {
+        if scanner.borrow_mut().read_line(&mut line)? == 0 {
             writeln!(o)?;
             break;
         }
 
+        if let Some(count) = line.trim().strip_prefix(":undo") {
+            let count = match count.trim() {
+                "" => Ok(1),
+                count => count.parse::<usize>(),
+            };
+            match count {
+                Ok(count) if count <= snapshots.len() => {
+                    let at = snapshots.len() - count;
+                    if let Some((values, macros)) = snapshots.drain(at..).next() {
+                        env.borrow_mut().Restore(values);
+                        macroEnv.borrow_mut().Restore(macros);
+                    }
+                }
+                Ok(_) => writeln!(o, "can only undo {} inputs", snapshots.len())?,
+                Err(_) => writeln!(o, "usage: :undo [count]")?,
+            }
+            continue;
+        }
+
         let l = Lexer::New(&line);
-        let mut p = Parser::New(l);
-        let program = p.ParseProgram();
+        let mut p = Parser::WithEdition(l, runtime.Edition());
+        let mut program = p.ParseProgram();
+        for warning in p.warnings.Take() {
+            commands::ReportTo(o, (&warning).into(), &line, color)?;
+        }
         if !p.errors.is_empty() {
-            for error in p.errors {
-                eprintln!("parse error: {}", error);
+            for error in &p.errors {
+                commands::ReportTo(o, error.into(), &line, color)?;
             }
             continue;
         }
 
-        if let Some(evaluated) = Eval(program.into()) {
+        // a Ctrl-C while waiting at the prompt shouldn't cancel this input,
+        // and each input gets a fresh budget
+        runtime.Reset();
+        let before = (env.borrow().Snapshot(), macroEnv.borrow().Snapshot());
+        DefineMacros(&mut program, &macroEnv);
+        let program = match ExpandMacros(program, &macroEnv) {
+            Ok(program) if optimize => program.Optimize(),
+            Ok(program) => program,
+            Err(err) => {
+                writeln!(o, "macro error: {}", err.Inspect())?;
+                continue;
+            }
+        };
+        let evaluated = Eval(program.into(), &env);
+        let changed = !before.0.Changes(&env.borrow()).is_empty()
+            || !before.1.Changes(&macroEnv.borrow()).is_empty();
+        if changed && history > 0 {
+            if snapshots.len() == history {
+                snapshots.pop_front();
+            }
+            snapshots.push_back(before);
+        }
+        for warning in runtime.Warnings().Take() {
+            commands::ReportTo(o, (&warning).into(), &line, color)?;
+        }
+        if let Some(code) = evaluated.as_ref().and_then(ObjectEnum::ExitCode) {
+            return Ok(code);
+        }
+        if let Some(evaluated) = evaluated {
             writeln!(o, "{}", evaluated.Inspect())?;
         }
     }
-    Ok(())
+    Ok(0)
+}
+
+/// Asks whether an evaluation that ran out of `limit` should keep going with
+/// another budget of the same size.
+fn askToContinue(limit: Limit, i: &mut dyn BufRead, o: &mut dyn io::Write) -> io::Result<bool> {
+    write!(o, "Evaluation exceeded {} — continue? [y/N] ", limit)?;
+    o.flush()?;
+    let mut answer = String::new();
+    i.read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
 }
@@ -0,0 +1,69 @@
+use std::rc::Rc;
+use std::thread;
+
+use monkey::runtime::Limits;
+use monkey::runtime::Runtime;
+
+use super::repl;
+use super::LIMITS;
+use super::STACK_SIZE;
+
+#[test]
+fn RecursesToTheDepthLimit() {
+    let input = "let r = fn(n) { if (n == 0) { 0 } else { [r(n - 1)][0] + 1 } };\n\
+                 r(990)\n\
+                 r(1001)\n";
+    let output = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let mut output = vec![];
+            let runtime = Rc::new(Runtime::WithLimits(LIMITS));
+            repl::Start(
+                Box::new(input.as_bytes()),
+                &mut output,
+                runtime,
+                0,
+                false,
+                false,
+                false,
+            )
+            .unwrap();
+            String::from_utf8(output).unwrap()
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    let lines: Vec<&str> = output.split(">> ").collect();
+    assert_eq!(lines[2], "990\n");
+    assert!(
+        lines[3].starts_with("ERROR: stack overflow: max depth 1000 exceeded"),
+        "{}",
+        lines[3]
+    );
+}
+
+#[test]
+fn AsksToContinueOnTheSameInput() {
+    let input = "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } };\n\
+                 f(30)\n\
+                 y\n\
+                 7\n";
+    let mut output = vec![];
+    let runtime = Rc::new(Runtime::WithLimits(Limits {
+        fuel: Some(50),
+        ..Limits::default()
+    }));
+    repl::Start(
+        Box::new(input.as_bytes()),
+        &mut output,
+        runtime,
+        0,
+        false,
+        false,
+        true,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // the answer is taken from the input, so it isn't evaluated
+    assert_eq!(output, ">> >> 0\n>> 7\n>> \n");
+}